@@ -37,7 +37,7 @@ fn mode_bench(c: &mut Criterion, name: &str, values: ArrayRef) {
     let mut accumulator = prepare_mode_accumulator();
     c.bench_function(name, |b| {
         b.iter(|| {
-            accumulator.update_batch(&[values.clone()]).unwrap();
+            accumulator.update_batch(std::slice::from_ref(&values)).unwrap();
             black_box(accumulator.evaluate().unwrap());
         });
     });
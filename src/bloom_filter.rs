@@ -0,0 +1,284 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::any::Any;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use arrow::array::{Array, ArrayRef, AsArray, BooleanArray};
+use arrow::datatypes::{DataType, Field, Int64Type};
+use datafusion::arrow;
+use datafusion::common::cast::as_binary_array;
+use datafusion::common::{internal_err, plan_err};
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, ColumnarValue, ScalarUDFImpl, Signature, Volatility};
+use datafusion::scalar::ScalarValue;
+
+use crate::common::bloom_filter::{bloom_filter_dimensions, BloomFilter};
+use crate::common::sketch::{coerce_sketch_type, sketch_parameter};
+
+make_udaf_expr!(
+    bloom_filter_agg,
+    x,
+    "Builds a serialized Bloom filter of the values, compatible with Spark.",
+    bloom_filter_agg_udaf
+);
+create_func!(BloomFilterAggFunction, bloom_filter_agg_udaf);
+
+make_udf_expr!(
+    might_contain,
+    filter value,
+    "Returns false when a value was certainly not added to a serialized Bloom filter.",
+    might_contain_udf
+);
+create_udf_func!(MightContainFunction, might_contain_udf);
+
+/// Coerces the items of a Bloom filter to the types hashed by [`BloomFilter`]: integers are hashed as 64-bit integers
+/// and strings as their UTF-8 bytes, as Spark does.
+fn coerce_item_type(item_type: &DataType) -> Option<DataType> {
+    match item_type {
+        DataType::UInt64 => None,
+        item_type if item_type.is_integer() || item_type.is_null() => Some(DataType::Int64),
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => Some(DataType::Utf8),
+        DataType::Binary | DataType::LargeBinary | DataType::BinaryView => Some(DataType::Binary),
+        _ => None,
+    }
+}
+
+/// Calls `f` with the index of each row of `items` and its item, `None` when it is null.
+fn for_each_item(items: &ArrayRef, mut f: impl FnMut(usize, Option<Item<'_>>) -> Result<()>) -> Result<()> {
+    match items.data_type() {
+        DataType::Int64 => items
+            .as_primitive::<Int64Type>()
+            .iter()
+            .enumerate()
+            .try_for_each(|(row, item)| f(row, item.map(Item::Long))),
+        DataType::Utf8 => items
+            .as_string::<i32>()
+            .iter()
+            .enumerate()
+            .try_for_each(|(row, item)| f(row, item.map(|item| Item::Bytes(item.as_bytes())))),
+        DataType::Binary => items
+            .as_binary::<i32>()
+            .iter()
+            .enumerate()
+            .try_for_each(|(row, item)| f(row, item.map(Item::Bytes))),
+        data_type => internal_err!("Unexpected Bloom filter item type {data_type}"),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Item<'a> {
+    Long(i64),
+    Bytes(&'a [u8]),
+}
+
+impl Item<'_> {
+    fn put(self, filter: &mut BloomFilter) {
+        match self {
+            Item::Long(item) => filter.put_long(item),
+            Item::Bytes(item) => filter.put_bytes(item),
+        }
+    }
+
+    fn might_be_in(self, filter: &BloomFilter) -> bool {
+        match self {
+            Item::Long(item) => filter.might_contain_long(item),
+            Item::Bytes(item) => filter.might_contain_bytes(item),
+        }
+    }
+}
+
+/// The `BloomFilterAggFunction` builds a Bloom filter of integers, strings or binaries like Spark's
+/// `bloom_filter_agg(x [, expected_items [, num_bits]])`, serialized in Spark's format, see [`BloomFilter`].
+///
+/// `expected_items` and `num_bits` must be positive constants, by default 1000000 and 8388608, or 8 bits per
+/// expected item when only `expected_items` is given. As in Spark, they are capped at 4000000 and 67108864. Returns
+/// null when there are no values.
+#[derive(Debug)]
+pub struct BloomFilterAggFunction {
+    signature: Signature,
+}
+
+impl Default for BloomFilterAggFunction {
+    fn default() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Immutable),
+        }
+    }
+}
+
+impl AggregateUDFImpl for BloomFilterAggFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "bloom_filter_agg"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let Some((item_type, parameter_types)) = arg_types.split_first().filter(|_| arg_types.len() <= 3) else {
+            return plan_err!("{} expects 1 to 3 arguments, got {}", self.name(), arg_types.len());
+        };
+        let Some(item_type) = coerce_item_type(item_type) else {
+            return plan_err!("{} does not support values of type {item_type}", self.name());
+        };
+        let mut coerced = vec![item_type];
+        for (parameter, parameter_type) in ["expected_items", "num_bits"].iter().zip(parameter_types) {
+            if !parameter_type.is_integer() && !parameter_type.is_null() {
+                return plan_err!(
+                    "The {parameter} of {} must be an integer, got {parameter_type}",
+                    self.name()
+                );
+            }
+            coerced.push(DataType::Int64);
+        }
+        Ok(coerced)
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Binary)
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let expected_items = sketch_parameter(&acc_args, 1, self.name(), "expected_items")?;
+        let num_bits = sketch_parameter(&acc_args, 2, self.name(), "num_bits")?;
+        let (expected_items, num_bits) = bloom_filter_dimensions(expected_items, num_bits)?;
+        Ok(Box::new(BloomFilterAccumulator {
+            filter: BloomFilter::new(expected_items, num_bits),
+        }))
+    }
+
+    fn state_fields(&self, _args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(vec![Field::new("filter", DataType::Binary, true)])
+    }
+}
+
+#[derive(Debug)]
+struct BloomFilterAccumulator {
+    filter: BloomFilter,
+}
+
+impl Accumulator for BloomFilterAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        for_each_item(&values[0], |_, item| {
+            if let Some(item) = item {
+                item.put(&mut self.filter);
+            }
+            Ok(())
+        })
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        as_binary_array(&states[0])?
+            .iter()
+            .flatten()
+            .try_for_each(|bytes| self.filter.merge(&BloomFilter::deserialize(bytes)?))
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        Ok(vec![self.evaluate()?])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        if self.filter.is_empty() {
+            return Ok(ScalarValue::Binary(None));
+        }
+        Ok(ScalarValue::Binary(Some(self.filter.to_bytes())))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) - std::mem::size_of_val(&self.filter) + self.filter.size()
+    }
+}
+
+/// The `MightContainFunction` probes a serialized Bloom filter built by `bloom_filter_agg` or by Spark, like Spark's
+/// `might_contain(filter, value)`: returns `false` when the value was certainly not added and `true` when it might
+/// have been. Returns null when the filter or the value is null.
+#[derive(Debug)]
+pub struct MightContainFunction {
+    signature: Signature,
+}
+
+impl Default for MightContainFunction {
+    fn default() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Immutable),
+        }
+    }
+}
+
+impl ScalarUDFImpl for MightContainFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "might_contain"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let [filter_type, item_type] = arg_types else {
+            return plan_err!("{} expects exactly 2 arguments, got {}", self.name(), arg_types.len());
+        };
+        let Some(item_type) = coerce_item_type(item_type) else {
+            return plan_err!("{} does not support values of type {item_type}", self.name());
+        };
+        Ok(vec![coerce_sketch_type(self.name(), filter_type)?, item_type])
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Boolean)
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        let arrays = ColumnarValue::values_to_arrays(args)?;
+        let filters = as_binary_array(&arrays[0])?;
+
+        // the common case of a filter probed with a column: the filter is only read once
+        let scalar_filter = match &args[0] {
+            ColumnarValue::Scalar(_) if filters.is_valid(0) => Some(BloomFilter::deserialize(filters.value(0))?),
+            _ => None,
+        };
+        let mut contained = Vec::with_capacity(arrays[1].len());
+        for_each_item(&arrays[1], |row, item| {
+            contained.push(match (item, &scalar_filter) {
+                (Some(item), Some(filter)) => Some(item.might_be_in(filter)),
+                (Some(item), None) if matches!(args[0], ColumnarValue::Array(_)) && filters.is_valid(row) => {
+                    Some(item.might_be_in(&BloomFilter::deserialize(filters.value(row))?))
+                }
+                _ => None,
+            });
+            Ok(())
+        })?;
+        let contained = BooleanArray::from(contained);
+
+        if args.iter().all(|arg| matches!(arg, ColumnarValue::Scalar(_))) {
+            return Ok(ColumnarValue::Scalar(ScalarValue::try_from_array(&contained, 0)?));
+        }
+        Ok(ColumnarValue::Array(Arc::new(contained)))
+    }
+}
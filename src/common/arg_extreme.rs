@@ -0,0 +1,362 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+use arrow::array::{make_comparator, Array, ArrayRef};
+use arrow::compute::SortOptions;
+use datafusion::arrow;
+use datafusion::arrow::datatypes::{DataType, Field};
+use datafusion::common::plan_err;
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::Accumulator;
+use datafusion::scalar::ScalarValue;
+
+/// The extreme of the ordering argument tracked by an [`ArgExtremeAccumulator`].
+///
+/// Ties are resolved by row position: `Min` keeps the first row reaching the minimum, `Max` keeps the last row
+/// reaching the maximum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgExtreme {
+    Min,
+    Max,
+}
+
+impl ArgExtreme {
+    /// Returns whether a row whose ordering compares as `ordering` against the current extreme replaces it.
+    fn replaces(&self, ordering: Ordering) -> bool {
+        match self {
+            ArgExtreme::Min => ordering == Ordering::Less,
+            ArgExtreme::Max => ordering != Ordering::Less,
+        }
+    }
+
+    /// Returns the index of the extreme row of `orderings`, ignoring null orderings.
+    fn find_index(&self, orderings: &dyn Array) -> Result<Option<usize>> {
+        let comparator = make_comparator(orderings, orderings, SortOptions::default())?;
+        let nulls = orderings.logical_nulls();
+
+        let mut index = None;
+        for i in 0..orderings.len() {
+            if nulls.as_ref().is_some_and(|nulls| nulls.is_null(i)) {
+                continue;
+            }
+            match index {
+                Some(current) if !self.replaces(comparator(i, current)) => {}
+                _ => index = Some(i),
+            }
+        }
+        Ok(index)
+    }
+
+    /// Compares the scalars with an arrow comparator rather than `ScalarValue::partial_cmp`, which has no order for
+    /// nested values such as lists of different lengths.
+    fn replaces_scalar(&self, ordering: &ScalarValue, current: &ScalarValue) -> Result<bool> {
+        let orderings = ScalarValue::iter_to_array([ordering.clone(), current.clone()])?;
+        let comparator = make_comparator(orderings.as_ref(), orderings.as_ref(), SortOptions::default())?;
+        Ok(self.replaces(comparator(0, 1)))
+    }
+}
+
+/// Returns whether values of `data_type` can be compared with each other, i.e. whether they can be used as the
+/// ordering argument of an arg-extreme aggregate. Nested types are orderable as long as all of their children are.
+pub fn is_orderable(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Map(_, _)
+        | DataType::Union(_, _)
+        | DataType::RunEndEncoded(_, _)
+        | DataType::ListView(_)
+        | DataType::LargeListView(_) => false,
+        DataType::List(field) | DataType::LargeList(field) | DataType::FixedSizeList(field, _) => {
+            is_orderable(field.data_type())
+        }
+        DataType::Struct(fields) => fields.iter().all(|field| is_orderable(field.data_type())),
+        DataType::Dictionary(_, value_type) => is_orderable(value_type),
+        _ => true,
+    }
+}
+
+/// Coerces the `(x, y)` arguments of the arg-extreme aggregate `name`: `y` must be orderable, and a dictionary encoded
+/// `x` is returned as its value type unless `dictionary_output` is set.
+pub fn coerce_arg_extreme_types(name: &str, arg_types: &[DataType], dictionary_output: bool) -> Result<Vec<DataType>> {
+    let [x_type, y_type] = arg_types else {
+        return plan_err!("{name} expects exactly 2 arguments, got {}", arg_types.len());
+    };
+
+    if !is_orderable(y_type) {
+        return plan_err!("{name} cannot order by a value of type {y_type}, it has no natural order");
+    }
+
+    let x_type = match x_type {
+        DataType::Dictionary(_, value_type) if !dictionary_output => value_type.as_ref().clone(),
+        _ => x_type.clone(),
+    };
+
+    Ok(vec![x_type, y_type.clone()])
+}
+
+/// State fields of an [`ArgExtremeAccumulator`], the value and the ordering of the extreme row.
+pub fn arg_extreme_state_fields(args: &StateFieldsArgs) -> Vec<Field> {
+    vec![
+        Field::new("value", args.input_types[0].clone(), true),
+        Field::new("ordering", args.input_types[1].clone(), true),
+    ]
+}
+
+/// Returns the types of the value and ordering arguments of an arg-extreme aggregate.
+pub fn arg_extreme_input_types(acc_args: &AccumulatorArgs) -> Result<(DataType, DataType)> {
+    Ok((
+        acc_args.exprs[0].data_type(acc_args.schema)?,
+        acc_args.exprs[1].data_type(acc_args.schema)?,
+    ))
+}
+
+/// Accumulator returning the value of its first argument at the row where its second argument reaches an
+/// [`ArgExtreme`]. Rows with a null ordering are ignored.
+///
+/// Only the extreme row seen so far is kept, so no sorting or buffering of the input is needed. The state is the
+/// `(value, ordering)` pair of that row, so merging is the same as updating with the states.
+#[derive(Debug)]
+pub struct ArgExtremeAccumulator {
+    extreme: ArgExtreme,
+    value: ScalarValue,
+    ordering: ScalarValue,
+}
+
+impl ArgExtremeAccumulator {
+    pub fn try_new(extreme: ArgExtreme, value_type: &DataType, ordering_type: &DataType) -> Result<Self> {
+        Ok(Self {
+            extreme,
+            value: ScalarValue::try_from(value_type)?,
+            ordering: ScalarValue::try_from(ordering_type)?,
+        })
+    }
+
+    fn update(&mut self, values: &ArrayRef, orderings: &ArrayRef) -> Result<()> {
+        let Some(index) = self.extreme.find_index(orderings.as_ref())? else {
+            return Ok(());
+        };
+
+        let ordering = ScalarValue::try_from_array(orderings, index)?;
+        if self.ordering.is_null() || self.extreme.replaces_scalar(&ordering, &self.ordering)? {
+            self.value = ScalarValue::try_from_array(values, index)?;
+            self.ordering = ordering;
+        }
+        Ok(())
+    }
+}
+
+impl Accumulator for ArgExtremeAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        self.update(&values[0], &values[1])
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        self.update(&states[0], &states[1])
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        Ok(vec![self.value.clone(), self.ordering.clone()])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        Ok(self.value.clone())
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) - std::mem::size_of_val(&self.value) - std::mem::size_of_val(&self.ordering)
+            + self.value.size()
+            + self.ordering.size()
+    }
+}
+
+/// Row kept by a [`SlidingArgExtremeAccumulator`] as a candidate for the extreme of the window frame.
+#[derive(Debug)]
+struct Candidate {
+    row: u64,
+    value: ScalarValue,
+    ordering: ScalarValue,
+}
+
+/// Version of [`ArgExtremeAccumulator`] supporting `retract_batch`, used for window frames with a moving start.
+///
+/// The candidates are kept as a monotonic queue: a row is dropped as soon as a later row replaces it, because it can
+/// never become the extreme of a frame again. The front of the queue is therefore the extreme of the current frame,
+/// and retracting rows only pops them from the front.
+#[derive(Debug)]
+pub struct SlidingArgExtremeAccumulator {
+    extreme: ArgExtreme,
+    value_type: DataType,
+    ordering_type: DataType,
+    candidates: VecDeque<Candidate>,
+    /// Number of rows added so far, used to number the rows
+    updated: u64,
+    /// Number of rows retracted so far, rows numbered below it are out of the frame
+    retracted: u64,
+}
+
+impl SlidingArgExtremeAccumulator {
+    pub fn new(extreme: ArgExtreme, value_type: &DataType, ordering_type: &DataType) -> Self {
+        Self {
+            extreme,
+            value_type: value_type.clone(),
+            ordering_type: ordering_type.clone(),
+            candidates: VecDeque::new(),
+            updated: 0,
+            retracted: 0,
+        }
+    }
+
+    fn push(&mut self, value: ScalarValue, ordering: ScalarValue) -> Result<()> {
+        let row = self.updated;
+        self.updated += 1;
+        if ordering.is_null() {
+            return Ok(());
+        }
+
+        while let Some(last) = self.candidates.back() {
+            if !self.extreme.replaces_scalar(&ordering, &last.ordering)? {
+                break;
+            }
+            self.candidates.pop_back();
+        }
+        self.candidates.push_back(Candidate { row, value, ordering });
+        Ok(())
+    }
+}
+
+impl Accumulator for SlidingArgExtremeAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        for i in 0..values[0].len() {
+            let value = ScalarValue::try_from_array(&values[0], i)?;
+            let ordering = ScalarValue::try_from_array(&values[1], i)?;
+            self.push(value, ordering)?;
+        }
+        Ok(())
+    }
+
+    fn retract_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        self.retracted += values[0].len() as u64;
+        while self
+            .candidates
+            .front()
+            .is_some_and(|candidate| candidate.row < self.retracted)
+        {
+            self.candidates.pop_front();
+        }
+        Ok(())
+    }
+
+    fn supports_retract_batch(&self) -> bool {
+        true
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        self.update_batch(states)
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        match self.candidates.front() {
+            Some(candidate) => Ok(vec![candidate.value.clone(), candidate.ordering.clone()]),
+            None => Ok(vec![
+                ScalarValue::try_from(&self.value_type)?,
+                ScalarValue::try_from(&self.ordering_type)?,
+            ]),
+        }
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        match self.candidates.front() {
+            Some(candidate) => Ok(candidate.value.clone()),
+            None => ScalarValue::try_from(&self.value_type),
+        }
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+            + self
+                .candidates
+                .iter()
+                .map(|candidate| std::mem::size_of::<Candidate>() + candidate.value.size() + candidate.ordering.size())
+                .sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int64Array, StringArray};
+    use std::sync::Arc;
+
+    fn batch(values: Vec<&str>, orderings: Vec<Option<i64>>) -> Vec<ArrayRef> {
+        vec![
+            Arc::new(StringArray::from(values)) as ArrayRef,
+            Arc::new(Int64Array::from(orderings)) as ArrayRef,
+        ]
+    }
+
+    #[test]
+    fn test_arg_extreme_accumulator_ties() -> Result<()> {
+        let values = batch(vec!["a", "b", "c", "d"], vec![Some(5), Some(5), Some(15), Some(15)]);
+
+        let mut acc = ArgExtremeAccumulator::try_new(ArgExtreme::Min, &DataType::Utf8, &DataType::Int64)?;
+        acc.update_batch(&values)?;
+        assert_eq!(acc.evaluate()?, ScalarValue::from("a"));
+
+        let mut acc = ArgExtremeAccumulator::try_new(ArgExtreme::Max, &DataType::Utf8, &DataType::Int64)?;
+        acc.update_batch(&values)?;
+        assert_eq!(acc.evaluate()?, ScalarValue::from("d"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_arg_extreme_accumulator_merge() -> Result<()> {
+        let mut acc1 = ArgExtremeAccumulator::try_new(ArgExtreme::Max, &DataType::Utf8, &DataType::Int64)?;
+        acc1.update_batch(&batch(vec!["a", "b"], vec![Some(10), None]))?;
+        let mut acc2 = ArgExtremeAccumulator::try_new(ArgExtreme::Max, &DataType::Utf8, &DataType::Int64)?;
+        acc2.update_batch(&batch(vec!["c", "d"], vec![Some(5), Some(20)]))?;
+
+        let states = acc2
+            .state()?
+            .into_iter()
+            .map(|state| state.to_array())
+            .collect::<Result<Vec<_>>>()?;
+        acc1.merge_batch(&states)?;
+        assert_eq!(acc1.evaluate()?, ScalarValue::from("d"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sliding_arg_extreme_accumulator_retract() -> Result<()> {
+        let mut acc = SlidingArgExtremeAccumulator::new(ArgExtreme::Max, &DataType::Utf8, &DataType::Int64);
+        acc.update_batch(&batch(vec!["a", "b", "c"], vec![Some(30), Some(20), Some(10)]))?;
+        assert_eq!(acc.evaluate()?, ScalarValue::from("a"));
+
+        acc.retract_batch(&batch(vec!["a"], vec![Some(30)]))?;
+        assert_eq!(acc.evaluate()?, ScalarValue::from("b"));
+
+        acc.update_batch(&batch(vec!["d"], vec![None]))?;
+        acc.retract_batch(&batch(vec!["b"], vec![Some(20)]))?;
+        assert_eq!(acc.evaluate()?, ScalarValue::from("c"));
+
+        acc.retract_batch(&batch(vec!["c", "d"], vec![Some(10), None]))?;
+        assert_eq!(acc.evaluate()?, ScalarValue::Utf8(None));
+        Ok(())
+    }
+}
@@ -0,0 +1,327 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::mem::size_of;
+
+use datafusion::common::{exec_err, plan_err};
+use datafusion::error::Result;
+
+use crate::common::sketch::SketchReader;
+
+/// Default number of items of `bloom_filter_agg`, as Spark's `spark.sql.optimizer.runtime.bloomFilter.expectedNumItems`.
+pub const DEFAULT_EXPECTED_ITEMS: i64 = 1_000_000;
+/// Default number of bits of `bloom_filter_agg`, as Spark's `spark.sql.optimizer.runtime.bloomFilter.numBits`.
+pub const DEFAULT_NUM_BITS: i64 = 8_388_608;
+/// Items above this are capped, as Spark's `spark.sql.optimizer.runtime.bloomFilter.maxNumItems`.
+pub const MAX_EXPECTED_ITEMS: i64 = 4_000_000;
+/// Bits above this are capped, as Spark's `spark.sql.optimizer.runtime.bloomFilter.maxNumBits`.
+pub const MAX_NUM_BITS: i64 = 67_108_864;
+
+/// Version of Spark's `BloomFilterImpl` serialization.
+const SERIAL_VERSION: i32 = 1;
+
+/// A Bloom filter binary compatible with Spark's `BloomFilterImpl`, so filters built by `bloom_filter_agg` can be
+/// probed by Spark's `might_contain` and the other way around.
+///
+/// Items are hashed as Spark does: 64-bit integers with `Murmur3_x86_32.hashLong` and strings and binaries with
+/// `Murmur3_x86_32.hashUnsafeBytes`, once with seed 0 giving `h1` and once with seed `h1` giving `h2`. The `i`-th
+/// of the `k` bits of an item is `h1 + i * h2`, bitwise inverted when negative, modulo the number of bits.
+///
+/// The serialization is Spark's version 1 layout, big-endian:
+///
+/// | Field | Type |
+/// |-------|------|
+/// | version 1 | `i32` |
+/// | number of hash functions `k` | `i32` |
+/// | number of 64-bit words | `i32` |
+/// | words, the bit `i` being bit `i % 64` of word `i / 64` | `i64` each |
+#[derive(Debug, Clone, PartialEq)]
+pub struct BloomFilter {
+    num_hash_functions: u32,
+    words: Vec<u64>,
+}
+
+impl BloomFilter {
+    /// Creates an empty filter sized as Spark's `BloomFilter.create(expected_items, num_bits)`.
+    pub fn new(expected_items: i64, num_bits: i64) -> Self {
+        let num_hash_functions = ((num_bits as f64 / expected_items as f64) * 2f64.ln()).round().max(1.0) as u32;
+        Self {
+            num_hash_functions,
+            words: vec![0; (num_bits as usize).div_ceil(64)],
+        }
+    }
+
+    pub fn num_hash_functions(&self) -> u32 {
+        self.num_hash_functions
+    }
+
+    /// Number of bits, a multiple of 64.
+    pub fn bit_size(&self) -> u64 {
+        self.words.len() as u64 * 64
+    }
+
+    /// Returns `true` when no item was added.
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|word| *word == 0)
+    }
+
+    pub fn put_long(&mut self, item: i64) {
+        let h1 = hash_long(item, 0);
+        self.put_hash(h1, hash_long(item, h1));
+    }
+
+    pub fn put_bytes(&mut self, item: &[u8]) {
+        let h1 = hash_bytes(item, 0);
+        self.put_hash(h1, hash_bytes(item, h1));
+    }
+
+    /// Returns `false` when `item` was certainly not added.
+    pub fn might_contain_long(&self, item: i64) -> bool {
+        let h1 = hash_long(item, 0);
+        self.might_contain_hash(h1, hash_long(item, h1))
+    }
+
+    /// Returns `false` when `item` was certainly not added.
+    pub fn might_contain_bytes(&self, item: &[u8]) -> bool {
+        let h1 = hash_bytes(item, 0);
+        self.might_contain_hash(h1, hash_bytes(item, h1))
+    }
+
+    /// Merges `other` into this filter, which must have the same number of bits and hash functions.
+    pub fn merge(&mut self, other: &Self) -> Result<()> {
+        if (self.words.len(), self.num_hash_functions) != (other.words.len(), other.num_hash_functions) {
+            return exec_err!(
+                "Cannot merge Bloom filters with {} bits and {} hash functions and {} bits and {} hash functions",
+                self.bit_size(),
+                self.num_hash_functions,
+                other.bit_size(),
+                other.num_hash_functions
+            );
+        }
+        for (word, other_word) in self.words.iter_mut().zip(&other.words) {
+            *word |= other_word;
+        }
+        Ok(())
+    }
+
+    pub fn serialize(&self, buffer: &mut Vec<u8>) {
+        buffer.reserve(12 + self.words.len() * size_of::<u64>());
+        buffer.extend_from_slice(&SERIAL_VERSION.to_be_bytes());
+        buffer.extend_from_slice(&(self.num_hash_functions as i32).to_be_bytes());
+        buffer.extend_from_slice(&(self.words.len() as i32).to_be_bytes());
+        for word in &self.words {
+            buffer.extend_from_slice(&word.to_be_bytes());
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = vec![];
+        self.serialize(&mut buffer);
+        buffer
+    }
+
+    /// Reads a filter serialized by [`Self::serialize`] or by Spark.
+    pub fn deserialize(bytes: &[u8]) -> Result<Self> {
+        let mut reader = SketchReader::new("Bloom filter", bytes);
+        let mut read_i32 = || -> Result<i32> { Ok(i32::from_be_bytes(reader.read_bytes(4)?.try_into().unwrap())) };
+        let version = read_i32()?;
+        let num_hash_functions = read_i32()?;
+        let num_words = read_i32()?;
+        if version != SERIAL_VERSION {
+            return reader.invalid(format!("unsupported version {version}"));
+        }
+        if num_hash_functions <= 0 || num_words <= 0 {
+            return reader.invalid(format!("{num_hash_functions} hash functions and {num_words} words"));
+        }
+        let words = reader
+            .read_bytes(num_words as usize * size_of::<u64>())?
+            .chunks_exact(size_of::<u64>())
+            .map(|chunk| u64::from_be_bytes(chunk.try_into().unwrap()))
+            .collect();
+        reader.finish()?;
+        Ok(Self {
+            num_hash_functions: num_hash_functions as u32,
+            words,
+        })
+    }
+
+    /// Size in bytes including `Self`.
+    pub fn size(&self) -> usize {
+        size_of::<Self>() + self.words.capacity() * size_of::<u64>()
+    }
+
+    fn put_hash(&mut self, h1: i32, h2: i32) {
+        let bit_size = self.bit_size();
+        for index in bit_indexes(h1, h2, self.num_hash_functions, bit_size) {
+            self.words[(index / 64) as usize] |= 1 << (index % 64);
+        }
+    }
+
+    fn might_contain_hash(&self, h1: i32, h2: i32) -> bool {
+        bit_indexes(h1, h2, self.num_hash_functions, self.bit_size())
+            .all(|index| self.words[(index / 64) as usize] & (1 << (index % 64)) != 0)
+    }
+}
+
+/// Checks the `expected_items` and `num_bits` arguments of `bloom_filter_agg` and caps them as Spark does.
+pub fn bloom_filter_dimensions(expected_items: Option<i64>, num_bits: Option<i64>) -> Result<(i64, i64)> {
+    let (expected_items, num_bits) = match (expected_items, num_bits) {
+        (None, _) => (DEFAULT_EXPECTED_ITEMS, DEFAULT_NUM_BITS),
+        // like Spark, the integer ratio of the default bits and items, i.e. 8 bits per item
+        (Some(expected_items), None) => (
+            expected_items,
+            expected_items.saturating_mul(DEFAULT_NUM_BITS / DEFAULT_EXPECTED_ITEMS),
+        ),
+        (Some(expected_items), Some(num_bits)) => (expected_items, num_bits),
+    };
+    if expected_items <= 0 {
+        return plan_err!("The expected_items of bloom_filter_agg must be positive, got {expected_items}");
+    }
+    if num_bits <= 0 {
+        return plan_err!("The num_bits of bloom_filter_agg must be positive, got {num_bits}");
+    }
+    Ok((expected_items.min(MAX_EXPECTED_ITEMS), num_bits.min(MAX_NUM_BITS)))
+}
+
+fn bit_indexes(h1: i32, h2: i32, num_hash_functions: u32, bit_size: u64) -> impl Iterator<Item = u64> {
+    (1..=num_hash_functions as i32).map(move |i| {
+        let combined = h1.wrapping_add(i.wrapping_mul(h2));
+        let combined = if combined < 0 { !combined } else { combined };
+        combined as u64 % bit_size
+    })
+}
+
+const C1: u32 = 0xcc9e_2d51;
+const C2: u32 = 0x1b87_3593;
+
+fn mix_k1(k1: u32) -> u32 {
+    k1.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2)
+}
+
+fn mix_h1(h1: u32, k1: u32) -> u32 {
+    (h1 ^ k1).rotate_left(13).wrapping_mul(5).wrapping_add(0xe654_6b64)
+}
+
+fn fmix(h1: u32, length: u32) -> i32 {
+    let mut h1 = h1 ^ length;
+    h1 ^= h1 >> 16;
+    h1 = h1.wrapping_mul(0x85eb_ca6b);
+    h1 ^= h1 >> 13;
+    h1 = h1.wrapping_mul(0xc2b2_ae35);
+    h1 ^= h1 >> 16;
+    h1 as i32
+}
+
+/// Spark's `Murmur3_x86_32.hashLong`.
+pub fn hash_long(item: i64, seed: i32) -> i32 {
+    let h1 = mix_h1(seed as u32, mix_k1(item as u32));
+    let h1 = mix_h1(h1, mix_k1((item as u64 >> 32) as u32));
+    fmix(h1, 8)
+}
+
+/// Spark's `Murmur3_x86_32.hashUnsafeBytes`, which unlike MurmurHash3 mixes each trailing byte as a sign-extended
+/// block of its own.
+pub fn hash_bytes(item: &[u8], seed: i32) -> i32 {
+    let blocks = item.chunks_exact(4);
+    let tail = blocks.remainder();
+    let mut h1 = seed as u32;
+    for block in blocks {
+        h1 = mix_h1(h1, mix_k1(u32::from_le_bytes(block.try_into().unwrap())));
+    }
+    for byte in tail {
+        h1 = mix_h1(h1, mix_k1(*byte as i8 as i32 as u32));
+    }
+    fmix(h1, item.len() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spark_hashes() {
+        // Spark's `hash(CAST(1 AS BIGINT))` and `hash('Spark')`, which use the seed 42
+        assert_eq!(hash_long(1, 42), -1712319331);
+        assert_eq!(hash_bytes(b"Spark", 42), 228093765);
+    }
+
+    #[test]
+    fn test_dimensions() {
+        let filter = BloomFilter::new(DEFAULT_EXPECTED_ITEMS, DEFAULT_NUM_BITS);
+        assert_eq!((filter.num_hash_functions(), filter.bit_size()), (6, 8_388_608));
+        assert_eq!(BloomFilter::new(10, 100).bit_size(), 128);
+        assert_eq!(BloomFilter::new(1000, 10).num_hash_functions(), 1);
+
+        assert_eq!(bloom_filter_dimensions(None, None).unwrap(), (1_000_000, 8_388_608));
+        assert_eq!(bloom_filter_dimensions(Some(1000), None).unwrap(), (1000, 8000));
+        assert_eq!(
+            bloom_filter_dimensions(Some(i64::MAX), Some(i64::MAX)).unwrap(),
+            (MAX_EXPECTED_ITEMS, MAX_NUM_BITS)
+        );
+        let err = bloom_filter_dimensions(Some(0), None).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("The expected_items of bloom_filter_agg must be positive, got 0"));
+        let err = bloom_filter_dimensions(Some(10), Some(-1)).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("The num_bits of bloom_filter_agg must be positive, got -1"));
+    }
+
+    #[test]
+    fn test_filter() {
+        let mut filter = BloomFilter::new(1000, 10_000);
+        assert!(filter.is_empty());
+        for i in 0..1000 {
+            filter.put_long(i * 3);
+        }
+        filter.put_bytes(b"apple");
+        filter.put_bytes(b"");
+        assert!(!filter.is_empty());
+
+        assert!((0..1000).all(|i| filter.might_contain_long(i * 3)));
+        let false_positives = (0..1000).filter(|i| filter.might_contain_long(i * 3 + 1)).count();
+        assert!(false_positives < 30, "{false_positives}");
+        assert!(filter.might_contain_bytes(b"apple") && filter.might_contain_bytes(b""));
+        assert!(!filter.might_contain_bytes(b"banana"));
+
+        let mut other = BloomFilter::new(1000, 10_000);
+        other.put_bytes(b"banana");
+        other.merge(&filter).unwrap();
+        assert!(other.might_contain_bytes(b"banana") && other.might_contain_long(2997));
+        let err = other.merge(&BloomFilter::new(1000, 100_000)).unwrap_err();
+        assert!(err.to_string().contains("Cannot merge Bloom filters"));
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut filter = BloomFilter::new(DEFAULT_EXPECTED_ITEMS, DEFAULT_NUM_BITS);
+        filter.put_long(42);
+        let bytes = filter.to_bytes();
+        assert_eq!(bytes[..12], [0, 0, 0, 1, 0, 0, 0, 6, 0, 2, 0, 0]);
+        assert_eq!(bytes.len(), 12 + 8_388_608 / 8);
+        assert_eq!(BloomFilter::deserialize(&bytes).unwrap(), filter);
+
+        let err = BloomFilter::deserialize(&bytes[..100]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Invalid Bloom filter sketch: unexpected end of input"));
+        let err = BloomFilter::deserialize(&[0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 0]).unwrap_err();
+        assert!(err.to_string().contains("unsupported version 2"));
+        let err = BloomFilter::deserialize(&[0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0]).unwrap_err();
+        assert!(err.to_string().contains("1 hash functions and 0 words"));
+    }
+}
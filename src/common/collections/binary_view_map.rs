@@ -59,7 +59,7 @@ use std::sync::Arc;
 /// This map is used by the special `COUNT DISTINCT` aggregate function to
 /// store the distinct values, and by the `GROUP BY` operator to store
 /// group values when they are a single string array.
-
+//
 // TODO: Remove after DataFusion next release once insert_or_update and get_payloads are added to the collection.
 // Copied from datafusion/physical-expr-common/binary_view_map.rs.
 pub struct ArrowBytesViewMap<V>
@@ -175,7 +175,7 @@ where
         let batch_hashes = &mut self.hashes_buffer;
         batch_hashes.clear();
         batch_hashes.resize(values.len(), 0);
-        create_hashes(&[values.clone()], &self.random_state, batch_hashes)
+        create_hashes(std::slice::from_ref(values), &self.random_state, batch_hashes)
             // hash is supported for all types and create_hashes only
             // returns errors for unsupported types
             .unwrap();
@@ -297,7 +297,7 @@ where
         let batch_hashes = &mut self.hashes_buffer;
         batch_hashes.clear();
         batch_hashes.resize(values.len(), 0);
-        create_hashes(&[values.clone()], &self.random_state, batch_hashes)
+        create_hashes(std::slice::from_ref(values), &self.random_state, batch_hashes)
             // hash is supported for all types and create_hashes only
             // returns errors for unsupported types
             .unwrap();
@@ -378,7 +378,7 @@ where
     {
         // Step 1: Compute hashes
         let mut batch_hashes = vec![0u64; values.len()];
-        create_hashes(&[values.clone()], &self.random_state, &mut batch_hashes).unwrap(); // Compute the hashes for the values
+        create_hashes(std::slice::from_ref(values), &self.random_state, &mut batch_hashes).unwrap(); // Compute the hashes for the values
 
         // Step 2: Get payloads for each value
         let values = values.as_byte_view::<B>();
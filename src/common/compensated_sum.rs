@@ -0,0 +1,302 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::sync::Arc;
+
+use arrow::array::{Array, ArrayRef, AsArray, Float64Array, Int64Array};
+use arrow::datatypes::{DataType, Field, Float64Type, Int64Type};
+use datafusion::arrow;
+
+/// Sum of `f64` values with Neumaier's variant of Kahan summation, which keeps the rounding error of each addition
+/// in a compensation term instead of losing it.
+///
+/// The error of the sum stays in the order of the rounding of the result, independently of the number of values,
+/// unlike a naive sum whose error grows with it. The compensation is kept separately, so that sums merged from
+/// several partitions keep the precision of a single sum of all the values.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CompensatedSum {
+    sum: f64,
+    compensation: f64,
+}
+
+impl CompensatedSum {
+    pub fn new(sum: f64, compensation: f64) -> Self {
+        Self { sum, compensation }
+    }
+
+    pub fn add(&mut self, value: f64) {
+        let sum = self.sum + value;
+        // the low-order bits lost by the addition are the ones of the smaller operand
+        if self.sum.abs() >= value.abs() {
+            self.compensation += (self.sum - sum) + value;
+        } else {
+            self.compensation += (value - sum) + self.sum;
+        }
+        self.sum = sum;
+    }
+
+    pub fn merge(&mut self, other: &CompensatedSum) {
+        self.add(other.sum);
+        self.compensation += other.compensation;
+    }
+
+    /// Returns the uncompensated sum, which is kept in the state next to [`Self::compensation`].
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    pub fn compensation(&self) -> f64 {
+        self.compensation
+    }
+
+    /// Returns the compensated sum of the values. Infinite and NaN sums are returned as is, as their compensation is
+    /// meaningless.
+    pub fn value(&self) -> f64 {
+        if self.sum.is_finite() {
+            self.sum + self.compensation
+        } else {
+            self.sum
+        }
+    }
+}
+
+impl FromIterator<f64> for CompensatedSum {
+    fn from_iter<I: IntoIterator<Item = f64>>(iter: I) -> Self {
+        let mut sum = CompensatedSum::default();
+        iter.into_iter().for_each(|value| sum.add(value));
+        sum
+    }
+}
+
+/// Numbers of the infinite and NaN values of a sum, which are counted instead of being added to its
+/// [`CompensatedSum`]: once added, they would keep it infinite or NaN even after they are retracted.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NonFiniteCounts {
+    pub positive_infinities: i64,
+    pub negative_infinities: i64,
+    pub nans: i64,
+}
+
+impl NonFiniteCounts {
+    /// Counts `value` `delta` times, -1 to retract it, and returns whether it is non-finite and counted.
+    pub fn count(&mut self, value: f64, delta: i64) -> bool {
+        if value.is_nan() {
+            self.nans += delta;
+        } else if value == f64::INFINITY {
+            self.positive_infinities += delta;
+        } else if value == f64::NEG_INFINITY {
+            self.negative_infinities += delta;
+        } else {
+            return false;
+        }
+        true
+    }
+
+    pub fn merge(&mut self, other: &NonFiniteCounts) {
+        self.positive_infinities += other.positive_infinities;
+        self.negative_infinities += other.negative_infinities;
+        self.nans += other.nans;
+    }
+
+    pub fn total(&self) -> i64 {
+        self.positive_infinities + self.negative_infinities + self.nans
+    }
+
+    /// Returns the sum of the counted values, which is NaN with a NaN or with infinities of both signs, or `None` when
+    /// there are none and the sum is the one of the finite values.
+    pub fn sum(&self) -> Option<f64> {
+        match (self.positive_infinities > 0, self.negative_infinities > 0) {
+            _ if self.nans > 0 => Some(f64::NAN),
+            (true, true) => Some(f64::NAN),
+            (true, false) => Some(f64::INFINITY),
+            (false, true) => Some(f64::NEG_INFINITY),
+            (false, false) => None,
+        }
+    }
+}
+
+/// Retractable sum of `f64` values with their number: the finite values are added to a [`CompensatedSum`], and the
+/// infinite and NaN ones to [`NonFiniteCounts`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CountedSum {
+    sum: CompensatedSum,
+    count: i64,
+    non_finite: NonFiniteCounts,
+}
+
+impl CountedSum {
+    /// Adds `value` to the sum, and removes it when `delta` is -1.
+    pub fn update(&mut self, value: f64, delta: i64) {
+        self.count += delta;
+        if !self.non_finite.count(value, delta) {
+            self.sum.add(delta as f64 * value);
+        }
+        // once the finite values are all retracted, the sum restarts from an exact zero, without their rounding errors
+        if self.count == self.non_finite.total() {
+            self.sum = CompensatedSum::default();
+        }
+    }
+
+    pub fn merge(&mut self, other: &CountedSum) {
+        self.sum.merge(&other.sum);
+        self.count += other.count;
+        self.non_finite.merge(&other.non_finite);
+    }
+
+    pub fn count(&self) -> i64 {
+        self.count
+    }
+
+    /// Returns the sum of the values, or `None` when there are none.
+    pub fn value(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.non_finite.sum().unwrap_or_else(|| self.sum.value()))
+    }
+
+    /// Returns the fields of the state of the sums, as returned by [`Self::state`].
+    pub fn state_fields() -> Vec<Field> {
+        vec![
+            Field::new("sum", DataType::Float64, true),
+            Field::new("compensation", DataType::Float64, true),
+            Field::new("count", DataType::Int64, true),
+            Field::new("positive_infinities", DataType::Int64, true),
+            Field::new("negative_infinities", DataType::Int64, true),
+            Field::new("nans", DataType::Int64, true),
+        ]
+    }
+
+    /// Returns the `sum`, `compensation`, `count`, `positive_infinities`, `negative_infinities` and `nans` states of
+    /// the sums.
+    pub fn state(sums: &[CountedSum]) -> Vec<ArrayRef> {
+        let totals = sums.iter().map(|sum| sum.sum.sum()).collect::<Float64Array>();
+        let compensations = sums.iter().map(|sum| sum.sum.compensation()).collect::<Float64Array>();
+        let counts =
+            |count: fn(&CountedSum) -> i64| -> ArrayRef { Arc::new(sums.iter().map(count).collect::<Int64Array>()) };
+        vec![
+            Arc::new(totals),
+            Arc::new(compensations),
+            counts(|sum| sum.count),
+            counts(|sum| sum.non_finite.positive_infinities),
+            counts(|sum| sum.non_finite.negative_infinities),
+            counts(|sum| sum.non_finite.nans),
+        ]
+    }
+
+    /// Calls `f` with the index and the sum of each non-empty row of a state returned by [`Self::state`].
+    pub fn for_each_state(states: &[ArrayRef], mut f: impl FnMut(usize, CountedSum)) {
+        let totals = states[0].as_primitive::<Float64Type>();
+        let compensations = states[1].as_primitive::<Float64Type>();
+        let counts = states[2].as_primitive::<Int64Type>();
+        let positive_infinities = states[3].as_primitive::<Int64Type>();
+        let negative_infinities = states[4].as_primitive::<Int64Type>();
+        let nans = states[5].as_primitive::<Int64Type>();
+        for index in 0..counts.len() {
+            if counts.is_valid(index) && counts.value(index) > 0 {
+                let sum = CountedSum {
+                    sum: CompensatedSum::new(totals.value(index), compensations.value(index)),
+                    count: counts.value(index),
+                    non_finite: NonFiniteCounts {
+                        positive_infinities: positive_infinities.value(index),
+                        negative_infinities: negative_infinities.value(index),
+                        nans: nans.value(index),
+                    },
+                };
+                f(index, sum);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compensated_sum_cancellation() {
+        let sum = [1e100, 1.0, -1e100].into_iter().collect::<CompensatedSum>();
+        assert_eq!(sum.value(), 1.0);
+        assert_eq!(sum.sum(), 0.0);
+
+        let sum = std::iter::repeat(0.1).take(10).collect::<CompensatedSum>();
+        assert_eq!(sum.value(), 1.0);
+        assert_ne!(std::iter::repeat(0.1).take(10).sum::<f64>(), 1.0);
+    }
+
+    #[test]
+    fn test_compensated_sum_merge() {
+        let mut merged = [1e16, 1.0].into_iter().collect::<CompensatedSum>();
+        merged.merge(&[1.0, -1e16].into_iter().collect());
+        assert_eq!(merged.value(), 2.0);
+
+        let mut merged = CompensatedSum::default();
+        merged.merge(&CompensatedSum::new(3.0, 1e-20));
+        assert_eq!(merged, CompensatedSum::new(3.0, 1e-20));
+    }
+
+    #[test]
+    fn test_compensated_sum_non_finite() {
+        let sum = [f64::MAX, f64::MAX, 1.0].into_iter().collect::<CompensatedSum>();
+        assert_eq!(sum.value(), f64::INFINITY);
+
+        let sum = [f64::INFINITY, f64::NEG_INFINITY]
+            .into_iter()
+            .collect::<CompensatedSum>();
+        assert!(sum.value().is_nan());
+    }
+
+    #[test]
+    fn test_non_finite_counts() {
+        let mut counts = NonFiniteCounts::default();
+        assert!(!counts.count(1.0, 1));
+        assert_eq!(counts.sum(), None);
+
+        assert!(counts.count(f64::INFINITY, 1));
+        assert_eq!(counts.sum(), Some(f64::INFINITY));
+        assert!(counts.count(f64::NEG_INFINITY, 1));
+        assert!(counts.sum().unwrap().is_nan());
+        counts.count(f64::INFINITY, -1);
+        assert_eq!(counts.sum(), Some(f64::NEG_INFINITY));
+
+        let mut merged = NonFiniteCounts::default();
+        merged.count(f64::NAN, 1);
+        counts.merge(&merged);
+        assert!(counts.sum().unwrap().is_nan());
+        assert_eq!(counts.total(), 2);
+        counts.count(f64::NAN, -1);
+        assert_eq!(counts.sum(), Some(f64::NEG_INFINITY));
+    }
+
+    #[test]
+    fn test_counted_sum_state() {
+        let mut sums = [CountedSum::default(), CountedSum::default(), CountedSum::default()];
+        for value in [1e100, 1.0, f64::INFINITY, -1e100] {
+            sums[0].update(value, 1);
+        }
+        sums[2].update(2.0, 1);
+        sums[2].update(f64::NAN, 1);
+        sums[2].update(f64::NAN, -1);
+
+        let mut merged = vec![];
+        CountedSum::for_each_state(&CountedSum::state(&sums), |index, sum| merged.push((index, sum)));
+        assert_eq!(merged, [(0, sums[0]), (2, sums[2])]);
+        assert_eq!(merged[0].1.value(), Some(f64::INFINITY));
+
+        sums[0].update(f64::INFINITY, -1);
+        assert_eq!(sums[0].value(), Some(1.0));
+        assert_eq!(sums[0].count(), 3);
+        assert_eq!(sums[1].value(), None);
+        assert_eq!(sums[2].value(), Some(2.0));
+    }
+}
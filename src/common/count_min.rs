@@ -0,0 +1,366 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::mem::size_of;
+
+use datafusion::arrow::array::{Array, ArrayRef, AsArray};
+use datafusion::arrow::datatypes::{DataType, Int64Type, UInt64Type};
+use datafusion::common::{exec_err, plan_err};
+use datafusion::error::Result;
+
+use crate::common::bloom_filter::{hash_bytes, hash_long};
+use crate::common::sketch::{write_header, SketchReader};
+
+const FAMILY: u8 = b'C';
+const SERIAL_VERSION: u8 = 1;
+
+/// The largest number of counters of a sketch, 128 MiB of counts.
+pub const MAX_CELLS: u64 = 1 << 24;
+
+/// A count-min sketch estimating how many times each value was added, never underestimating it.
+///
+/// Like Spark's `CountMinSketch`, a sketch with relative error `eps` and confidence `confidence` has
+/// `ceil(2 / eps)` counters per row and `ceil(-ln(1 - confidence) / ln 2)` rows. Values are hashed with Spark's
+/// `Murmur3_x86_32` as in [`BloomFilter`](crate::common::bloom_filter::BloomFilter): 64-bit integers with `hashLong`
+/// and strings and binaries with `hashUnsafeBytes`, once with the seed folded to 32 bits giving `h1` and once with seed
+/// `h1` giving `h2`. The counter of a value in row `i` is `h1 + i * h2` modulo the width, so the serialized sketches
+/// can be read by any build and platform, but the counters are not laid out as the ones of Spark.
+///
+/// The serialization is little-endian:
+///
+/// | Field | Type |
+/// |-------|------|
+/// | family `C`, version 1 | `u8`, `u8` |
+/// | depth, width | `u32`, `u32` |
+/// | seed | `i64` |
+/// | total count | `u64` |
+/// | counters, row by row | `depth * width` x `u64` |
+#[derive(Debug, Clone, PartialEq)]
+pub struct CountMinSketch {
+    depth: u32,
+    width: u32,
+    seed: i64,
+    total_count: u64,
+    counts: Vec<u64>,
+}
+
+impl CountMinSketch {
+    /// Creates an empty sketch with the relative error `eps` and the confidence `confidence`, both in `(0, 1)`.
+    pub fn try_new(eps: f64, confidence: f64, seed: i64) -> Result<Self> {
+        if !(eps > 0.0 && eps < 1.0) {
+            return plan_err!("The eps of a count-min sketch must be between 0 and 1 (exclusive), got {eps}");
+        }
+        if !(confidence > 0.0 && confidence < 1.0) {
+            return plan_err!(
+                "The confidence of a count-min sketch must be between 0 and 1 (exclusive), got {confidence}"
+            );
+        }
+        let width = (2.0 / eps).ceil() as u64;
+        let depth = (-(1.0 - confidence).ln() / 2f64.ln()).ceil().max(1.0) as u64;
+        if width.saturating_mul(depth) > MAX_CELLS {
+            return plan_err!(
+                "A count-min sketch with eps {eps} and confidence {confidence} would have {depth} x {width} counters, more than {MAX_CELLS}"
+            );
+        }
+        Ok(Self::with_dimensions(depth as u32, width as u32, seed))
+    }
+
+    fn with_dimensions(depth: u32, width: u32, seed: i64) -> Self {
+        Self {
+            depth,
+            width,
+            seed,
+            total_count: 0,
+            counts: vec![0; depth as usize * width as usize],
+        }
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Number of values added to the sketch.
+    pub fn total_count(&self) -> u64 {
+        self.total_count
+    }
+
+    /// Adds the non-null values of `values`.
+    pub fn update(&mut self, values: &ArrayRef) -> Result<()> {
+        let hashes = self.hashes(values)?;
+        for (row, hash) in hashes.into_iter().enumerate() {
+            if values.is_valid(row) {
+                for index in counter_indexes(self.depth, self.width, hash) {
+                    self.counts[index] = self.counts[index].saturating_add(1);
+                }
+                self.total_count += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Estimates how many times each value of `values` was added, `None` for nulls.
+    pub fn estimate(&self, values: &ArrayRef) -> Result<Vec<Option<u64>>> {
+        let hashes = self.hashes(values)?;
+        Ok(hashes
+            .into_iter()
+            .enumerate()
+            .map(|(row, hash)| {
+                values.is_valid(row).then(|| {
+                    counter_indexes(self.depth, self.width, hash)
+                        .map(|index| self.counts[index])
+                        .min()
+                        .unwrap_or_default()
+                })
+            })
+            .collect())
+    }
+
+    /// Merges `other` into this sketch, which must have the same dimensions and seed.
+    pub fn merge(&mut self, other: &Self) -> Result<()> {
+        if (self.depth, self.width, self.seed) != (other.depth, other.width, other.seed) {
+            return exec_err!(
+                "Cannot merge count-min sketches with different dimensions or seeds: {} x {} with seed {} and {} x {} with seed {}",
+                self.depth,
+                self.width,
+                self.seed,
+                other.depth,
+                other.width,
+                other.seed
+            );
+        }
+        for (count, other_count) in self.counts.iter_mut().zip(&other.counts) {
+            *count = count.saturating_add(*other_count);
+        }
+        self.total_count = self.total_count.saturating_add(other.total_count);
+        Ok(())
+    }
+
+    pub fn serialize(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, FAMILY, SERIAL_VERSION);
+        buffer.reserve(24 + self.counts.len() * size_of::<u64>());
+        buffer.extend_from_slice(&self.depth.to_le_bytes());
+        buffer.extend_from_slice(&self.width.to_le_bytes());
+        buffer.extend_from_slice(&self.seed.to_le_bytes());
+        buffer.extend_from_slice(&self.total_count.to_le_bytes());
+        for count in &self.counts {
+            buffer.extend_from_slice(&count.to_le_bytes());
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = vec![];
+        self.serialize(&mut buffer);
+        buffer
+    }
+
+    /// Reads a sketch written by [`Self::serialize`], rejecting truncated or inconsistent input.
+    pub fn deserialize(bytes: &[u8]) -> Result<Self> {
+        let mut reader = SketchReader::new("count-min", bytes);
+        reader.read_header(FAMILY, SERIAL_VERSION)?;
+        let depth = reader.read_u32()?;
+        let width = reader.read_u32()?;
+        let seed = reader.read_u64()? as i64;
+        let total_count = reader.read_u64()?;
+        if depth == 0 || width == 0 || depth as u64 * width as u64 > MAX_CELLS {
+            return reader.invalid(format!("unsupported dimensions {depth} x {width}"));
+        }
+        let counts = reader
+            .read_bytes(depth as usize * width as usize * size_of::<u64>())?
+            .chunks_exact(size_of::<u64>())
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        // every value increments one counter per row
+        for row in counts.chunks_exact(width as usize) {
+            if row.iter().fold(0u64, |sum, count| sum.saturating_add(*count)) != total_count {
+                return reader.invalid("the counters do not add up to the total count");
+            }
+        }
+        reader.finish()?;
+        Ok(Self {
+            depth,
+            width,
+            seed,
+            total_count,
+            counts,
+        })
+    }
+
+    /// Size in bytes including `Self`.
+    pub fn size(&self) -> usize {
+        size_of::<Self>() + self.counts.capacity() * size_of::<u64>()
+    }
+
+    /// Returns the hash of each value of `values`, `h1` in the low half and `h2` in the high one. The hashes of nulls are
+    /// meaningless.
+    fn hashes(&self, values: &ArrayRef) -> Result<Vec<u64>> {
+        let seed = (self.seed ^ (self.seed >> 32)) as i32;
+        let hash_long = |item: i64| {
+            let h1 = hash_long(item, seed);
+            (h1, hash_long(item, h1))
+        };
+        let hash_bytes = |item: &[u8]| {
+            let h1 = hash_bytes(item, seed);
+            (h1, hash_bytes(item, h1))
+        };
+        let hashes = match values.data_type() {
+            DataType::Null => return Ok(vec![0; values.len()]),
+            DataType::Int64 => values
+                .as_primitive::<Int64Type>()
+                .values()
+                .iter()
+                .map(|item| hash_long(*item))
+                .collect::<Vec<_>>(),
+            DataType::UInt64 => values
+                .as_primitive::<UInt64Type>()
+                .values()
+                .iter()
+                .map(|item| hash_long(*item as i64))
+                .collect(),
+            DataType::Utf8 => values
+                .as_string::<i32>()
+                .iter()
+                .map(|item| hash_bytes(item.unwrap_or_default().as_bytes()))
+                .collect(),
+            DataType::Binary => values
+                .as_binary::<i32>()
+                .iter()
+                .map(|item| hash_bytes(item.unwrap_or_default()))
+                .collect(),
+            value_type => return exec_err!("Count-min sketches do not support values of type {value_type}"),
+        };
+        Ok(hashes
+            .into_iter()
+            .map(|(h1, h2)| h1 as u32 as u64 | (h2 as u32 as u64) << 32)
+            .collect())
+    }
+}
+
+/// Returns the index of the counter of each row for a value hashed to `hash`.
+fn counter_indexes(depth: u32, width: u32, hash: u64) -> impl Iterator<Item = usize> {
+    let h1 = hash as u32;
+    let h2 = (hash >> 32) as u32;
+    (0..depth).map(move |row| {
+        let column = h1.wrapping_add(row.wrapping_mul(h2)) % width;
+        row as usize * width as usize + column as usize
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use datafusion::arrow::array::{Int64Array, StringArray};
+
+    use super::*;
+
+    #[test]
+    fn test_dimensions() {
+        let sketch = CountMinSketch::try_new(0.01, 0.95, 1).unwrap();
+        assert_eq!((sketch.depth(), sketch.width()), (5, 200));
+        let sketch = CountMinSketch::try_new(0.5, 0.1, 1).unwrap();
+        assert_eq!((sketch.depth(), sketch.width()), (1, 4));
+
+        let err = CountMinSketch::try_new(0.0, 0.9, 1).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("The eps of a count-min sketch must be between 0 and 1"));
+        let err = CountMinSketch::try_new(0.1, 1.0, 1).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("The confidence of a count-min sketch must be between 0 and 1"));
+        let err = CountMinSketch::try_new(1e-9, 0.9, 1).unwrap_err();
+        assert!(err.to_string().contains("more than 16777216"));
+    }
+
+    #[test]
+    fn test_estimate() {
+        let mut sketch = CountMinSketch::try_new(0.001, 0.99, 42).unwrap();
+        let values: ArrayRef = Arc::new(Int64Array::from_iter((0..10_000i64).map(|i| Some(i % 100 * (i % 7)))));
+        sketch.update(&values).unwrap();
+        assert_eq!(sketch.total_count(), 10_000);
+
+        let probes: ArrayRef = Arc::new(Int64Array::from(vec![Some(0), Some(99 * 6), Some(-1), None]));
+        let estimates = sketch.estimate(&probes).unwrap();
+        // 0 is added once per multiple of 7 and once per multiple of 100
+        let exact = (0..10_000i64).filter(|i| i % 100 * (i % 7) == 0).count() as u64;
+        assert!((exact..=exact + 20).contains(&estimates[0].unwrap()));
+        assert!((14..=34).contains(&estimates[1].unwrap()));
+        assert!(estimates[2].unwrap() <= 20);
+        assert_eq!(estimates[3], None);
+    }
+
+    #[test]
+    fn test_merge_and_serialize() {
+        let strings: ArrayRef = Arc::new(StringArray::from(vec![Some("a"), Some("b"), None, Some("a")]));
+        let mut left = CountMinSketch::try_new(0.1, 0.9, 7).unwrap();
+        left.update(&strings).unwrap();
+        let mut right = CountMinSketch::try_new(0.1, 0.9, 7).unwrap();
+        right.update(&strings).unwrap();
+        left.merge(&right).unwrap();
+        assert_eq!(left.total_count(), 6);
+
+        let probes: ArrayRef = Arc::new(StringArray::from(vec!["a", "b"]));
+        let estimates = left.estimate(&probes).unwrap();
+        assert!(estimates[0].unwrap() >= 4 && estimates[1].unwrap() >= 2);
+
+        let bytes = left.to_bytes();
+        assert_eq!(bytes.len(), 2 + 24 + 4 * 20 * 8);
+        let read = CountMinSketch::deserialize(&bytes).unwrap();
+        assert_eq!(read, left);
+        assert_eq!(read.estimate(&probes).unwrap(), estimates);
+
+        let err = CountMinSketch::deserialize(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert!(err.to_string().contains("unexpected end of input"));
+        let mut corrupted = bytes.clone();
+        corrupted[18] += 1;
+        let err = CountMinSketch::deserialize(&corrupted).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("the counters do not add up to the total count"));
+
+        let other_seed = CountMinSketch::try_new(0.1, 0.9, 8).unwrap();
+        let err = left.merge(&other_seed).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Cannot merge count-min sketches with different dimensions or seeds"));
+    }
+
+    #[test]
+    fn test_stable_hashes() {
+        // the counters only depend on Spark's Murmur3 hashes, not on the build
+        let mut sketch = CountMinSketch::try_new(0.1, 0.9, 42).unwrap();
+        sketch
+            .update(&(Arc::new(Int64Array::from(vec![1, 2, 3])) as ArrayRef))
+            .unwrap();
+        sketch
+            .update(&(Arc::new(StringArray::from(vec!["Spark"])) as ArrayRef))
+            .unwrap();
+        let counters = |row: usize| {
+            sketch.counts[row * 20..(row + 1) * 20]
+                .iter()
+                .enumerate()
+                .filter(|(_, count)| **count > 0)
+                .map(|(index, count)| (index, *count))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(counters(0), [(4, 1), (5, 2), (7, 1)]);
+        assert_eq!(counters(3), [(1, 1), (8, 1), (13, 1), (14, 1)]);
+    }
+}
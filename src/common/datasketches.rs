@@ -0,0 +1,251 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use arrow::array::{Array, ArrayRef, AsArray};
+use arrow::datatypes::{DataType, Float64Type, Int64Type, UInt64Type};
+use datafusion::arrow;
+use datafusion::common::cast::as_binary_array;
+use datafusion::common::exec_err;
+use datafusion::error::Result;
+use datafusion::logical_expr::Accumulator;
+use datafusion::scalar::ScalarValue;
+
+use crate::common::sketch::DistinctSketch;
+
+/// Seed of the hash of the items in Apache DataSketches, which must be used to read sketches built by it.
+pub const DEFAULT_UPDATE_SEED: u64 = 9001;
+
+/// The 128-bit x64 variant of MurmurHash3, returning the two 64-bit halves of the hash.
+pub fn murmur3_x64_128(data: &[u8], seed: u64) -> (u64, u64) {
+    const C1: u64 = 0x87c3_7b91_1142_53d5;
+    const C2: u64 = 0x4cf5_ad43_2745_937f;
+
+    let mix_k1 = |k1: u64| k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2);
+    let mix_k2 = |k2: u64| k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1);
+
+    let mut h1 = seed;
+    let mut h2 = seed;
+    let blocks = data.chunks_exact(16);
+    let tail = blocks.remainder();
+    for block in blocks {
+        let k1 = u64::from_le_bytes(block[..8].try_into().unwrap());
+        let k2 = u64::from_le_bytes(block[8..].try_into().unwrap());
+
+        h1 ^= mix_k1(k1);
+        h1 = h1
+            .rotate_left(27)
+            .wrapping_add(h2)
+            .wrapping_mul(5)
+            .wrapping_add(0x52dc_e729);
+        h2 ^= mix_k2(k2);
+        h2 = h2
+            .rotate_left(31)
+            .wrapping_add(h1)
+            .wrapping_mul(5)
+            .wrapping_add(0x3849_5ab5);
+    }
+
+    let mut k1 = 0u64;
+    let mut k2 = 0u64;
+    for (index, byte) in tail.iter().enumerate() {
+        match index {
+            0..=7 => k1 ^= (*byte as u64) << (8 * index),
+            _ => k2 ^= (*byte as u64) << (8 * (index - 8)),
+        }
+    }
+    if tail.len() > 8 {
+        h2 ^= mix_k2(k2);
+    }
+    if !tail.is_empty() {
+        h1 ^= mix_k1(k1);
+    }
+
+    h1 ^= data.len() as u64;
+    h2 ^= data.len() as u64;
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    h1 = fmix64(h1);
+    h2 = fmix64(h2);
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    (h1, h2)
+}
+
+fn fmix64(mut k: u64) -> u64 {
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    k ^ (k >> 33)
+}
+
+/// Coerces the values hashed by [`for_each_item_hash`]: integers, dates, times and timestamps are hashed as a Java
+/// `long`, floats as a `double`, strings as their UTF-8 bytes and binaries as their bytes. Returns `None` for
+/// unsupported types.
+pub fn coerce_item_type(data_type: &DataType) -> Option<DataType> {
+    match data_type {
+        DataType::UInt64 => Some(DataType::UInt64),
+        DataType::Null => Some(DataType::Int64),
+        data_type if data_type.is_integer() || data_type.is_temporal() => Some(DataType::Int64),
+        data_type if data_type.is_floating() => Some(DataType::Float64),
+        DataType::Utf8
+        | DataType::LargeUtf8
+        | DataType::Utf8View
+        | DataType::Binary
+        | DataType::LargeBinary
+        | DataType::BinaryView => Some(data_type.clone()),
+        _ => None,
+    }
+}
+
+/// Calls `f` with the hash of each non-null item of an array coerced by [`coerce_item_type`], as Apache DataSketches
+/// computes it in `update(long)`, `update(double)`, `update(String)` and `update(byte[])`. Like DataSketches, empty
+/// strings and binaries are ignored, `-0.0` is hashed as `0.0` and all NaNs as the same value.
+pub fn for_each_item_hash(array: &ArrayRef, mut f: impl FnMut((u64, u64))) -> Result<()> {
+    let mut hash_bytes = |bytes: &[u8]| {
+        if !bytes.is_empty() {
+            f(murmur3_x64_128(bytes, DEFAULT_UPDATE_SEED))
+        }
+    };
+    match array.data_type() {
+        DataType::Int64 => array
+            .as_primitive::<Int64Type>()
+            .iter()
+            .flatten()
+            .for_each(|value| hash_bytes(&value.to_le_bytes())),
+        DataType::UInt64 => array
+            .as_primitive::<UInt64Type>()
+            .iter()
+            .flatten()
+            .for_each(|value| hash_bytes(&value.to_le_bytes())),
+        DataType::Float64 => array.as_primitive::<Float64Type>().iter().flatten().for_each(|value| {
+            let canonical = if value == 0.0 {
+                0.0
+            } else if value.is_nan() {
+                f64::NAN
+            } else {
+                value
+            };
+            hash_bytes(&canonical.to_bits().to_le_bytes())
+        }),
+        DataType::Utf8 => array
+            .as_string::<i32>()
+            .iter()
+            .flatten()
+            .for_each(|value| hash_bytes(value.as_bytes())),
+        DataType::LargeUtf8 => array
+            .as_string::<i64>()
+            .iter()
+            .flatten()
+            .for_each(|value| hash_bytes(value.as_bytes())),
+        DataType::Utf8View => array
+            .as_string_view()
+            .iter()
+            .flatten()
+            .for_each(|value| hash_bytes(value.as_bytes())),
+        DataType::Binary => array.as_binary::<i32>().iter().flatten().for_each(hash_bytes),
+        DataType::LargeBinary => array.as_binary::<i64>().iter().flatten().for_each(hash_bytes),
+        DataType::BinaryView => array.as_binary_view().iter().flatten().for_each(hash_bytes),
+        data_type => return exec_err!("Unsupported data type {data_type} for a DataSketches sketch"),
+    }
+    Ok(())
+}
+
+/// Accumulator of the aggregates building a [`DistinctSketch`] of items, such as `hll_sketch_agg`, or the union of
+/// serialized sketches, such as `hll_union_agg`. Its state and result are the serialized sketch.
+#[derive(Debug)]
+pub struct DistinctSketchAccumulator<S> {
+    sketch: S,
+    /// Whether the input holds serialized sketches rather than items.
+    union: bool,
+}
+
+impl<S: DistinctSketch> DistinctSketchAccumulator<S> {
+    pub fn new(sketch: S, union: bool) -> Self {
+        Self { sketch, union }
+    }
+
+    fn merge_sketches(&mut self, sketches: &ArrayRef) -> Result<()> {
+        for bytes in as_binary_array(sketches)?.iter().flatten() {
+            self.sketch.merge(&S::deserialize(bytes)?);
+        }
+        Ok(())
+    }
+}
+
+impl<S: DistinctSketch> Accumulator for DistinctSketchAccumulator<S> {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        if self.union {
+            return self.merge_sketches(&values[0]);
+        }
+        for_each_item_hash(&values[0], |hash| self.sketch.update_hash(hash))
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        self.merge_sketches(&states[0])
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        Ok(vec![self.evaluate()?])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        Ok(ScalarValue::Binary(Some(self.sketch.to_bytes())))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) - std::mem::size_of_val(&self.sketch) + self.sketch.size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::array::{Float64Array, Int64Array, StringArray};
+
+    use super::*;
+
+    #[test]
+    fn test_murmur3_x64_128() {
+        assert_eq!(murmur3_x64_128(b"", 0), (0, 0));
+        assert_eq!(
+            murmur3_x64_128(b"The quick brown fox jumps over the lazy dog", 0),
+            (0xe34b_bc7b_bc07_1b6c, 0x7a43_3ca9_c49a_9347)
+        );
+    }
+
+    #[test]
+    fn test_item_hashes() {
+        let hashes = |array: ArrayRef| {
+            let mut hashes = vec![];
+            for_each_item_hash(&array, |hash| hashes.push(hash)).unwrap();
+            hashes
+        };
+
+        let longs = hashes(Arc::new(Int64Array::from(vec![Some(1), None, Some(-1)])));
+        assert_eq!(longs.len(), 2);
+        assert_eq!(longs[0], murmur3_x64_128(&1i64.to_le_bytes(), DEFAULT_UPDATE_SEED));
+
+        let doubles = hashes(Arc::new(Float64Array::from(vec![0.0, -0.0, f64::NAN, -f64::NAN])));
+        assert_eq!(doubles[0], doubles[1]);
+        assert_eq!(doubles[2], doubles[3]);
+        assert_ne!(doubles[0], doubles[2]);
+
+        let strings = hashes(Arc::new(StringArray::from(vec!["a", "", "a"])));
+        assert_eq!(strings, vec![murmur3_x64_128(b"a", DEFAULT_UPDATE_SEED); 2]);
+    }
+}
@@ -0,0 +1,291 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use ahash::RandomState;
+use arrow::array::{Array, ArrayRef, Float64Array, Int64Array, UInt64Array};
+use arrow::compute::{filter, is_not_null};
+use arrow::datatypes::{DataType, Float64Type, Int64Type, UInt64Type};
+use datafusion::arrow;
+use datafusion::common::cast::as_primitive_array;
+use datafusion::common::{exec_err, plan_err};
+use datafusion::error::Result;
+use datafusion::physical_expr::binary_map::OutputType;
+
+use crate::common::collections::{ArrowBytesMap, ArrowBytesViewMap};
+
+/// The distinct values of a column, each identified by its position in the array returned by [`Self::take_values`].
+///
+/// Strings and binaries are kept in an [`ArrowBytesMap`] or [`ArrowBytesViewMap`], and numbers by their 64 bits:
+/// the values must be coerced by [`coerce_item_type`](crate::common::datasketches::coerce_item_type) first. As for
+/// DataSketches, `-0.0` is the same value as `0.0` and all NaNs are the same value.
+#[derive(Debug)]
+pub enum DistinctValues {
+    Native {
+        ids: HashMap<u64, usize, RandomState>,
+        values: Vec<u64>,
+        data_type: DataType,
+    },
+    Bytes(ArrowBytesMap<i32, usize>),
+    LargeBytes(ArrowBytesMap<i64, usize>),
+    BytesView(ArrowBytesViewMap<usize>),
+}
+
+impl DistinctValues {
+    pub fn try_new(data_type: &DataType) -> Result<Self> {
+        Ok(match data_type {
+            DataType::Int64 | DataType::UInt64 | DataType::Float64 => Self::Native {
+                ids: HashMap::default(),
+                values: vec![],
+                data_type: data_type.clone(),
+            },
+            DataType::Utf8 => Self::Bytes(ArrowBytesMap::new(OutputType::Utf8)),
+            DataType::Binary => Self::Bytes(ArrowBytesMap::new(OutputType::Binary)),
+            DataType::LargeUtf8 => Self::LargeBytes(ArrowBytesMap::new(OutputType::Utf8)),
+            DataType::LargeBinary => Self::LargeBytes(ArrowBytesMap::new(OutputType::Binary)),
+            DataType::Utf8View => Self::BytesView(ArrowBytesViewMap::new(OutputType::Utf8View)),
+            DataType::BinaryView => Self::BytesView(ArrowBytesViewMap::new(OutputType::BinaryView)),
+            data_type => return plan_err!("Unsupported data type {data_type} for distinct values"),
+        })
+    }
+
+    /// Inserts the values, calling `f` with the index and the id of each non-null value.
+    pub fn insert(&mut self, values: &ArrayRef, mut f: impl FnMut(usize, usize)) -> Result<()> {
+        match self {
+            Self::Native {
+                ids, values: distinct, ..
+            } => {
+                for_each_native_bits(values, |index, bits| {
+                    let id = *ids.entry(bits).or_insert_with(|| {
+                        distinct.push(bits);
+                        distinct.len() - 1
+                    });
+                    f(index, id)
+                })?;
+            }
+            // the maps also give an id to the null value, which is then at its position in the array of the values
+            Self::Bytes(map) => {
+                let mut next_id = map.len();
+                let mut index = 0;
+                map.insert_if_new(
+                    values,
+                    |_| new_id(&mut next_id),
+                    |id| observe(values, &mut index, id, &mut f),
+                );
+            }
+            Self::LargeBytes(map) => {
+                let mut next_id = map.len();
+                let mut index = 0;
+                map.insert_if_new(
+                    values,
+                    |_| new_id(&mut next_id),
+                    |id| observe(values, &mut index, id, &mut f),
+                );
+            }
+            Self::BytesView(map) => {
+                let mut next_id = map.len();
+                let mut index = 0;
+                map.insert_if_new(
+                    values,
+                    |_| new_id(&mut next_id),
+                    |id| observe(values, &mut index, id, &mut f),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of distinct non-null values.
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Native { values, .. } => values.len(),
+            Self::Bytes(map) => map.non_null_len(),
+            Self::LargeBytes(map) => map.non_null_len(),
+            Self::BytesView(map) => map.non_null_len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the distinct values ordered by id, which contain a null if one was inserted, and clears them.
+    pub fn take_values(&mut self) -> ArrayRef {
+        match self {
+            Self::Native { ids, values, data_type } => {
+                ids.clear();
+                let values = std::mem::take(values);
+                match data_type {
+                    DataType::Int64 => {
+                        Arc::new(Int64Array::from_iter_values(values.into_iter().map(|bits| bits as i64)))
+                    }
+                    DataType::UInt64 => Arc::new(UInt64Array::from(values)),
+                    _ => Arc::new(Float64Array::from_iter_values(values.into_iter().map(f64::from_bits))),
+                }
+            }
+            Self::Bytes(map) => map.take().into_state(),
+            Self::LargeBytes(map) => map.take().into_state(),
+            Self::BytesView(map) => map.take().into_state(),
+        }
+    }
+
+    /// Returns the distinct non-null values ordered by id, and keeps them.
+    pub fn values(&mut self) -> Result<ArrayRef> {
+        let values = self.take_values();
+        // the values have the same ids when inserted again in order
+        self.insert(&values, |_, _| ())?;
+        non_null_values(values)
+    }
+
+    /// Size in bytes, not including `Self`.
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Native { ids, values, .. } => {
+                ids.capacity() * (std::mem::size_of::<(u64, usize)>() + 1)
+                    + values.capacity() * std::mem::size_of::<u64>()
+            }
+            Self::Bytes(map) => map.size(),
+            Self::LargeBytes(map) => map.size(),
+            Self::BytesView(map) => map.size(),
+        }
+    }
+}
+
+/// Removes the null value from the distinct values returned by [`DistinctValues::take_values`].
+pub fn non_null_values(values: ArrayRef) -> Result<ArrayRef> {
+    match values.null_count() {
+        0 => Ok(values),
+        _ => Ok(filter(&values, &is_not_null(&values)?)?),
+    }
+}
+
+fn new_id(next_id: &mut usize) -> usize {
+    *next_id += 1;
+    *next_id - 1
+}
+
+fn observe(values: &ArrayRef, index: &mut usize, id: usize, f: &mut impl FnMut(usize, usize)) {
+    if values.is_valid(*index) {
+        f(*index, id);
+    }
+    *index += 1;
+}
+
+/// Calls `f` with the index and the 64 bits of each non-null number.
+fn for_each_native_bits(values: &ArrayRef, mut f: impl FnMut(usize, u64)) -> Result<()> {
+    match values.data_type() {
+        DataType::Int64 => as_primitive_array::<Int64Type>(values)?
+            .iter()
+            .enumerate()
+            .for_each(|(index, value)| value.into_iter().for_each(|value| f(index, value as u64))),
+        DataType::UInt64 => as_primitive_array::<UInt64Type>(values)?
+            .iter()
+            .enumerate()
+            .for_each(|(index, value)| value.into_iter().for_each(|value| f(index, value))),
+        DataType::Float64 => {
+            as_primitive_array::<Float64Type>(values)?
+                .iter()
+                .enumerate()
+                .for_each(|(index, value)| {
+                    value.into_iter().for_each(|value| {
+                        let canonical = if value == 0.0 {
+                            0.0
+                        } else if value.is_nan() {
+                            f64::NAN
+                        } else {
+                            value
+                        };
+                        f(index, canonical.to_bits())
+                    })
+                })
+        }
+        data_type => return exec_err!("Unsupported data type {data_type} for distinct numbers"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{AsArray, StringArray, StringViewArray};
+
+    use super::*;
+
+    fn insert(distinct: &mut DistinctValues, values: ArrayRef) -> Vec<(usize, usize)> {
+        let mut ids = vec![];
+        distinct.insert(&values, |index, id| ids.push((index, id))).unwrap();
+        ids
+    }
+
+    #[test]
+    fn test_native_ids() {
+        let mut distinct = DistinctValues::try_new(&DataType::Float64).unwrap();
+        let ids = insert(
+            &mut distinct,
+            Arc::new(Float64Array::from(vec![
+                Some(1.0),
+                None,
+                Some(-0.0),
+                Some(f64::NAN),
+                Some(0.0),
+            ])),
+        );
+        assert_eq!(ids, vec![(0, 0), (2, 1), (3, 2), (4, 1)]);
+        let ids = insert(&mut distinct, Arc::new(Float64Array::from(vec![-f64::NAN, 2.0])));
+        assert_eq!(ids, vec![(0, 2), (1, 3)]);
+        assert_eq!(distinct.len(), 4);
+
+        let values = distinct.take_values();
+        assert_eq!(values.as_primitive::<Float64Type>().values()[..2], [1.0, 0.0]);
+        assert_eq!(values.len(), 4);
+        assert!(distinct.is_empty());
+    }
+
+    #[test]
+    fn test_bytes_ids() {
+        for values in [
+            Arc::new(StringArray::from(vec![
+                Some("a"),
+                None,
+                Some("a long string value"),
+                Some("a"),
+            ])) as ArrayRef,
+            Arc::new(StringViewArray::from(vec![
+                Some("a"),
+                None,
+                Some("a long string value"),
+                Some("a"),
+            ])),
+        ] {
+            let mut distinct = DistinctValues::try_new(values.data_type()).unwrap();
+            // the null value takes the id 1
+            assert_eq!(insert(&mut distinct, values.clone()), vec![(0, 0), (2, 2), (3, 0)]);
+            assert_eq!(distinct.len(), 2);
+
+            let taken = distinct.take_values();
+            assert_eq!(taken.len(), 3);
+            assert!(taken.is_null(1));
+            assert!(distinct.is_empty());
+
+            // inserting the values again gives the same ids
+            let ids = insert(&mut distinct, taken);
+            assert_eq!(ids, vec![(0, 0), (2, 2)]);
+            assert_eq!(distinct.values().unwrap().len(), 2);
+            assert_eq!(distinct.len(), 2);
+        }
+    }
+}
@@ -0,0 +1,24 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use arrow::array::{Array, BooleanArray};
+use datafusion::arrow;
+
+/// Returns whether the row `index` of a batch of a `GroupsAccumulator` is selected by its `opt_filter`, which does not
+/// select the rows where it is false or null.
+pub fn is_selected(opt_filter: Option<&BooleanArray>, index: usize) -> bool {
+    opt_filter.map_or(true, |filter| filter.is_valid(index) && filter.value(index))
+}
@@ -0,0 +1,138 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::mem::size_of;
+
+/// The streaming histogram of Hive's and Spark's `histogram_numeric`, from Ben-Haim and Tom-Tov's "A Streaming
+/// Parallel Decision Tree Algorithm".
+///
+/// The histogram keeps at most `max_bins` bins, each a centroid `x` with the number `y` of values it stands for. A new
+/// value is added as a bin of its own, or to the bin with the same centroid, and while there are too many bins the two
+/// closest ones are replaced by their weighted average. Unlike Hive, which breaks ties between equally close pairs at
+/// random, the first pair is merged so that results are reproducible.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumericHistogram {
+    max_bins: usize,
+    /// `(x, y)` sorted by `x`
+    bins: Vec<(f64, f64)>,
+}
+
+impl NumericHistogram {
+    pub fn new(max_bins: usize) -> Self {
+        Self {
+            max_bins: max_bins.max(1),
+            bins: vec![],
+        }
+    }
+
+    /// The `(x, y)` bins sorted by `x`.
+    pub fn bins(&self) -> &[(f64, f64)] {
+        &self.bins
+    }
+
+    /// Adds a value, ignoring NaN.
+    pub fn add(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+        let index = self.bins.partition_point(|(x, _)| *x < value);
+        match self.bins.get_mut(index) {
+            Some((x, y)) if *x == value => *y += 1.0,
+            _ => {
+                self.bins.insert(index, (value, 1.0));
+                self.trim();
+            }
+        }
+    }
+
+    /// Merges the bins of another histogram with the same number of bins, e.g. read back from a partial state.
+    pub fn merge(&mut self, bins: impl IntoIterator<Item = (f64, f64)>) {
+        self.bins.extend(bins);
+        self.bins.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        self.trim();
+    }
+
+    /// Size in bytes including `Self`.
+    pub fn size(&self) -> usize {
+        size_of::<Self>() + self.bins.capacity() * size_of::<(f64, f64)>()
+    }
+
+    fn trim(&mut self) {
+        while self.bins.len() > self.max_bins {
+            let index = self
+                .bins
+                .windows(2)
+                .enumerate()
+                .min_by(|(_, a), (_, b)| (a[1].0 - a[0].0).total_cmp(&(b[1].0 - b[0].0)))
+                .map(|(index, _)| index)
+                .unwrap();
+            let (x1, y1) = self.bins.remove(index + 1);
+            let (x0, y0) = self.bins[index];
+            let y = y0 + y1;
+            self.bins[index] = ((x0 * y0 + x1 * y1) / y, y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_when_few_values() {
+        let mut histogram = NumericHistogram::new(5);
+        for value in [3.0, 1.0, 2.0, 3.0, f64::NAN, 1.0, 3.0] {
+            histogram.add(value);
+        }
+        assert_eq!(histogram.bins(), [(1.0, 2.0), (2.0, 1.0), (3.0, 3.0)]);
+    }
+
+    #[test]
+    fn test_merges_closest_bins() {
+        let mut histogram = NumericHistogram::new(3);
+        for value in [0.0, 10.0, 11.0, 20.0, 30.0] {
+            histogram.add(value);
+        }
+        // 10 and 11 are merged when 20 is added, then 10.5 and 20, closer than 20 and 30, when 30 is added
+        assert_eq!(histogram.bins(), [(0.0, 1.0), (41.0 / 3.0, 3.0), (30.0, 1.0)]);
+
+        let mut histogram = NumericHistogram::new(2);
+        (0..1000).for_each(|i| histogram.add((i % 2 * 100 + i % 5) as f64));
+        let [(x0, y0), (x1, y1)] = histogram.bins() else {
+            panic!("expected 2 bins, got {:?}", histogram.bins());
+        };
+        assert!((x0 - 2.0).abs() < 1e-9 && (x1 - 102.0).abs() < 1e-9);
+        assert_eq!((*y0, *y1), (500.0, 500.0));
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut left = NumericHistogram::new(2);
+        (0..100).for_each(|i| left.add(i as f64));
+        let mut right = NumericHistogram::new(2);
+        (0..100).for_each(|i| right.add((i + 1000) as f64));
+        left.merge(right.bins().to_vec());
+
+        let total: f64 = left.bins().iter().map(|(_, y)| y).sum();
+        assert_eq!(total, 200.0);
+        assert_eq!(left.bins().len(), 2);
+        assert!((left.bins()[0].0 - 49.5).abs() < 1e-9 && (left.bins()[1].0 - 1049.5).abs() < 1e-9);
+
+        let mut empty = NumericHistogram::new(2);
+        empty.merge(left.bins().to_vec());
+        assert_eq!(empty, left);
+    }
+}
@@ -0,0 +1,754 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::collections::HashSet;
+
+use datafusion::error::Result;
+
+use crate::common::sketch::{DistinctSketch, Sketch, SketchReader};
+
+/// Default `lg_k` of an [`HllSketch`], as in Apache DataSketches and Spark.
+pub const DEFAULT_LG_K: u8 = 12;
+/// Smallest `lg_k` accepted by an [`HllSketch`].
+pub const MIN_LG_K: u8 = 4;
+/// Largest `lg_k` accepted by an [`HllSketch`].
+pub const MAX_LG_K: u8 = 21;
+
+const SERIAL_VERSION: u8 = 1;
+const FAMILY_ID: u8 = 7;
+
+const LIST_PREAMBLE_INTS: u8 = 2;
+const SET_PREAMBLE_INTS: u8 = 3;
+const HLL_PREAMBLE_INTS: u8 = 10;
+
+const MODE_LIST: u8 = 0;
+const MODE_SET: u8 = 1;
+const MODE_HLL: u8 = 2;
+
+const TYPE_HLL_4: u8 = 0;
+const TYPE_HLL_6: u8 = 1;
+const TYPE_HLL_8: u8 = 2;
+
+const FLAG_BIG_ENDIAN: u8 = 1;
+const FLAG_EMPTY: u8 = 4;
+const FLAG_COMPACT: u8 = 8;
+const FLAG_OUT_OF_ORDER: u8 = 16;
+
+const LG_LIST_INTS: u8 = 3;
+const LG_INIT_SET_INTS: u8 = 5;
+const KEY_BITS: u32 = 26;
+const KEY_MASK: u32 = (1 << KEY_BITS) - 1;
+const AUX_TOKEN: u8 = 15;
+
+/// A HyperLogLog sketch estimating the number of distinct items, binary compatible with the `HllSketch` of
+/// Apache DataSketches.
+///
+/// Items are hashed into coupons made of a 26-bit address and the number of leading zeros of the hash plus one.
+/// Like DataSketches, the sketch first keeps the distinct coupons, in the `LIST` then `SET` modes, and switches to
+/// an array of `2^lg_k` registers in the `HLL` mode once the coupons would take more memory than the registers. The
+/// relative standard error is then about `1.04 / sqrt(2^lg_k)`.
+///
+/// The estimate of a sketch built from items is the HIP estimate, as in DataSketches. After a union the HIP estimate
+/// is no longer valid and the registers are used instead, with the estimator of Ertl ("New cardinality estimation
+/// algorithms for HyperLogLog sketches") where DataSketches uses interpolation tables, so the estimates of such
+/// sketches may differ slightly between the two libraries.
+///
+/// # Binary format
+///
+/// [`Sketch::serialize`] writes the compact little-endian format of DataSketches, with an 8-byte preamble:
+///
+/// | Byte | Content                                                                           |
+/// |------|-----------------------------------------------------------------------------------|
+/// | 0    | Preamble size in 4-byte integers: 2 (`LIST`), 3 (`SET`) or 10 (`HLL`)             |
+/// | 1    | Serial version, 1                                                                 |
+/// | 2    | Family, 7                                                                         |
+/// | 3    | `lg_k`                                                                            |
+/// | 4    | Log2 of the size of the coupon array (`LIST` and `SET`) or of the `HLL_4` aux map  |
+/// | 5    | Flags: 4 empty, 8 compact, 16 out of order, 1 big-endian (unsupported)            |
+/// | 6    | Number of coupons (`LIST`) or minimum register value (`HLL`)                      |
+/// | 7    | Mode (0 `LIST`, 1 `SET`, 2 `HLL`) plus 4 times the target type (0 `HLL_4`, 1 `HLL_6`, 2 `HLL_8`) |
+///
+/// - `LIST`: the coupons as `u32` from byte 8.
+/// - `SET`: the number of coupons as `u32` at byte 8, then the coupons.
+/// - `HLL`: the HIP estimate, `kxq0` and `kxq1` as `f64` at bytes 8, 16 and 24, the number of registers at the
+///   minimum value as `u32` at byte 32, the number of aux entries as `u32` at byte 36 and the registers from byte 40.
+///
+/// Sketches are always written with the `HLL_8` target type, one byte per register. All modes and target types,
+/// compact or updatable, can be read.
+#[derive(Debug, Clone)]
+pub struct HllSketch {
+    lg_k: u8,
+    mode: HllMode,
+    /// Whether the sketch is a union, whose HIP estimate is not valid.
+    out_of_order: bool,
+}
+
+#[derive(Debug, Clone)]
+enum HllMode {
+    /// The distinct coupons of the `LIST` and `SET` modes.
+    Coupons(HashSet<u32>),
+    Registers(Registers),
+}
+
+#[derive(Debug, Clone)]
+struct Registers {
+    values: Vec<u8>,
+    hip: f64,
+    /// Sums of `2^-value` over the registers with values below and from 32.
+    kxq0: f64,
+    kxq1: f64,
+    zeros: u32,
+}
+
+impl Registers {
+    fn new(lg_k: u8) -> Self {
+        let k = 1usize << lg_k;
+        Self {
+            values: vec![0; k],
+            hip: 0.0,
+            kxq0: k as f64,
+            kxq1: 0.0,
+            zeros: k as u32,
+        }
+    }
+
+    /// Builds registers from their values, with `hip` as the HIP estimate.
+    fn from_values(values: Vec<u8>, hip: f64) -> Self {
+        let mut registers = Self {
+            values,
+            hip,
+            kxq0: 0.0,
+            kxq1: 0.0,
+            zeros: 0,
+        };
+        for value in &registers.values {
+            match *value < 32 {
+                true => registers.kxq0 += inv_pow2(*value),
+                false => registers.kxq1 += inv_pow2(*value),
+            }
+            if *value == 0 {
+                registers.zeros += 1;
+            }
+        }
+        registers
+    }
+
+    fn update(&mut self, slot: usize, value: u8) {
+        let old = self.values[slot];
+        if value <= old {
+            return;
+        }
+        self.values[slot] = value;
+        self.hip += self.values.len() as f64 / (self.kxq0 + self.kxq1);
+        match old < 32 {
+            true => self.kxq0 -= inv_pow2(old),
+            false => self.kxq1 -= inv_pow2(old),
+        }
+        match value < 32 {
+            true => self.kxq0 += inv_pow2(value),
+            false => self.kxq1 += inv_pow2(value),
+        }
+        if old == 0 {
+            self.zeros -= 1;
+        }
+    }
+
+    /// Returns the registers of a sketch with a smaller `lg_k`, keeping the largest value of the folded slots.
+    fn fold(&self, lg_k: u8) -> Vec<u8> {
+        let mut values = vec![0; 1 << lg_k];
+        let mask = values.len() - 1;
+        for (slot, value) in self.values.iter().enumerate() {
+            values[slot & mask] = values[slot & mask].max(*value);
+        }
+        values
+    }
+
+    /// The estimator of Ertl for registers holding values up to 63.
+    fn estimate(&self) -> f64 {
+        const Q: usize = 62;
+        let m = self.values.len() as f64;
+        let mut histogram = [0u32; Q + 2];
+        for value in &self.values {
+            histogram[*value as usize] += 1;
+        }
+
+        let mut z = m * tau(1.0 - histogram[Q + 1] as f64 / m);
+        for count in histogram[1..=Q].iter().rev() {
+            z = 0.5 * (z + *count as f64);
+        }
+        z += m * sigma(histogram[0] as f64 / m);
+        m * m / (2.0 * std::f64::consts::LN_2 * z)
+    }
+}
+
+fn inv_pow2(value: u8) -> f64 {
+    f64::powi(2.0, -(value as i32))
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let mut y = 1.0;
+    let mut z = x;
+    loop {
+        x *= x;
+        let previous = z;
+        z += x * y;
+        y += y;
+        if z == previous {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let mut y = 1.0;
+    let mut z = 1.0 - x;
+    loop {
+        x = x.sqrt();
+        let previous = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if z == previous {
+            return z / 3.0;
+        }
+    }
+}
+
+/// Builds the coupon of the two halves of a 128-bit item hash, as DataSketches does.
+fn coupon(hash: (u64, u64)) -> u32 {
+    let address = hash.0 as u32 & KEY_MASK;
+    let value = hash.1.leading_zeros().min(62) + 1;
+    (value << KEY_BITS) | address
+}
+
+impl HllSketch {
+    pub fn new(lg_k: u8) -> Self {
+        debug_assert!((MIN_LG_K..=MAX_LG_K).contains(&lg_k));
+        Self {
+            lg_k,
+            mode: HllMode::Coupons(HashSet::new()),
+            out_of_order: false,
+        }
+    }
+
+    pub fn lg_k(&self) -> u8 {
+        self.lg_k
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match &self.mode {
+            HllMode::Coupons(coupons) => coupons.is_empty(),
+            HllMode::Registers(registers) => registers.zeros as usize == registers.values.len(),
+        }
+    }
+
+    fn update_coupon(&mut self, coupon: u32) {
+        match &mut self.mode {
+            HllMode::Coupons(coupons) => {
+                if coupons.insert(coupon) && coupons.len() > self.max_coupons() {
+                    self.promote();
+                }
+            }
+            HllMode::Registers(registers) => {
+                let slot = (coupon & ((1 << self.lg_k) - 1)) as usize;
+                registers.update(slot, (coupon >> KEY_BITS) as u8);
+            }
+        }
+    }
+
+    /// Largest number of coupons kept before switching to registers: the `LIST` of 8 coupons for small sketches,
+    /// else a `SET` whose hash table would be larger than `2^(lg_k - 3)` integers.
+    fn max_coupons(&self) -> usize {
+        match self.lg_k < 8 {
+            true => (1 << LG_LIST_INTS) - 1,
+            false => 3 << (self.lg_k - 5),
+        }
+    }
+
+    /// Switches from coupons to registers, starting the HIP estimate from the estimate of the coupons.
+    fn promote(&mut self) {
+        let HllMode::Coupons(coupons) = &self.mode else {
+            return;
+        };
+        let estimate = coupons_estimate(coupons.len());
+        let mut registers = Registers::new(self.lg_k);
+        let mask = (1 << self.lg_k) - 1;
+        for coupon in coupons {
+            registers.update((coupon & mask) as usize, (coupon >> KEY_BITS) as u8);
+        }
+        registers.hip = estimate;
+        self.mode = HllMode::Registers(registers);
+    }
+
+    /// Estimates the number of distinct items.
+    pub fn estimate(&self) -> f64 {
+        match &self.mode {
+            HllMode::Coupons(coupons) => coupons_estimate(coupons.len()),
+            HllMode::Registers(registers) if !self.out_of_order => registers.hip,
+            HllMode::Registers(registers) => registers.estimate(),
+        }
+    }
+
+    /// Lowers `lg_k`, which makes the sketch a union.
+    fn downsample(&mut self, lg_k: u8) {
+        if let HllMode::Registers(registers) = &self.mode {
+            self.mode = HllMode::Registers(Registers::from_values(registers.fold(lg_k), 0.0));
+            self.out_of_order = true;
+        }
+        self.lg_k = lg_k;
+        if let HllMode::Coupons(coupons) = &self.mode {
+            if coupons.len() > self.max_coupons() {
+                self.promote();
+            }
+        }
+    }
+}
+
+/// Estimates the number of distinct items from the number of distinct coupons, which is a little lower because two
+/// items have the same coupon with a probability of `1/3` of the probability that they have the same address.
+fn coupons_estimate(coupons: usize) -> f64 {
+    let coupons = coupons as f64;
+    coupons + coupons * coupons / (6.0 * (1u64 << KEY_BITS) as f64)
+}
+
+impl Sketch for HllSketch {
+    /// Computes the union of both sketches. Its `lg_k` is the smallest one, except that an empty sketch takes the
+    /// `lg_k` of the sketch merged into it.
+    fn merge(&mut self, other: &Self) {
+        if other.is_empty() {
+            return;
+        }
+        if self.is_empty() {
+            *self = other.clone();
+            return;
+        }
+        if other.lg_k < self.lg_k {
+            self.downsample(other.lg_k);
+        }
+
+        match (&mut self.mode, &other.mode) {
+            (_, HllMode::Coupons(coupons)) => {
+                for coupon in coupons {
+                    self.update_coupon(*coupon);
+                }
+                self.out_of_order |= other.out_of_order;
+            }
+            (HllMode::Coupons(coupons), HllMode::Registers(registers)) => {
+                let coupons = std::mem::take(coupons);
+                self.mode = HllMode::Registers(Registers::from_values(registers.fold(self.lg_k), 0.0));
+                for coupon in coupons {
+                    self.update_coupon(coupon);
+                }
+                self.out_of_order = true;
+            }
+            (HllMode::Registers(registers), HllMode::Registers(other_registers)) => {
+                let mut values = std::mem::take(&mut registers.values);
+                for (value, other_value) in values.iter_mut().zip(other_registers.fold(self.lg_k)) {
+                    *value = (*value).max(other_value);
+                }
+                *registers = Registers::from_values(values, 0.0);
+                self.out_of_order = true;
+            }
+        }
+    }
+
+    fn serialize(&self, buffer: &mut Vec<u8>) {
+        let mut flags = FLAG_COMPACT;
+        if self.out_of_order {
+            flags |= FLAG_OUT_OF_ORDER;
+        }
+        match &self.mode {
+            HllMode::Coupons(coupons) if coupons.len() < 1 << LG_LIST_INTS => {
+                if coupons.is_empty() {
+                    flags |= FLAG_EMPTY;
+                }
+                let mode = MODE_LIST | (TYPE_HLL_8 << 2);
+                buffer.extend_from_slice(&[
+                    LIST_PREAMBLE_INTS,
+                    SERIAL_VERSION,
+                    FAMILY_ID,
+                    self.lg_k,
+                    LG_LIST_INTS,
+                    flags,
+                    coupons.len() as u8,
+                    mode,
+                ]);
+                write_coupons(buffer, coupons);
+            }
+            HllMode::Coupons(coupons) => {
+                let mut lg_array = LG_INIT_SET_INTS;
+                while 4 * coupons.len() > 3 << lg_array {
+                    lg_array += 1;
+                }
+                let mode = MODE_SET | (TYPE_HLL_8 << 2);
+                buffer.extend_from_slice(&[
+                    SET_PREAMBLE_INTS,
+                    SERIAL_VERSION,
+                    FAMILY_ID,
+                    self.lg_k,
+                    lg_array,
+                    flags,
+                    0,
+                    mode,
+                ]);
+                buffer.extend_from_slice(&(coupons.len() as u32).to_le_bytes());
+                write_coupons(buffer, coupons);
+            }
+            HllMode::Registers(registers) => {
+                let mode = MODE_HLL | (TYPE_HLL_8 << 2);
+                buffer.extend_from_slice(&[
+                    HLL_PREAMBLE_INTS,
+                    SERIAL_VERSION,
+                    FAMILY_ID,
+                    self.lg_k,
+                    0,
+                    flags,
+                    0,
+                    mode,
+                ]);
+                for value in [registers.hip, registers.kxq0, registers.kxq1] {
+                    buffer.extend_from_slice(&value.to_le_bytes());
+                }
+                buffer.extend_from_slice(&registers.zeros.to_le_bytes());
+                buffer.extend_from_slice(&0u32.to_le_bytes());
+                buffer.extend_from_slice(&registers.values);
+            }
+        }
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self> {
+        let mut reader = SketchReader::new("HLL", bytes);
+        let [preamble_ints, serial_version, family, lg_k, lg_array, flags, count_or_min, mode] =
+            *reader.read_bytes(8)?
+        else {
+            unreachable!()
+        };
+        if family != FAMILY_ID {
+            return reader.invalid(format!("unexpected sketch family {family}"));
+        }
+        if serial_version != SERIAL_VERSION {
+            return reader.invalid(format!("unsupported version {serial_version}"));
+        }
+        if !(MIN_LG_K..=MAX_LG_K).contains(&lg_k) {
+            return reader.invalid(format!("lg_k must be between {MIN_LG_K} and {MAX_LG_K}, got {lg_k}"));
+        }
+        if flags & FLAG_BIG_ENDIAN != 0 {
+            return reader.invalid("big-endian sketches are not supported");
+        }
+        let compact = flags & FLAG_COMPACT != 0;
+        let out_of_order = flags & FLAG_OUT_OF_ORDER != 0;
+        let (current_mode, target_type) = (mode & 3, (mode >> 2) & 3);
+        let expected_preamble_ints = match current_mode {
+            MODE_LIST => LIST_PREAMBLE_INTS,
+            MODE_SET => SET_PREAMBLE_INTS,
+            MODE_HLL => HLL_PREAMBLE_INTS,
+            _ => return reader.invalid(format!("unexpected mode {current_mode}")),
+        };
+        if preamble_ints != expected_preamble_ints {
+            return reader.invalid(format!("unexpected preamble size {preamble_ints}"));
+        }
+
+        let mut sketch = Self::new(lg_k);
+        sketch.out_of_order = out_of_order;
+        if flags & FLAG_EMPTY != 0 {
+            return Ok(sketch);
+        }
+
+        match current_mode {
+            MODE_LIST | MODE_SET => {
+                let count = match current_mode {
+                    MODE_LIST => count_or_min as usize,
+                    _ => reader.read_u32()? as usize,
+                };
+                let stored = match compact {
+                    true => count,
+                    false if lg_array <= 26 => 1 << lg_array,
+                    false => return reader.invalid(format!("unexpected coupon array size 2^{lg_array}")),
+                };
+                let mut coupons = HashSet::with_capacity(count);
+                for _ in 0..stored {
+                    let coupon = reader.read_u32()?;
+                    if coupon == 0 {
+                        continue;
+                    }
+                    if coupon >> KEY_BITS > 63 || coupon >> KEY_BITS == 0 {
+                        return reader.invalid(format!("invalid coupon {coupon}"));
+                    }
+                    coupons.insert(coupon);
+                }
+                if coupons.len() != count {
+                    return reader.invalid(format!("{} distinct coupons for a count of {count}", coupons.len()));
+                }
+                reader.finish()?;
+                sketch.mode = HllMode::Coupons(coupons);
+                if count > sketch.max_coupons() {
+                    sketch.promote();
+                }
+            }
+            _ => {
+                let hip = reader.read_f64()?;
+                let _kxq0 = reader.read_f64()?;
+                let _kxq1 = reader.read_f64()?;
+                let _num_at_min = reader.read_u32()?;
+                let aux_count = reader.read_u32()? as usize;
+                let k = 1usize << lg_k;
+                let values = match target_type {
+                    TYPE_HLL_8 => reader.read_bytes(k)?.to_vec(),
+                    TYPE_HLL_6 => {
+                        let packed = reader.read_bytes(((k * 3) >> 2) + 1)?;
+                        (0..k)
+                            .map(|slot| {
+                                let start = slot * 6;
+                                let pair = u16::from_le_bytes([packed[start >> 3], packed[(start >> 3) + 1]]);
+                                ((pair >> (start & 7)) & 0x3f) as u8
+                            })
+                            .collect()
+                    }
+                    TYPE_HLL_4 => {
+                        let packed = reader.read_bytes(k / 2)?;
+                        let stored_aux = match compact {
+                            true => aux_count,
+                            false if lg_array <= 26 => 1 << lg_array,
+                            false => return reader.invalid(format!("unexpected aux map size 2^{lg_array}")),
+                        };
+                        // Values at least 15 above the minimum are replaced by a token and kept in the aux map
+                        let mut values = vec![0u8; k];
+                        let mut in_aux = vec![false; k];
+                        for slot in 0..k {
+                            match (packed[slot >> 1] >> ((slot & 1) * 4)) & 0xf {
+                                AUX_TOKEN => in_aux[slot] = true,
+                                nibble => values[slot] = nibble.saturating_add(count_or_min),
+                            }
+                        }
+                        let mut found_aux = 0;
+                        for _ in 0..stored_aux {
+                            let pair = reader.read_u32()?;
+                            if pair == 0 {
+                                continue;
+                            }
+                            let slot = (pair & KEY_MASK) as usize;
+                            if slot >= k || !in_aux[slot] {
+                                return reader.invalid(format!("unexpected aux entry for slot {slot}"));
+                            }
+                            in_aux[slot] = false;
+                            values[slot] = (pair >> KEY_BITS) as u8;
+                            found_aux += 1;
+                        }
+                        if found_aux != aux_count || in_aux.contains(&true) {
+                            return reader.invalid(format!("{found_aux} aux entries for a count of {aux_count}"));
+                        }
+                        values
+                    }
+                    _ => return reader.invalid(format!("unexpected target type {target_type}")),
+                };
+                if let Some(value) = values.iter().find(|value| **value > 63) {
+                    return reader.invalid(format!("invalid register value {value}"));
+                }
+                reader.finish()?;
+                sketch.mode = HllMode::Registers(Registers::from_values(values, hip));
+            }
+        }
+        Ok(sketch)
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+            + match &self.mode {
+                HllMode::Coupons(coupons) => coupons.capacity() * std::mem::size_of::<u32>(),
+                HllMode::Registers(registers) => registers.values.capacity(),
+            }
+    }
+}
+
+impl DistinctSketch for HllSketch {
+    fn update_hash(&mut self, hash: (u64, u64)) {
+        self.update_coupon(coupon(hash));
+    }
+}
+
+fn write_coupons(buffer: &mut Vec<u8>, coupons: &HashSet<u32>) {
+    let mut coupons: Vec<u32> = coupons.iter().copied().collect();
+    coupons.sort_unstable();
+    for coupon in coupons {
+        buffer.extend_from_slice(&coupon.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::datasketches::{murmur3_x64_128, DEFAULT_UPDATE_SEED};
+
+    use super::*;
+
+    fn sketch_of(lg_k: u8, items: impl IntoIterator<Item = i64>) -> HllSketch {
+        let mut sketch = HllSketch::new(lg_k);
+        for item in items {
+            sketch.update_hash(murmur3_x64_128(&item.to_le_bytes(), DEFAULT_UPDATE_SEED));
+        }
+        sketch
+    }
+
+    fn assert_relative_error(estimate: f64, expected: f64, max_error: f64) {
+        let error = (estimate - expected).abs() / expected;
+        assert!(error < max_error, "estimated {estimate} for {expected}");
+    }
+
+    fn registers(sketch: &HllSketch) -> &Registers {
+        match &sketch.mode {
+            HllMode::Registers(registers) => registers,
+            HllMode::Coupons(_) => panic!("expected registers"),
+        }
+    }
+
+    #[test]
+    fn test_estimates() {
+        let sketch = sketch_of(DEFAULT_LG_K, [1, 2, 3, 2, 1]);
+        assert_eq!(sketch.estimate().round(), 3.0);
+
+        let sketch = sketch_of(DEFAULT_LG_K, 0..100_000);
+        assert!(matches!(sketch.mode, HllMode::Registers(_)));
+        assert_relative_error(sketch.estimate(), 100_000.0, 0.05);
+        assert_relative_error(registers(&sketch).estimate(), 100_000.0, 0.05);
+
+        let sketch = sketch_of(MIN_LG_K, 0..100);
+        assert!(matches!(sketch.mode, HllMode::Registers(_)));
+        assert_relative_error(registers(&sketch).estimate(), 100.0, 0.8);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut union = sketch_of(DEFAULT_LG_K, 0..60_000);
+        union.merge(&sketch_of(DEFAULT_LG_K, 40_000..100_000));
+        assert!(union.out_of_order);
+        assert_relative_error(union.estimate(), 100_000.0, 0.05);
+
+        let mut union = sketch_of(DEFAULT_LG_K, 0..5);
+        union.merge(&sketch_of(10, 0..50_000));
+        assert_eq!(union.lg_k(), 10);
+        assert_relative_error(union.estimate(), 50_000.0, 0.1);
+
+        let mut union = sketch_of(DEFAULT_LG_K, 0..50_000);
+        union.merge(&sketch_of(DEFAULT_LG_K, [1, 100_000]));
+        assert!(!union.out_of_order);
+
+        let mut empty = HllSketch::new(DEFAULT_LG_K);
+        empty.merge(&sketch_of(14, 0..10));
+        assert_eq!(empty.lg_k(), 14);
+        assert_eq!(empty.estimate().round(), 10.0);
+    }
+
+    #[test]
+    fn test_serialization() {
+        let empty = HllSketch::new(DEFAULT_LG_K).to_bytes();
+        assert_eq!(empty, vec![2, 1, 7, 12, 3, 12, 0, 8]);
+        assert!(HllSketch::deserialize(&empty).unwrap().is_empty());
+
+        for count in [5, 100, 10_000] {
+            let sketch = sketch_of(DEFAULT_LG_K, 0..count);
+            let bytes = sketch.to_bytes();
+            let read = HllSketch::deserialize(&bytes).unwrap();
+            assert_eq!(read.estimate(), sketch.estimate());
+            assert_eq!(read.to_bytes(), bytes);
+        }
+
+        let bytes = sketch_of(DEFAULT_LG_K, 0..5).to_bytes();
+        assert_eq!(bytes.len(), 8 + 5 * 4);
+        assert_eq!(bytes[..8], [2, 1, 7, 12, 3, 8, 5, 8]);
+        let bytes = sketch_of(DEFAULT_LG_K, 0..100).to_bytes();
+        assert_eq!(bytes[..8], [3, 1, 7, 12, 8, 8, 0, 9]);
+        assert_eq!(bytes.len(), 12 + 100 * 4);
+        let bytes = sketch_of(DEFAULT_LG_K, 0..10_000).to_bytes();
+        assert_eq!(bytes[..8], [10, 1, 7, 12, 0, 8, 0, 10]);
+        assert_eq!(bytes.len(), 40 + 4096);
+
+        let err = HllSketch::deserialize(&bytes[..100]).unwrap_err();
+        assert!(err.to_string().contains("Invalid HLL sketch: unexpected end of input"));
+        let mut wrong_family = bytes.clone();
+        wrong_family[2] = 3;
+        let err = HllSketch::deserialize(&wrong_family).unwrap_err();
+        assert!(err.to_string().contains("unexpected sketch family 3"));
+    }
+
+    /// Writes registers in the `HLL_4` and `HLL_6` layouts of DataSketches.
+    fn packed_sketch(values: &[u8], target_type: u8) -> Vec<u8> {
+        let lg_k = values.len().trailing_zeros() as u8;
+        let cur_min = *values.iter().min().unwrap();
+        let mut bytes = vec![
+            10,
+            1,
+            7,
+            lg_k,
+            0,
+            FLAG_COMPACT | FLAG_OUT_OF_ORDER,
+            0,
+            MODE_HLL | (target_type << 2),
+        ];
+        bytes.extend_from_slice(&[0; 32]);
+        let mut aux = vec![];
+        match target_type {
+            TYPE_HLL_4 => {
+                bytes[6] = cur_min;
+                let mut packed = vec![0u8; values.len() / 2];
+                for (slot, value) in values.iter().enumerate() {
+                    let nibble = match value - cur_min {
+                        offset if offset >= AUX_TOKEN => {
+                            aux.extend_from_slice(&(((*value as u32) << KEY_BITS) | slot as u32).to_le_bytes());
+                            AUX_TOKEN
+                        }
+                        offset => offset,
+                    };
+                    packed[slot >> 1] |= nibble << ((slot & 1) * 4);
+                }
+                bytes[36..40].copy_from_slice(&((aux.len() / 4) as u32).to_le_bytes());
+                bytes.extend(packed);
+            }
+            _ => {
+                let mut packed = vec![0u8; ((values.len() * 3) >> 2) + 1];
+                for (slot, value) in values.iter().enumerate() {
+                    let start = slot * 6;
+                    let pair = (*value as u16) << (start & 7);
+                    packed[start >> 3] |= pair as u8;
+                    packed[(start >> 3) + 1] |= (pair >> 8) as u8;
+                }
+                bytes.extend(packed);
+            }
+        }
+        bytes.extend(aux);
+        bytes
+    }
+
+    #[test]
+    fn test_read_packed_registers() {
+        let values: Vec<u8> = (0..16u8).map(|slot| 2 + (slot * 7) % 20).collect();
+        for target_type in [TYPE_HLL_4, TYPE_HLL_6] {
+            let sketch = HllSketch::deserialize(&packed_sketch(&values, target_type)).unwrap();
+            assert_eq!(sketch.lg_k(), MIN_LG_K);
+            assert!(sketch.out_of_order);
+            assert_eq!(registers(&sketch).values, values);
+        }
+
+        let mut missing_aux = packed_sketch(&values, TYPE_HLL_4);
+        missing_aux.truncate(missing_aux.len() - 4);
+        missing_aux[36] -= 1;
+        let err = HllSketch::deserialize(&missing_aux).unwrap_err();
+        assert!(err.to_string().contains("aux entries for a count of"));
+    }
+}
@@ -0,0 +1,375 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use datafusion::error::Result;
+
+use crate::common::sketch::{write_header, QuantileSketch, Sketch, SketchReader};
+
+/// Default `k` of a [`KllSketch`], giving a normalized rank error of about 1.3% with 99% confidence.
+pub const DEFAULT_K: u16 = 200;
+/// Smallest `k` accepted by a [`KllSketch`], as in Apache DataSketches.
+pub const MIN_K: u16 = 8;
+
+const FAMILY: u8 = b'K';
+const VERSION: u8 = 1;
+const COIN_SEED: u64 = 0x853c_49e6_748f_ea9b;
+
+/// A KLL quantile sketch (Karnin, Lang and Liberty, "Optimal Quantile Approximation in Streams").
+///
+/// Values are appended to the bottom level. When the sketch holds more values than its capacity, the lowest full
+/// level is sorted and compacted: every other value is promoted to the level above, doubling its weight. Level `h`
+/// holds values of weight `2^h` and its capacity shrinks geometrically with its distance to the top level, so the
+/// sketch keeps `O(k)` values whatever the number of inputs.
+///
+/// The coin choosing which half of a level is promoted comes from a seeded generator, so building a sketch from the
+/// same values in the same order always gives the same result.
+///
+/// The serialization is little-endian: the family byte `K` and the version `1`, `k` as `u16`, the number of values as
+/// `u64`, the minimum and maximum as `f64`, the number of levels as `u8` and, for each level from the bottom, its
+/// length as `u32` followed by its values as `f64`.
+#[derive(Debug, Clone)]
+pub struct KllSketch {
+    k: u16,
+    n: u64,
+    min: f64,
+    max: f64,
+    levels: Vec<Vec<f64>>,
+    /// Number of values retained over all levels.
+    retained: usize,
+    /// Number of values the levels can retain before a compaction.
+    capacity: usize,
+    coin: u64,
+}
+
+impl KllSketch {
+    pub fn new(k: u16) -> Self {
+        debug_assert!(k >= MIN_K, "k must be at least {MIN_K}");
+        let mut sketch = Self {
+            k,
+            n: 0,
+            min: f64::NAN,
+            max: f64::NAN,
+            levels: vec![vec![]],
+            retained: 0,
+            capacity: 0,
+            coin: COIN_SEED,
+        };
+        sketch.capacity = sketch.total_capacity();
+        sketch
+    }
+
+    pub fn k(&self) -> u16 {
+        self.k
+    }
+
+    fn level_capacity(&self, level: usize) -> usize {
+        let depth = (self.levels.len() - 1 - level) as i32;
+        let capacity = (self.k as f64 * (2.0f64 / 3.0).powi(depth)).ceil();
+        (capacity as usize).max(2)
+    }
+
+    fn total_capacity(&self) -> usize {
+        (0..self.levels.len()).map(|level| self.level_capacity(level)).sum()
+    }
+
+    fn flip_coin(&mut self) -> bool {
+        self.coin = self
+            .coin
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        self.coin >> 63 == 1
+    }
+
+    fn insert(&mut self, value: f64) {
+        self.n += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.levels[0].push(value);
+        self.retained += 1;
+        if self.retained > self.capacity {
+            self.compress();
+        }
+    }
+
+    /// Compacts the lowest full levels until the sketch fits its capacity.
+    fn compress(&mut self) {
+        while self.retained > self.capacity {
+            let level = (0..self.levels.len())
+                .find(|&level| self.levels[level].len() >= self.level_capacity(level))
+                .expect("a sketch over capacity has a full level");
+            self.compact(level);
+        }
+    }
+
+    /// Promotes every other value of `level` to the level above, keeping the largest one when the length is odd.
+    fn compact(&mut self, level: usize) {
+        if level + 1 == self.levels.len() {
+            self.levels.push(vec![]);
+            self.capacity = self.total_capacity();
+        }
+
+        let mut values = std::mem::take(&mut self.levels[level]);
+        values.sort_unstable_by(f64::total_cmp);
+        if values.len() % 2 == 1 {
+            self.levels[level].extend(values.pop());
+        }
+        let offset = self.flip_coin() as usize;
+        self.levels[level + 1].extend(values.iter().skip(offset).step_by(2));
+        self.retained -= values.len() / 2;
+    }
+}
+
+impl Sketch for KllSketch {
+    /// Merges `other` into this sketch. The accuracy of the result is the one of the smallest `k`, except that an
+    /// empty sketch takes the `k` of the sketch merged into it.
+    fn merge(&mut self, other: &Self) {
+        if other.n == 0 {
+            return;
+        }
+        if self.n == 0 {
+            *self = other.clone();
+            return;
+        }
+
+        self.k = self.k.min(other.k);
+        self.n += other.n;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        if self.levels.len() < other.levels.len() {
+            self.levels.resize(other.levels.len(), vec![]);
+        }
+        for (level, values) in other.levels.iter().enumerate() {
+            self.levels[level].extend_from_slice(values);
+        }
+        self.retained += other.retained;
+        self.capacity = self.total_capacity();
+        self.compress();
+    }
+
+    fn serialize(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, FAMILY, VERSION);
+        buffer.extend_from_slice(&self.k.to_le_bytes());
+        buffer.extend_from_slice(&self.n.to_le_bytes());
+        buffer.extend_from_slice(&self.min.to_le_bytes());
+        buffer.extend_from_slice(&self.max.to_le_bytes());
+        buffer.push(self.levels.len() as u8);
+        for values in &self.levels {
+            buffer.extend_from_slice(&(values.len() as u32).to_le_bytes());
+            for value in values {
+                buffer.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self> {
+        let mut reader = SketchReader::new("KLL", bytes);
+        reader.read_header(FAMILY, VERSION)?;
+        let k = reader.read_u16()?;
+        if k < MIN_K {
+            return reader.invalid(format!("k must be at least {MIN_K}, got {k}"));
+        }
+        let n = reader.read_u64()?;
+        let min = reader.read_f64()?;
+        let max = reader.read_f64()?;
+        let num_levels = reader.read_u8()? as usize;
+        if num_levels == 0 || num_levels > 64 {
+            return reader.invalid(format!("unexpected number of levels {num_levels}"));
+        }
+
+        let mut levels = Vec::with_capacity(num_levels);
+        let mut weight = 0u128;
+        for level in 0..num_levels {
+            let len = reader.read_u32()? as usize;
+            let bytes = reader.read_bytes(len.saturating_mul(8))?;
+            let values: Vec<f64> = bytes
+                .chunks_exact(8)
+                .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap()))
+                .collect();
+            if values.iter().any(|value| !(min..=max).contains(value)) {
+                return reader.invalid("values outside of the minimum and maximum");
+            }
+            weight += (len as u128) << level;
+            levels.push(values);
+        }
+        if weight != n as u128 {
+            return reader.invalid(format!("the levels hold a weight of {weight} for {n} values"));
+        }
+        reader.finish()?;
+
+        let mut sketch = Self {
+            k,
+            n,
+            min: if n == 0 { f64::NAN } else { min },
+            max: if n == 0 { f64::NAN } else { max },
+            retained: levels.iter().map(Vec::len).sum(),
+            levels,
+            capacity: 0,
+            coin: COIN_SEED ^ n,
+        };
+        sketch.capacity = sketch.total_capacity();
+        Ok(sketch)
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+            + self.levels.capacity() * std::mem::size_of::<Vec<f64>>()
+            + self
+                .levels
+                .iter()
+                .map(|values| values.capacity() * std::mem::size_of::<f64>())
+                .sum::<usize>()
+    }
+}
+
+impl QuantileSketch for KllSketch {
+    fn count(&self) -> u64 {
+        self.n
+    }
+
+    fn update(&mut self, values: &[f64]) {
+        for value in values.iter().filter(|value| !value.is_nan()) {
+            self.insert(*value);
+        }
+    }
+
+    /// Returns the first retained value whose cumulative weight reaches `q` times the number of values, which is
+    /// exactly `quantile_disc` as long as no compaction happened. `0` and `1` return the exact minimum and maximum.
+    fn quantile(&self, q: f64) -> Option<f64> {
+        if self.n == 0 {
+            return None;
+        }
+        if q <= 0.0 {
+            return Some(self.min);
+        }
+        if q >= 1.0 {
+            return Some(self.max);
+        }
+
+        let mut weighted: Vec<(f64, u64)> = self
+            .levels
+            .iter()
+            .enumerate()
+            .flat_map(|(level, values)| values.iter().map(move |value| (*value, 1u64 << level)))
+            .collect();
+        weighted.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+
+        let rank = (q * self.n as f64).ceil().max(1.0) as u64;
+        let mut cumulative = 0;
+        for (value, weight) in weighted {
+            cumulative += weight;
+            if cumulative >= rank {
+                return Some(value);
+            }
+        }
+        Some(self.max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Values from 0 to n - 1 in a scrambled order.
+    fn scrambled(n: u64) -> Vec<f64> {
+        (0..n).map(|i| ((i * 7919) % n) as f64).collect()
+    }
+
+    fn assert_rank_error(sketch: &KllSketch, n: u64, max_error: f64) {
+        for q in [0.01, 0.1, 0.25, 0.5, 0.75, 0.9, 0.99] {
+            let estimate = sketch.quantile(q).unwrap();
+            let error = (estimate / n as f64 - q).abs();
+            assert!(error < max_error, "quantile {q} estimated as {estimate}");
+        }
+    }
+
+    #[test]
+    fn test_exact_while_small() {
+        let mut sketch = KllSketch::new(DEFAULT_K);
+        assert_eq!(sketch.quantile(0.5), None);
+        sketch.update(&[5.0, 1.0, f64::NAN, 4.0, 2.0, 3.0]);
+        assert_eq!(sketch.count(), 5);
+        assert_eq!(sketch.quantile(0.0), Some(1.0));
+        assert_eq!(sketch.quantile(0.2), Some(1.0));
+        assert_eq!(sketch.quantile(0.5), Some(3.0));
+        assert_eq!(sketch.quantile(0.61), Some(4.0));
+        assert_eq!(sketch.quantile(1.0), Some(5.0));
+    }
+
+    #[test]
+    fn test_bounded_size_and_accuracy() {
+        let n = 100_000;
+        let mut sketch = KllSketch::new(DEFAULT_K);
+        sketch.update(&scrambled(n));
+        assert_eq!(sketch.count(), n);
+        assert!(
+            sketch.retained < 3 * DEFAULT_K as usize + 64,
+            "retained {}",
+            sketch.retained
+        );
+        assert_eq!(sketch.quantile(0.0), Some(0.0));
+        assert_eq!(sketch.quantile(1.0), Some((n - 1) as f64));
+        assert_rank_error(&sketch, n, 0.02);
+    }
+
+    #[test]
+    fn test_merge() {
+        let n = 100_000;
+        let values = scrambled(n);
+        let mut merged = KllSketch::new(DEFAULT_K);
+        for chunk in values.chunks(30_000) {
+            let mut sketch = KllSketch::new(DEFAULT_K);
+            sketch.update(chunk);
+            merged.merge(&sketch);
+        }
+        assert_eq!(merged.count(), n);
+        assert_rank_error(&merged, n, 0.02);
+
+        let mut smaller = KllSketch::new(MIN_K);
+        smaller.update(&[1.0]);
+        merged.merge(&smaller);
+        assert_eq!(merged.k(), MIN_K);
+
+        let mut empty = KllSketch::new(DEFAULT_K);
+        empty.merge(&smaller);
+        assert_eq!(empty.k(), MIN_K);
+        assert_eq!(empty.quantile(0.5), Some(1.0));
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut sketch = KllSketch::new(64);
+        sketch.update(&scrambled(10_000));
+        let bytes = sketch.to_bytes();
+        let read = KllSketch::deserialize(&bytes).unwrap();
+        assert_eq!(read.k(), 64);
+        assert_eq!(read.count(), 10_000);
+        assert_eq!(read.levels, sketch.levels);
+        assert_eq!(read.to_bytes(), bytes);
+
+        let empty = KllSketch::deserialize(&KllSketch::new(DEFAULT_K).to_bytes()).unwrap();
+        assert_eq!(empty.quantile(0.5), None);
+
+        let err = KllSketch::deserialize(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert!(err.to_string().contains("Invalid KLL sketch: unexpected end of input"));
+
+        let mut wrong_count = bytes.clone();
+        wrong_count[4] ^= 1;
+        let err = KllSketch::deserialize(&wrong_count).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("the levels hold a weight of 10000 for 10001 values"));
+    }
+}
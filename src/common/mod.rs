@@ -15,5 +15,22 @@
 // specific language governing permissions and limitations
 // under the License.
 
+pub mod aliases;
+pub mod arg_extreme;
+pub mod bloom_filter;
 pub mod collections;
+pub mod compensated_sum;
+pub mod count_min;
+pub mod datasketches;
+pub mod distinct;
+pub mod groups;
+pub mod histogram;
+pub mod hll;
+pub mod kll;
 pub mod mode;
+pub mod moments;
+pub mod quantile;
+pub mod reservoir;
+pub mod sketch;
+pub mod tdigest;
+pub mod theta;
@@ -18,7 +18,100 @@
 mod bytes;
 mod native;
 
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, Int64Array};
+use arrow::datatypes::{
+    DataType, Date32Type, Date64Type, Field, Float16Type, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type,
+    Int8Type, Time32MillisecondType, Time32SecondType, Time64MicrosecondType, Time64NanosecondType, TimeUnit,
+    TimestampMicrosecondType, TimestampMillisecondType, TimestampNanosecondType, TimestampSecondType, UInt16Type,
+    UInt32Type, UInt64Type, UInt8Type,
+};
+use datafusion::arrow;
+use datafusion::common::not_impl_err;
+use datafusion::common::utils::array_into_list_array_nullable;
+use datafusion::error::Result;
+use datafusion::logical_expr::Accumulator;
+use datafusion::physical_expr::binary_map::OutputType;
+use datafusion::scalar::ScalarValue;
+
 pub use bytes::BytesModeAccumulator;
 pub use bytes::BytesViewModeAccumulator;
 pub use native::FloatModeAccumulator;
 pub use native::PrimitiveModeAccumulator;
+
+/// A mode accumulator exposing its table of value frequencies, so that other functions such as `histogram` can be
+/// built on the same counting.
+pub trait ValueCountsAccumulator: Accumulator {
+    /// Returns the distinct non-null values, in no particular order, and how many times each was seen, and keeps
+    /// counting them.
+    fn value_counts(&mut self) -> Result<(ArrayRef, Int64Array)>;
+
+    fn into_accumulator(self: Box<Self>) -> Box<dyn Accumulator>;
+}
+
+/// Creates the accumulator counting values of type `data_type` for the aggregate function `function`.
+pub fn value_counts_accumulator(function: &str, data_type: &DataType) -> Result<Box<dyn ValueCountsAccumulator>> {
+    let accumulator: Box<dyn ValueCountsAccumulator> = match data_type {
+        DataType::Int8 => Box::new(PrimitiveModeAccumulator::<Int8Type>::new(data_type)),
+        DataType::Int16 => Box::new(PrimitiveModeAccumulator::<Int16Type>::new(data_type)),
+        DataType::Int32 => Box::new(PrimitiveModeAccumulator::<Int32Type>::new(data_type)),
+        DataType::Int64 => Box::new(PrimitiveModeAccumulator::<Int64Type>::new(data_type)),
+        DataType::UInt8 => Box::new(PrimitiveModeAccumulator::<UInt8Type>::new(data_type)),
+        DataType::UInt16 => Box::new(PrimitiveModeAccumulator::<UInt16Type>::new(data_type)),
+        DataType::UInt32 => Box::new(PrimitiveModeAccumulator::<UInt32Type>::new(data_type)),
+        DataType::UInt64 => Box::new(PrimitiveModeAccumulator::<UInt64Type>::new(data_type)),
+
+        DataType::Date32 => Box::new(PrimitiveModeAccumulator::<Date32Type>::new(data_type)),
+        DataType::Date64 => Box::new(PrimitiveModeAccumulator::<Date64Type>::new(data_type)),
+        DataType::Time32(TimeUnit::Millisecond) => {
+            Box::new(PrimitiveModeAccumulator::<Time32MillisecondType>::new(data_type))
+        }
+        DataType::Time32(TimeUnit::Second) => Box::new(PrimitiveModeAccumulator::<Time32SecondType>::new(data_type)),
+        DataType::Time64(TimeUnit::Microsecond) => {
+            Box::new(PrimitiveModeAccumulator::<Time64MicrosecondType>::new(data_type))
+        }
+        DataType::Time64(TimeUnit::Nanosecond) => {
+            Box::new(PrimitiveModeAccumulator::<Time64NanosecondType>::new(data_type))
+        }
+        DataType::Timestamp(TimeUnit::Microsecond, _) => {
+            Box::new(PrimitiveModeAccumulator::<TimestampMicrosecondType>::new(data_type))
+        }
+        DataType::Timestamp(TimeUnit::Millisecond, _) => {
+            Box::new(PrimitiveModeAccumulator::<TimestampMillisecondType>::new(data_type))
+        }
+        DataType::Timestamp(TimeUnit::Nanosecond, _) => {
+            Box::new(PrimitiveModeAccumulator::<TimestampNanosecondType>::new(data_type))
+        }
+        DataType::Timestamp(TimeUnit::Second, _) => {
+            Box::new(PrimitiveModeAccumulator::<TimestampSecondType>::new(data_type))
+        }
+
+        DataType::Float16 => Box::new(FloatModeAccumulator::<Float16Type>::new(data_type)),
+        DataType::Float32 => Box::new(FloatModeAccumulator::<Float32Type>::new(data_type)),
+        DataType::Float64 => Box::new(FloatModeAccumulator::<Float64Type>::new(data_type)),
+
+        DataType::Utf8 => Box::new(BytesModeAccumulator::<i32>::new(OutputType::Utf8)),
+        DataType::LargeUtf8 => Box::new(BytesModeAccumulator::<i64>::new(OutputType::Utf8)),
+        DataType::Utf8View => Box::new(BytesViewModeAccumulator::new(OutputType::Utf8View)),
+        _ => {
+            return not_impl_err!("Unsupported data type: {:?} for {function} function", data_type);
+        }
+    };
+    Ok(accumulator)
+}
+
+/// The state of a [`ValueCountsAccumulator`]: the list of distinct values and the list of their counts.
+pub fn value_counts_state_fields(value_type: &DataType) -> Vec<Field> {
+    vec![
+        Field::new_list("values", Field::new_list_field(value_type.clone(), true), true),
+        Field::new_list("frequencies", Field::new_list_field(DataType::Int64, true), true),
+    ]
+}
+
+fn value_counts_state(values: ArrayRef, counts: Int64Array) -> Vec<ScalarValue> {
+    vec![
+        ScalarValue::List(Arc::new(array_into_list_array_nullable(values))),
+        ScalarValue::List(Arc::new(array_into_list_array_nullable(Arc::new(counts)))),
+    ]
+}
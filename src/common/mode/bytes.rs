@@ -15,16 +15,17 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use std::sync::Arc;
+use std::cell::Cell;
 
 use arrow::array::ArrayRef;
 use arrow::array::AsArray;
+use arrow::array::Int64Array;
 use arrow::array::OffsetSizeTrait;
-use arrow::datatypes::DataType;
+use arrow::compute::{filter, is_not_null};
+use arrow::datatypes::Int64Type;
 use datafusion::arrow;
 use datafusion::common::cast::as_list_array;
 use datafusion::common::cast::as_primitive_array;
-use datafusion::common::utils::array_into_list_array_nullable;
 use datafusion::error::Result;
 use datafusion::logical_expr::Accumulator;
 use datafusion::physical_expr::binary_map::ArrowBytesSet;
@@ -35,6 +36,38 @@ use datafusion::scalar::ScalarValue;
 use crate::common::collections::ArrowBytesMap;
 use crate::common::collections::ArrowBytesViewMap;
 
+use super::{value_counts_state, ValueCountsAccumulator};
+
+/// Calls `merge` with each list of values of the state of a mode accumulator of strings, and a function returning
+/// the count of each of these values in turn.
+fn merge_value_counts(states: &[ArrayRef], mut merge: impl FnMut(&ArrayRef, &dyn Fn() -> i64)) -> Result<()> {
+    let values_lists = as_list_array(&states[0])?;
+    let counts_lists = as_list_array(&states[1])?;
+    for (values, counts) in values_lists.iter().zip(counts_lists.iter()) {
+        let (Some(values), Some(counts)) = (values, counts) else {
+            continue;
+        };
+        let counts = as_primitive_array::<Int64Type>(&counts)?;
+        // the maps call back once per value, in order
+        let index = Cell::new(0);
+        let next_count = || {
+            let count = counts.value(index.get());
+            index.set(index.get() + 1);
+            count
+        };
+        merge(&values, &next_count);
+    }
+    Ok(())
+}
+
+/// Removes the null value, and its count, from the distinct values of the tables of a mode accumulator and their
+/// counts.
+fn non_null_value_counts(values: ArrayRef, counts: Vec<Option<i64>>) -> Result<(ArrayRef, Int64Array)> {
+    let non_null = is_not_null(&values)?;
+    let counts = filter(&Int64Array::from(counts), &non_null)?;
+    Ok((filter(&values, &non_null)?, counts.as_primitive::<Int64Type>().clone()))
+}
+
 #[derive(Debug)]
 pub struct BytesModeAccumulator<O: OffsetSizeTrait> {
     values: ArrowBytesSet<O>,
@@ -48,6 +81,13 @@ impl<O: OffsetSizeTrait> BytesModeAccumulator<O> {
             value_counts: ArrowBytesMap::new(output_type),
         }
     }
+
+    /// Returns the distinct values, with the null value if one was seen, and their counts, and clears the tables.
+    fn take_value_counts(&mut self) -> (ArrayRef, Vec<Option<i64>>) {
+        let values = self.values.take().into_state();
+        let counts = self.value_counts.take().get_payloads(&values);
+        (values, counts)
+    }
 }
 
 impl<O: OffsetSizeTrait> Accumulator for BytesModeAccumulator<O> {
@@ -74,22 +114,9 @@ impl<O: OffsetSizeTrait> Accumulator for BytesModeAccumulator<O> {
     }
 
     fn state(&mut self) -> Result<Vec<ScalarValue>> {
-        let values = self.values.take().into_state();
-        let payloads: Vec<ScalarValue> = self
-            .value_counts
-            .take()
-            .get_payloads(&values)
-            .into_iter()
-            .map(|count| match count {
-                Some(c) => ScalarValue::Int64(Some(c)),
-                None => ScalarValue::Int64(None),
-            })
-            .collect();
-
-        let values_list = Arc::new(array_into_list_array_nullable(values));
-        let payloads_list = ScalarValue::new_list_nullable(&payloads, &DataType::Int64);
-
-        Ok(vec![ScalarValue::List(values_list), ScalarValue::List(payloads_list)])
+        let (values, counts) = self.take_value_counts();
+        let (values, counts) = non_null_value_counts(values, counts)?;
+        Ok(value_counts_state(values, counts))
     }
 
     fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
@@ -97,16 +124,10 @@ impl<O: OffsetSizeTrait> Accumulator for BytesModeAccumulator<O> {
             return Ok(());
         }
 
-        let arr = as_list_array(&states[0])?;
-        let counts = as_primitive_array::<arrow::datatypes::Int64Type>(&states[1])?;
-
-        arr.iter().zip(counts.iter()).try_for_each(|(maybe_list, maybe_count)| {
-            if let (Some(list), Some(count)) = (maybe_list, maybe_count) {
-                // Insert or update the count for each value
-                self.value_counts
-                    .insert_or_update(&list, |_| count, |existing_count| *existing_count += count);
-            }
-            Ok(())
+        merge_value_counts(states, |values, next_count| {
+            self.values.insert(values);
+            self.value_counts
+                .insert_or_update(values, |_| next_count(), |count| *count += next_count());
         })
     }
 
@@ -153,6 +174,26 @@ impl<O: OffsetSizeTrait> Accumulator for BytesModeAccumulator<O> {
     }
 }
 
+impl<O: OffsetSizeTrait> ValueCountsAccumulator for BytesModeAccumulator<O> {
+    fn value_counts(&mut self) -> Result<(ArrayRef, Int64Array)> {
+        let (values, counts) = self.take_value_counts();
+        // the tables are filled again with the distinct values in the order they were first seen, so that the
+        // accumulator keeps counting, e.g. over a growing window frame
+        self.values.insert(&values);
+        let mut payloads = counts.iter();
+        self.value_counts.insert_or_update(
+            &values,
+            |_| payloads.next().copied().flatten().unwrap_or_default(),
+            |_| unreachable!("the values are distinct"),
+        );
+        non_null_value_counts(values, counts)
+    }
+
+    fn into_accumulator(self: Box<Self>) -> Box<dyn Accumulator> {
+        self
+    }
+}
+
 #[derive(Debug)]
 pub struct BytesViewModeAccumulator {
     values: ArrowBytesViewSet,
@@ -166,6 +207,13 @@ impl BytesViewModeAccumulator {
             value_counts: ArrowBytesViewMap::new(output_type),
         }
     }
+
+    /// Returns the distinct values, with the null value if one was seen, and their counts, and clears the tables.
+    fn take_value_counts(&mut self) -> (ArrayRef, Vec<Option<i64>>) {
+        let values = self.values.take().into_state();
+        let counts = self.value_counts.take().get_payloads(&values);
+        (values, counts)
+    }
 }
 
 impl Accumulator for BytesViewModeAccumulator {
@@ -191,22 +239,9 @@ impl Accumulator for BytesViewModeAccumulator {
     }
 
     fn state(&mut self) -> Result<Vec<ScalarValue>> {
-        let values = self.values.take().into_state();
-        let payloads: Vec<ScalarValue> = self
-            .value_counts
-            .take()
-            .get_payloads(&values)
-            .into_iter()
-            .map(|count| match count {
-                Some(c) => ScalarValue::Int64(Some(c)),
-                None => ScalarValue::Int64(None),
-            })
-            .collect();
-
-        let values_list = Arc::new(array_into_list_array_nullable(values));
-        let payloads_list = ScalarValue::new_list_nullable(&payloads, &DataType::Int64);
-
-        Ok(vec![ScalarValue::List(values_list), ScalarValue::List(payloads_list)])
+        let (values, counts) = self.take_value_counts();
+        let (values, counts) = non_null_value_counts(values, counts)?;
+        Ok(value_counts_state(values, counts))
     }
 
     fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
@@ -214,16 +249,10 @@ impl Accumulator for BytesViewModeAccumulator {
             return Ok(());
         }
 
-        let arr = as_list_array(&states[0])?;
-        let counts = as_primitive_array::<arrow::datatypes::Int64Type>(&states[1])?;
-
-        arr.iter().zip(counts.iter()).try_for_each(|(maybe_list, maybe_count)| {
-            if let (Some(list), Some(count)) = (maybe_list, maybe_count) {
-                // Insert or update the count for each value
-                self.value_counts
-                    .insert_or_update(&list, |_| count, |existing_count| *existing_count += count);
-            }
-            Ok(())
+        merge_value_counts(states, |values, next_count| {
+            self.values.insert(values);
+            self.value_counts
+                .insert_or_update(values, |_| next_count(), |count| *count += next_count());
         })
     }
 
@@ -262,6 +291,26 @@ impl Accumulator for BytesViewModeAccumulator {
     }
 }
 
+impl ValueCountsAccumulator for BytesViewModeAccumulator {
+    fn value_counts(&mut self) -> Result<(ArrayRef, Int64Array)> {
+        let (values, counts) = self.take_value_counts();
+        // the tables are filled again with the distinct values in the order they were first seen, so that the
+        // accumulator keeps counting, e.g. over a growing window frame
+        self.values.insert(&values);
+        let mut payloads = counts.iter();
+        self.value_counts.insert_or_update(
+            &values,
+            |_| payloads.next().copied().flatten().unwrap_or_default(),
+            |_| unreachable!("the values are distinct"),
+        );
+        non_null_value_counts(values, counts)
+    }
+
+    fn into_accumulator(self: Box<Self>) -> Box<dyn Accumulator> {
+        self
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -407,4 +456,32 @@ mod tests {
         assert_eq!(result, ScalarValue::Utf8View(Some("apple".to_string())));
         Ok(())
     }
+
+    #[test]
+    fn test_value_counts_keeps_counting_utf8() -> Result<()> {
+        let mut acc = BytesModeAccumulator::<i32>::new(OutputType::Utf8);
+        acc.update_batch(&[Arc::new(StringArray::from(vec![Some("a"), None, Some("b")]))])?;
+        let (values, counts) = acc.value_counts()?;
+        assert_eq!(values.as_string::<i32>(), &StringArray::from(vec!["a", "b"]));
+        assert_eq!(counts, Int64Array::from(vec![1, 1]));
+
+        acc.update_batch(&[Arc::new(StringArray::from(vec![Some("a"), None, Some("c")]))])?;
+        let (values, counts) = acc.value_counts()?;
+        assert_eq!(values.as_string::<i32>(), &StringArray::from(vec!["a", "b", "c"]));
+        assert_eq!(counts, Int64Array::from(vec![2, 1, 1]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_value_counts_keeps_counting_utf8view() -> Result<()> {
+        let mut acc = BytesViewModeAccumulator::new(OutputType::Utf8View);
+        acc.update_batch(&[Arc::new(GenericByteViewArray::from(vec![Some("a"), Some("b")]))])?;
+        acc.value_counts()?;
+
+        acc.update_batch(&[Arc::new(GenericByteViewArray::from(vec![Some("b"), None]))])?;
+        let (values, counts) = acc.value_counts()?;
+        assert_eq!(values.as_string_view(), &GenericByteViewArray::from(vec!["a", "b"]));
+        assert_eq!(counts, Int64Array::from(vec![1, 2]));
+        Ok(())
+    }
 }
@@ -18,16 +18,36 @@
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::sync::Arc;
 
-use datafusion::common::cast::as_primitive_array;
+use datafusion::common::cast::{as_list_array, as_primitive_array};
 use datafusion::error::Result;
 
 use arrow::{
-    array::{ArrayRef, ArrowPrimitiveType},
-    datatypes::DataType,
+    array::{ArrayRef, ArrowPrimitiveType, Int64Array, PrimitiveArray},
+    datatypes::{DataType, Int64Type},
 };
 use datafusion::{arrow, logical_expr::Accumulator, physical_expr::aggregate::utils::Hashable, scalar::ScalarValue};
 
+use super::{value_counts_state, ValueCountsAccumulator};
+
+/// Adds the counts of each `(values, frequencies)` row of the state of a mode accumulator to `value_counts`.
+fn merge_value_counts<T: ArrowPrimitiveType>(states: &[ArrayRef], mut add: impl FnMut(T::Native, i64)) -> Result<()> {
+    let values_lists = as_list_array(&states[0])?;
+    let counts_lists = as_list_array(&states[1])?;
+    for (values, counts) in values_lists.iter().zip(counts_lists.iter()) {
+        let (Some(values), Some(counts)) = (values, counts) else {
+            continue;
+        };
+        let values = as_primitive_array::<T>(&values)?;
+        let counts = as_primitive_array::<Int64Type>(&counts)?;
+        for (value, count) in values.values().iter().zip(counts.values()) {
+            add(*value, *count);
+        }
+    }
+    Ok(())
+}
+
 #[derive(Debug)]
 pub struct PrimitiveModeAccumulator<T>
 where
@@ -71,25 +91,8 @@ where
     }
 
     fn state(&mut self) -> Result<Vec<ScalarValue>> {
-        let values: Vec<ScalarValue> = self
-            .value_counts
-            .keys()
-            .map(|key| ScalarValue::new_primitive::<T>(Some(*key), &self.data_type))
-            .collect::<Result<Vec<_>>>()?;
-
-        let frequencies: Vec<ScalarValue> = self
-            .value_counts
-            .values()
-            .map(|count| ScalarValue::from(*count))
-            .collect();
-
-        let values_scalar = ScalarValue::new_list_nullable(&values, &self.data_type.clone());
-        let frequencies_scalar = ScalarValue::new_list_nullable(&frequencies, &DataType::Int64);
-
-        Ok(vec![
-            ScalarValue::List(values_scalar),
-            ScalarValue::List(frequencies_scalar),
-        ])
+        let (values, counts) = self.value_counts()?;
+        Ok(value_counts_state(values, counts))
     }
 
     fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
@@ -97,17 +100,9 @@ where
             return Ok(());
         }
 
-        let values_array = as_primitive_array::<T>(&states[0])?;
-        let counts_array = as_primitive_array::<arrow::datatypes::Int64Type>(&states[1])?;
-
-        for i in 0..values_array.len() {
-            let value = values_array.value(i);
-            let count = counts_array.value(i);
-            let entry = self.value_counts.entry(value).or_insert(0);
-            *entry += count;
-        }
-
-        Ok(())
+        merge_value_counts::<T>(states, |value, count| {
+            *self.value_counts.entry(value).or_insert(0) += count;
+        })
     }
 
     fn evaluate(&mut self) -> Result<ScalarValue> {
@@ -142,6 +137,23 @@ where
     }
 }
 
+impl<T> ValueCountsAccumulator for PrimitiveModeAccumulator<T>
+where
+    T: ArrowPrimitiveType + Send + Debug,
+    T::Native: Eq + Hash + Clone + PartialOrd + Debug,
+{
+    fn value_counts(&mut self) -> Result<(ArrayRef, Int64Array)> {
+        let (values, counts): (Vec<_>, Vec<_>) =
+            self.value_counts.iter().map(|(value, count)| (*value, *count)).unzip();
+        let values = PrimitiveArray::<T>::new(values.into(), None).with_data_type(self.data_type.clone());
+        Ok((Arc::new(values), Int64Array::from(counts)))
+    }
+
+    fn into_accumulator(self: Box<Self>) -> Box<dyn Accumulator> {
+        self
+    }
+}
+
 #[derive(Debug)]
 pub struct FloatModeAccumulator<T>
 where
@@ -184,25 +196,8 @@ where
     }
 
     fn state(&mut self) -> Result<Vec<ScalarValue>> {
-        let values: Vec<ScalarValue> = self
-            .value_counts
-            .keys()
-            .map(|key| ScalarValue::new_primitive::<T>(Some(key.0), &self.data_type))
-            .collect::<Result<Vec<_>>>()?;
-
-        let frequencies: Vec<ScalarValue> = self
-            .value_counts
-            .values()
-            .map(|count| ScalarValue::from(*count))
-            .collect();
-
-        let values_scalar = ScalarValue::new_list_nullable(&values, &self.data_type.clone());
-        let frequencies_scalar = ScalarValue::new_list_nullable(&frequencies, &DataType::Int64);
-
-        Ok(vec![
-            ScalarValue::List(values_scalar),
-            ScalarValue::List(frequencies_scalar),
-        ])
+        let (values, counts) = self.value_counts()?;
+        Ok(value_counts_state(values, counts))
     }
 
     fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
@@ -210,16 +205,9 @@ where
             return Ok(());
         }
 
-        let values_array = as_primitive_array::<T>(&states[0])?;
-        let counts_array = as_primitive_array::<arrow::datatypes::Int64Type>(&states[1])?;
-
-        for i in 0..values_array.len() {
-            let count = counts_array.value(i);
-            let entry = self.value_counts.entry(Hashable(values_array.value(i))).or_insert(0);
-            *entry += count;
-        }
-
-        Ok(())
+        merge_value_counts::<T>(states, |value, count| {
+            *self.value_counts.entry(Hashable(value)).or_insert(0) += count;
+        })
     }
 
     fn evaluate(&mut self) -> Result<ScalarValue> {
@@ -255,6 +243,23 @@ where
     }
 }
 
+impl<T> ValueCountsAccumulator for FloatModeAccumulator<T>
+where
+    T: ArrowPrimitiveType + Send + Debug,
+    T::Native: PartialOrd + Debug + Clone,
+{
+    fn value_counts(&mut self) -> Result<(ArrayRef, Int64Array)> {
+        let (values, counts): (Vec<_>, Vec<_>) =
+            self.value_counts.iter().map(|(value, count)| (value.0, *count)).unzip();
+        let values = PrimitiveArray::<T>::new(values.into(), None).with_data_type(self.data_type.clone());
+        Ok((Arc::new(values), Int64Array::from(counts)))
+    }
+
+    fn into_accumulator(self: Box<Self>) -> Box<dyn Accumulator> {
+        self
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
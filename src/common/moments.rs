@@ -0,0 +1,700 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use arrow::array::{Array, ArrayRef, ArrowPrimitiveType, Decimal128Array, Float64Array};
+use arrow::datatypes::{DataType, Decimal128Type, Field, Float64Type, Int64Type, DECIMAL128_MAX_PRECISION};
+use datafusion::arrow;
+use datafusion::common::cast::{as_list_array, as_primitive_array};
+use datafusion::common::{downcast_value, exec_err, plan_err, DataFusionError};
+use datafusion::error::Result;
+use datafusion::logical_expr::Accumulator;
+use datafusion::scalar::ScalarValue;
+
+use crate::common::compensated_sum::CompensatedSum;
+
+/// Computes a statistic from the [`PowerSums`] of a set of values, returning `None` when it is undefined.
+pub type MomentStatistic = Arc<dyn Fn(&PowerSums) -> Option<f64> + Send + Sync>;
+
+/// Count and power sums `Σx, Σx², ..., Σxᵏ` of a set of values, up to an order `k`, from which the central moments
+/// of the values up to that order are computed.
+///
+/// Values can be weighted by frequency weights, the count is then the sum of the weights and the sums are the ones
+/// of `w * xᵖ`: a value with an integer weight `w` contributes as `w` repetitions of it.
+///
+/// The sums are [`CompensatedSum`]s, as the central moments are computed from their differences, which amplify
+/// their rounding errors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PowerSums {
+    /// Number of values, or sum of their weights
+    count: f64,
+    /// `sums[p - 1]` is the sum of the `p`-th powers
+    sums: Vec<CompensatedSum>,
+    /// Offset of the values from the ones the sums are computed from, see [`ShiftedPowerSums`]
+    shift: f64,
+    /// Power of ten dividing the values, see [`Self::with_scale`]
+    scale: i32,
+}
+
+impl PowerSums {
+    pub fn new(order: usize) -> Self {
+        Self {
+            count: 0.0,
+            sums: vec![CompensatedSum::default(); order],
+            shift: 0.0,
+            scale: 0,
+        }
+    }
+
+    pub fn count(&self) -> f64 {
+        self.count
+    }
+
+    /// Returns the sum of the `p`-th powers of the values, with the sum of the 0-th powers being the count.
+    pub fn sum(&self, p: usize) -> f64 {
+        match p {
+            0 => self.count,
+            _ => self.sums[p - 1].value(),
+        }
+    }
+
+    pub fn update(&mut self, value: f64) {
+        self.update_weighted(value, 1.0);
+    }
+
+    pub fn update_weighted(&mut self, value: f64, weight: f64) {
+        self.count += weight;
+        let mut power = weight;
+        for sum in self.sums.iter_mut() {
+            power *= value;
+            sum.add(power);
+        }
+    }
+
+    pub fn merge(&mut self, other: &PowerSums) {
+        self.count += other.count;
+        for (sum, other) in self.sums.iter_mut().zip(&other.sums) {
+            sum.merge(other);
+        }
+    }
+
+    /// Returns the power sums of the values moved by `delta`, using the binomial expansion of `(x + delta)^p`.
+    pub fn shifted(&self, delta: f64) -> PowerSums {
+        let sums = (1..=self.sums.len())
+            .map(|p| {
+                binomial_expansion(p, delta)
+                    .map(|(j, coefficient)| coefficient * self.sum(j))
+                    .collect()
+            })
+            .collect();
+        PowerSums {
+            count: self.count,
+            sums,
+            shift: self.shift,
+            scale: self.scale,
+        }
+    }
+
+    /// Returns the power sums with the values divided by `10^scale`, e.g. for decimals accumulated as unscaled
+    /// integers.
+    ///
+    /// The sums themselves are kept unscaled and only the central moments are divided by `10^(scale * k)`: scaling
+    /// each sum before they cancel out in [`Self::central_moment`] would lose precision.
+    pub fn with_scale(mut self, scale: i32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Returns the mean of the values, or `None` if there are none.
+    pub fn mean(&self) -> Option<f64> {
+        if self.count == 0.0 {
+            return None;
+        }
+        Some((self.shift + self.sum(1) / self.count) / 10_f64.powi(self.scale))
+    }
+
+    /// Returns the `k`-th central moment `Σ(x - mean)ᵏ / n` of the values, or `None` if there are none.
+    pub fn central_moment(&self, k: usize) -> Option<f64> {
+        if self.count == 0.0 {
+            return None;
+        }
+        let count = self.count;
+        let mean = self.sum(1) / count;
+        let moment = binomial_expansion(k, -mean)
+            .map(|(j, coefficient)| coefficient * self.sum(j))
+            .sum::<f64>();
+        Some(moment / count / 10_f64.powi(self.scale * k as i32))
+    }
+
+    /// Returns the `k`-th standardized moment `m_k / m_2^(k/2)` of the values, or `None` if they have no variance.
+    pub fn standardized_moment(&self, k: usize) -> Option<f64> {
+        let variance = self.central_moment(2)?;
+        if variance <= 0.0 {
+            return None;
+        }
+        Some(self.central_moment(k)? / variance.powf(k as f64 / 2.0))
+    }
+}
+
+/// Returns the terms `(j, C(p, j) * delta^(p - j))` of the binomial expansion of `(x + delta)^p`, for `j` in `0..=p`.
+fn binomial_expansion(p: usize, delta: f64) -> impl Iterator<Item = (usize, f64)> {
+    let mut binomial = 1.0;
+    (0..=p).rev().map(move |j| {
+        let term = (j, binomial * delta.powi((p - j) as i32));
+        binomial = binomial * j as f64 / (p - j + 1) as f64;
+        term
+    })
+}
+
+/// Power sums of the distances `x - shift` of integer or decimal values to a shift, which is the first value seen.
+///
+/// The distances are computed exactly in `i128` before being converted to `f64`, unless they overflow it. When the
+/// values are large compared to their spread, the distances stay small, whereas the powers of the values themselves
+/// would cancel out when computing central moments and lose all precision. Central moments do not depend on the
+/// shift, so the power sums of the distances are used in place of the ones of the values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShiftedPowerSums {
+    shift: i128,
+    sums: PowerSums,
+}
+
+impl ShiftedPowerSums {
+    pub fn new(order: usize) -> Self {
+        Self {
+            shift: 0,
+            sums: PowerSums::new(order),
+        }
+    }
+
+    pub fn update(&mut self, value: i128, weight: f64) {
+        if self.sums.count == 0.0 {
+            self.shift = value;
+        }
+        self.sums.update_weighted(distance(value, self.shift), weight);
+    }
+
+    pub fn merge(&mut self, other: &ShiftedPowerSums) {
+        if other.sums.count == 0.0 {
+            return;
+        }
+        if self.sums.count == 0.0 {
+            self.clone_from(other);
+            return;
+        }
+
+        self.sums.merge(&other.sums.shifted(distance(other.shift, self.shift)));
+    }
+
+    /// Power sums of the distances to the shift, which give the same central moments as the values.
+    pub fn power_sums(&self) -> PowerSums {
+        PowerSums {
+            shift: self.shift as f64,
+            ..self.sums.clone()
+        }
+    }
+}
+
+/// Returns `value - shift`, computed in `f64` when values of opposite signs are too far apart for an `i128`, in
+/// which case the rounding of the `f64`s is negligible next to the distance.
+fn distance(value: i128, shift: i128) -> f64 {
+    match value.checked_sub(shift) {
+        Some(distance) => distance as f64,
+        None => value as f64 - shift as f64,
+    }
+}
+
+/// Coerces the argument of the moment aggregate `name`: integers are kept exact as `Int64`, or as `Decimal128(20, 0)`
+/// for `UInt64`, and `Decimal128`s as they are, so that they can be accumulated by a [`ShiftedMomentsAccumulator`].
+/// The other numbers, `Decimal256` included, nulls and strings are cast to `Float64`, the strings being parsed as
+/// numbers as with the `Float64` signature the aggregates used to have. Other types are rejected.
+pub fn coerce_moment_type(name: &str, arg_type: &DataType) -> Result<DataType> {
+    match arg_type {
+        DataType::Int8
+        | DataType::Int16
+        | DataType::Int32
+        | DataType::Int64
+        | DataType::UInt8
+        | DataType::UInt16
+        | DataType::UInt32 => Ok(DataType::Int64),
+        DataType::UInt64 => Ok(DataType::Decimal128(20, 0)),
+        DataType::Decimal128(_, _) => Ok(arg_type.clone()),
+        DataType::Float16
+        | DataType::Float32
+        | DataType::Float64
+        | DataType::Decimal256(_, _)
+        | DataType::Null
+        | DataType::Utf8
+        | DataType::LargeUtf8
+        | DataType::Utf8View => Ok(DataType::Float64),
+        _ => plan_err!("{name} does not support values of type {arg_type}"),
+    }
+}
+
+/// Coerces the arguments of a moment aggregate taking a single value, see [`coerce_moment_type`].
+pub fn coerce_moment_types(name: &str, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+    let [arg_type] = arg_types else {
+        return plan_err!("{name} expects exactly 1 argument, got {}", arg_types.len());
+    };
+    Ok(vec![coerce_moment_type(name, arg_type)?])
+}
+
+/// State fields of the accumulator returned by [`moments_accumulator`] for `input_type`.
+///
+/// The compensations of the power sums are kept next to them, so that merging the states of several partitions is
+/// as precise as summing all the values in one.
+pub fn moments_state_fields(input_type: &DataType) -> Vec<Field> {
+    let power_sums = [
+        Field::new("power_sums", DataType::new_list(DataType::Float64, true), true),
+        Field::new("compensations", DataType::new_list(DataType::Float64, true), true),
+    ];
+
+    let mut fields = vec![Field::new("count", DataType::Float64, true)];
+    if matches!(input_type, DataType::Int64 | DataType::Decimal128(_, _)) {
+        fields.push(Field::new(
+            "shift",
+            DataType::Decimal128(DECIMAL128_MAX_PRECISION, 0),
+            true,
+        ));
+    }
+    fields.extend(power_sums);
+    fields
+}
+
+/// Creates the accumulator of the power sums up to `order` of values of `input_type`.
+///
+/// Integers and decimals are accumulated exactly by a [`ShiftedPowerSumsAccumulator`], other values by a
+/// [`FloatPowerSumsAccumulator`]. Its state has the fields returned by [`moments_state_fields`].
+///
+/// When `weighted`, the second column of the batches holds the `Float64` frequency weights of the values.
+pub fn power_sums_accumulator(
+    input_type: &DataType,
+    order: usize,
+    weighted: bool,
+) -> Result<Box<dyn PowerSumsAccumulator>> {
+    let accumulator: Box<dyn PowerSumsAccumulator> = match input_type {
+        DataType::Int64 => Box::new(ShiftedPowerSumsAccumulator::<Int64Type>::new(order, 0, weighted)),
+        DataType::Decimal128(_, scale) => Box::new(ShiftedPowerSumsAccumulator::<Decimal128Type>::new(
+            order, *scale, weighted,
+        )),
+        DataType::Float64 => Box::new(FloatPowerSumsAccumulator::new(order, weighted)),
+        _ => return exec_err!("Unsupported data type {input_type} for a moment aggregate"),
+    };
+    Ok(accumulator)
+}
+
+/// Creates the accumulator computing `statistic` from the power sums up to `order` of values of `input_type`,
+/// weighted by the second column of the batches when `weighted`.
+pub fn moments_accumulator(
+    input_type: &DataType,
+    order: usize,
+    weighted: bool,
+    statistic: MomentStatistic,
+) -> Result<Box<dyn Accumulator>> {
+    Ok(Box::new(MomentsAccumulator {
+        sums: power_sums_accumulator(input_type, order, weighted)?,
+        statistic,
+    }))
+}
+
+/// Returns the `power_sums` and `compensations` states of the power sums.
+fn power_sums_to_scalars(sums: &PowerSums) -> [ScalarValue; 2] {
+    let to_scalar = |f: fn(&CompensatedSum) -> f64| {
+        let values = sums
+            .sums
+            .iter()
+            .map(|sum| ScalarValue::from(f(sum)))
+            .collect::<Vec<_>>();
+        ScalarValue::List(ScalarValue::new_list_nullable(&values, &DataType::Float64))
+    };
+    [to_scalar(CompensatedSum::sum), to_scalar(CompensatedSum::compensation)]
+}
+
+/// Reads the power sums stored at `index` of the `power_sums` and `compensations` states, as written by
+/// [`power_sums_to_scalars`].
+fn power_sums_from_state(count: f64, states: &[ArrayRef], index: usize) -> Result<PowerSums> {
+    let power_sums = as_list_array(&states[0])?.value(index);
+    let compensations = as_list_array(&states[1])?.value(index);
+    let sums = as_primitive_array::<Float64Type>(&power_sums)?
+        .values()
+        .iter()
+        .zip(as_primitive_array::<Float64Type>(&compensations)?.values())
+        .map(|(sum, compensation)| CompensatedSum::new(*sum, *compensation))
+        .collect();
+    Ok(PowerSums {
+        count,
+        sums,
+        ..PowerSums::new(0)
+    })
+}
+
+/// Calls `f` with each non-null value of the first column and its weight, read from the second column when
+/// `weighted` and 1 otherwise. Rows with a null weight are skipped.
+fn try_for_each_weighted<T: ArrowPrimitiveType>(
+    values: &[ArrayRef],
+    weighted: bool,
+    mut f: impl FnMut(T::Native, f64) -> Result<()>,
+) -> Result<()> {
+    let array = as_primitive_array::<T>(&values[0])?;
+    if !weighted {
+        return array.iter().flatten().try_for_each(|value| f(value, 1.0));
+    }
+
+    let weights = as_primitive_array::<Float64Type>(&values[1])?;
+    array
+        .iter()
+        .zip(weights.iter())
+        .try_for_each(|(value, weight)| match (value, weight) {
+            (Some(value), Some(weight)) if weight >= 0.0 && weight.is_finite() => f(value, weight),
+            (Some(_), Some(weight)) => exec_err!("Weights must be finite and non-negative, got {weight}"),
+            _ => Ok(()),
+        })
+}
+
+/// Accumulates the [`PowerSums`] of values, for aggregates computing one or several statistics from them.
+///
+/// The methods mirror the ones of [`Accumulator`], with [`PowerSumsAccumulator::power_sums`] in place of
+/// `evaluate`.
+pub trait PowerSumsAccumulator: Debug + Send + Sync {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()>;
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()>;
+
+    fn state(&self) -> Vec<ScalarValue>;
+
+    /// Power sums of the values accumulated so far.
+    fn power_sums(&self) -> PowerSums;
+
+    fn size(&self) -> usize;
+}
+
+/// Accumulator of the [`PowerSums`] of `Float64` values.
+#[derive(Debug)]
+pub struct FloatPowerSumsAccumulator {
+    sums: PowerSums,
+    weighted: bool,
+}
+
+impl FloatPowerSumsAccumulator {
+    pub fn new(order: usize, weighted: bool) -> Self {
+        Self {
+            sums: PowerSums::new(order),
+            weighted,
+        }
+    }
+}
+
+impl PowerSumsAccumulator for FloatPowerSumsAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        try_for_each_weighted::<Float64Type>(values, self.weighted, |value, weight| {
+            self.sums.update_weighted(value, weight);
+            Ok(())
+        })
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        let counts = downcast_value!(states[0], Float64Array);
+
+        for i in 0..counts.len() {
+            let count = counts.value(i);
+            if count == 0.0 {
+                continue;
+            }
+            self.sums.merge(&power_sums_from_state(count, &states[1..], i)?);
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> Vec<ScalarValue> {
+        let mut state = vec![ScalarValue::from(self.sums.count)];
+        state.extend(power_sums_to_scalars(&self.sums));
+        state
+    }
+
+    fn power_sums(&self) -> PowerSums {
+        self.sums.clone()
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.sums.sums.capacity() * std::mem::size_of::<CompensatedSum>()
+    }
+}
+
+/// Accumulator of the [`ShiftedPowerSums`] of integer or decimal values, instead of casting the values to `f64`
+/// and summing their powers.
+///
+/// Decimals are accumulated as their unscaled values, and scaled back by [`PowerSums::with_scale`].
+pub struct ShiftedPowerSumsAccumulator<T: ArrowPrimitiveType> {
+    sums: ShiftedPowerSums,
+    scale: i8,
+    weighted: bool,
+    phantom: PhantomData<T>,
+}
+
+impl<T: ArrowPrimitiveType> Debug for ShiftedPowerSumsAccumulator<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShiftedPowerSumsAccumulator")
+            .field("data_type", &T::DATA_TYPE)
+            .field("sums", &self.sums)
+            .field("scale", &self.scale)
+            .field("weighted", &self.weighted)
+            .finish()
+    }
+}
+
+impl<T: ArrowPrimitiveType> ShiftedPowerSumsAccumulator<T> {
+    pub fn new(order: usize, scale: i8, weighted: bool) -> Self {
+        Self {
+            sums: ShiftedPowerSums::new(order),
+            scale,
+            weighted,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> PowerSumsAccumulator for ShiftedPowerSumsAccumulator<T>
+where
+    T: ArrowPrimitiveType + Send + Sync,
+    T::Native: Into<i128>,
+{
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        try_for_each_weighted::<T>(values, self.weighted, |value, weight| {
+            self.sums.update(value.into(), weight);
+            Ok(())
+        })
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        let counts = downcast_value!(states[0], Float64Array);
+        let shifts = downcast_value!(states[1], Decimal128Array);
+
+        for i in 0..counts.len() {
+            let count = counts.value(i);
+            if count == 0.0 {
+                continue;
+            }
+            self.sums.merge(&ShiftedPowerSums {
+                shift: shifts.value(i),
+                sums: power_sums_from_state(count, &states[2..], i)?,
+            });
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> Vec<ScalarValue> {
+        let mut state = vec![
+            ScalarValue::from(self.sums.sums.count),
+            ScalarValue::Decimal128(Some(self.sums.shift), DECIMAL128_MAX_PRECISION, 0),
+        ];
+        state.extend(power_sums_to_scalars(&self.sums.sums));
+        state
+    }
+
+    fn power_sums(&self) -> PowerSums {
+        self.sums.power_sums().with_scale(self.scale as i32)
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.sums.sums.sums.capacity() * std::mem::size_of::<CompensatedSum>()
+    }
+}
+
+/// Accumulator computing a [`MomentStatistic`] from the power sums of a [`PowerSumsAccumulator`].
+pub struct MomentsAccumulator {
+    sums: Box<dyn PowerSumsAccumulator>,
+    statistic: MomentStatistic,
+}
+
+impl Debug for MomentsAccumulator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MomentsAccumulator").field("sums", &self.sums).finish()
+    }
+}
+
+impl Accumulator for MomentsAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        self.sums.update_batch(values)
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        self.sums.merge_batch(states)
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        Ok(ScalarValue::Float64((self.statistic)(&self.sums.power_sums())))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.sums.size()
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        Ok(self.sums.state())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn power_sums(values: &[f64], order: usize) -> PowerSums {
+        let mut sums = PowerSums::new(order);
+        values.iter().for_each(|value| sums.update(*value));
+        sums
+    }
+
+    fn shifted_power_sums(values: &[i128]) -> ShiftedPowerSums {
+        let mut sums = ShiftedPowerSums::new(4);
+        values.iter().for_each(|value| sums.update(*value, 1.0));
+        sums
+    }
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.unwrap();
+        assert!((actual - expected).abs() < 1e-9, "expected {expected}, got {actual}");
+    }
+
+    #[test]
+    fn test_power_sums_central_moments() {
+        let sums = power_sums(&[1.0, 2.0, 2.0, 3.0, 3.0, 3.0], 4);
+        let mean = 14.0 / 6.0;
+        let central = |k: i32| {
+            [1.0, 2.0, 2.0, 3.0, 3.0, 3.0]
+                .iter()
+                .map(|x: &f64| (x - mean).powi(k))
+                .sum::<f64>()
+                / 6.0
+        };
+
+        assert_close(sums.central_moment(1), 0.0);
+        assert_close(sums.central_moment(2), central(2));
+        assert_close(sums.central_moment(3), central(3));
+        assert_close(sums.central_moment(4), central(4));
+        assert_close(sums.standardized_moment(4), central(4) / central(2).powi(2));
+        assert_eq!(PowerSums::new(4).central_moment(2), None);
+        assert_eq!(power_sums(&[1.0, 1.0], 4).standardized_moment(3), None);
+    }
+
+    #[test]
+    fn test_power_sums_shifted_and_scale() {
+        let shifted = power_sums(&[1.0, 2.0, 4.0], 4).shifted(10.0);
+        assert_eq!(shifted, power_sums(&[11.0, 12.0, 14.0], 4));
+
+        let scaled = power_sums(&[10.0, 20.0, 40.0], 4).with_scale(1);
+        let expected = power_sums(&[1.0, 2.0, 4.0], 4);
+        assert_close(scaled.mean(), expected.mean().unwrap());
+        assert_close(scaled.central_moment(2), expected.central_moment(2).unwrap());
+        assert_close(scaled.central_moment(3), expected.central_moment(3).unwrap());
+    }
+
+    #[test]
+    fn test_shifted_power_sums_update() {
+        let sums = shifted_power_sums(&[10, 11, 13]).power_sums();
+        let distances = power_sums(&[0.0, 1.0, 3.0], 4);
+        assert_eq!((sums.sum(1), sums.sum(4)), (distances.sum(1), distances.sum(4)));
+        assert_eq!(sums.central_moment(3), distances.central_moment(3));
+        assert_close(sums.mean(), 34.0 / 3.0);
+    }
+
+    #[test]
+    fn test_shifted_power_sums_merge() {
+        let mut merged = shifted_power_sums(&[10, 11]);
+        merged.merge(&shifted_power_sums(&[13, 7, 9]));
+        merged.merge(&ShiftedPowerSums::new(4));
+
+        let expected = shifted_power_sums(&[10, 11, 13, 7, 9]);
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn test_shifted_power_sums_merge_into_empty() {
+        let mut merged = ShiftedPowerSums::new(4);
+        merged.merge(&shifted_power_sums(&[i128::MAX - 1, i128::MAX]));
+
+        assert_eq!(merged, shifted_power_sums(&[i128::MAX - 1, i128::MAX]));
+    }
+
+    #[test]
+    fn test_shifted_power_sums_far_apart() {
+        let sums = shifted_power_sums(&[i128::MAX, i128::MIN]).power_sums();
+        assert_close(sums.mean().map(|mean| mean / i128::MAX as f64), 0.0);
+        assert_close(sums.standardized_moment(3), 0.0);
+        assert_close(sums.standardized_moment(4), 1.0);
+    }
+
+    #[test]
+    fn test_power_sums_weighted() {
+        let mut weighted = PowerSums::new(4);
+        weighted.update_weighted(1.0, 2.0);
+        weighted.update_weighted(5.0, 3.0);
+        weighted.update_weighted(7.0, 0.0);
+        assert_eq!(weighted, power_sums(&[1.0, 1.0, 5.0, 5.0, 5.0], 4));
+
+        let mut weighted = ShiftedPowerSums::new(4);
+        weighted.update(10, 2.0);
+        weighted.update(13, 1.0);
+        assert_eq!(weighted, shifted_power_sums(&[10, 10, 13]));
+    }
+
+    #[test]
+    fn test_moments_accumulator_weights() -> Result<()> {
+        let statistic: MomentStatistic = Arc::new(|sums| sums.central_moment(3));
+        let values: ArrayRef = Arc::new(Float64Array::from(vec![Some(1.0), Some(2.0), None, Some(3.0)]));
+        let weights: ArrayRef = Arc::new(Float64Array::from(vec![Some(3.0), None, Some(1.0), Some(2.0)]));
+
+        let mut weighted = moments_accumulator(&DataType::Float64, 3, true, Arc::clone(&statistic))?;
+        weighted.update_batch(&[values, weights])?;
+
+        let expanded: ArrayRef = Arc::new(Float64Array::from(vec![1.0, 1.0, 1.0, 3.0, 3.0]));
+        let mut expected = moments_accumulator(&DataType::Float64, 3, false, statistic)?;
+        expected.update_batch(&[expanded])?;
+        assert_eq!(weighted.evaluate()?, expected.evaluate()?);
+
+        let negative: ArrayRef = Arc::new(Float64Array::from(vec![-1.0]));
+        let err = weighted
+            .update_batch(&[Arc::new(Float64Array::from(vec![1.0])), negative])
+            .unwrap_err();
+        assert!(err.to_string().contains("Weights must be finite and non-negative"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_moments_accumulator_merge() -> Result<()> {
+        let statistic: MomentStatistic = Arc::new(|sums| sums.central_moment(2));
+        let values: ArrayRef = Arc::new(Float64Array::from(vec![1.0, 2.0, 2.0, 3.0, 3.0, 3.0]));
+
+        let mut acc1 = moments_accumulator(&DataType::Float64, 2, false, Arc::clone(&statistic))?;
+        acc1.update_batch(&[values.slice(0, 2)])?;
+        let mut acc2 = moments_accumulator(&DataType::Float64, 2, false, Arc::clone(&statistic))?;
+        acc2.update_batch(&[values.slice(2, 4)])?;
+
+        let states = acc2
+            .state()?
+            .into_iter()
+            .map(|state| state.to_array())
+            .collect::<Result<Vec<_>>>()?;
+        acc1.merge_batch(&states)?;
+
+        let mut expected = moments_accumulator(&DataType::Float64, 2, false, statistic)?;
+        expected.update_batch(&[values])?;
+        assert_eq!(acc1.evaluate()?, expected.evaluate()?);
+        Ok(())
+    }
+}
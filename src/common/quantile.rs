@@ -0,0 +1,283 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::fmt::Debug;
+
+use arrow::array::{ArrayRef, ArrowNativeTypeOp, ArrowPrimitiveType, PrimitiveArray};
+use arrow::datatypes::{DataType, IntervalMonthDayNano, TimeUnit};
+use datafusion::arrow;
+use datafusion::common::cast::{as_list_array, as_primitive_array};
+use datafusion::common::utils::array_into_list_array_nullable;
+use datafusion::error::Result;
+use datafusion::scalar::ScalarValue;
+
+const NANOS_PER_DAY: i128 = 86_400_000_000_000;
+
+/// Non-null values of a column collected by an exact quantile accumulator, with a `List` state holding them.
+pub struct CollectedValues<T: ArrowPrimitiveType> {
+    values: Vec<T::Native>,
+    data_type: DataType,
+}
+
+impl<T: ArrowPrimitiveType> Debug for CollectedValues<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CollectedValues")
+            .field("values", &self.values)
+            .field("data_type", &self.data_type)
+            .finish()
+    }
+}
+
+impl<T: ArrowPrimitiveType> CollectedValues<T> {
+    pub fn new(data_type: &DataType) -> Self {
+        Self {
+            values: vec![],
+            data_type: data_type.clone(),
+        }
+    }
+
+    pub fn update(&mut self, values: &ArrayRef) -> Result<()> {
+        let array = as_primitive_array::<T>(values)?;
+        self.values.extend(array.iter().flatten());
+        Ok(())
+    }
+
+    /// Merges the `List` state written by [`Self::state`].
+    pub fn merge(&mut self, state: &ArrayRef) -> Result<()> {
+        let lists = as_list_array(state)?;
+        for list in lists.iter().flatten() {
+            self.update(&list)?;
+        }
+        Ok(())
+    }
+
+    pub fn state(&self) -> ScalarValue {
+        let array =
+            PrimitiveArray::<T>::from_iter_values(self.values.iter().copied()).with_data_type(self.data_type.clone());
+        ScalarValue::List(std::sync::Arc::new(array_into_list_array_nullable(
+            std::sync::Arc::new(array),
+        )))
+    }
+
+    /// Returns the collected values sorted in ascending order.
+    pub fn sorted(&mut self) -> &[T::Native] {
+        self.values.sort_unstable_by(|a, b| a.compare(*b));
+        &self.values
+    }
+
+    pub fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.values.capacity() * std::mem::size_of::<T::Native>()
+    }
+}
+
+/// Returns the interpolated `q` quantile of sorted values, i.e. the value at the fractional position `q * (n - 1)`.
+pub fn interpolate_f64(sorted: &[f64], q: f64) -> Option<f64> {
+    let (lo, hi, fraction) = interpolation_bounds(sorted.len(), q)?;
+    Some(sorted[lo] + (sorted[hi] - sorted[lo]) * fraction)
+}
+
+/// Returns the interpolated `q` quantile of sorted exact values, rounded half away from zero.
+pub fn interpolate_i128(sorted: &[i128], q: f64) -> Option<i128> {
+    let (lo, hi, fraction) = interpolation_bounds(sorted.len(), q)?;
+    Some(lerp_i128(sorted[lo], sorted[hi], fraction))
+}
+
+/// Returns the value at `fraction` of the way from `lo` to `hi`, rounded half away from zero.
+pub fn lerp_i128(lo: i128, hi: i128, fraction: f64) -> i128 {
+    let Some(distance) = hi.checked_sub(lo) else {
+        // Values of opposite signs too far apart for `i128` are interpolated in `f64`, whose precision is
+        // negligible next to their distance
+        let value = lo as f64 + (hi as f64 - lo as f64) * fraction;
+        return (value.round() as i128).clamp(lo, hi);
+    };
+    let offset = distance as f64 * fraction;
+    // Only the offset from the lower value goes through `f64`, so large values keep their precision
+    let value = lo + offset.trunc() as i128;
+    let rest = offset.fract();
+    if rest > 0.5 || (rest == 0.5 && value >= 0) {
+        value + 1
+    } else {
+        value
+    }
+}
+
+/// Returns the indexes of the values surrounding the fractional position `q * (len - 1)` of the `q` quantile, and
+/// the position between them.
+pub fn interpolation_bounds(len: usize, q: f64) -> Option<(usize, usize, f64)> {
+    if len == 0 {
+        return None;
+    }
+    let position = q * (len - 1) as f64;
+    let lo = position.floor() as usize;
+    let hi = position.ceil() as usize;
+    Some((lo, hi, position - lo as f64))
+}
+
+/// Returns the index of the `q` quantile among `len` sorted values without interpolation, i.e. the first value
+/// such that a fraction `q` of the values are lower or equal, as DuckDB does.
+pub fn discrete_index(len: usize, q: f64) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+    Some(((len as f64 * q).ceil() as usize).clamp(1, len) - 1)
+}
+
+/// Number of nanoseconds in one unit of a temporal type, used to compute distances between temporal values.
+pub fn nanos_per_unit(data_type: &DataType) -> Option<i128> {
+    let unit = match data_type {
+        DataType::Date32 => return Some(NANOS_PER_DAY),
+        DataType::Date64 => TimeUnit::Millisecond,
+        DataType::Time32(unit) | DataType::Time64(unit) | DataType::Timestamp(unit, _) => *unit,
+        _ => return None,
+    };
+    Some(match unit {
+        TimeUnit::Second => 1_000_000_000,
+        TimeUnit::Millisecond => 1_000_000,
+        TimeUnit::Microsecond => 1_000,
+        TimeUnit::Nanosecond => 1,
+    })
+}
+
+/// Converts a duration in nanoseconds to an interval of days and nanoseconds, like DuckDB does.
+pub fn nanos_to_interval(nanos: i128) -> IntervalMonthDayNano {
+    let days = (nanos / NANOS_PER_DAY) as i32;
+    let nanos = (nanos % NANOS_PER_DAY) as i64;
+    IntervalMonthDayNano::new(0, days, nanos)
+}
+
+/// Calls `$fn::<T>(...)` with the [`ArrowPrimitiveType`] `T` of `$data_type`, for the decimal and temporal types
+/// whose values exact quantile aggregates handle as `i128`, or evaluates `$otherwise` for other types.
+macro_rules! dispatch_exact_quantile_type {
+    ($data_type:expr, $fn:ident($($arg:expr),*), $otherwise:expr) => {{
+        use datafusion::arrow::datatypes::*;
+        match $data_type {
+            DataType::Decimal128(_, _) => $fn::<Decimal128Type>($($arg),*),
+            DataType::Date32 => $fn::<Date32Type>($($arg),*),
+            DataType::Date64 => $fn::<Date64Type>($($arg),*),
+            DataType::Time32(TimeUnit::Second) => $fn::<Time32SecondType>($($arg),*),
+            DataType::Time32(TimeUnit::Millisecond) => $fn::<Time32MillisecondType>($($arg),*),
+            DataType::Time64(TimeUnit::Microsecond) => $fn::<Time64MicrosecondType>($($arg),*),
+            DataType::Time64(TimeUnit::Nanosecond) => $fn::<Time64NanosecondType>($($arg),*),
+            DataType::Timestamp(TimeUnit::Second, _) => $fn::<TimestampSecondType>($($arg),*),
+            DataType::Timestamp(TimeUnit::Millisecond, _) => $fn::<TimestampMillisecondType>($($arg),*),
+            DataType::Timestamp(TimeUnit::Microsecond, _) => $fn::<TimestampMicrosecondType>($($arg),*),
+            DataType::Timestamp(TimeUnit::Nanosecond, _) => $fn::<TimestampNanosecondType>($($arg),*),
+            _ => $otherwise,
+        }
+    }};
+}
+pub(crate) use dispatch_exact_quantile_type;
+
+/// Calls `$fn::<T>(...)` with the [`ArrowPrimitiveType`] `T` of `$data_type`, for all the types supported by exact
+/// quantile aggregates, or evaluates `$otherwise` for other types.
+macro_rules! dispatch_quantile_type {
+    ($data_type:expr, $fn:ident($($arg:expr),*), $otherwise:expr) => {{
+        use datafusion::arrow::datatypes::*;
+        match $data_type {
+            DataType::Int64 => $fn::<Int64Type>($($arg),*),
+            DataType::UInt64 => $fn::<UInt64Type>($($arg),*),
+            DataType::Float64 => $fn::<Float64Type>($($arg),*),
+            data_type => $crate::common::quantile::dispatch_exact_quantile_type!(data_type, $fn($($arg),*), $otherwise),
+        }
+    }};
+}
+pub(crate) use dispatch_quantile_type;
+
+/// Coerces the values of an exact quantile aggregate: numbers are cast to `Float64`, decimals and temporal values
+/// are kept as they are.
+pub fn coerce_quantile_type(data_type: &DataType) -> Option<DataType> {
+    match data_type {
+        DataType::Null => Some(DataType::Float64),
+        _ if data_type.is_numeric()
+            && !matches!(data_type, DataType::Decimal128(_, _) | DataType::Decimal256(_, _)) =>
+        {
+            Some(DataType::Float64)
+        }
+        DataType::Decimal128(_, _)
+        | DataType::Date32
+        | DataType::Date64
+        | DataType::Time32(TimeUnit::Second | TimeUnit::Millisecond)
+        | DataType::Time64(TimeUnit::Microsecond | TimeUnit::Nanosecond)
+        | DataType::Timestamp(_, _) => Some(data_type.clone()),
+        _ => None,
+    }
+}
+
+/// Coerces the values of an exact quantile aggregate that does not interpolate: integers are cast to `Int64` or
+/// `UInt64` and floats to `Float64`, decimals and temporal values are kept as they are.
+pub fn coerce_discrete_quantile_type(data_type: &DataType) -> Option<DataType> {
+    match data_type {
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 => Some(DataType::Int64),
+        DataType::UInt8 | DataType::UInt16 | DataType::UInt32 | DataType::UInt64 => Some(DataType::UInt64),
+        _ => coerce_quantile_type(data_type),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::datatypes::Date32Type;
+
+    #[test]
+    fn test_interpolate() {
+        assert_eq!(interpolate_f64(&[1.0, 2.0, 4.0, 8.0], 0.5), Some(3.0));
+        assert_eq!(interpolate_f64(&[1.0, 2.0, 4.0, 8.0], 0.0), Some(1.0));
+        assert_eq!(interpolate_f64(&[1.0, 2.0, 4.0, 8.0], 1.0), Some(8.0));
+        assert_eq!(interpolate_f64(&[], 0.5), None);
+        assert_eq!(interpolate_i128(&[10, 15, 20], 0.5), Some(15));
+        assert_eq!(interpolate_i128(&[10, 15], 0.5), Some(13));
+        assert_eq!(interpolate_i128(&[-15, -10], 0.5), Some(-13));
+        assert_eq!(interpolate_i128(&[-1, 0], 0.5), Some(-1));
+        assert_eq!(interpolate_i128(&[i128::MIN, i128::MAX], 0.5), Some(0));
+        assert_eq!(interpolate_i128(&[i128::MIN, i128::MAX], 1.0), Some(i128::MAX));
+        assert_eq!(lerp_i128(-i128::MAX, i128::MAX, 0.75), i128::MAX / 2 + 1);
+    }
+
+    #[test]
+    fn test_discrete_index() {
+        assert_eq!(discrete_index(4, 0.5), Some(1));
+        assert_eq!(discrete_index(5, 0.5), Some(2));
+        assert_eq!(discrete_index(5, 0.0), Some(0));
+        assert_eq!(discrete_index(5, 1.0), Some(4));
+        assert_eq!(discrete_index(10, 0.25), Some(2));
+        assert_eq!(discrete_index(0, 0.5), None);
+    }
+
+    #[test]
+    fn test_collected_values_state() -> Result<()> {
+        let mut values = CollectedValues::<Date32Type>::new(&DataType::Date32);
+        values.update(
+            &(std::sync::Arc::new(arrow::array::Date32Array::from(vec![Some(3), None, Some(1)])) as ArrayRef),
+        )?;
+
+        let mut merged = CollectedValues::<Date32Type>::new(&DataType::Date32);
+        merged.merge(&values.state().to_array()?)?;
+        merged.merge(&values.state().to_array()?)?;
+        assert_eq!(merged.sorted(), &[1, 1, 3, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_nanos_to_interval() {
+        assert_eq!(nanos_to_interval(NANOS_PER_DAY + 5), IntervalMonthDayNano::new(0, 1, 5));
+        assert_eq!(
+            nanos_per_unit(&DataType::Timestamp(TimeUnit::Microsecond, None)),
+            Some(1_000)
+        );
+        assert_eq!(nanos_per_unit(&DataType::Float64), None);
+    }
+}
@@ -0,0 +1,194 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::cmp::Ordering;
+use std::collections::hash_map::RandomState;
+use std::collections::BinaryHeap;
+use std::hash::{BuildHasher, Hasher};
+
+use datafusion::error::Result;
+
+/// The SplitMix64 pseudo-random generator, small and fast, and reproducible from a seed.
+#[derive(Debug, Clone)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Creates a generator with a random seed.
+    pub fn from_entropy() -> Self {
+        Self::new(RandomState::new().build_hasher().finish())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// A uniform sample without replacement of at most `capacity` items of a stream, kept by priority sampling
+/// (bottom-k): each item comes with a uniformly distributed priority, and the sample is made of the `capacity` items
+/// of lowest priorities.
+///
+/// The sample only depends on the items and their priorities, not on their order nor on how the stream is split, as
+/// two reservoirs are merged by keeping the items of lowest priorities of both.
+#[derive(Debug)]
+pub struct Reservoir<T> {
+    capacity: usize,
+    /// The sampled items, with the one of highest priority on top.
+    items: BinaryHeap<Prioritized<T>>,
+}
+
+impl<T> Reservoir<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            items: BinaryHeap::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns the sampled items and their priorities, by increasing priority.
+    pub fn items(&self) -> Vec<(u64, &T)> {
+        let mut items = self
+            .items
+            .iter()
+            .map(|item| (item.priority, &item.item))
+            .collect::<Vec<_>>();
+        items.sort_unstable_by_key(|(priority, _)| *priority);
+        items
+    }
+
+    /// Adds an item of the given priority, calling `make_item` only when the item is kept in the sample.
+    pub fn add_with(&mut self, priority: u64, make_item: impl FnOnce() -> Result<T>) -> Result<()> {
+        if self.items.len() < self.capacity {
+            self.items.push(Prioritized {
+                priority,
+                item: make_item()?,
+            });
+        } else if let Some(mut highest) = self.items.peek_mut() {
+            if priority < highest.priority {
+                *highest = Prioritized {
+                    priority,
+                    item: make_item()?,
+                };
+            }
+        }
+        Ok(())
+    }
+
+    /// Size in bytes of the reservoir, including `Self` but not the heap allocations of the items.
+    pub fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.items.capacity() * std::mem::size_of::<Prioritized<T>>()
+    }
+}
+
+/// An item of a [`Reservoir`], ordered by its priority only.
+#[derive(Debug)]
+struct Prioritized<T> {
+    priority: u64,
+    item: T,
+}
+
+impl<T> PartialEq for Prioritized<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl<T> Eq for Prioritized<T> {}
+
+impl<T> PartialOrd for Prioritized<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Prioritized<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.cmp(&other.priority)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the sampled items of a reservoir of `items` with random priorities.
+    fn sample_of(capacity: usize, seed: u64, items: impl IntoIterator<Item = u64>) -> Vec<u64> {
+        let mut rng = SplitMix64::new(seed);
+        let mut reservoir = Reservoir::new(capacity);
+        for item in items {
+            reservoir.add_with(rng.next_u64(), || Ok(item)).unwrap();
+        }
+        reservoir.items().into_iter().map(|(_, item)| *item).collect()
+    }
+
+    #[test]
+    fn test_split_mix() {
+        // reference values of SplitMix64 seeded with 0
+        let mut rng = SplitMix64::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+    }
+
+    #[test]
+    fn test_uniform_sample() {
+        let mut sample = sample_of(5, 1, 0..3);
+        sample.sort_unstable();
+        assert_eq!(sample, [0, 1, 2]);
+
+        // each of 10 items is kept in about 3 of 10 samples of 3 items
+        let mut kept = [0; 10];
+        for seed in 0..10_000 {
+            for item in sample_of(3, seed, 0..10) {
+                kept[item as usize] += 1;
+            }
+        }
+        assert!(kept.iter().all(|kept| (2800..3200).contains(kept)), "{kept:?}");
+    }
+
+    #[test]
+    fn test_order_independent_sample() {
+        // the items of lowest priorities are kept whatever the order and the splitting of the stream
+        let priority = |item: u64| SplitMix64::new(item).next_u64();
+        let mut reservoir = Reservoir::new(10);
+        for item in 0..1000 {
+            reservoir.add_with(priority(item), || Ok(item)).unwrap();
+        }
+        let mut first = Reservoir::new(10);
+        let mut second = Reservoir::new(10);
+        for item in (0..1000).rev() {
+            let half = if item % 3 == 0 { &mut first } else { &mut second };
+            half.add_with(priority(item), || Ok(item)).unwrap();
+        }
+        for (priority, item) in second.items() {
+            first.add_with(priority, || Ok(*item)).unwrap();
+        }
+        assert_eq!(first.items(), reservoir.items());
+        assert_eq!(reservoir.items().len(), 10);
+    }
+}
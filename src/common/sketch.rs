@@ -0,0 +1,195 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::fmt::Debug;
+
+use datafusion::arrow::datatypes::DataType;
+use datafusion::common::{exec_err, plan_err};
+use datafusion::error::Result;
+use datafusion::logical_expr::function::AccumulatorArgs;
+use datafusion::physical_expr::expressions::Literal;
+use datafusion::scalar::ScalarValue;
+
+/// A mergeable summary of a stream of values with a stable binary serialization, so it can be stored in a table and
+/// rolled up later by a `*_merge` or `*_union` aggregate.
+pub trait Sketch: Debug + Send + Sync + Sized {
+    /// Merges `other` into this sketch, as if this sketch had seen the values of both.
+    fn merge(&mut self, other: &Self);
+
+    /// Appends the binary serialization of this sketch to `buffer`.
+    fn serialize(&self, buffer: &mut Vec<u8>);
+
+    /// Reads a sketch written by [`Self::serialize`], rejecting truncated or inconsistent input.
+    fn deserialize(bytes: &[u8]) -> Result<Self>;
+
+    /// Size in bytes including `Self`.
+    fn size(&self) -> usize;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = vec![];
+        self.serialize(&mut buffer);
+        buffer
+    }
+}
+
+/// A [`Sketch`] of numbers estimating their quantiles.
+pub trait QuantileSketch: Sketch {
+    /// Number of values summarized by the sketch.
+    fn count(&self) -> u64;
+
+    /// Adds the non-NaN values to the sketch.
+    fn update(&mut self, values: &[f64]);
+
+    /// Estimates the quantile `q` of the values, `None` when the sketch is empty.
+    fn quantile(&self, q: f64) -> Option<f64>;
+}
+
+/// A [`Sketch`] of the distinct items of a column, hashed by
+/// [`for_each_item_hash`](crate::common::datasketches::for_each_item_hash).
+pub trait DistinctSketch: Sketch {
+    /// Adds an item given the two halves of its 128-bit hash.
+    fn update_hash(&mut self, hash: (u64, u64));
+}
+
+/// Little-endian cursor over a serialized sketch, returning an error instead of panicking on truncated input.
+pub struct SketchReader<'a> {
+    name: &'static str,
+    bytes: &'a [u8],
+}
+
+macro_rules! read_le {
+    ($FN:ident, $TYPE:ty) => {
+        pub fn $FN(&mut self) -> Result<$TYPE> {
+            let bytes = self.read_bytes(std::mem::size_of::<$TYPE>())?;
+            Ok(<$TYPE>::from_le_bytes(bytes.try_into().unwrap()))
+        }
+    };
+}
+
+impl<'a> SketchReader<'a> {
+    /// Creates a reader of a sketch named `name` in error messages.
+    pub fn new(name: &'static str, bytes: &'a [u8]) -> Self {
+        Self { name, bytes }
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return exec_err!("Invalid {} sketch: unexpected end of input", self.name);
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    read_le!(read_u8, u8);
+    read_le!(read_u16, u16);
+    read_le!(read_u32, u32);
+    read_le!(read_u64, u64);
+    read_le!(read_f64, f64);
+
+    /// Checks the header written by [`write_header`].
+    pub fn read_header(&mut self, family: u8, version: u8) -> Result<()> {
+        let found_family = self.read_u8()?;
+        if found_family != family {
+            return exec_err!("Invalid {} sketch: unexpected sketch family {found_family}", self.name);
+        }
+        let found_version = self.read_u8()?;
+        if found_version != version {
+            return exec_err!("Invalid {} sketch: unsupported version {found_version}", self.name);
+        }
+        Ok(())
+    }
+
+    /// Checks that the whole input was read.
+    pub fn finish(self) -> Result<()> {
+        if !self.bytes.is_empty() {
+            return exec_err!("Invalid {} sketch: {} trailing bytes", self.name, self.bytes.len());
+        }
+        Ok(())
+    }
+
+    /// Returns an error for input that was read successfully but describes an impossible sketch.
+    pub fn invalid<T>(&self, reason: impl std::fmt::Display) -> Result<T> {
+        exec_err!("Invalid {} sketch: {reason}", self.name)
+    }
+}
+
+/// Coerces the argument of a function taking serialized sketches to `Binary`.
+pub fn coerce_sketch_type(name: &str, arg_type: &DataType) -> Result<DataType> {
+    match arg_type {
+        DataType::Binary | DataType::LargeBinary | DataType::BinaryView | DataType::Null => Ok(DataType::Binary),
+        _ => plan_err!("{name} expects a binary sketch, got {arg_type}"),
+    }
+}
+
+/// Returns the optional integer argument at `index` of a sketch aggregate, e.g. the `lg_k` of `hll_sketch_agg`,
+/// which must be a constant. Returns `None` when the argument is missing or null.
+pub fn sketch_parameter(
+    acc_args: &AccumulatorArgs,
+    index: usize,
+    function: &str,
+    parameter: &str,
+) -> Result<Option<i64>> {
+    let Some(expr) = acc_args.exprs.get(index) else {
+        return Ok(None);
+    };
+    match expr.as_any().downcast_ref::<Literal>().map(|literal| literal.value()) {
+        Some(ScalarValue::Int64(value)) => Ok(*value),
+        _ => plan_err!("The {parameter} of {function} must be a constant integer"),
+    }
+}
+
+/// Writes the two bytes identifying the sketch family and its serialization version.
+pub fn write_header(buffer: &mut Vec<u8>, family: u8, version: u8) {
+    buffer.push(family);
+    buffer.push(version);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reader() {
+        let mut buffer = vec![];
+        write_header(&mut buffer, 7, 1);
+        buffer.extend_from_slice(&513u16.to_le_bytes());
+        buffer.extend_from_slice(&1.5f64.to_le_bytes());
+
+        let mut reader = SketchReader::new("test", &buffer);
+        reader.read_header(7, 1).unwrap();
+        assert_eq!(reader.read_u16().unwrap(), 513);
+        assert_eq!(reader.read_f64().unwrap(), 1.5);
+        reader.finish().unwrap();
+
+        let err = SketchReader::new("test", &buffer).read_header(8, 1).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Invalid test sketch: unexpected sketch family 7"));
+        let err = SketchReader::new("test", &buffer).read_header(7, 2).unwrap_err();
+        assert!(err.to_string().contains("unsupported version 1"));
+
+        let mut reader = SketchReader::new("test", &buffer[..5]);
+        reader.read_header(7, 1).unwrap();
+        reader.read_u16().unwrap();
+        let err = reader.read_f64().unwrap_err();
+        assert!(err.to_string().contains("unexpected end of input"));
+
+        let mut reader = SketchReader::new("test", &buffer);
+        reader.read_header(7, 1).unwrap();
+        assert!(reader.finish().unwrap_err().to_string().contains("10 trailing bytes"));
+    }
+}
@@ -0,0 +1,236 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use arrow::datatypes::DataType;
+use datafusion::arrow;
+use datafusion::common::cast::as_float64_array;
+use datafusion::error::Result;
+use datafusion::scalar::ScalarValue;
+use datafusion_functions_aggregate_common::tdigest::TDigest;
+
+use crate::common::sketch::{write_header, QuantileSketch, Sketch, SketchReader};
+
+/// Default compression of a [`TDigestSketch`], the one `approx_percentile_cont` uses.
+pub const DEFAULT_COMPRESSION: u32 = datafusion_functions_aggregate_common::tdigest::DEFAULT_MAX_SIZE as u32;
+/// Largest compression accepted by a [`TDigestSketch`], which bounds the memory of a sketch.
+pub const MAX_COMPRESSION: u32 = 100_000;
+
+const FAMILY: u8 = b'T';
+const VERSION: u8 = 1;
+
+/// A t-digest quantile sketch, backed by the [`TDigest`] of DataFusion's `approx_percentile_cont`.
+///
+/// The compression is the maximum number of centroids kept by the digest: larger values are more accurate and use
+/// more memory. The digest is most accurate at the extreme quantiles.
+///
+/// The serialization is little-endian: the family byte `T` and the version `1`, the compression as `u32`, the number
+/// of values as `u64`, their sum, minimum and maximum as `f64`, the number of centroids as `u32` followed by the mean
+/// and weight of each centroid as `f64`, in ascending order of mean.
+#[derive(Debug, Clone)]
+pub struct TDigestSketch {
+    digest: TDigest,
+}
+
+impl TDigestSketch {
+    pub fn new(compression: u32) -> Self {
+        debug_assert!((1..=MAX_COMPRESSION).contains(&compression));
+        Self {
+            digest: TDigest::new(compression as usize),
+        }
+    }
+
+    pub fn compression(&self) -> u32 {
+        self.digest.max_size() as u32
+    }
+}
+
+impl Sketch for TDigestSketch {
+    /// Merges `other` into this sketch, keeping the compression of this sketch unless it is empty.
+    fn merge(&mut self, other: &Self) {
+        if other.digest.count() == 0 {
+            return;
+        }
+        if self.digest.count() == 0 {
+            *self = other.clone();
+            return;
+        }
+        self.digest = TDigest::merge_digests([&self.digest, &other.digest]);
+    }
+
+    fn serialize(&self, buffer: &mut Vec<u8>) {
+        let state = self.digest.to_scalar_state();
+        let [ScalarValue::UInt64(Some(max_size)), ScalarValue::Float64(Some(sum)), ScalarValue::UInt64(Some(count)), ScalarValue::Float64(Some(max)), ScalarValue::Float64(Some(min)), ScalarValue::List(centroids)] =
+            state.as_slice()
+        else {
+            unreachable!("unexpected TDigest state {state:?}")
+        };
+        let centroids = as_float64_array(centroids.values()).expect("TDigest centroids are Float64");
+
+        write_header(buffer, FAMILY, VERSION);
+        buffer.extend_from_slice(&(*max_size as u32).to_le_bytes());
+        buffer.extend_from_slice(&count.to_le_bytes());
+        for value in [sum, min, max] {
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+        buffer.extend_from_slice(&((centroids.len() / 2) as u32).to_le_bytes());
+        for value in centroids.values().iter() {
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self> {
+        let mut reader = SketchReader::new("t-digest", bytes);
+        reader.read_header(FAMILY, VERSION)?;
+        let compression = reader.read_u32()?;
+        if !(1..=MAX_COMPRESSION).contains(&compression) {
+            return reader.invalid(format!(
+                "compression must be between 1 and {MAX_COMPRESSION}, got {compression}"
+            ));
+        }
+        let count = reader.read_u64()?;
+        let sum = reader.read_f64()?;
+        let min = reader.read_f64()?;
+        let max = reader.read_f64()?;
+        let num_centroids = reader.read_u32()? as usize;
+        let bytes = reader.read_bytes(num_centroids.saturating_mul(16))?;
+        let centroids: Vec<f64> = bytes
+            .chunks_exact(8)
+            .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        if (count == 0) != (num_centroids == 0) {
+            return reader.invalid(format!("{num_centroids} centroids for {count} values"));
+        }
+        if count > 0 && (min.is_nan() || max.is_nan() || min > max) {
+            return reader.invalid("the minimum and maximum are not ordered");
+        }
+        let mut previous_mean = f64::NEG_INFINITY;
+        for centroid in centroids.chunks_exact(2) {
+            let [mean, weight] = [centroid[0], centroid[1]];
+            if !(previous_mean..=max).contains(&mean) || mean < min {
+                return reader.invalid("centroids out of order");
+            }
+            if !(weight.is_finite() && weight > 0.0) {
+                return reader.invalid(format!("invalid centroid weight {weight}"));
+            }
+            previous_mean = mean;
+        }
+        reader.finish()?;
+
+        let centroids = centroids
+            .into_iter()
+            .map(|value| ScalarValue::Float64(Some(value)))
+            .collect::<Vec<_>>();
+        let digest = TDigest::from_scalar_state(&[
+            ScalarValue::UInt64(Some(compression as u64)),
+            ScalarValue::Float64(Some(sum)),
+            ScalarValue::UInt64(Some(count)),
+            ScalarValue::Float64(Some(if count == 0 { f64::NAN } else { max })),
+            ScalarValue::Float64(Some(if count == 0 { f64::NAN } else { min })),
+            ScalarValue::List(ScalarValue::new_list_nullable(&centroids, &DataType::Float64)),
+        ]);
+        Ok(Self { digest })
+    }
+
+    fn size(&self) -> usize {
+        self.digest.size()
+    }
+}
+
+impl QuantileSketch for TDigestSketch {
+    fn count(&self) -> u64 {
+        self.digest.count()
+    }
+
+    fn update(&mut self, values: &[f64]) {
+        let values: Vec<f64> = values.iter().copied().filter(|value| !value.is_nan()).collect();
+        if !values.is_empty() {
+            self.digest = self.digest.merge_unsorted_f64(values);
+        }
+    }
+
+    /// Interpolates between the centroids surrounding the quantile. `0` and `1` return the exact minimum and maximum.
+    fn quantile(&self, q: f64) -> Option<f64> {
+        (self.digest.count() > 0).then(|| self.digest.estimate_quantile(q))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scrambled(n: u64) -> Vec<f64> {
+        (0..n).map(|i| ((i * 7919) % n) as f64).collect()
+    }
+
+    #[test]
+    fn test_quantiles_and_merge() {
+        let n = 100_000;
+        let values = scrambled(n);
+        let mut merged = TDigestSketch::new(DEFAULT_COMPRESSION);
+        assert_eq!(merged.quantile(0.5), None);
+        for chunk in values.chunks(30_000) {
+            let mut sketch = TDigestSketch::new(DEFAULT_COMPRESSION);
+            for batch in chunk.chunks(1000) {
+                sketch.update(batch);
+            }
+            merged.merge(&sketch);
+        }
+        assert_eq!(merged.count(), n);
+        assert_eq!(merged.quantile(0.0), Some(0.0));
+        assert_eq!(merged.quantile(1.0), Some((n - 1) as f64));
+        for q in [0.01, 0.1, 0.5, 0.9, 0.99] {
+            let estimate = merged.quantile(q).unwrap();
+            assert!(
+                (estimate / n as f64 - q).abs() < 0.01,
+                "quantile {q} estimated as {estimate}"
+            );
+        }
+
+        let mut empty = TDigestSketch::new(DEFAULT_COMPRESSION);
+        let mut other = TDigestSketch::new(20);
+        other.update(&[1.0, f64::NAN]);
+        empty.merge(&other);
+        assert_eq!(empty.compression(), 20);
+        assert_eq!(empty.count(), 1);
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut sketch = TDigestSketch::new(50);
+        sketch.update(&scrambled(10_000));
+        let bytes = sketch.to_bytes();
+        let read = TDigestSketch::deserialize(&bytes).unwrap();
+        assert_eq!(read.compression(), 50);
+        assert_eq!(read.digest, sketch.digest);
+        assert_eq!(read.to_bytes(), bytes);
+
+        let empty = TDigestSketch::deserialize(&TDigestSketch::new(50).to_bytes()).unwrap();
+        assert_eq!(empty.quantile(0.5), None);
+
+        let err = TDigestSketch::deserialize(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Invalid t-digest sketch: unexpected end of input"));
+
+        // Swaps the means of the first two centroids
+        let mut unordered = bytes.clone();
+        let first = 42;
+        let (a, b) = unordered[first..first + 32].split_at_mut(16);
+        a[..8].swap_with_slice(&mut b[..8]);
+        let err = TDigestSketch::deserialize(&unordered).unwrap_err();
+        assert!(err.to_string().contains("centroids out of order"));
+    }
+}
@@ -0,0 +1,364 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::collections::HashSet;
+
+use datafusion::error::Result;
+
+use crate::common::datasketches::{murmur3_x64_128, DEFAULT_UPDATE_SEED};
+use crate::common::sketch::{DistinctSketch, Sketch, SketchReader};
+
+/// Default `lg_k` of a [`ThetaSketch`], as in Apache DataSketches.
+pub const DEFAULT_LG_K: u8 = 12;
+/// Smallest `lg_k` accepted by a [`ThetaSketch`].
+pub const MIN_LG_K: u8 = 4;
+/// Largest `lg_k` accepted by a [`ThetaSketch`].
+pub const MAX_LG_K: u8 = 26;
+
+/// Theta of a sketch that retains all the hashes, i.e. of an exact sketch.
+const MAX_THETA: u64 = i64::MAX as u64;
+
+const SERIAL_VERSION: u8 = 3;
+const FAMILY_COMPACT: u8 = 3;
+
+const FLAG_BIG_ENDIAN: u8 = 1;
+const FLAG_READ_ONLY: u8 = 2;
+const FLAG_EMPTY: u8 = 4;
+const FLAG_COMPACT: u8 = 8;
+const FLAG_ORDERED: u8 = 16;
+const FLAG_SINGLE_ITEM: u8 = 32;
+
+/// A theta sketch estimating the number of distinct items, which unlike HyperLogLog supports intersections and
+/// differences. It is binary compatible with the compact sketches of Apache DataSketches.
+///
+/// The sketch retains the hashes of the items, as 63-bit integers, below a threshold `theta`. Once more than `2k`
+/// hashes are retained, with `k = 2^lg_k`, only the `k` smallest are kept and `theta` becomes the next one, so that
+/// the retained hashes are a uniform sample of a fraction `theta / 2^63` of the distinct items. The relative
+/// standard error is about `1 / sqrt(k)`.
+///
+/// # Binary format
+///
+/// [`Sketch::serialize`] writes the ordered compact format of DataSketches (serial version 3), little-endian:
+///
+/// | Byte  | Content                                                                           |
+/// |-------|-----------------------------------------------------------------------------------|
+/// | 0     | Preamble size in 8-byte longs: 1 (empty or single item), 2 (exact) or 3           |
+/// | 1     | Serial version, 3                                                                 |
+/// | 2     | Family, 3 (compact)                                                               |
+/// | 3-4   | Unused, 0                                                                         |
+/// | 5     | Flags: 2 read-only, 4 empty, 8 compact, 16 ordered, 32 single item                |
+/// | 6-7   | 16-bit hash of the hash seed, 9001                                                |
+/// | 8-11  | Number of retained hashes as `u32`, when the preamble has 2 or 3 longs           |
+/// | 12-15 | Sampling probability as `f32`, 1                                                  |
+/// | 16-23 | `theta` as `u64`, when the preamble has 3 longs                                   |
+///
+/// followed by the retained hashes as `u64` in ascending order. A single item sketch holds its hash at byte 8.
+#[derive(Debug, Clone)]
+pub struct ThetaSketch {
+    /// Number of hashes kept when the sketch is trimmed.
+    k: usize,
+    theta: u64,
+    hashes: HashSet<u64>,
+}
+
+impl ThetaSketch {
+    pub fn new(lg_k: u8) -> Self {
+        debug_assert!((MIN_LG_K..=MAX_LG_K).contains(&lg_k));
+        Self {
+            k: 1 << lg_k,
+            theta: MAX_THETA,
+            hashes: HashSet::new(),
+        }
+    }
+
+    /// Whether the sketch has seen no item, as opposed to a sketch that retains no hash below a lower `theta`.
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty() && self.theta == MAX_THETA
+    }
+
+    /// Estimates the number of distinct items.
+    pub fn estimate(&self) -> f64 {
+        self.hashes.len() as f64 * (MAX_THETA as f64 / self.theta as f64)
+    }
+
+    fn insert(&mut self, hash: u64) {
+        if hash != 0 && hash < self.theta && self.hashes.insert(hash) && self.hashes.len() > 2 * self.k {
+            self.trim();
+        }
+    }
+
+    /// Keeps the `k` smallest hashes, lowering `theta` to the next one.
+    fn trim(&mut self) {
+        let mut hashes: Vec<u64> = self.hashes.drain().collect();
+        hashes.sort_unstable();
+        if hashes.len() > self.k {
+            self.theta = hashes[self.k];
+            hashes.truncate(self.k);
+        }
+        self.hashes = hashes.into_iter().collect();
+    }
+
+    /// Returns a sketch of the items in both sketches.
+    pub fn intersect(&self, other: &Self) -> Self {
+        let theta = self.theta.min(other.theta);
+        let hashes = self
+            .hashes
+            .iter()
+            .filter(|hash| **hash < theta && other.hashes.contains(hash))
+            .copied()
+            .collect();
+        self.with_hashes(theta, hashes)
+    }
+
+    /// Returns a sketch of the items in this sketch but not in `other`.
+    pub fn a_not_b(&self, other: &Self) -> Self {
+        if self.is_empty() {
+            return self.clone();
+        }
+        let theta = self.theta.min(other.theta);
+        let hashes = self
+            .hashes
+            .iter()
+            .filter(|hash| **hash < theta && !other.hashes.contains(hash))
+            .copied()
+            .collect();
+        self.with_hashes(theta, hashes)
+    }
+
+    fn with_hashes(&self, theta: u64, hashes: HashSet<u64>) -> Self {
+        Self {
+            k: self.k.max(hashes.len()),
+            theta,
+            hashes,
+        }
+    }
+
+    fn seed_hash() -> u16 {
+        murmur3_x64_128(&DEFAULT_UPDATE_SEED.to_le_bytes(), 0).0 as u16
+    }
+}
+
+impl DistinctSketch for ThetaSketch {
+    fn update_hash(&mut self, hash: (u64, u64)) {
+        self.insert(hash.0 >> 1);
+    }
+}
+
+impl Sketch for ThetaSketch {
+    /// Computes the union of both sketches, keeping at most `2k` hashes of this sketch.
+    fn merge(&mut self, other: &Self) {
+        if other.theta < self.theta {
+            self.theta = other.theta;
+            let theta = self.theta;
+            self.hashes.retain(|hash| *hash < theta);
+        }
+        for hash in &other.hashes {
+            self.insert(*hash);
+        }
+    }
+
+    fn serialize(&self, buffer: &mut Vec<u8>) {
+        let seed_hash = Self::seed_hash().to_le_bytes();
+        let mut flags = FLAG_READ_ONLY | FLAG_COMPACT | FLAG_ORDERED;
+        if self.is_empty() {
+            flags |= FLAG_EMPTY;
+            buffer.extend_from_slice(&[
+                1,
+                SERIAL_VERSION,
+                FAMILY_COMPACT,
+                0,
+                0,
+                flags,
+                seed_hash[0],
+                seed_hash[1],
+            ]);
+            return;
+        }
+
+        let mut hashes: Vec<u64> = self.hashes.iter().copied().collect();
+        hashes.sort_unstable();
+        if self.theta == MAX_THETA && hashes.len() == 1 {
+            flags |= FLAG_SINGLE_ITEM;
+            buffer.extend_from_slice(&[
+                1,
+                SERIAL_VERSION,
+                FAMILY_COMPACT,
+                0,
+                0,
+                flags,
+                seed_hash[0],
+                seed_hash[1],
+            ]);
+        } else {
+            let preamble_longs = if self.theta == MAX_THETA { 2 } else { 3 };
+            buffer.extend_from_slice(&[
+                preamble_longs,
+                SERIAL_VERSION,
+                FAMILY_COMPACT,
+                0,
+                0,
+                flags,
+                seed_hash[0],
+                seed_hash[1],
+            ]);
+            buffer.extend_from_slice(&(hashes.len() as u32).to_le_bytes());
+            buffer.extend_from_slice(&1f32.to_le_bytes());
+            if preamble_longs == 3 {
+                buffer.extend_from_slice(&self.theta.to_le_bytes());
+            }
+        }
+        for hash in hashes {
+            buffer.extend_from_slice(&hash.to_le_bytes());
+        }
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self> {
+        let mut reader = SketchReader::new("theta", bytes);
+        let [preamble_longs, serial_version, family, _, _, flags, seed_hash_0, seed_hash_1] = *reader.read_bytes(8)?
+        else {
+            unreachable!()
+        };
+        if family != FAMILY_COMPACT {
+            return reader.invalid(format!(
+                "unexpected sketch family {family}, only compact sketches are supported"
+            ));
+        }
+        if serial_version != SERIAL_VERSION {
+            return reader.invalid(format!("unsupported version {serial_version}"));
+        }
+        if flags & FLAG_BIG_ENDIAN != 0 {
+            return reader.invalid("big-endian sketches are not supported");
+        }
+
+        let mut sketch = Self::new(DEFAULT_LG_K);
+        if flags & FLAG_EMPTY != 0 {
+            return Ok(sketch);
+        }
+        let seed_hash = u16::from_le_bytes([seed_hash_0, seed_hash_1]);
+        if seed_hash != Self::seed_hash() {
+            return reader.invalid(format!("unexpected seed hash {seed_hash}"));
+        }
+
+        let count = match preamble_longs {
+            1 if flags & FLAG_SINGLE_ITEM != 0 => 1,
+            2 | 3 => {
+                let count = reader.read_u32()? as usize;
+                let _sampling_probability = reader.read_bytes(4)?;
+                if preamble_longs == 3 {
+                    sketch.theta = reader.read_u64()?;
+                }
+                count
+            }
+            _ => return reader.invalid(format!("unexpected preamble size {preamble_longs}")),
+        };
+        if sketch.theta == 0 || sketch.theta > MAX_THETA {
+            return reader.invalid(format!("invalid theta {}", sketch.theta));
+        }
+
+        let hashes = reader.read_bytes(count.saturating_mul(8))?;
+        for hash in hashes.chunks_exact(8) {
+            let hash = u64::from_le_bytes(hash.try_into().unwrap());
+            if hash == 0 || hash >= sketch.theta || !sketch.hashes.insert(hash) {
+                return reader.invalid(format!("invalid hash {hash}"));
+            }
+        }
+        reader.finish()?;
+        sketch.k = sketch.k.max(count);
+        Ok(sketch)
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.hashes.capacity() * std::mem::size_of::<u64>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sketch_of(lg_k: u8, items: impl IntoIterator<Item = i64>) -> ThetaSketch {
+        let mut sketch = ThetaSketch::new(lg_k);
+        for item in items {
+            sketch.update_hash(murmur3_x64_128(&item.to_le_bytes(), DEFAULT_UPDATE_SEED));
+        }
+        sketch
+    }
+
+    fn assert_relative_error(estimate: f64, expected: f64, max_error: f64) {
+        let error = (estimate - expected).abs() / expected;
+        assert!(error < max_error, "estimated {estimate} for {expected}");
+    }
+
+    #[test]
+    fn test_seed_hash() {
+        assert_eq!(ThetaSketch::seed_hash(), 0x93cc);
+    }
+
+    #[test]
+    fn test_estimates_and_set_operations() {
+        let sketch = sketch_of(DEFAULT_LG_K, [1, 2, 3, 2, 1]);
+        assert_eq!(sketch.estimate(), 3.0);
+
+        let a = sketch_of(DEFAULT_LG_K, 0..100_000);
+        assert!(a.hashes.len() <= 2 * a.k);
+        assert_relative_error(a.estimate(), 100_000.0, 0.05);
+        let b = sketch_of(DEFAULT_LG_K, 50_000..200_000);
+
+        let mut union = a.clone();
+        union.merge(&b);
+        assert_relative_error(union.estimate(), 200_000.0, 0.05);
+        assert_relative_error(a.intersect(&b).estimate(), 50_000.0, 0.1);
+        assert_relative_error(a.a_not_b(&b).estimate(), 50_000.0, 0.1);
+
+        let small = sketch_of(DEFAULT_LG_K, 0..10);
+        assert_eq!(small.intersect(&sketch_of(DEFAULT_LG_K, 5..20)).estimate(), 5.0);
+        assert_eq!(small.a_not_b(&sketch_of(DEFAULT_LG_K, 5..20)).estimate(), 5.0);
+        assert!(small.intersect(&sketch_of(DEFAULT_LG_K, 20..30)).is_empty());
+        assert!(!a.intersect(&sketch_of(DEFAULT_LG_K, [-1])).is_empty());
+        assert!(ThetaSketch::new(DEFAULT_LG_K).a_not_b(&small).is_empty());
+    }
+
+    #[test]
+    fn test_serialization() {
+        let seed_hash = [0xcc, 0x93];
+        let empty = ThetaSketch::new(DEFAULT_LG_K).to_bytes();
+        assert_eq!(empty, vec![1, 3, 3, 0, 0, 30, seed_hash[0], seed_hash[1]]);
+        assert!(ThetaSketch::deserialize(&empty).unwrap().is_empty());
+
+        let single = sketch_of(DEFAULT_LG_K, [1]).to_bytes();
+        assert_eq!(single[..8], [1, 3, 3, 0, 0, 58, seed_hash[0], seed_hash[1]]);
+        assert_eq!(single.len(), 16);
+
+        for count in [1, 10, 100_000] {
+            let sketch = sketch_of(DEFAULT_LG_K, 0..count);
+            let bytes = sketch.to_bytes();
+            let read = ThetaSketch::deserialize(&bytes).unwrap();
+            assert_eq!(read.estimate(), sketch.estimate());
+            assert_eq!(read.to_bytes(), bytes);
+        }
+
+        let bytes = sketch_of(DEFAULT_LG_K, 0..100_000).to_bytes();
+        assert_eq!(bytes[0], 3);
+        let err = ThetaSketch::deserialize(&bytes[..bytes.len() - 8]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Invalid theta sketch: unexpected end of input"));
+        let mut wrong_seed = bytes.clone();
+        wrong_seed[6] ^= 1;
+        let err = ThetaSketch::deserialize(&wrong_seed).unwrap_err();
+        assert!(err.to_string().contains("unexpected seed hash"));
+    }
+}
@@ -0,0 +1,73 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use datafusion::common::config::ConfigExtension;
+use datafusion::common::{extensions_options, DataFusionError};
+
+extensions_options! {
+    /// Session options of the functions in this crate.
+    ///
+    /// Add them to a session with [`SessionConfig::with_option_extension`], after which they can be changed with
+    /// `SET functions_extra.<option> = <value>`.
+    ///
+    /// [`SessionConfig::with_option_extension`]: datafusion::prelude::SessionConfig::with_option_extension
+    pub struct ExtraFunctionsConfig {
+        /// Definition used by the bare `skewness` function, either `sample` (bias corrected, as in DuckDB) or
+        /// `population` (as in Spark). `skewness_samp` and `skewness_pop` are not affected.
+        pub skewness: SkewnessDefinition, default = SkewnessDefinition::Sample
+    }
+}
+
+impl ConfigExtension for ExtraFunctionsConfig {
+    const PREFIX: &'static str = "functions_extra";
+}
+
+/// Definition of skewness selected by [`ExtraFunctionsConfig::skewness`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SkewnessDefinition {
+    /// Bias corrected sample skewness, see [`SkewnessFunc`](crate::skewness::SkewnessFunc)
+    #[default]
+    Sample,
+    /// Population skewness, see [`SkewnessPopFunction`](crate::skewness_pop::SkewnessPopFunction)
+    Population,
+}
+
+impl FromStr for SkewnessDefinition {
+    type Err = DataFusionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sample" | "samp" => Ok(Self::Sample),
+            "population" | "pop" => Ok(Self::Population),
+            _ => Err(DataFusionError::Configuration(format!(
+                "Unknown skewness definition '{s}', expected 'sample' or 'population'"
+            ))),
+        }
+    }
+}
+
+impl Display for SkewnessDefinition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sample => write!(f, "sample"),
+            Self::Population => write!(f, "population"),
+        }
+    }
+}
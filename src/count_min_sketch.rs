@@ -0,0 +1,248 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::any::Any;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use arrow::array::{Array, ArrayRef, Int64Array};
+use arrow::datatypes::{DataType, Field};
+use datafusion::arrow;
+use datafusion::common::cast::as_binary_array;
+use datafusion::common::plan_err;
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, ColumnarValue, ScalarUDFImpl, Signature, Volatility};
+use datafusion::physical_expr::expressions::Literal;
+use datafusion::scalar::ScalarValue;
+
+use crate::common::count_min::CountMinSketch;
+use crate::common::sketch::{coerce_sketch_type, sketch_parameter};
+
+make_udaf_expr!(
+    count_min_sketch,
+    x eps confidence seed,
+    "Builds a serialized count-min sketch estimating the frequency of each value.",
+    count_min_sketch_udaf
+);
+create_func!(CountMinSketchFunction, count_min_sketch_udaf);
+
+make_udf_expr!(
+    cms_estimate,
+    sketch value,
+    "Estimates how many times a value was added to a serialized count-min sketch.",
+    cms_estimate_udf
+);
+create_udf_func!(CmsEstimateFunction, cms_estimate_udf);
+
+/// Coerces the values summarized by a count-min sketch, so that `count_min_sketch` and `cms_estimate` hash equal
+/// values of different types, e.g. an `Int32` column and an `Int64` literal, the same way.
+fn coerce_value_type(value_type: &DataType) -> Option<DataType> {
+    match value_type {
+        DataType::UInt64 => Some(DataType::UInt64),
+        value_type if value_type.is_integer() || value_type.is_null() => Some(DataType::Int64),
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => Some(DataType::Utf8),
+        DataType::Binary | DataType::LargeBinary | DataType::BinaryView => Some(DataType::Binary),
+        _ => None,
+    }
+}
+
+/// Returns the floating point argument at `index`, which must be a constant.
+fn float_parameter(acc_args: &AccumulatorArgs, index: usize, parameter: &str) -> Result<f64> {
+    let literal = acc_args.exprs[index]
+        .as_any()
+        .downcast_ref::<Literal>()
+        .map(|literal| literal.value());
+    match literal {
+        Some(ScalarValue::Float64(Some(value))) => Ok(*value),
+        _ => plan_err!("The {parameter} of count_min_sketch must be a constant number"),
+    }
+}
+
+/// The `CountMinSketchFunction` builds a count-min sketch of integers, strings or binaries, like Spark's
+/// `count_min_sketch(x, eps, confidence, seed)`, see [`CountMinSketch`] for its dimensions and format.
+///
+/// `eps` and `confidence` must be constants between 0 and 1 and `seed` a constant integer. Null values are ignored and
+/// an empty sketch is returned when there are no values. Frequencies are estimated by `cms_estimate(sketch, value)`.
+#[derive(Debug)]
+pub struct CountMinSketchFunction {
+    signature: Signature,
+}
+
+impl Default for CountMinSketchFunction {
+    fn default() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Immutable),
+        }
+    }
+}
+
+impl AggregateUDFImpl for CountMinSketchFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "count_min_sketch"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let [value_type, eps_type, confidence_type, seed_type] = arg_types else {
+            return plan_err!("{} expects exactly 4 arguments, got {}", self.name(), arg_types.len());
+        };
+        let Some(value_type) = coerce_value_type(value_type) else {
+            return plan_err!("{} does not support values of type {value_type}", self.name());
+        };
+        if !eps_type.is_numeric() {
+            return plan_err!("The eps of {} must be a number, got {eps_type}", self.name());
+        }
+        if !confidence_type.is_numeric() {
+            return plan_err!(
+                "The confidence of {} must be a number, got {confidence_type}",
+                self.name()
+            );
+        }
+        if !seed_type.is_integer() {
+            return plan_err!("The seed of {} must be an integer, got {seed_type}", self.name());
+        }
+        Ok(vec![value_type, DataType::Float64, DataType::Float64, DataType::Int64])
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Binary)
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let eps = float_parameter(&acc_args, 1, "eps")?;
+        let confidence = float_parameter(&acc_args, 2, "confidence")?;
+        let Some(seed) = sketch_parameter(&acc_args, 3, self.name(), "seed")? else {
+            return plan_err!("The seed of {} must be a constant integer", self.name());
+        };
+        Ok(Box::new(CountMinSketchAccumulator {
+            sketch: CountMinSketch::try_new(eps, confidence, seed)?,
+        }))
+    }
+
+    fn state_fields(&self, _args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(vec![Field::new("sketch", DataType::Binary, true)])
+    }
+}
+
+#[derive(Debug)]
+struct CountMinSketchAccumulator {
+    sketch: CountMinSketch,
+}
+
+impl Accumulator for CountMinSketchAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        self.sketch.update(&values[0])
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        as_binary_array(&states[0])?
+            .iter()
+            .flatten()
+            .try_for_each(|bytes| self.sketch.merge(&CountMinSketch::deserialize(bytes)?))
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        Ok(vec![self.evaluate()?])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        Ok(ScalarValue::Binary(Some(self.sketch.to_bytes())))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) - std::mem::size_of_val(&self.sketch) + self.sketch.size()
+    }
+}
+
+/// The `CmsEstimateFunction` estimates how many times a value was added to a serialized count-min sketch built by
+/// `count_min_sketch`, never underestimating it. Returns null when the sketch or the value is null.
+#[derive(Debug)]
+pub struct CmsEstimateFunction {
+    signature: Signature,
+}
+
+impl Default for CmsEstimateFunction {
+    fn default() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Immutable),
+        }
+    }
+}
+
+impl ScalarUDFImpl for CmsEstimateFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "cms_estimate"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let [sketch_type, value_type] = arg_types else {
+            return plan_err!("{} expects exactly 2 arguments, got {}", self.name(), arg_types.len());
+        };
+        let Some(value_type) = coerce_value_type(value_type) else {
+            return plan_err!("{} does not support values of type {value_type}", self.name());
+        };
+        Ok(vec![coerce_sketch_type(self.name(), sketch_type)?, value_type])
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Int64)
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        let arrays = ColumnarValue::values_to_arrays(args)?;
+        let (sketches, values) = (as_binary_array(&arrays[0])?, &arrays[1]);
+
+        let estimates = if let ColumnarValue::Scalar(_) = &args[0] {
+            // the common case of a sketch probed with a column: the sketch is only read once
+            match sketches.is_valid(0).then(|| sketches.value(0)) {
+                Some(bytes) => CountMinSketch::deserialize(bytes)?.estimate(values)?,
+                None => vec![None; values.len()],
+            }
+        } else {
+            (0..values.len())
+                .map(|row| match sketches.is_valid(row).then(|| sketches.value(row)) {
+                    Some(bytes) => Ok(CountMinSketch::deserialize(bytes)?.estimate(&values.slice(row, 1))?[0]),
+                    None => Ok(None),
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+        let estimates = estimates
+            .into_iter()
+            .map(|estimate| estimate.map(|estimate| estimate as i64))
+            .collect::<Int64Array>();
+
+        if args.iter().all(|arg| matches!(arg, ColumnarValue::Scalar(_))) {
+            return Ok(ColumnarValue::Scalar(ScalarValue::try_from_array(&estimates, 0)?));
+        }
+        Ok(ColumnarValue::Array(Arc::new(estimates)))
+    }
+}
@@ -0,0 +1,198 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::any::Any;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use arrow::array::ArrayRef;
+use datafusion::arrow;
+use datafusion::arrow::datatypes::{DataType, Field, Fields};
+use datafusion::common::scalar::ScalarStructBuilder;
+use datafusion::error::Result;
+use datafusion::functions_aggregate::min_max::{MaxAccumulator, MinAccumulator};
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, Signature, Volatility};
+use datafusion::scalar::ScalarValue;
+
+use crate::common::moments::{
+    coerce_moment_types, moments_state_fields, power_sums_accumulator, PowerSums, PowerSumsAccumulator,
+};
+use crate::kurtosis::sample_kurtosis;
+use crate::skewness::sample_skewness;
+
+make_udaf_expr_and_func!(
+    DescribeStatsFunction,
+    describe_stats,
+    x,
+    "Computes the count, mean, standard deviation, variance, minimum, maximum, skewness and kurtosis of the values.",
+    describe_stats_udaf
+);
+
+/// The `DescribeStatsFunction` computes summary statistics of the values in a single pass, returned as a
+/// `Struct{count, mean, stddev, variance, min, max, skewness, kurtosis}`.
+///
+/// - `stddev` and `variance` are the sample ones, like the `stddev` and `var` functions.
+/// - `skewness` and `kurtosis` are the ones of the `skewness_samp` and `kurtosis` functions.
+/// - `min` and `max` have the type of the values after coercion, i.e. `Int64` for integers.
+pub struct DescribeStatsFunction {
+    signature: Signature,
+}
+
+impl Debug for DescribeStatsFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DescribeStatsFunction")
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl Default for DescribeStatsFunction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DescribeStatsFunction {
+    pub fn new() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Immutable),
+        }
+    }
+}
+
+impl AggregateUDFImpl for DescribeStatsFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "describe_stats"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        coerce_moment_types(self.name(), arg_types)
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Struct(describe_stats_fields(&arg_types[0])))
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let input_type = acc_args.exprs[0].data_type(acc_args.schema)?;
+        Ok(Box::new(DescribeStatsAccumulator {
+            sums: power_sums_accumulator(&input_type, 4, false)?,
+            min: MinAccumulator::try_new(&input_type)?,
+            max: MaxAccumulator::try_new(&input_type)?,
+            fields: describe_stats_fields(&input_type),
+        }))
+    }
+
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        let input_type = &args.input_types[0];
+        let mut fields = moments_state_fields(input_type);
+        fields.push(Field::new("min", input_type.clone(), true));
+        fields.push(Field::new("max", input_type.clone(), true));
+        Ok(fields)
+    }
+}
+
+fn describe_stats_fields(input_type: &DataType) -> Fields {
+    Fields::from(vec![
+        Field::new("count", DataType::Int64, false),
+        Field::new("mean", DataType::Float64, true),
+        Field::new("stddev", DataType::Float64, true),
+        Field::new("variance", DataType::Float64, true),
+        Field::new("min", input_type.clone(), true),
+        Field::new("max", input_type.clone(), true),
+        Field::new("skewness", DataType::Float64, true),
+        Field::new("kurtosis", DataType::Float64, true),
+    ])
+}
+
+/// Sample variance, undefined for fewer than 2 values.
+fn sample_variance(sums: &PowerSums) -> Option<f64> {
+    if sums.count() <= 1.0 {
+        return None;
+    }
+    let count = sums.count();
+    Some(sums.central_moment(2)? * count / (count - 1.0))
+}
+
+/// Accumulates the power sums of the values up to the 4th, from which all the statistics but the extremes are
+/// computed, next to the minimum and maximum.
+#[derive(Debug)]
+struct DescribeStatsAccumulator {
+    sums: Box<dyn PowerSumsAccumulator>,
+    min: MinAccumulator,
+    max: MaxAccumulator,
+    fields: Fields,
+}
+
+impl Accumulator for DescribeStatsAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        self.sums.update_batch(values)?;
+        self.min.update_batch(values)?;
+        self.max.update_batch(values)
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        // The extremes are the last two fields of the state, after the ones of the power sums
+        let (sums, extremes) = states.split_at(states.len() - 2);
+        self.sums.merge_batch(sums)?;
+        self.min.merge_batch(&extremes[..1])?;
+        self.max.merge_batch(&extremes[1..])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        let sums = self.sums.power_sums();
+        let variance = sample_variance(&sums);
+        let values = [
+            ScalarValue::Int64(Some(sums.count() as i64)),
+            ScalarValue::Float64(sums.mean()),
+            ScalarValue::Float64(variance.map(f64::sqrt)),
+            ScalarValue::Float64(variance),
+            self.min.evaluate()?,
+            self.max.evaluate()?,
+            ScalarValue::Float64(sample_skewness(&sums)),
+            ScalarValue::Float64(sample_kurtosis(&sums)),
+        ];
+
+        self.fields
+            .iter()
+            .zip(values)
+            .fold(ScalarStructBuilder::new(), |builder, (field, value)| {
+                builder.with_scalar(Arc::clone(field), value)
+            })
+            .build()
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.sums.size() + self.min.size() + self.max.size()
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        let mut state = self.sums.state();
+        state.extend(self.min.state()?);
+        state.extend(self.max.state()?);
+        Ok(state)
+    }
+}
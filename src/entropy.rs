@@ -0,0 +1,182 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::any::Any;
+use std::fmt::Debug;
+
+use arrow::array::ArrayRef;
+use arrow::datatypes::{DataType, Field};
+use datafusion::arrow;
+use datafusion::common::plan_err;
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, Signature, Volatility};
+use datafusion::scalar::ScalarValue;
+
+use crate::common::mode::{value_counts_accumulator, value_counts_state_fields, ValueCountsAccumulator};
+
+make_udaf_expr!(
+    entropy,
+    x,
+    "Calculates the Shannon entropy, in bits, of the distribution of the values.",
+    entropy_udaf
+);
+create_func!(EntropyFunction, entropy_udaf, EntropyFunction::new_entropy());
+
+make_udaf_expr!(
+    normalized_entropy,
+    x,
+    "Calculates the Shannon entropy of the values divided by its maximum for their number of distinct values.",
+    normalized_entropy_udaf
+);
+create_func!(
+    EntropyFunction,
+    normalized_entropy_udaf,
+    EntropyFunction::new_normalized_entropy()
+);
+
+/// The `EntropyFunction` calculates the Shannon entropy of the distribution of the values, from the frequency tables
+/// of `mode`.
+///
+/// - `entropy(x)` is `-Σ p log2(p)` over the frequencies `p` of the distinct values, as in DuckDB and ClickHouse.
+/// - `normalized_entropy(x)` divides it by `log2(n)` for `n` distinct values, from 0 when all the values are equal to
+///   1 when they are all equally frequent. It is 0 when there is a single distinct value.
+/// - Supports the types of `mode`, and dictionaries of them. Null values are ignored, and the result is null when there
+///   are no values.
+pub struct EntropyFunction {
+    name: String,
+    signature: Signature,
+    normalized: bool,
+}
+
+impl Debug for EntropyFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EntropyFunction")
+            .field("name", &self.name)
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl EntropyFunction {
+    /// Creates the `entropy` function.
+    pub fn new_entropy() -> Self {
+        Self {
+            name: "entropy".to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
+            normalized: false,
+        }
+    }
+
+    /// Creates the `normalized_entropy` function.
+    pub fn new_normalized_entropy() -> Self {
+        Self {
+            name: "normalized_entropy".to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
+            normalized: true,
+        }
+    }
+}
+
+impl AggregateUDFImpl for EntropyFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        match arg_types {
+            // the frequencies of the values of a dictionary are counted as those of the values themselves
+            [DataType::Dictionary(_, value_type)] => Ok(vec![value_type.as_ref().clone()]),
+            [value_type] => Ok(vec![value_type.clone()]),
+            _ => plan_err!("{} expects exactly 1 argument, got {}", self.name, arg_types.len()),
+        }
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Float64)
+    }
+
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(value_counts_state_fields(&args.input_types[0]))
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let data_type = acc_args.exprs[0].data_type(acc_args.schema)?;
+        Ok(Box::new(EntropyAccumulator {
+            value_counts: value_counts_accumulator(&self.name, &data_type)?,
+            normalized: self.normalized,
+        }))
+    }
+}
+
+#[derive(Debug)]
+struct EntropyAccumulator {
+    value_counts: Box<dyn ValueCountsAccumulator>,
+    normalized: bool,
+}
+
+impl Accumulator for EntropyAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        self.value_counts.update_batch(values)
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        self.value_counts.merge_batch(states)
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        self.value_counts.state()
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        let (_, counts) = self.value_counts.value_counts()?;
+        // summed in a fixed order, so that the result does not depend on the order of the frequency table
+        let mut counts = counts.values().to_vec();
+        counts.sort_unstable();
+        let total = counts.iter().sum::<i64>() as f64;
+        if total == 0.0 {
+            return Ok(ScalarValue::Float64(None));
+        }
+
+        let entropy = counts
+            .iter()
+            .map(|count| {
+                let p = *count as f64 / total;
+                p * (1.0 / p).log2()
+            })
+            .sum::<f64>();
+        if !self.normalized {
+            return Ok(ScalarValue::Float64(Some(entropy)));
+        }
+        let normalized = match counts.len() {
+            0 | 1 => 0.0,
+            distinct => (entropy / (distinct as f64).log2()).min(1.0),
+        };
+        Ok(ScalarValue::Float64(Some(normalized)))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.value_counts.size()
+    }
+}
@@ -0,0 +1,215 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::any::Any;
+use std::fmt::Debug;
+
+use datafusion::arrow::datatypes::{DataType, Field};
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, Signature, Volatility};
+
+use crate::common::arg_extreme::{
+    arg_extreme_input_types, arg_extreme_state_fields, coerce_arg_extreme_types, ArgExtreme, ArgExtremeAccumulator,
+    SlidingArgExtremeAccumulator,
+};
+
+make_udaf_expr_and_func!(
+    FirstByFunction,
+    first_by,
+    x y,
+    "Returns the value of the first column at the row with the smallest value in the second column.",
+    first_by_udaf
+);
+
+make_udaf_expr_and_func!(
+    LastByFunction,
+    last_by,
+    x y,
+    "Returns the value of the first column at the row with the largest value in the second column.",
+    last_by_udaf
+);
+
+/// The `FirstByFunction` returns the value of `x` at the earliest row according to `y`, e.g. `first_by(price, ts)`.
+///
+/// - Rows where `y` is null are ignored.
+/// - If several rows share the smallest `y`, the first one encountered is returned.
+/// - Unlike `first_value(x ORDER BY y)` the input is not sorted, only the current earliest row is kept.
+pub struct FirstByFunction {
+    signature: Signature,
+    dictionary_output: bool,
+}
+
+impl Debug for FirstByFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FirstByFunction")
+            .field("signature", &self.signature)
+            .field("dictionary_output", &self.dictionary_output)
+            .finish()
+    }
+}
+
+impl Default for FirstByFunction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FirstByFunction {
+    pub fn new() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Immutable),
+            dictionary_output: false,
+        }
+    }
+
+    /// Keeps a dictionary encoded first argument dictionary encoded in the result, instead of returning its
+    /// value type, as `max_by` and `min_by` do.
+    pub fn with_dictionary_output(mut self, dictionary_output: bool) -> Self {
+        self.dictionary_output = dictionary_output;
+        self
+    }
+}
+
+impl AggregateUDFImpl for FirstByFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "first_by"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        coerce_arg_extreme_types(self.name(), arg_types, self.dictionary_output)
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+        Ok(arg_types[0].clone())
+    }
+
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(arg_extreme_state_fields(&args))
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let (value_type, ordering_type) = arg_extreme_input_types(&acc_args)?;
+        Ok(Box::new(ArgExtremeAccumulator::try_new(
+            ArgExtreme::Min,
+            &value_type,
+            &ordering_type,
+        )?))
+    }
+
+    fn create_sliding_accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let (value_type, ordering_type) = arg_extreme_input_types(&acc_args)?;
+        Ok(Box::new(SlidingArgExtremeAccumulator::new(
+            ArgExtreme::Min,
+            &value_type,
+            &ordering_type,
+        )))
+    }
+}
+
+/// The `LastByFunction` returns the value of `x` at the latest row according to `y`, e.g. `last_by(price, ts)`.
+///
+/// - Rows where `y` is null are ignored.
+/// - If several rows share the largest `y`, the last one encountered is returned.
+/// - Unlike `last_value(x ORDER BY y)` the input is not sorted, only the current latest row is kept.
+pub struct LastByFunction {
+    signature: Signature,
+    dictionary_output: bool,
+}
+
+impl Debug for LastByFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LastByFunction")
+            .field("signature", &self.signature)
+            .field("dictionary_output", &self.dictionary_output)
+            .finish()
+    }
+}
+
+impl Default for LastByFunction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LastByFunction {
+    pub fn new() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Immutable),
+            dictionary_output: false,
+        }
+    }
+
+    /// Keeps a dictionary encoded first argument dictionary encoded in the result, instead of returning its
+    /// value type, as `max_by` and `min_by` do.
+    pub fn with_dictionary_output(mut self, dictionary_output: bool) -> Self {
+        self.dictionary_output = dictionary_output;
+        self
+    }
+}
+
+impl AggregateUDFImpl for LastByFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "last_by"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        coerce_arg_extreme_types(self.name(), arg_types, self.dictionary_output)
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+        Ok(arg_types[0].clone())
+    }
+
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(arg_extreme_state_fields(&args))
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let (value_type, ordering_type) = arg_extreme_input_types(&acc_args)?;
+        Ok(Box::new(ArgExtremeAccumulator::try_new(
+            ArgExtreme::Max,
+            &value_type,
+            &ordering_type,
+        )?))
+    }
+
+    fn create_sliding_accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let (value_type, ordering_type) = arg_extreme_input_types(&acc_args)?;
+        Ok(Box::new(SlidingArgExtremeAccumulator::new(
+            ArgExtreme::Max,
+            &value_type,
+            &ordering_type,
+        )))
+    }
+}
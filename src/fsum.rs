@@ -0,0 +1,255 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::any::Any;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, AsArray, BooleanArray, Float64Array};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Field, Float64Type};
+use datafusion::arrow;
+use datafusion::common::plan_err;
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, EmitTo, GroupsAccumulator, Signature, Volatility};
+use datafusion::scalar::ScalarValue;
+
+use crate::common::compensated_sum::CountedSum;
+use crate::common::groups::is_selected;
+
+make_udaf_expr!(fsum, x, "Sums the values with compensated summation.", fsum_udaf);
+create_func!(
+    FsumFunction,
+    fsum_udaf,
+    FsumFunction::new_fsum(),
+    aliases = ["kahan_sum"]
+);
+
+make_udaf_expr!(favg, x, "Averages the values with compensated summation.", favg_udaf);
+create_func!(FsumFunction, favg_udaf, FsumFunction::new_favg());
+
+/// The `FsumFunction` sums or averages the values as DuckDB's `fsum` and `favg`, with the Neumaier variant of Kahan
+/// summation of a [`CountedSum`].
+///
+/// - The values are summed as `Float64`, and the error of the sum does not grow with the number of values, unlike
+///   the one of `sum` and `avg`. The compensation is kept in the state, so that the result barely depends on how the
+///   values are partitioned.
+/// - `fsum` is also named `kahan_sum`.
+/// - Infinite and NaN values are counted apart from the finite ones, so that they leave the sum of a sliding window
+///   frame once retracted.
+/// - Null values are ignored, and the result is null when there are no values.
+pub struct FsumFunction {
+    name: String,
+    signature: Signature,
+    average: bool,
+}
+
+impl Debug for FsumFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FsumFunction")
+            .field("name", &self.name)
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl FsumFunction {
+    /// Creates the `fsum` function, also named `kahan_sum`.
+    pub fn new_fsum() -> Self {
+        Self {
+            name: "fsum".to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
+            average: false,
+        }
+    }
+
+    /// Creates the `favg` function, which divides the compensated sum by the number of values.
+    pub fn new_favg() -> Self {
+        Self {
+            name: "favg".to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
+            average: true,
+        }
+    }
+}
+
+impl AggregateUDFImpl for FsumFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let [value_type] = arg_types else {
+            return plan_err!("{} expects exactly 1 argument, got {}", self.name, arg_types.len());
+        };
+        if !value_type.is_numeric() && !value_type.is_null() {
+            return plan_err!("{} does not support values of type {value_type}", self.name);
+        }
+        Ok(vec![DataType::Float64])
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Float64)
+    }
+
+    fn state_fields(&self, _args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(CountedSum::state_fields())
+    }
+
+    fn accumulator(&self, _acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        Ok(Box::new(FsumAccumulator {
+            sum: CountedSum::default(),
+            average: self.average,
+        }))
+    }
+
+    fn groups_accumulator_supported(&self, _args: AccumulatorArgs) -> bool {
+        true
+    }
+
+    fn create_groups_accumulator(&self, _args: AccumulatorArgs) -> Result<Box<dyn GroupsAccumulator>> {
+        Ok(Box::new(FsumGroupsAccumulator {
+            sums: vec![],
+            average: self.average,
+        }))
+    }
+}
+
+/// Returns the sum, or the average when `average`, of the values summed to `sum`, or `None` when there are none.
+fn evaluate(sum: &CountedSum, average: bool) -> Option<f64> {
+    let value = sum.value()?;
+    Some(if average { value / sum.count() as f64 } else { value })
+}
+
+#[derive(Debug)]
+struct FsumAccumulator {
+    sum: CountedSum,
+    average: bool,
+}
+
+impl Accumulator for FsumAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        let values = cast(&values[0], &DataType::Float64)?;
+        for value in values.as_primitive::<Float64Type>().iter().flatten() {
+            self.sum.update(value, 1);
+        }
+        Ok(())
+    }
+
+    fn retract_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        let values = cast(&values[0], &DataType::Float64)?;
+        for value in values.as_primitive::<Float64Type>().iter().flatten() {
+            self.sum.update(value, -1);
+        }
+        Ok(())
+    }
+
+    fn supports_retract_batch(&self) -> bool {
+        true
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        CountedSum::for_each_state(states, |_, sum| self.sum.merge(&sum));
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        CountedSum::state(&[self.sum])
+            .iter()
+            .map(|array| ScalarValue::try_from_array(array, 0))
+            .collect()
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        Ok(ScalarValue::Float64(evaluate(&self.sum, self.average)))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+    }
+}
+
+#[derive(Debug)]
+struct FsumGroupsAccumulator {
+    sums: Vec<CountedSum>,
+    average: bool,
+}
+
+impl GroupsAccumulator for FsumGroupsAccumulator {
+    fn update_batch(
+        &mut self,
+        values: &[ArrayRef],
+        group_indices: &[usize],
+        opt_filter: Option<&BooleanArray>,
+        total_num_groups: usize,
+    ) -> Result<()> {
+        self.sums.resize(total_num_groups, CountedSum::default());
+        let values = cast(&values[0], &DataType::Float64)?;
+        for (index, (value, group)) in values
+            .as_primitive::<Float64Type>()
+            .iter()
+            .zip(group_indices)
+            .enumerate()
+        {
+            if let Some(value) = value.filter(|_| is_selected(opt_filter, index)) {
+                self.sums[*group].update(value, 1);
+            }
+        }
+        Ok(())
+    }
+
+    fn merge_batch(
+        &mut self,
+        values: &[ArrayRef],
+        group_indices: &[usize],
+        opt_filter: Option<&BooleanArray>,
+        total_num_groups: usize,
+    ) -> Result<()> {
+        self.sums.resize(total_num_groups, CountedSum::default());
+        CountedSum::for_each_state(values, |index, sum| {
+            if is_selected(opt_filter, index) {
+                self.sums[group_indices[index]].merge(&sum);
+            }
+        });
+        Ok(())
+    }
+
+    fn evaluate(&mut self, emit_to: EmitTo) -> Result<ArrayRef> {
+        let values = emit_to
+            .take_needed(&mut self.sums)
+            .iter()
+            .map(|sum| evaluate(sum, self.average))
+            .collect::<Float64Array>();
+        Ok(Arc::new(values))
+    }
+
+    fn state(&mut self, emit_to: EmitTo) -> Result<Vec<ArrayRef>> {
+        Ok(CountedSum::state(&emit_to.take_needed(&mut self.sums)))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.sums.capacity() * std::mem::size_of::<CountedSum>()
+    }
+}
@@ -0,0 +1,196 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::any::Any;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use arrow::array::ArrayRef;
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Field};
+use datafusion::arrow;
+use datafusion::common::cast::as_list_array;
+use datafusion::common::plan_err;
+use datafusion::common::utils::array_into_list_array_nullable;
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, Signature, Volatility};
+use datafusion::scalar::ScalarValue;
+
+use crate::common::datasketches::coerce_item_type;
+use crate::common::distinct::{non_null_values, DistinctValues};
+use crate::common::sketch::sketch_parameter;
+
+make_udaf_expr!(
+    group_uniq_array,
+    x,
+    "Returns a list of the distinct values in the order they were first seen.",
+    group_uniq_array_udaf
+);
+create_func!(GroupUniqArrayFunction, group_uniq_array_udaf);
+
+/// The `GroupUniqArrayFunction` collects the distinct values in the order they are first seen, as ClickHouse's
+/// `groupUniqArray`, keeping them in the insertion ordered maps of [`DistinctValues`].
+///
+/// - `group_uniq_array(x [, max_size])` supports integers, floats, temporals, strings and binaries, and dictionaries
+///   of them. `max_size` must be a constant positive integer, and only the first `max_size` distinct values are kept.
+/// - The values of the partitions are appended in the order the partitions are merged, so that the order is only
+///   reproducible on a single partition.
+/// - As in `uniq_exact`, `-0.0` and `0.0` are the same value, and so are all NaNs.
+/// - Null values are ignored, and the result is null when there are no values.
+#[derive(Debug)]
+pub struct GroupUniqArrayFunction {
+    signature: Signature,
+}
+
+impl Default for GroupUniqArrayFunction {
+    fn default() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Immutable),
+        }
+    }
+}
+
+impl AggregateUDFImpl for GroupUniqArrayFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "group_uniq_array"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let (value_type, max_size_type) = match arg_types {
+            [value_type] => (value_type, None),
+            [value_type, max_size_type] => (value_type, Some(max_size_type)),
+            _ => return plan_err!("{} expects 1 or 2 arguments, got {}", self.name(), arg_types.len()),
+        };
+        let value_type = match value_type {
+            DataType::Dictionary(_, value_type) => value_type.as_ref(),
+            value_type => value_type,
+        };
+        let value_type = match coerce_item_type(value_type) {
+            Some(_) if value_type.is_null() => DataType::Int64,
+            Some(_) => value_type.clone(),
+            None => return plan_err!("{} does not support values of type {value_type}", self.name()),
+        };
+        match max_size_type {
+            None => Ok(vec![value_type]),
+            Some(max_size_type) if max_size_type.is_integer() || max_size_type.is_null() => {
+                Ok(vec![value_type, DataType::Int64])
+            }
+            Some(max_size_type) => {
+                plan_err!(
+                    "The max_size of {} must be an integer, got {max_size_type}",
+                    self.name()
+                )
+            }
+        }
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::new_list(arg_types[0].clone(), true))
+    }
+
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(vec![Field::new_list(
+            "values",
+            Field::new_list_field(args.input_types[0].clone(), true),
+            true,
+        )])
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let max_size = match sketch_parameter(&acc_args, 1, self.name(), "max_size")? {
+            None => usize::MAX,
+            Some(max_size) if max_size > 0 => max_size as usize,
+            Some(max_size) => return plan_err!("The max_size of {} must be positive, got {max_size}", self.name()),
+        };
+        let value_type = acc_args.exprs[0].data_type(acc_args.schema)?;
+        // the values are kept as the 64-bit integers or floats, strings or binaries that `DistinctValues` supports
+        let Some(item_type) = coerce_item_type(&value_type) else {
+            return plan_err!("{} does not support values of type {value_type}", self.name());
+        };
+        Ok(Box::new(GroupUniqArrayAccumulator {
+            values: DistinctValues::try_new(&item_type)?,
+            max_size,
+            value_type,
+            item_type,
+        }))
+    }
+}
+
+#[derive(Debug)]
+struct GroupUniqArrayAccumulator {
+    values: DistinctValues,
+    max_size: usize,
+    value_type: DataType,
+    item_type: DataType,
+}
+
+impl GroupUniqArrayAccumulator {
+    fn insert(&mut self, values: &ArrayRef) -> Result<()> {
+        // the values seen later cannot be among the first `max_size` distinct ones
+        if self.values.len() >= self.max_size {
+            return Ok(());
+        }
+        self.values.insert(&cast(values, &self.item_type)?, |_, _| ())?;
+        if self.values.len() > self.max_size {
+            let values = non_null_values(self.values.take_values())?;
+            self.values.insert(&values.slice(0, self.max_size), |_, _| ())?;
+        }
+        Ok(())
+    }
+
+    /// Returns the list of the distinct values, in the type of the input.
+    fn list(&mut self) -> Result<ScalarValue> {
+        let values = cast(&self.values.values()?, &self.value_type)?;
+        Ok(ScalarValue::List(Arc::new(array_into_list_array_nullable(values))))
+    }
+}
+
+impl Accumulator for GroupUniqArrayAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        self.insert(&values[0])
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        for values in as_list_array(&states[0])?.iter().flatten() {
+            self.insert(&values)?;
+        }
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        Ok(vec![self.list()?])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        if self.values.is_empty() {
+            return ScalarValue::try_from(&DataType::new_list(self.value_type.clone(), true));
+        }
+        self.list()
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.values.size()
+    }
+}
@@ -0,0 +1,468 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::any::Any;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use arrow::array::{
+    Array, ArrayRef, ArrowNativeTypeOp, ArrowPrimitiveType, AsArray, Float64Array, Int64Array, MapArray,
+    PrimitiveArray, StructArray,
+};
+use arrow::buffer::{NullBuffer, OffsetBuffer};
+use arrow::compute::{cast, sort_to_indices, take};
+use arrow::datatypes::{DataType, Field, FieldRef, Fields, Float64Type, Int64Type};
+use datafusion::arrow;
+use datafusion::common::cast::as_list_array;
+use datafusion::common::utils::array_into_list_array_nullable;
+use datafusion::common::{internal_err, plan_err};
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, Signature, Volatility};
+use datafusion::physical_expr::expressions::Literal;
+use datafusion::scalar::ScalarValue;
+
+use crate::common::histogram::NumericHistogram;
+use crate::common::mode::{value_counts_accumulator, value_counts_state_fields, ValueCountsAccumulator};
+use crate::common::sketch::sketch_parameter;
+
+make_udaf_expr_and_func!(
+    HistogramFunction,
+    histogram,
+    "Counts how many times each value occurs, as a map sorted by value, or how many values fall in each bin.",
+    histogram_udaf
+);
+
+make_udaf_expr_and_func!(
+    HistogramNumericFunction,
+    histogram_numeric,
+    x nb,
+    "Summarizes the values with a histogram of at most `nb` adaptive bins.",
+    histogram_numeric_udaf
+);
+
+/// The `HistogramFunction` counts values, as DuckDB's `histogram`.
+///
+/// - `histogram(x)` returns a `Map` from each distinct value to its count, sorted by value. It supports the types of
+///   `mode`, on whose frequency tables it is built.
+/// - `histogram(x, bins)` counts the numbers or timestamps in each bin given by a constant list of boundaries, the bin
+///   of a boundary holding the values up to it and above the previous boundary. Returns a list of `{x, y}` structs
+///   with the boundaries `x` in ascending order and the counts `y`, followed by a bin with a null `x` for the values
+///   above the last boundary, if any. NaN is above every boundary. Integers are binned as `Float64` when the
+///   boundaries are not integers.
+/// - Null values are ignored, and the result is null when there are no values.
+pub struct HistogramFunction {
+    signature: Signature,
+}
+
+impl Debug for HistogramFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HistogramFunction")
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl Default for HistogramFunction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HistogramFunction {
+    pub fn new() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Immutable),
+        }
+    }
+}
+
+/// Coerces the values of the binned histograms: integers and timestamps are binned as integers and the other numbers
+/// as `Float64`.
+fn coerce_binned_type(value_type: &DataType) -> Option<DataType> {
+    match value_type {
+        DataType::UInt64 => Some(DataType::Float64),
+        value_type if value_type.is_integer() || value_type.is_null() => Some(DataType::Int64),
+        value_type if value_type.is_numeric() => Some(DataType::Float64),
+        DataType::Timestamp(_, _) => Some(value_type.clone()),
+        _ => None,
+    }
+}
+
+/// The type of the bins of the binned histograms, a list of `{x, y}` structs.
+fn bins_type(x_type: &DataType, y_type: DataType) -> DataType {
+    let fields = Fields::from(vec![
+        Field::new("x", x_type.clone(), true),
+        Field::new("y", y_type, true),
+    ]);
+    DataType::new_list(DataType::Struct(fields), true)
+}
+
+/// Returns the list of bins `{x, y}` as a scalar, `x` being cast from its physical type to `x_type`.
+fn bins_scalar(x: ArrayRef, x_type: &DataType, y: ArrayRef) -> Result<ScalarValue> {
+    let x = cast(&x, x_type)?;
+    let DataType::List(field) = bins_type(x_type, y.data_type().clone()) else {
+        unreachable!("the bins are a list")
+    };
+    let DataType::Struct(fields) = field.data_type() else {
+        unreachable!("the bins are structs")
+    };
+    let bins = StructArray::try_new(fields.clone(), vec![x, y], None)?;
+    Ok(ScalarValue::List(Arc::new(array_into_list_array_nullable(Arc::new(
+        bins,
+    )))))
+}
+
+/// The entries of a histogram of values of type `value_type`, named as the maps built by DataFusion's `map`.
+fn entries_field(value_type: &DataType) -> FieldRef {
+    let fields = Fields::from(vec![
+        Field::new("key", value_type.clone(), false),
+        Field::new("value", DataType::Int64, true),
+    ]);
+    Arc::new(Field::new("entries", DataType::Struct(fields), false))
+}
+
+impl AggregateUDFImpl for HistogramFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "histogram"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        match arg_types {
+            [value_type] => Ok(vec![value_type.clone()]),
+            [value_type, bins_type] => {
+                let boundary_type = match bins_type {
+                    DataType::List(field) | DataType::LargeList(field) | DataType::FixedSizeList(field, _) => {
+                        field.data_type()
+                    }
+                    _ => return plan_err!("The bins of {} must be a list, got {bins_type}", self.name()),
+                };
+                let value_type = match coerce_binned_type(value_type) {
+                    // integers are binned as floats by boundaries that are not integers, which are not truncated
+                    Some(DataType::Int64) if boundary_type.is_numeric() && !boundary_type.is_integer() => {
+                        DataType::Float64
+                    }
+                    Some(value_type) => value_type,
+                    None => return plan_err!("{} with bins does not support values of type {value_type}", self.name()),
+                };
+                Ok(vec![value_type.clone(), DataType::new_list(value_type, true)])
+            }
+            _ => plan_err!("{} expects 1 or 2 arguments, got {}", self.name(), arg_types.len()),
+        }
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+        match arg_types {
+            [value_type] => Ok(DataType::Map(entries_field(value_type), false)),
+            [value_type, _] => Ok(bins_type(value_type, DataType::Int64)),
+            _ => plan_err!("{} expects 1 or 2 arguments, got {}", self.name(), arg_types.len()),
+        }
+    }
+
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        if args.input_types.len() == 2 {
+            return Ok(vec![Field::new_list(
+                "counts",
+                Field::new_list_field(DataType::Int64, true),
+                true,
+            )]);
+        }
+        Ok(value_counts_state_fields(&args.input_types[0]))
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let data_type = acc_args.exprs[0].data_type(acc_args.schema)?;
+        if acc_args.exprs.len() == 2 {
+            return match data_type {
+                DataType::Float64 => BinnedHistogramAccumulator::<Float64Type>::try_new(&acc_args, data_type),
+                _ => BinnedHistogramAccumulator::<Int64Type>::try_new(&acc_args, data_type),
+            };
+        }
+        Ok(Box::new(HistogramAccumulator {
+            value_counts: value_counts_accumulator(self.name(), &data_type)?,
+            entries_field: entries_field(&data_type),
+        }))
+    }
+}
+
+#[derive(Debug)]
+struct HistogramAccumulator {
+    value_counts: Box<dyn ValueCountsAccumulator>,
+    entries_field: FieldRef,
+}
+
+impl Accumulator for HistogramAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        self.value_counts.update_batch(values)
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        self.value_counts.merge_batch(states)
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        self.value_counts.state()
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        let (values, counts) = self.value_counts.value_counts()?;
+        let indices = sort_to_indices(&values, None, None)?;
+        let keys = take(&values, &indices, None)?;
+        let counts = take(&counts, &indices, None)?;
+
+        let DataType::Struct(fields) = self.entries_field.data_type() else {
+            unreachable!("the entries of a map are a struct")
+        };
+        let nulls = keys.is_empty().then(|| NullBuffer::new_null(1));
+        let offsets = OffsetBuffer::from_lengths([keys.len()]);
+        let entries = StructArray::try_new(fields.clone(), vec![keys, counts], None)?;
+        let map = MapArray::try_new(Arc::clone(&self.entries_field), offsets, entries, nulls, false)?;
+        Ok(ScalarValue::Map(Arc::new(map)))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.value_counts.size()
+    }
+}
+
+/// Counts the values of each bin of `histogram(x, bins)`, the values being cast to `T`.
+#[derive(Debug)]
+struct BinnedHistogramAccumulator<T: ArrowPrimitiveType> {
+    /// Sorted distinct boundaries
+    boundaries: Vec<T::Native>,
+    /// Count of the values of the bin of each boundary, followed by the count of the values above the last one
+    counts: Vec<i64>,
+    value_type: DataType,
+}
+
+impl<T: ArrowPrimitiveType + Debug> BinnedHistogramAccumulator<T> {
+    fn try_new(acc_args: &AccumulatorArgs, value_type: DataType) -> Result<Box<dyn Accumulator>> {
+        let bins = match acc_args.exprs[1]
+            .as_any()
+            .downcast_ref::<Literal>()
+            .map(|literal| literal.value())
+        {
+            Some(ScalarValue::List(list)) if !list.is_null(0) => cast(&list.value(0), &T::DATA_TYPE)?,
+            _ => return plan_err!("The bins of histogram must be a constant list"),
+        };
+        if bins.is_empty() || bins.null_count() > 0 {
+            return plan_err!("The bins of histogram must be a non-empty list without nulls");
+        }
+        let mut boundaries = bins.as_primitive::<T>().values().to_vec();
+        boundaries.sort_by(|a, b| a.compare(*b));
+        boundaries.dedup_by(|a, b| a.is_eq(*b));
+
+        Ok(Box::new(Self {
+            counts: vec![0; boundaries.len() + 1],
+            boundaries,
+            value_type,
+        }))
+    }
+}
+
+impl<T: ArrowPrimitiveType + Debug> Accumulator for BinnedHistogramAccumulator<T> {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        let values = cast(&values[0], &T::DATA_TYPE)?;
+        for value in values.as_primitive::<T>().iter().flatten() {
+            let bin = self.boundaries.partition_point(|boundary| boundary.is_lt(value));
+            self.counts[bin] += 1;
+        }
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        for counts in as_list_array(&states[0])?.iter().flatten() {
+            let counts = counts.as_primitive::<Int64Type>();
+            if counts.len() != self.counts.len() {
+                return internal_err!("Expected {} histogram counts, got {}", self.counts.len(), counts.len());
+            }
+            for (count, other) in self.counts.iter_mut().zip(counts.values()) {
+                *count += other;
+            }
+        }
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        let counts = Int64Array::from(self.counts.clone());
+        Ok(vec![ScalarValue::List(Arc::new(array_into_list_array_nullable(
+            Arc::new(counts),
+        )))])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        if self.counts.iter().all(|count| *count == 0) {
+            return ScalarValue::try_from(&bins_type(&self.value_type, DataType::Int64));
+        }
+        let above = self.counts[self.boundaries.len()];
+        let x = self
+            .boundaries
+            .iter()
+            .map(|boundary| Some(*boundary))
+            .chain((above > 0).then_some(None))
+            .collect::<PrimitiveArray<T>>();
+        let y = Int64Array::from_iter_values(self.counts.iter().take(x.len()).copied());
+        bins_scalar(Arc::new(x), &self.value_type, Arc::new(y))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+            + self.boundaries.capacity() * std::mem::size_of::<T::Native>()
+            + self.counts.capacity() * std::mem::size_of::<i64>()
+    }
+}
+
+/// The `HistogramNumericFunction` summarizes numbers or timestamps with a histogram of at most `nb` bins whose
+/// boundaries adapt to the values, as Hive's and Spark's `histogram_numeric(x, nb)`, see [`NumericHistogram`].
+///
+/// - `nb` must be a constant integer of at least 2.
+/// - Returns a list of `{x, y}` structs with the centers `x` of the bins in ascending order, of the type of the values
+///   and rounded for integers and timestamps, and the number `y` of values of each bin as a `Float64`.
+/// - Null and NaN values are ignored, and the result is null when there are no values.
+#[derive(Debug)]
+pub struct HistogramNumericFunction {
+    signature: Signature,
+}
+
+impl Default for HistogramNumericFunction {
+    fn default() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Immutable),
+        }
+    }
+}
+
+impl AggregateUDFImpl for HistogramNumericFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "histogram_numeric"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let [value_type, nb_type] = arg_types else {
+            return plan_err!("{} expects exactly 2 arguments, got {}", self.name(), arg_types.len());
+        };
+        let Some(value_type) = coerce_binned_type(value_type) else {
+            return plan_err!("{} does not support values of type {value_type}", self.name());
+        };
+        if !nb_type.is_integer() {
+            return plan_err!("The nb of {} must be an integer, got {nb_type}", self.name());
+        }
+        Ok(vec![value_type, DataType::Int64])
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+        Ok(bins_type(&arg_types[0], DataType::Float64))
+    }
+
+    fn state_fields(&self, _args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(vec![
+            Field::new_list("x", Field::new_list_field(DataType::Float64, true), true),
+            Field::new_list("y", Field::new_list_field(DataType::Float64, true), true),
+        ])
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let nb = match sketch_parameter(&acc_args, 1, self.name(), "nb")? {
+            Some(nb) if nb >= 2 => nb,
+            Some(nb) => return plan_err!("The nb of {} must be at least 2, got {nb}", self.name()),
+            None => return plan_err!("The nb of {} must be a constant integer", self.name()),
+        };
+        Ok(Box::new(HistogramNumericAccumulator {
+            histogram: NumericHistogram::new(nb as usize),
+            value_type: acc_args.exprs[0].data_type(acc_args.schema)?,
+        }))
+    }
+}
+
+#[derive(Debug)]
+struct HistogramNumericAccumulator {
+    histogram: NumericHistogram,
+    value_type: DataType,
+}
+
+impl Accumulator for HistogramNumericAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        let values = match values[0].data_type() {
+            DataType::Float64 => Arc::clone(&values[0]),
+            _ => cast(&cast(&values[0], &DataType::Int64)?, &DataType::Float64)?,
+        };
+        values
+            .as_primitive::<Float64Type>()
+            .iter()
+            .flatten()
+            .for_each(|value| self.histogram.add(value));
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        let xs = as_list_array(&states[0])?;
+        let ys = as_list_array(&states[1])?;
+        for (x, y) in xs.iter().zip(ys.iter()) {
+            let (Some(x), Some(y)) = (x, y) else {
+                continue;
+            };
+            let x = x.as_primitive::<Float64Type>().values();
+            let y = y.as_primitive::<Float64Type>().values();
+            self.histogram.merge(x.iter().copied().zip(y.iter().copied()));
+        }
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        let (x, y): (Vec<_>, Vec<_>) = self.histogram.bins().iter().copied().unzip();
+        Ok(vec![
+            ScalarValue::List(Arc::new(array_into_list_array_nullable(Arc::new(Float64Array::from(
+                x,
+            ))))),
+            ScalarValue::List(Arc::new(array_into_list_array_nullable(Arc::new(Float64Array::from(
+                y,
+            ))))),
+        ])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        let bins = self.histogram.bins();
+        if bins.is_empty() {
+            return ScalarValue::try_from(&bins_type(&self.value_type, DataType::Float64));
+        }
+        let x: ArrayRef = match self.value_type {
+            DataType::Float64 => Arc::new(bins.iter().map(|(x, _)| *x).collect::<Float64Array>()),
+            _ => Arc::new(bins.iter().map(|(x, _)| x.round() as i64).collect::<Int64Array>()),
+        };
+        let y = bins.iter().map(|(_, y)| *y).collect::<Float64Array>();
+        bins_scalar(x, &self.value_type, Arc::new(y))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) - std::mem::size_of_val(&self.histogram) + self.histogram.size()
+    }
+}
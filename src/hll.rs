@@ -0,0 +1,219 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::any::Any;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use arrow::array::Int64Array;
+use arrow::datatypes::{DataType, Field};
+use datafusion::arrow;
+use datafusion::common::cast::as_binary_array;
+use datafusion::common::plan_err;
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, ColumnarValue, ScalarUDFImpl, Signature, Volatility};
+use datafusion::scalar::ScalarValue;
+
+use crate::common::datasketches::{coerce_item_type, DistinctSketchAccumulator};
+use crate::common::hll::{HllSketch, DEFAULT_LG_K, MAX_LG_K, MIN_LG_K};
+use crate::common::sketch::{coerce_sketch_type, sketch_parameter, Sketch};
+
+make_udaf_expr!(
+    hll_sketch_agg,
+    x,
+    "Builds a serialized HyperLogLog sketch of the distinct values.",
+    hll_sketch_agg_udaf
+);
+create_func!(HllFunction, hll_sketch_agg_udaf, HllFunction::new_sketch_agg());
+
+make_udaf_expr!(
+    hll_union_agg,
+    sketch,
+    "Computes the union of serialized HyperLogLog sketches.",
+    hll_union_agg_udaf
+);
+create_func!(HllFunction, hll_union_agg_udaf, HllFunction::new_union_agg());
+
+make_udf_expr!(
+    hll_sketch_estimate,
+    sketch,
+    "Estimates the number of distinct values summarized by a serialized HyperLogLog sketch.",
+    hll_sketch_estimate_udf
+);
+create_udf_func!(HllSketchEstimateFunction, hll_sketch_estimate_udf);
+
+/// The `HllFunction` builds HyperLogLog sketches of the distinct values, binary compatible with Apache DataSketches
+/// and Spark, see [`HllSketch`] for the format.
+///
+/// - `hll_sketch_agg(x [, lg_k])` hashes integers, floats, strings and binaries as DataSketches does. `lg_k` must be
+///   a constant between 4 and 21 and defaults to 12.
+/// - `hll_union_agg(sketch)` merges sketches, which may have different `lg_k`: the result has the smallest one.
+/// - Both return an empty sketch when there are no values.
+pub struct HllFunction {
+    name: String,
+    signature: Signature,
+    union: bool,
+}
+
+impl Debug for HllFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HllFunction")
+            .field("name", &self.name)
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl HllFunction {
+    /// Creates the `hll_sketch_agg` function, which builds a sketch of values.
+    pub fn new_sketch_agg() -> Self {
+        Self {
+            name: "hll_sketch_agg".to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
+            union: false,
+        }
+    }
+
+    /// Creates the `hll_union_agg` function, which merges sketches.
+    pub fn new_union_agg() -> Self {
+        Self {
+            name: "hll_union_agg".to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
+            union: true,
+        }
+    }
+}
+
+impl AggregateUDFImpl for HllFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        if self.union {
+            let [sketch_type] = arg_types else {
+                return plan_err!("{} expects exactly 1 argument, got {}", self.name, arg_types.len());
+            };
+            return Ok(vec![coerce_sketch_type(&self.name, sketch_type)?]);
+        }
+
+        let (value_type, lg_k_type) = match arg_types {
+            [value_type] => (value_type, None),
+            [value_type, lg_k_type] => (value_type, Some(lg_k_type)),
+            _ => return plan_err!("{} expects 1 or 2 arguments, got {}", self.name, arg_types.len()),
+        };
+        let Some(value_type) = coerce_item_type(value_type) else {
+            return plan_err!("{} does not support values of type {value_type}", self.name);
+        };
+        match lg_k_type {
+            None => Ok(vec![value_type]),
+            Some(lg_k_type) if lg_k_type.is_integer() || lg_k_type.is_null() => Ok(vec![value_type, DataType::Int64]),
+            Some(lg_k_type) => plan_err!("The lg_k of {} must be an integer, got {lg_k_type}", self.name),
+        }
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Binary)
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let lg_k = match sketch_parameter(&acc_args, 1, &self.name, "lg_k")? {
+            None => DEFAULT_LG_K,
+            Some(lg_k) if (MIN_LG_K as i64..=MAX_LG_K as i64).contains(&lg_k) => lg_k as u8,
+            Some(lg_k) => {
+                return plan_err!(
+                    "The lg_k of {} must be between {MIN_LG_K} and {MAX_LG_K}, got {lg_k}",
+                    self.name
+                )
+            }
+        };
+
+        Ok(Box::new(DistinctSketchAccumulator::new(
+            HllSketch::new(lg_k),
+            self.union,
+        )))
+    }
+
+    fn state_fields(&self, _args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(vec![Field::new("sketch", DataType::Binary, true)])
+    }
+}
+
+/// The `HllSketchEstimateFunction` estimates the number of distinct values summarized by a serialized HyperLogLog
+/// sketch, rounded to the nearest integer, e.g. `hll_sketch_estimate(hll_union_agg(sketch))`.
+#[derive(Debug)]
+pub struct HllSketchEstimateFunction {
+    signature: Signature,
+}
+
+impl Default for HllSketchEstimateFunction {
+    fn default() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Immutable),
+        }
+    }
+}
+
+impl ScalarUDFImpl for HllSketchEstimateFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "hll_sketch_estimate"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let [sketch_type] = arg_types else {
+            return plan_err!("{} expects exactly 1 argument, got {}", self.name(), arg_types.len());
+        };
+        Ok(vec![coerce_sketch_type(self.name(), sketch_type)?])
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Int64)
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        let estimate = |bytes: &[u8]| Ok(HllSketch::deserialize(bytes)?.estimate().round() as i64);
+        match &args[0] {
+            ColumnarValue::Scalar(ScalarValue::Binary(bytes)) => Ok(ColumnarValue::Scalar(ScalarValue::Int64(
+                bytes.as_deref().map(estimate).transpose()?,
+            ))),
+            ColumnarValue::Array(array) => {
+                let estimates = as_binary_array(array)?
+                    .iter()
+                    .map(|bytes| bytes.map(estimate).transpose())
+                    .collect::<Result<Int64Array>>()?;
+                Ok(ColumnarValue::Array(Arc::new(estimates)))
+            }
+            ColumnarValue::Scalar(scalar) => plan_err!("{} expects a binary sketch, got {scalar}", self.name()),
+        }
+    }
+}
@@ -15,26 +15,25 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use arrow::array::{ArrayRef, Float64Array, UInt64Array};
 use arrow::datatypes::{DataType, Field};
 use datafusion::arrow;
 use std::any::Any;
 use std::fmt::Debug;
+use std::sync::Arc;
 
-use datafusion::common::cast::as_float64_array;
-use datafusion::common::downcast_value;
-use datafusion::common::DataFusionError;
 use datafusion::error::Result;
 use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
 use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, Signature, Volatility};
-use datafusion::scalar::ScalarValue;
+
+use crate::common::moments::{coerce_moment_types, moments_accumulator, moments_state_fields, PowerSums};
 
 make_udaf_expr_and_func!(
     KurtosisFunction,
     kurtosis,
     x,
     "Calculates the excess kurtosis (Fisher’s definition) with bias correction according to the sample size.",
-    kurtosis_udaf
+    kurtosis_udaf,
+    aliases = ["kurtSamp", "kurtsamp"]
 );
 
 pub struct KurtosisFunction {
@@ -58,7 +57,7 @@ impl Default for KurtosisFunction {
 impl KurtosisFunction {
     pub fn new() -> Self {
         Self {
-            signature: Signature::coercible(vec![DataType::Float64], Volatility::Immutable),
+            signature: Signature::user_defined(Volatility::Immutable),
         }
     }
 }
@@ -76,120 +75,45 @@ impl AggregateUDFImpl for KurtosisFunction {
         &self.signature
     }
 
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        coerce_moment_types(self.name(), arg_types)
+    }
+
     fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
         Ok(DataType::Float64)
     }
 
-    fn accumulator(&self, _acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
-        Ok(Box::new(KurtosisAccumulator::new()))
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        moments_accumulator(
+            &acc_args.exprs[0].data_type(acc_args.schema)?,
+            4,
+            false,
+            Arc::new(sample_kurtosis),
+        )
     }
 
-    fn state_fields(&self, _args: StateFieldsArgs) -> Result<Vec<Field>> {
-        Ok(vec![
-            Field::new("count", DataType::UInt64, true),
-            Field::new("sum", DataType::Float64, true),
-            Field::new("sum_sqr", DataType::Float64, true),
-            Field::new("sum_cub", DataType::Float64, true),
-            Field::new("sum_four", DataType::Float64, true),
-        ])
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(moments_state_fields(&args.input_types[0]))
     }
 }
 
-/// Accumulator for calculating the excess kurtosis (Fisher’s definition) with bias correction according to the sample size.
+/// Excess kurtosis with bias correction according to the sample size, undefined for fewer than 4 values.
 /// This implementation follows the [DuckDB implementation]:
 /// <https://github.com/duckdb/duckdb/blob/main/src/core_functions/aggregate/distributive/kurtosis.cpp>
-#[derive(Debug, Default)]
-pub struct KurtosisAccumulator {
-    count: u64,
-    sum: f64,
-    sum_sqr: f64,
-    sum_cub: f64,
-    sum_four: f64,
-}
-
-impl KurtosisAccumulator {
-    pub fn new() -> Self {
-        Self {
-            count: 0,
-            sum: 0.0,
-            sum_sqr: 0.0,
-            sum_cub: 0.0,
-            sum_four: 0.0,
-        }
+pub(crate) fn sample_kurtosis(sums: &PowerSums) -> Option<f64> {
+    if sums.count() <= 3.0 {
+        return None;
     }
-}
 
-impl Accumulator for KurtosisAccumulator {
-    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
-        let array = as_float64_array(&values[0])?;
-        for value in array.iter().flatten() {
-            self.count += 1;
-            self.sum += value;
-            self.sum_sqr += value.powi(2);
-            self.sum_cub += value.powi(3);
-            self.sum_four += value.powi(4);
-        }
-        Ok(())
+    let m2 = sums.central_moment(2)?;
+    if m2 <= 0.0 {
+        return None;
     }
+    let m4 = sums.central_moment(4)?;
 
-    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
-        let counts = downcast_value!(states[0], UInt64Array);
-        let sums = downcast_value!(states[1], Float64Array);
-        let sum_sqrs = downcast_value!(states[2], Float64Array);
-        let sum_cubs = downcast_value!(states[3], Float64Array);
-        let sum_fours = downcast_value!(states[4], Float64Array);
-
-        for i in 0..counts.len() {
-            let c = counts.value(i);
-            if c == 0 {
-                continue;
-            }
-            self.count += c;
-            self.sum += sums.value(i);
-            self.sum_sqr += sum_sqrs.value(i);
-            self.sum_cub += sum_cubs.value(i);
-            self.sum_four += sum_fours.value(i);
-        }
-
-        Ok(())
-    }
+    let count = sums.count();
+    let numerator = (count - 1.0) * ((count + 1.0) * m4 / m2.powi(2) - 3.0 * (count - 1.0));
+    let denominator = (count - 2.0) * (count - 3.0);
 
-    fn evaluate(&mut self) -> Result<ScalarValue> {
-        if self.count <= 3 {
-            return Ok(ScalarValue::Float64(None));
-        }
-
-        let count_64 = 1_f64 / self.count as f64;
-        let m4 = count_64
-            * (self.sum_four - 4.0 * self.sum_cub * self.sum * count_64
-                + 6.0 * self.sum_sqr * self.sum.powi(2) * count_64.powi(2)
-                - 3.0 * self.sum.powi(4) * count_64.powi(3));
-
-        let m2 = (self.sum_sqr - self.sum.powi(2) * count_64) * count_64;
-        if m2 <= 0.0 {
-            return Ok(ScalarValue::Float64(None));
-        }
-
-        let count = self.count as f64;
-        let numerator = (count - 1.0) * ((count + 1.0) * m4 / m2.powi(2) - 3.0 * (count - 1.0));
-        let denominator = (count - 2.0) * (count - 3.0);
-
-        let target = numerator / denominator;
-
-        Ok(ScalarValue::Float64(Some(target)))
-    }
-
-    fn size(&self) -> usize {
-        std::mem::size_of_val(self)
-    }
-
-    fn state(&mut self) -> Result<Vec<ScalarValue>> {
-        Ok(vec![
-            ScalarValue::from(self.count),
-            ScalarValue::from(self.sum),
-            ScalarValue::from(self.sum_sqr),
-            ScalarValue::from(self.sum_cub),
-            ScalarValue::from(self.sum_four),
-        ])
-    }
+    Some(numerator / denominator)
 }
@@ -18,21 +18,23 @@
 // Copired from `datafusion/functions-aggregate/src/kurtosis_pop.rs`
 // Originally authored by goldmedal
 
-use arrow::array::{Array, ArrayRef, Float64Array, UInt64Array};
 use datafusion::arrow::datatypes::{DataType, Field};
-use datafusion::common::cast::as_float64_array;
-use datafusion::common::{downcast_value, DataFusionError, Result, ScalarValue};
+use datafusion::common::Result;
 use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
 use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, Signature, Volatility};
 use std::any::Any;
 use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::common::moments::{coerce_moment_types, moments_accumulator, moments_state_fields, PowerSums};
 
 make_udaf_expr_and_func!(
     KurtosisPopFunction,
     kurtosis_pop,
     x,
     "Calculates the excess kurtosis (Fisher’s definition) without bias correction.",
-    kurtosis_pop_udaf
+    kurtosis_pop_udaf,
+    aliases = ["kurtPop", "kurtpop"]
 );
 
 pub struct KurtosisPopFunction {
@@ -56,7 +58,7 @@ impl Default for KurtosisPopFunction {
 impl KurtosisPopFunction {
     pub fn new() -> Self {
         Self {
-            signature: Signature::coercible(vec![DataType::Float64], Volatility::Immutable),
+            signature: Signature::user_defined(Volatility::Immutable),
         }
     }
 }
@@ -74,115 +76,31 @@ impl AggregateUDFImpl for KurtosisPopFunction {
         &self.signature
     }
 
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        coerce_moment_types(self.name(), arg_types)
+    }
+
     fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
         Ok(DataType::Float64)
     }
 
-    fn state_fields(&self, _args: StateFieldsArgs) -> Result<Vec<Field>> {
-        Ok(vec![
-            Field::new("count", DataType::UInt64, true),
-            Field::new("sum", DataType::Float64, true),
-            Field::new("sum_sqr", DataType::Float64, true),
-            Field::new("sum_cub", DataType::Float64, true),
-            Field::new("sum_four", DataType::Float64, true),
-        ])
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(moments_state_fields(&args.input_types[0]))
     }
 
-    fn accumulator(&self, _acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
-        Ok(Box::new(KurtosisPopAccumulator::new()))
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        moments_accumulator(
+            &acc_args.exprs[0].data_type(acc_args.schema)?,
+            4,
+            false,
+            Arc::new(population_kurtosis),
+        )
     }
 }
 
-/// Accumulator for calculating the excess kurtosis (Fisher’s definition) without bias correction.
+/// Excess kurtosis without bias correction, undefined when the values have no variance.
 /// This implementation follows the [DuckDB implementation]:
 /// <https://github.com/duckdb/duckdb/blob/main/src/core_functions/aggregate/distributive/kurtosis.cpp>
-#[derive(Debug, Default)]
-pub struct KurtosisPopAccumulator {
-    count: u64,
-    sum: f64,
-    sum_sqr: f64,
-    sum_cub: f64,
-    sum_four: f64,
-}
-
-impl KurtosisPopAccumulator {
-    pub fn new() -> Self {
-        Self {
-            count: 0,
-            sum: 0.0,
-            sum_sqr: 0.0,
-            sum_cub: 0.0,
-            sum_four: 0.0,
-        }
-    }
-}
-
-impl Accumulator for KurtosisPopAccumulator {
-    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
-        let array = as_float64_array(&values[0])?;
-        for value in array.iter().flatten() {
-            self.count += 1;
-            self.sum += value;
-            self.sum_sqr += value.powi(2);
-            self.sum_cub += value.powi(3);
-            self.sum_four += value.powi(4);
-        }
-        Ok(())
-    }
-
-    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
-        let counts = downcast_value!(states[0], UInt64Array);
-        let sums = downcast_value!(states[1], Float64Array);
-        let sum_sqrs = downcast_value!(states[2], Float64Array);
-        let sum_cubs = downcast_value!(states[3], Float64Array);
-        let sum_fours = downcast_value!(states[4], Float64Array);
-
-        for i in 0..counts.len() {
-            let c = counts.value(i);
-            if c == 0 {
-                continue;
-            }
-            self.count += c;
-            self.sum += sums.value(i);
-            self.sum_sqr += sum_sqrs.value(i);
-            self.sum_cub += sum_cubs.value(i);
-            self.sum_four += sum_fours.value(i);
-        }
-
-        Ok(())
-    }
-
-    fn evaluate(&mut self) -> Result<ScalarValue> {
-        if self.count < 1 {
-            return Ok(ScalarValue::Float64(None));
-        }
-
-        let count_64 = 1_f64 / self.count as f64;
-        let m4 = count_64
-            * (self.sum_four - 4.0 * self.sum_cub * self.sum * count_64
-                + 6.0 * self.sum_sqr * self.sum.powi(2) * count_64.powi(2)
-                - 3.0 * self.sum.powi(4) * count_64.powi(3));
-
-        let m2 = (self.sum_sqr - self.sum.powi(2) * count_64) * count_64;
-        if m2 <= 0.0 {
-            return Ok(ScalarValue::Float64(None));
-        }
-
-        let target = m4 / (m2.powi(2)) - 3.0;
-        Ok(ScalarValue::Float64(Some(target)))
-    }
-
-    fn size(&self) -> usize {
-        std::mem::size_of_val(self)
-    }
-
-    fn state(&mut self) -> Result<Vec<ScalarValue>> {
-        Ok(vec![
-            ScalarValue::from(self.count),
-            ScalarValue::from(self.sum),
-            ScalarValue::from(self.sum_sqr),
-            ScalarValue::from(self.sum_cub),
-            ScalarValue::from(self.sum_four),
-        ])
-    }
+pub(crate) fn population_kurtosis(sums: &PowerSums) -> Option<f64> {
+    Some(sums.standardized_moment(4)? - 3.0)
 }
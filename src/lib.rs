@@ -21,23 +21,93 @@ use std::sync::Arc;
 
 use datafusion::common::Result;
 use datafusion::execution::FunctionRegistry;
-use datafusion::logical_expr::AggregateUDF;
+use datafusion::logical_expr::{AggregateUDF, ScalarUDF};
 
 #[macro_use]
 pub mod macros;
+pub mod bloom_filter;
 pub mod common;
+pub mod config;
+pub mod count_min_sketch;
+pub mod describe_stats;
+pub mod entropy;
+pub mod first_last_by;
+pub mod fsum;
+pub mod group_uniq_array;
+pub mod histogram;
+pub mod hll;
 pub mod kurtosis;
 pub mod kurtosis_pop;
+pub mod mad;
+pub mod max_by_struct;
 pub mod max_min_by;
 pub mod mode;
+pub mod moment;
+pub mod power_mean;
+pub mod product;
+pub mod quantile;
+pub mod quantile_sketch;
+pub mod reservoir_sample;
 pub mod skewness;
+pub mod skewness_pop;
+pub mod theta;
+pub mod uniq;
+pub mod weighted_moments;
 pub mod expr_extra_fn {
+    pub use super::bloom_filter::bloom_filter_agg;
+    pub use super::bloom_filter::might_contain;
+    pub use super::count_min_sketch::cms_estimate;
+    pub use super::count_min_sketch::count_min_sketch;
+    pub use super::describe_stats::describe_stats;
+    pub use super::entropy::entropy;
+    pub use super::entropy::normalized_entropy;
+    pub use super::first_last_by::first_by;
+    pub use super::first_last_by::last_by;
+    pub use super::fsum::favg;
+    pub use super::fsum::fsum;
+    pub use super::group_uniq_array::group_uniq_array;
+    pub use super::histogram::histogram;
+    pub use super::histogram::histogram_numeric;
+    pub use super::hll::hll_sketch_agg;
+    pub use super::hll::hll_sketch_estimate;
+    pub use super::hll::hll_union_agg;
     pub use super::kurtosis::kurtosis;
     pub use super::kurtosis_pop::kurtosis_pop;
+    pub use super::mad::mad;
+    pub use super::max_by_struct::max_by_struct;
     pub use super::max_min_by::max_by;
     pub use super::max_min_by::min_by;
     pub use super::mode::mode;
+    pub use super::moment::moment;
+    pub use super::moment::standardized_moment;
+    pub use super::power_mean::geometric_mean;
+    pub use super::power_mean::harmonic_mean;
+    pub use super::product::product;
+    pub use super::product::try_product;
+    pub use super::quantile::quantile_cont;
+    pub use super::quantile::quantile_disc;
+    pub use super::quantile_sketch::approx_quantile_kll;
+    pub use super::quantile_sketch::approx_quantile_tdigest;
+    pub use super::quantile_sketch::kll_estimate;
+    pub use super::quantile_sketch::kll_merge;
+    pub use super::quantile_sketch::kll_sketch;
+    pub use super::quantile_sketch::tdigest_estimate;
+    pub use super::quantile_sketch::tdigest_merge;
+    pub use super::quantile_sketch::tdigest_sketch;
+    pub use super::reservoir_sample::reservoir_sample;
     pub use super::skewness::skewness;
+    pub use super::skewness::skewness_samp;
+    pub use super::skewness_pop::skewness_pop;
+    pub use super::theta::theta_a_not_b;
+    pub use super::theta::theta_estimate;
+    pub use super::theta::theta_intersect;
+    pub use super::theta::theta_sketch_agg;
+    pub use super::theta::theta_union_agg;
+    pub use super::uniq::uniq_combined;
+    pub use super::uniq::uniq_exact;
+    pub use super::weighted_moments::weighted_kurtosis;
+    pub use super::weighted_moments::weighted_kurtosis_pop;
+    pub use super::weighted_moments::weighted_skewness;
 }
 
 pub fn all_extra_aggregate_functions() -> Vec<Arc<AggregateUDF>> {
@@ -47,7 +117,60 @@ pub fn all_extra_aggregate_functions() -> Vec<Arc<AggregateUDF>> {
         max_min_by::min_by_udaf(),
         kurtosis::kurtosis_udaf(),
         skewness::skewness_udaf(),
+        skewness::skewness_samp_udaf(),
+        skewness_pop::skewness_pop_udaf(),
         kurtosis_pop::kurtosis_pop_udaf(),
+        first_last_by::first_by_udaf(),
+        first_last_by::last_by_udaf(),
+        max_by_struct::max_by_struct_udaf(),
+        moment::moment_udaf(),
+        moment::standardized_moment_udaf(),
+        describe_stats::describe_stats_udaf(),
+        weighted_moments::weighted_skewness_udaf(),
+        weighted_moments::weighted_kurtosis_udaf(),
+        weighted_moments::weighted_kurtosis_pop_udaf(),
+        mad::mad_udaf(),
+        quantile::quantile_cont_udaf(),
+        quantile::quantile_disc_udaf(),
+        quantile_sketch::approx_quantile_kll_udaf(),
+        quantile_sketch::kll_sketch_udaf(),
+        quantile_sketch::kll_merge_udaf(),
+        quantile_sketch::approx_quantile_tdigest_udaf(),
+        quantile_sketch::tdigest_sketch_udaf(),
+        quantile_sketch::tdigest_merge_udaf(),
+        hll::hll_sketch_agg_udaf(),
+        hll::hll_union_agg_udaf(),
+        theta::theta_sketch_agg_udaf(),
+        theta::theta_union_agg_udaf(),
+        count_min_sketch::count_min_sketch_udaf(),
+        bloom_filter::bloom_filter_agg_udaf(),
+        histogram::histogram_udaf(),
+        histogram::histogram_numeric_udaf(),
+        entropy::entropy_udaf(),
+        entropy::normalized_entropy_udaf(),
+        uniq::uniq_exact_udaf(),
+        uniq::uniq_combined_udaf(),
+        reservoir_sample::reservoir_sample_udaf(),
+        group_uniq_array::group_uniq_array_udaf(),
+        product::product_udaf(),
+        product::try_product_udaf(),
+        fsum::fsum_udaf(),
+        fsum::favg_udaf(),
+        power_mean::geometric_mean_udaf(),
+        power_mean::harmonic_mean_udaf(),
+    ]
+}
+
+pub fn all_extra_scalar_functions() -> Vec<Arc<ScalarUDF>> {
+    vec![
+        quantile_sketch::kll_estimate_udf(),
+        quantile_sketch::tdigest_estimate_udf(),
+        hll::hll_sketch_estimate_udf(),
+        theta::theta_intersect_udf(),
+        theta::theta_a_not_b_udf(),
+        theta::theta_estimate_udf(),
+        count_min_sketch::cms_estimate_udf(),
+        bloom_filter::might_contain_udf(),
     ]
 }
 
@@ -63,5 +186,16 @@ pub fn register_all_extra_functions(registry: &mut dyn FunctionRegistry) -> Resu
         Ok(()) as Result<()>
     })?;
 
+    all_extra_scalar_functions().into_iter().try_for_each(|udf| {
+        let existing_udf = registry.register_udf(udf)?;
+        if let Some(existing_udf) = existing_udf {
+            debug!("Overwrite existing UDF: {}", existing_udf.name());
+        }
+        Ok(()) as Result<()>
+    })?;
+
+    // Applies the session options of `config::ExtraFunctionsConfig` to the registered functions
+    registry.register_function_rewrite(Arc::new(skewness::SkewnessRewrite::default()))?;
+
     Ok(())
 }
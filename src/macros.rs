@@ -35,12 +35,14 @@ macro_rules! make_udaf_expr {
     };
 }
 
+/// Creates the expression function and the singleton [`AggregateUDF`](datafusion::logical_expr::AggregateUDF) of a
+/// UDAF created with `Default`, with the optional `aliases = [...]` of [`create_func`].
 macro_rules! make_udaf_expr_and_func {
-    ($UDAF:ty, $EXPR_FN:ident, $($arg:ident)*, $DOC:expr, $AGGREGATE_UDF_FN:ident) => {
+    ($UDAF:ty, $EXPR_FN:ident, $($arg:ident)*, $DOC:expr, $AGGREGATE_UDF_FN:ident $(, aliases = [$($ALIAS:literal),* $(,)?])?) => {
         make_udaf_expr!($EXPR_FN, $($arg)*, $DOC, $AGGREGATE_UDF_FN);
-        create_func!($UDAF, $AGGREGATE_UDF_FN);
+        create_func!($UDAF, $AGGREGATE_UDF_FN $(, aliases = [$($ALIAS),*])?);
     };
-    ($UDAF:ty, $EXPR_FN:ident, $DOC:expr, $AGGREGATE_UDF_FN:ident) => {
+    ($UDAF:ty, $EXPR_FN:ident, $DOC:expr, $AGGREGATE_UDF_FN:ident $(, aliases = [$($ALIAS:literal),* $(,)?])?) => {
         // "fluent expr_fn" style function
         #[doc = $DOC]
         pub fn $EXPR_FN(
@@ -56,25 +58,38 @@ macro_rules! make_udaf_expr_and_func {
             ))
         }
 
-        create_func!($UDAF, $AGGREGATE_UDF_FN);
+        create_func!($UDAF, $AGGREGATE_UDF_FN $(, aliases = [$($ALIAS),*])?);
     };
 }
 
+/// Creates the singleton [`AggregateUDF`](datafusion::logical_expr::AggregateUDF) of a UDAF, created with `Default`
+/// or with the given expression.
+///
+/// The names following `aliases =`, such as the names of the function in other SQL dialects, are registered with
+/// [`with_aliases`](crate::common::aliases::with_aliases), in addition to the ones of
+/// [`AggregateUDFImpl::aliases`](datafusion::logical_expr::AggregateUDFImpl::aliases).
 macro_rules! create_func {
-    ($UDAF:ty, $AGGREGATE_UDF_FN:ident) => {
-        create_func!($UDAF, $AGGREGATE_UDF_FN, <$UDAF>::default());
+    ($UDAF:ty, $AGGREGATE_UDF_FN:ident $(, aliases = [$($ALIAS:literal),* $(,)?])?) => {
+        create_func!($UDAF, $AGGREGATE_UDF_FN, <$UDAF>::default() $(, aliases = [$($ALIAS),*])?);
+    };
+    ($UDAF:ty, $AGGREGATE_UDF_FN:ident, $CREATE:expr, aliases = [$($ALIAS:literal),* $(,)?]) => {
+        create_func!(
+            $UDAF,
+            $AGGREGATE_UDF_FN,
+            crate::common::aliases::with_aliases($CREATE, [$($ALIAS),*])
+        );
     };
     ($UDAF:ty, $AGGREGATE_UDF_FN:ident, $CREATE:expr) => {
         paste::paste! {
             /// Singleton instance of [$UDAF], ensures the UDAF is only created once
-            /// named STATIC_$(UDAF). For example `STATIC_FirstValue`
+            /// named STATIC_$(AGGREGATE_UDF_FN). For example `STATIC_first_value_udaf`
             #[allow(non_upper_case_globals)]
-            static [< STATIC_ $UDAF >]: std::sync::OnceLock<std::sync::Arc<datafusion::logical_expr::AggregateUDF>> =
+            static [< STATIC_ $AGGREGATE_UDF_FN >]: std::sync::OnceLock<std::sync::Arc<datafusion::logical_expr::AggregateUDF>> =
                 std::sync::OnceLock::new();
 
             #[doc = concat!("AggregateFunction that returns a [`AggregateUDF`](datafusion_expr::AggregateUDF) for [`", stringify!($UDAF), "`]")]
             pub fn $AGGREGATE_UDF_FN() -> std::sync::Arc<datafusion::logical_expr::AggregateUDF> {
-                [< STATIC_ $UDAF >]
+                [< STATIC_ $AGGREGATE_UDF_FN >]
                     .get_or_init(|| {
                         std::sync::Arc::new(datafusion::logical_expr::AggregateUDF::from($CREATE))
                     })
@@ -83,3 +98,41 @@ macro_rules! create_func {
         }
     }
 }
+
+/// Scalar function counterpart of [`make_udaf_expr`].
+macro_rules! make_udf_expr {
+    ($EXPR_FN:ident, $($arg:ident)*, $DOC:expr, $SCALAR_UDF_FN:ident) => {
+        // "fluent expr_fn" style function
+        #[doc = $DOC]
+        pub fn $EXPR_FN(
+            $($arg: datafusion::logical_expr::Expr,)*
+        ) -> datafusion::logical_expr::Expr {
+            $SCALAR_UDF_FN().call(vec![$($arg),*])
+        }
+    };
+}
+
+/// Scalar function counterpart of [`create_func`].
+macro_rules! create_udf_func {
+    ($UDF:ty, $SCALAR_UDF_FN:ident) => {
+        create_udf_func!($UDF, $SCALAR_UDF_FN, <$UDF>::default());
+    };
+    ($UDF:ty, $SCALAR_UDF_FN:ident, $CREATE:expr) => {
+        paste::paste! {
+            /// Singleton instance of [$UDF], ensures the UDF is only created once
+            /// named STATIC_$(SCALAR_UDF_FN). For example `STATIC_kll_estimate_udf`
+            #[allow(non_upper_case_globals)]
+            static [< STATIC_ $SCALAR_UDF_FN >]: std::sync::OnceLock<std::sync::Arc<datafusion::logical_expr::ScalarUDF>> =
+                std::sync::OnceLock::new();
+
+            #[doc = concat!("ScalarFunction that returns a [`ScalarUDF`](datafusion_expr::ScalarUDF) for [`", stringify!($UDF), "`]")]
+            pub fn $SCALAR_UDF_FN() -> std::sync::Arc<datafusion::logical_expr::ScalarUDF> {
+                [< STATIC_ $SCALAR_UDF_FN >]
+                    .get_or_init(|| {
+                        std::sync::Arc::new(datafusion::logical_expr::ScalarUDF::new_from_impl($CREATE))
+                    })
+                    .clone()
+            }
+        }
+    }
+}
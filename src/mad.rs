@@ -0,0 +1,246 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::any::Any;
+use std::fmt::Debug;
+
+use arrow::array::{ArrayRef, ArrowPrimitiveType};
+use arrow::datatypes::{DataType, Field, Float64Type, IntervalUnit};
+use datafusion::arrow;
+use datafusion::common::{exec_err, plan_err};
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, Signature, Volatility};
+use datafusion::scalar::ScalarValue;
+
+use crate::common::quantile::{
+    coerce_quantile_type, dispatch_exact_quantile_type, interpolate_f64, interpolate_i128, interpolation_bounds,
+    lerp_i128, nanos_per_unit, nanos_to_interval, CollectedValues,
+};
+
+make_udaf_expr_and_func!(
+    MadFunction,
+    mad,
+    x,
+    "Computes the median absolute deviation of the values.",
+    mad_udaf
+);
+
+/// The `MadFunction` computes the median absolute deviation `median(|x - median(x)|)` of the values, as DuckDB does.
+///
+/// - Medians are interpolated between the two middle values when there is an even number of values.
+/// - Numbers return a `Float64`, decimals a decimal of the same type.
+/// - Dates, times and timestamps return an `Interval` of days and nanoseconds.
+pub struct MadFunction {
+    signature: Signature,
+}
+
+impl Debug for MadFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MadFunction")
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl Default for MadFunction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MadFunction {
+    pub fn new() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Immutable),
+        }
+    }
+}
+
+impl AggregateUDFImpl for MadFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "mad"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let [arg_type] = arg_types else {
+            return plan_err!("mad expects exactly 1 argument, got {}", arg_types.len());
+        };
+        match coerce_quantile_type(arg_type) {
+            Some(data_type) => Ok(vec![data_type]),
+            None => plan_err!("mad does not support values of type {arg_type}"),
+        }
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+        match &arg_types[0] {
+            data_type if nanos_per_unit(data_type).is_some() => Ok(DataType::Interval(IntervalUnit::MonthDayNano)),
+            data_type => Ok(data_type.clone()),
+        }
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let data_type = acc_args.exprs[0].data_type(acc_args.schema)?;
+        if data_type == DataType::Float64 {
+            return Ok(Box::new(FloatMadAccumulator {
+                values: CollectedValues::new(&data_type),
+            }));
+        }
+
+        fn exact<T>(data_type: &DataType) -> Result<Box<dyn Accumulator>>
+        where
+            T: ArrowPrimitiveType + Send + Sync,
+            T::Native: Into<i128>,
+        {
+            Ok(Box::new(ExactMadAccumulator::<T> {
+                values: CollectedValues::new(data_type),
+                data_type: data_type.clone(),
+            }))
+        }
+        dispatch_exact_quantile_type!(
+            &data_type,
+            exact(&data_type),
+            exec_err!("Unsupported data type {data_type} for mad")
+        )
+    }
+
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(vec![Field::new_list(
+            "values",
+            Field::new("item", args.input_types[0].clone(), true),
+            true,
+        )])
+    }
+}
+
+/// Accumulator collecting `Float64` values to compute their median absolute deviation.
+#[derive(Debug)]
+struct FloatMadAccumulator {
+    values: CollectedValues<Float64Type>,
+}
+
+impl Accumulator for FloatMadAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        self.values.update(&values[0])
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        self.values.merge(&states[0])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        let sorted = self.values.sorted();
+        let Some(median) = interpolate_f64(sorted, 0.5) else {
+            return Ok(ScalarValue::Float64(None));
+        };
+
+        let mut deviations = sorted.iter().map(|value| (value - median).abs()).collect::<Vec<_>>();
+        deviations.sort_unstable_by(f64::total_cmp);
+        Ok(ScalarValue::Float64(interpolate_f64(&deviations, 0.5)))
+    }
+
+    fn size(&self) -> usize {
+        self.values.size()
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        Ok(vec![self.values.state()])
+    }
+}
+
+/// Returns the median of the absolute deviations of the exact values from their `median`.
+///
+/// The deviations are computed as `u128`, as the ones of values of opposite signs may not fit in an `i128`, and the
+/// median deviation fails when it does not.
+fn median_deviation(values: &[i128], median: i128) -> Result<Option<i128>> {
+    let mut deviations = values.iter().map(|value| value.abs_diff(median)).collect::<Vec<_>>();
+    deviations.sort_unstable();
+    let Some((lo, hi, fraction)) = interpolation_bounds(deviations.len(), 0.5) else {
+        return Ok(None);
+    };
+    match (i128::try_from(deviations[lo]), i128::try_from(deviations[hi])) {
+        (Ok(lo), Ok(hi)) => Ok(Some(lerp_i128(lo, hi, fraction))),
+        _ => exec_err!("Overflow while computing the median absolute deviation from {median}"),
+    }
+}
+
+/// Accumulator collecting decimal or temporal values to compute their median absolute deviation exactly, on their
+/// unscaled values or on nanoseconds.
+struct ExactMadAccumulator<T: ArrowPrimitiveType> {
+    values: CollectedValues<T>,
+    data_type: DataType,
+}
+
+impl<T: ArrowPrimitiveType> Debug for ExactMadAccumulator<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExactMadAccumulator")
+            .field("values", &self.values)
+            .field("data_type", &self.data_type)
+            .finish()
+    }
+}
+
+impl<T> Accumulator for ExactMadAccumulator<T>
+where
+    T: ArrowPrimitiveType + Send + Sync,
+    T::Native: Into<i128>,
+{
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        self.values.update(&values[0])
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        self.values.merge(&states[0])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        let nanos_per_unit = nanos_per_unit(&self.data_type);
+        let sorted = self
+            .values
+            .sorted()
+            .iter()
+            .map(|value| (*value).into() * nanos_per_unit.unwrap_or(1))
+            .collect::<Vec<_>>();
+
+        let mad = match interpolate_i128(&sorted, 0.5) {
+            Some(median) => median_deviation(&sorted, median)?,
+            None => None,
+        };
+
+        match (&self.data_type, nanos_per_unit) {
+            (DataType::Decimal128(precision, scale), _) => Ok(ScalarValue::Decimal128(mad, *precision, *scale)),
+            (_, Some(_)) => Ok(ScalarValue::IntervalMonthDayNano(mad.map(nanos_to_interval))),
+            _ => exec_err!("Unsupported data type {} for mad", self.data_type),
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.values.size()
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        Ok(vec![self.values.state()])
+    }
+}
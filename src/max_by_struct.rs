@@ -0,0 +1,260 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::any::Any;
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, StructArray};
+use datafusion::arrow;
+use datafusion::arrow::datatypes::{DataType, Field, Fields};
+use datafusion::common::plan_err;
+use datafusion::error::Result;
+use datafusion::logical_expr::expr::AggregateFunction;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDF, AggregateUDFImpl, Expr, Signature, Volatility};
+use datafusion::scalar::ScalarValue;
+
+use crate::common::arg_extreme::{is_orderable, ArgExtreme, ArgExtremeAccumulator, SlidingArgExtremeAccumulator};
+
+create_func!(MaxByStructFunction, max_by_struct_udaf, aliases = ["arg_max_row"]);
+
+/// Returns a struct of the columns following the first one, taken from the row with the maximum value in the first
+/// column. The struct fields are named after the arguments, see [`MaxByStructFunction::with_field_names`]. Names
+/// already taken, such as those of columns of the same name from different relations, get a `_1`, `_2`, ... suffix.
+pub fn max_by_struct(args: Vec<Expr>) -> Expr {
+    let mut taken = HashSet::new();
+    let field_names = args
+        .iter()
+        .skip(1)
+        .map(|arg| unique_name(argument_name(arg), &mut taken))
+        .collect();
+    Expr::AggregateFunction(AggregateFunction::new_udf(
+        Arc::new(AggregateUDF::from(
+            MaxByStructFunction::new().with_field_names(field_names),
+        )),
+        args,
+        false,
+        None,
+        None,
+        None,
+    ))
+}
+
+/// Returns `name`, suffixed with `_1`, `_2`, ... until it is not one of the `taken` names, and takes it.
+fn unique_name(name: String, taken: &mut HashSet<String>) -> String {
+    let mut unique = name.clone();
+    let mut suffix = 0;
+    while !taken.insert(unique.clone()) {
+        suffix += 1;
+        unique = format!("{name}_{suffix}");
+    }
+    unique
+}
+
+/// Names a column or an aliased expression after itself, and any other expression after its schema name.
+fn argument_name(expr: &Expr) -> String {
+    match expr {
+        Expr::Column(column) => column.name.clone(),
+        Expr::Alias(alias) => alias.name.clone(),
+        _ => expr.schema_name().to_string(),
+    }
+}
+
+/// The `MaxByStructFunction` returns the whole row with the maximum `y`, e.g. `max_by_struct(ts, price, volume)`.
+///
+/// - The result is a `Struct` with one field per `x` column. The [`max_by_struct`] expression function names the
+///   fields after its arguments, so `max_by_struct(vec![col("ts"), col("price")])` has a `price` field. In SQL, the
+///   return type is planned from the argument types only, so the fields are named `c0`, `c1`, ... like the `struct`
+///   function.
+/// - All fields come from the same row, and the input is accumulated once instead of once per column.
+/// - Rows where `y` is null are ignored. If several rows share the maximum `y`, the last one encountered is returned.
+pub struct MaxByStructFunction {
+    signature: Signature,
+    field_names: Vec<String>,
+}
+
+impl Debug for MaxByStructFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MaxByStructFunction")
+            .field("signature", &self.signature)
+            .field("field_names", &self.field_names)
+            .finish()
+    }
+}
+
+impl Default for MaxByStructFunction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MaxByStructFunction {
+    pub fn new() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Immutable),
+            field_names: vec![],
+        }
+    }
+
+    /// Names the struct fields, in the order of the `x` columns. Fields without a name keep their positional `c0`,
+    /// `c1`, ... name.
+    pub fn with_field_names(mut self, field_names: Vec<String>) -> Self {
+        self.field_names = field_names;
+        self
+    }
+
+    fn row_fields(&self, value_types: &[DataType]) -> Fields {
+        value_types
+            .iter()
+            .enumerate()
+            .map(|(i, data_type)| {
+                let name = self.field_names.get(i).cloned().unwrap_or_else(|| format!("c{i}"));
+                Field::new(name, data_type.clone(), true)
+            })
+            .collect()
+    }
+}
+
+impl AggregateUDFImpl for MaxByStructFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "max_by_struct"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        if arg_types.len() < 2 {
+            return plan_err!("max_by_struct expects at least 2 arguments, got {}", arg_types.len());
+        }
+
+        if !is_orderable(&arg_types[0]) {
+            return plan_err!(
+                "max_by_struct cannot order by a value of type {}, it has no natural order",
+                arg_types[0]
+            );
+        }
+
+        Ok(arg_types.to_vec())
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Struct(self.row_fields(&arg_types[1..])))
+    }
+
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(vec![
+            Field::new("value", args.return_type.clone(), true),
+            Field::new("ordering", args.input_types[0].clone(), true),
+        ])
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let ordering_type = acc_args.exprs[0].data_type(acc_args.schema)?;
+        let inner = ArgExtremeAccumulator::try_new(ArgExtreme::Max, acc_args.return_type, &ordering_type)?;
+        Ok(Box::new(MaxByStructAccumulator::new(
+            acc_args.return_type,
+            Box::new(inner),
+        )))
+    }
+
+    fn create_sliding_accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let ordering_type = acc_args.exprs[0].data_type(acc_args.schema)?;
+        let inner = SlidingArgExtremeAccumulator::new(ArgExtreme::Max, acc_args.return_type, &ordering_type);
+        Ok(Box::new(MaxByStructAccumulator::new(
+            acc_args.return_type,
+            Box::new(inner),
+        )))
+    }
+
+    fn equals(&self, other: &dyn AggregateUDFImpl) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<Self>()
+            .is_some_and(|other| self.field_names == other.field_names)
+    }
+
+    fn hash_value(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.name().hash(&mut hasher);
+        self.field_names.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Packs the `x` columns of each input batch into a single struct column and tracks the row with the maximum `y`
+/// with an arg-extreme accumulator, whose state already has the `(value, ordering)` layout.
+#[derive(Debug)]
+struct MaxByStructAccumulator {
+    fields: Fields,
+    inner: Box<dyn Accumulator>,
+}
+
+impl MaxByStructAccumulator {
+    fn new(return_type: &DataType, inner: Box<dyn Accumulator>) -> Self {
+        let fields = match return_type {
+            DataType::Struct(fields) => fields.clone(),
+            _ => unreachable!("max_by_struct always returns a struct"),
+        };
+        Self { fields, inner }
+    }
+
+    fn pack_row(&self, values: &[ArrayRef]) -> Result<Vec<ArrayRef>> {
+        let row = StructArray::try_new(self.fields.clone(), values[1..].to_vec(), None)?;
+        Ok(vec![Arc::new(row), Arc::clone(&values[0])])
+    }
+}
+
+impl Accumulator for MaxByStructAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        let values = self.pack_row(values)?;
+        self.inner.update_batch(&values)
+    }
+
+    fn retract_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        let values = self.pack_row(values)?;
+        self.inner.retract_batch(&values)
+    }
+
+    fn supports_retract_batch(&self) -> bool {
+        self.inner.supports_retract_batch()
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        self.inner.merge_batch(states)
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        self.inner.state()
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        self.inner.evaluate()
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.inner.size()
+    }
+}
@@ -1,29 +1,34 @@
-use arrow::datatypes::DataType;
-use datafusion::error::DataFusionError;
-use datafusion::functions_aggregate::first_last::last_value_udaf;
-use datafusion::logical_expr::expr::AggregateFunction;
-use datafusion::logical_expr::expr::Sort;
-use datafusion::logical_expr::simplify::SimplifyInfo;
-use datafusion::logical_expr::{expr, function, Accumulator, AggregateUDFImpl};
-use datafusion::prelude::Expr;
-use datafusion::{
-    common::exec_err,
-    logical_expr::{function::AccumulatorArgs, Signature, Volatility},
-};
 use std::any::Any;
 use std::fmt::Debug;
-use std::ops::Deref;
+
+use datafusion::arrow::datatypes::{DataType, Field};
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, Signature, Volatility};
+
+use crate::common::arg_extreme::{
+    arg_extreme_input_types, arg_extreme_state_fields, coerce_arg_extreme_types, ArgExtreme, ArgExtremeAccumulator,
+    SlidingArgExtremeAccumulator,
+};
 
 make_udaf_expr_and_func!(
     MaxByFunction,
     max_by,
     x y,
     "Returns the value of the first column corresponding to the maximum value in the second column.",
-    max_by_udaf
+    max_by_udaf,
+    aliases = ["arg_max", "argmax", "argMax"]
 );
 
+/// The `MaxByFunction` returns the value of `x` at the row with the largest `y`, e.g. `max_by(price, ts)`.
+///
+/// - Rows where `y` is null are ignored.
+/// - If several rows share the largest `y`, the last one encountered is returned, as `last_by` does.
+/// - It shares the [`ArgExtremeAccumulator`] of `last_by`, so only the current extreme row is kept and the input is
+///   not sorted.
 pub struct MaxByFunction {
     signature: Signature,
+    dictionary_output: bool,
 }
 
 impl Debug for MaxByFunction {
@@ -31,6 +36,7 @@ impl Debug for MaxByFunction {
         f.debug_struct("MaxBy")
             .field("name", &self.name())
             .field("signature", &self.signature)
+            .field("dictionary_output", &self.dictionary_output)
             .field("accumulator", &"<FUNC>")
             .finish()
     }
@@ -45,17 +51,15 @@ impl MaxByFunction {
     pub fn new() -> Self {
         Self {
             signature: Signature::user_defined(Volatility::Immutable),
+            dictionary_output: false,
         }
     }
-}
 
-fn get_min_max_by_result_type(input_types: &[DataType]) -> Result<Vec<DataType>, DataFusionError> {
-    match &input_types[0] {
-        DataType::Dictionary(_, dict_value_type) => {
-            // TODO add checker, if the value type is complex data type
-            Ok(vec![dict_value_type.deref().clone()])
-        }
-        _ => Ok(input_types.to_vec()),
+    /// Keeps a dictionary encoded first argument dictionary encoded in the result, instead of returning its
+    /// value type.
+    pub fn with_dictionary_output(mut self, dictionary_output: bool) -> Self {
+        self.dictionary_output = dictionary_output;
+        self
     }
 }
 
@@ -72,34 +76,34 @@ impl AggregateUDFImpl for MaxByFunction {
         &self.signature
     }
 
-    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType, DataFusionError> {
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
         Ok(arg_types[0].to_owned())
     }
 
-    fn accumulator(&self, _acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>, DataFusionError> {
-        exec_err!("should not reach here")
-    }
-    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>, DataFusionError> {
-        get_min_max_by_result_type(arg_types)
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        coerce_arg_extreme_types("max_by/min_by", arg_types, self.dictionary_output)
     }
 
-    fn simplify(&self) -> Option<function::AggregateFunctionSimplification> {
-        let simplify = |mut aggr_func: expr::AggregateFunction, _: &dyn SimplifyInfo| {
-            let mut order_by = aggr_func.order_by.unwrap_or_default();
-            let (second_arg, first_arg) = (aggr_func.args.remove(1), aggr_func.args.remove(0));
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(arg_extreme_state_fields(&args))
+    }
 
-            order_by.push(Sort::new(second_arg, true, false));
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let (value_type, ordering_type) = arg_extreme_input_types(&acc_args)?;
+        Ok(Box::new(ArgExtremeAccumulator::try_new(
+            ArgExtreme::Max,
+            &value_type,
+            &ordering_type,
+        )?))
+    }
 
-            Ok(Expr::AggregateFunction(AggregateFunction::new_udf(
-                last_value_udaf(),
-                vec![first_arg],
-                aggr_func.distinct,
-                aggr_func.filter,
-                Some(order_by),
-                aggr_func.null_treatment,
-            )))
-        };
-        Some(Box::new(simplify))
+    fn create_sliding_accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let (value_type, ordering_type) = arg_extreme_input_types(&acc_args)?;
+        Ok(Box::new(SlidingArgExtremeAccumulator::new(
+            ArgExtreme::Max,
+            &value_type,
+            &ordering_type,
+        )))
     }
 }
 
@@ -108,11 +112,19 @@ make_udaf_expr_and_func!(
     min_by,
     x y,
     "Returns the value of the first column corresponding to the minimum value in the second column.",
-    min_by_udaf
+    min_by_udaf,
+    aliases = ["arg_min", "argmin", "argMin"]
 );
 
+/// The `MinByFunction` returns the value of `x` at the row with the smallest `y`, e.g. `min_by(price, ts)`.
+///
+/// - Rows where `y` is null are ignored.
+/// - If several rows share the smallest `y`, the first one encountered is returned, as `first_by` does.
+/// - It shares the [`ArgExtremeAccumulator`] of `first_by`, so only the current extreme row is kept and the input is
+///   not sorted.
 pub struct MinByFunction {
     signature: Signature,
+    dictionary_output: bool,
 }
 
 impl Debug for MinByFunction {
@@ -120,6 +132,7 @@ impl Debug for MinByFunction {
         f.debug_struct("MinBy")
             .field("name", &self.name())
             .field("signature", &self.signature)
+            .field("dictionary_output", &self.dictionary_output)
             .field("accumulator", &"<FUNC>")
             .finish()
     }
@@ -135,8 +148,16 @@ impl MinByFunction {
     pub fn new() -> Self {
         Self {
             signature: Signature::user_defined(Volatility::Immutable),
+            dictionary_output: false,
         }
     }
+
+    /// Keeps a dictionary encoded first argument dictionary encoded in the result, instead of returning its
+    /// value type.
+    pub fn with_dictionary_output(mut self, dictionary_output: bool) -> Self {
+        self.dictionary_output = dictionary_output;
+        self
+    }
 }
 
 impl AggregateUDFImpl for MinByFunction {
@@ -152,34 +173,33 @@ impl AggregateUDFImpl for MinByFunction {
         &self.signature
     }
 
-    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType, DataFusionError> {
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
         Ok(arg_types[0].to_owned())
     }
 
-    fn accumulator(&self, _acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>, DataFusionError> {
-        exec_err!("should not reach here")
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        coerce_arg_extreme_types("max_by/min_by", arg_types, self.dictionary_output)
     }
 
-    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>, DataFusionError> {
-        get_min_max_by_result_type(arg_types)
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(arg_extreme_state_fields(&args))
     }
 
-    fn simplify(&self) -> Option<function::AggregateFunctionSimplification> {
-        let simplify = |mut aggr_func: expr::AggregateFunction, _: &dyn SimplifyInfo| {
-            let mut order_by = aggr_func.order_by.unwrap_or_default();
-            let (second_arg, first_arg) = (aggr_func.args.remove(1), aggr_func.args.remove(0));
-
-            order_by.push(Sort::new(second_arg, false, false)); // false for ascending sort
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let (value_type, ordering_type) = arg_extreme_input_types(&acc_args)?;
+        Ok(Box::new(ArgExtremeAccumulator::try_new(
+            ArgExtreme::Min,
+            &value_type,
+            &ordering_type,
+        )?))
+    }
 
-            Ok(Expr::AggregateFunction(AggregateFunction::new_udf(
-                last_value_udaf(),
-                vec![first_arg],
-                aggr_func.distinct,
-                aggr_func.filter,
-                Some(order_by),
-                aggr_func.null_treatment,
-            )))
-        };
-        Some(Box::new(simplify))
+    fn create_sliding_accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let (value_type, ordering_type) = arg_extreme_input_types(&acc_args)?;
+        Ok(Box::new(SlidingArgExtremeAccumulator::new(
+            ArgExtreme::Min,
+            &value_type,
+            &ordering_type,
+        )))
     }
 }
@@ -15,30 +15,25 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use arrow::datatypes::{
-    Date32Type, Date64Type, Float16Type, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type,
-    Time32MillisecondType, Time32SecondType, Time64MicrosecondType, Time64NanosecondType, TimestampMicrosecondType,
-    TimestampMillisecondType, TimestampNanosecondType, TimestampSecondType, UInt16Type, UInt32Type, UInt64Type,
-    UInt8Type,
-};
-use datafusion::arrow;
-
 use datafusion::error::Result;
 
-use datafusion::arrow::datatypes::{DataType, Field, TimeUnit};
-use datafusion::common::not_impl_err;
+use datafusion::arrow::datatypes::{DataType, Field};
 use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
 use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, Signature, Volatility};
-use datafusion::physical_expr::binary_map::OutputType;
 
 use std::any::Any;
 use std::fmt::Debug;
 
-use crate::common::mode::{
-    BytesModeAccumulator, BytesViewModeAccumulator, FloatModeAccumulator, PrimitiveModeAccumulator,
-};
+use crate::common::mode::{value_counts_accumulator, value_counts_state_fields};
 
-make_udaf_expr_and_func!(ModeFunction, mode, x, "Calculates the most frequent value.", mode_udaf);
+make_udaf_expr_and_func!(
+    ModeFunction,
+    mode,
+    x,
+    "Calculates the most frequent value.",
+    mode_udaf,
+    aliases = ["stats_mode"]
+);
 
 /// The `ModeFunction` calculates the mode (most frequent value) from a set of values.
 ///
@@ -89,66 +84,11 @@ impl AggregateUDFImpl for ModeFunction {
     }
 
     fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
-        let value_type = args.input_types[0].clone();
-
-        Ok(vec![
-            Field::new("values", value_type, true),
-            Field::new("frequencies", DataType::UInt64, true),
-        ])
+        Ok(value_counts_state_fields(&args.input_types[0]))
     }
 
     fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
         let data_type = &acc_args.exprs[0].data_type(acc_args.schema)?;
-
-        let accumulator: Box<dyn Accumulator> = match data_type {
-            DataType::Int8 => Box::new(PrimitiveModeAccumulator::<Int8Type>::new(data_type)),
-            DataType::Int16 => Box::new(PrimitiveModeAccumulator::<Int16Type>::new(data_type)),
-            DataType::Int32 => Box::new(PrimitiveModeAccumulator::<Int32Type>::new(data_type)),
-            DataType::Int64 => Box::new(PrimitiveModeAccumulator::<Int64Type>::new(data_type)),
-            DataType::UInt8 => Box::new(PrimitiveModeAccumulator::<UInt8Type>::new(data_type)),
-            DataType::UInt16 => Box::new(PrimitiveModeAccumulator::<UInt16Type>::new(data_type)),
-            DataType::UInt32 => Box::new(PrimitiveModeAccumulator::<UInt32Type>::new(data_type)),
-            DataType::UInt64 => Box::new(PrimitiveModeAccumulator::<UInt64Type>::new(data_type)),
-
-            DataType::Date32 => Box::new(PrimitiveModeAccumulator::<Date32Type>::new(data_type)),
-            DataType::Date64 => Box::new(PrimitiveModeAccumulator::<Date64Type>::new(data_type)),
-            DataType::Time32(TimeUnit::Millisecond) => {
-                Box::new(PrimitiveModeAccumulator::<Time32MillisecondType>::new(data_type))
-            }
-            DataType::Time32(TimeUnit::Second) => {
-                Box::new(PrimitiveModeAccumulator::<Time32SecondType>::new(data_type))
-            }
-            DataType::Time64(TimeUnit::Microsecond) => {
-                Box::new(PrimitiveModeAccumulator::<Time64MicrosecondType>::new(data_type))
-            }
-            DataType::Time64(TimeUnit::Nanosecond) => {
-                Box::new(PrimitiveModeAccumulator::<Time64NanosecondType>::new(data_type))
-            }
-            DataType::Timestamp(TimeUnit::Microsecond, _) => {
-                Box::new(PrimitiveModeAccumulator::<TimestampMicrosecondType>::new(data_type))
-            }
-            DataType::Timestamp(TimeUnit::Millisecond, _) => {
-                Box::new(PrimitiveModeAccumulator::<TimestampMillisecondType>::new(data_type))
-            }
-            DataType::Timestamp(TimeUnit::Nanosecond, _) => {
-                Box::new(PrimitiveModeAccumulator::<TimestampNanosecondType>::new(data_type))
-            }
-            DataType::Timestamp(TimeUnit::Second, _) => {
-                Box::new(PrimitiveModeAccumulator::<TimestampSecondType>::new(data_type))
-            }
-
-            DataType::Float16 => Box::new(FloatModeAccumulator::<Float16Type>::new(data_type)),
-            DataType::Float32 => Box::new(FloatModeAccumulator::<Float32Type>::new(data_type)),
-            DataType::Float64 => Box::new(FloatModeAccumulator::<Float64Type>::new(data_type)),
-
-            DataType::Utf8 => Box::new(BytesModeAccumulator::<i32>::new(OutputType::Utf8)),
-            DataType::LargeUtf8 => Box::new(BytesModeAccumulator::<i64>::new(OutputType::Utf8)),
-            DataType::Utf8View => Box::new(BytesViewModeAccumulator::new(OutputType::Utf8View)),
-            _ => {
-                return not_impl_err!("Unsupported data type: {:?} for mode function", data_type);
-            }
-        };
-
-        Ok(accumulator)
+        Ok(value_counts_accumulator(self.name(), data_type)?.into_accumulator())
     }
 }
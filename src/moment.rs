@@ -0,0 +1,208 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::any::Any;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use datafusion::arrow::datatypes::{DataType, Field};
+use datafusion::common::{plan_err, ScalarValue};
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, Signature, Volatility};
+use datafusion::physical_expr::expressions::Literal;
+
+use crate::common::moments::{coerce_moment_type, moments_accumulator, moments_state_fields};
+
+make_udaf_expr_and_func!(
+    MomentFunction,
+    moment,
+    x k,
+    "Computes the k-th central moment of the values.",
+    moment_udaf
+);
+
+make_udaf_expr_and_func!(
+    StandardizedMomentFunction,
+    standardized_moment,
+    x k,
+    "Computes the k-th standardized moment of the values.",
+    standardized_moment_udaf
+);
+
+/// The `MomentFunction` computes the `k`-th central moment `Σ(x - mean)ᵏ / n` of the values, e.g. `moment(x, 2)`
+/// is the population variance.
+///
+/// - `k` must be a constant positive integer, up to 16.
+/// - Returns null when there are no values.
+pub struct MomentFunction {
+    signature: Signature,
+}
+
+impl Debug for MomentFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MomentFunction")
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl Default for MomentFunction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MomentFunction {
+    pub fn new() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Immutable),
+        }
+    }
+}
+
+impl AggregateUDFImpl for MomentFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "moment"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        coerce_moment_order_types(self.name(), arg_types)
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Float64)
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let k = moment_order(self.name(), &acc_args)?;
+        moments_accumulator(
+            &acc_args.exprs[0].data_type(acc_args.schema)?,
+            k,
+            false,
+            Arc::new(move |sums| sums.central_moment(k)),
+        )
+    }
+
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(moments_state_fields(&args.input_types[0]))
+    }
+}
+
+/// The `StandardizedMomentFunction` computes the `k`-th standardized moment `m_k / m_2^(k/2)` of the values, where
+/// `m_k` is their `k`-th central moment, e.g. `standardized_moment(x, 3)` is the population skewness.
+///
+/// - `k` must be a constant positive integer, up to 16.
+/// - Returns null when the values have no variance.
+pub struct StandardizedMomentFunction {
+    signature: Signature,
+}
+
+impl Debug for StandardizedMomentFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StandardizedMomentFunction")
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl Default for StandardizedMomentFunction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StandardizedMomentFunction {
+    pub fn new() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Immutable),
+        }
+    }
+}
+
+impl AggregateUDFImpl for StandardizedMomentFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "standardized_moment"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        coerce_moment_order_types(self.name(), arg_types)
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Float64)
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let k = moment_order(self.name(), &acc_args)?;
+        moments_accumulator(
+            &acc_args.exprs[0].data_type(acc_args.schema)?,
+            k.max(2),
+            false,
+            Arc::new(move |sums| sums.standardized_moment(k)),
+        )
+    }
+
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(moments_state_fields(&args.input_types[0]))
+    }
+}
+
+fn coerce_moment_order_types(name: &str, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+    let [arg_type, order_type] = arg_types else {
+        return plan_err!("{name} expects exactly 2 arguments, got {}", arg_types.len());
+    };
+    if !order_type.is_integer() && !order_type.is_null() {
+        return plan_err!("{name} expects an integer order, got {order_type}");
+    }
+    Ok(vec![coerce_moment_type(name, arg_type)?, DataType::Int64])
+}
+
+/// Highest order of the moments, above which the binomial expansions of the power sums lose all precision and
+/// their accumulators grow with the order.
+const MAX_MOMENT_ORDER: i64 = 16;
+
+/// Returns the order `k` of the moment, which must be a positive integer literal up to [`MAX_MOMENT_ORDER`].
+fn moment_order(name: &str, acc_args: &AccumulatorArgs) -> Result<usize> {
+    let order = acc_args.exprs[1]
+        .as_any()
+        .downcast_ref::<Literal>()
+        .map(|literal| literal.value());
+    match order {
+        Some(ScalarValue::Int64(Some(k))) if *k > MAX_MOMENT_ORDER => {
+            plan_err!("The order of {name} must be at most {MAX_MOMENT_ORDER}, got {k}")
+        }
+        Some(ScalarValue::Int64(Some(k))) if *k > 0 => Ok(*k as usize),
+        _ => plan_err!("The order of {name} must be a constant positive integer"),
+    }
+}
@@ -0,0 +1,307 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::any::Any;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, AsArray, BooleanArray, Float64Array};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Field, Float64Type};
+use datafusion::arrow;
+use datafusion::common::{exec_err, plan_err};
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, EmitTo, GroupsAccumulator, Signature, Volatility};
+use datafusion::scalar::ScalarValue;
+
+use crate::common::compensated_sum::CountedSum;
+use crate::common::groups::is_selected;
+
+make_udaf_expr!(
+    geometric_mean,
+    x,
+    "Computes the geometric mean of the values.",
+    geometric_mean_udaf
+);
+create_func!(
+    PowerMeanFunction,
+    geometric_mean_udaf,
+    PowerMeanFunction::new_geometric(),
+    aliases = ["geomean"]
+);
+
+make_udaf_expr!(
+    harmonic_mean,
+    x,
+    "Computes the harmonic mean of the values.",
+    harmonic_mean_udaf
+);
+create_func!(PowerMeanFunction, harmonic_mean_udaf, PowerMeanFunction::new_harmonic());
+
+/// The `PowerMeanFunction` computes the geometric mean `exp(Σln(x) / n)` or the harmonic mean `n / Σ(1 / x)` of the
+/// values, which are the power means of exponents 0 and -1.
+///
+/// - The values are cast to `Float64`, and the logarithms or inverses are summed with a [`CountedSum`].
+/// - Both means are only defined for non-negative values, and fail on a negative one. They are 0 when a value is 0,
+///   which is their limit when a value tends to 0. The infinite and NaN logarithms or inverses, such as the ones of 0,
+///   are counted apart from the sum, so that they leave a sliding window frame once retracted.
+/// - `geometric_mean` is also named `geomean`, as in DuckDB.
+/// - Null values are ignored, and the result is null when there are no values.
+/// - Values leaving a sliding window frame are retracted from the sums.
+pub struct PowerMeanFunction {
+    name: String,
+    signature: Signature,
+    mean: PowerMean,
+}
+
+impl Debug for PowerMeanFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PowerMeanFunction")
+            .field("name", &self.name)
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl PowerMeanFunction {
+    /// Creates the `geometric_mean` function, also named `geomean`.
+    pub fn new_geometric() -> Self {
+        Self {
+            name: "geometric_mean".to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
+            mean: PowerMean::Geometric,
+        }
+    }
+
+    /// Creates the `harmonic_mean` function.
+    pub fn new_harmonic() -> Self {
+        Self {
+            name: "harmonic_mean".to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
+            mean: PowerMean::Harmonic,
+        }
+    }
+
+    fn mean_type(&self) -> MeanType {
+        MeanType {
+            name: self.name.clone(),
+            mean: self.mean,
+        }
+    }
+}
+
+impl AggregateUDFImpl for PowerMeanFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let [value_type] = arg_types else {
+            return plan_err!("{} expects exactly 1 argument, got {}", self.name, arg_types.len());
+        };
+        if !value_type.is_numeric() && !value_type.is_null() {
+            return plan_err!("{} does not support values of type {value_type}", self.name);
+        }
+        Ok(vec![DataType::Float64])
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Float64)
+    }
+
+    fn state_fields(&self, _args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(CountedSum::state_fields())
+    }
+
+    fn accumulator(&self, _acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        Ok(Box::new(PowerMeanAccumulator {
+            sum: CountedSum::default(),
+            mean_type: self.mean_type(),
+        }))
+    }
+
+    fn groups_accumulator_supported(&self, _args: AccumulatorArgs) -> bool {
+        true
+    }
+
+    fn create_groups_accumulator(&self, _args: AccumulatorArgs) -> Result<Box<dyn GroupsAccumulator>> {
+        Ok(Box::new(PowerMeanGroupsAccumulator {
+            sums: vec![],
+            mean_type: self.mean_type(),
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PowerMean {
+    Geometric,
+    Harmonic,
+}
+
+/// How the means of the values are computed, with the name of the function for the errors.
+#[derive(Debug)]
+struct MeanType {
+    name: String,
+    mean: PowerMean,
+}
+
+impl MeanType {
+    /// Adds the logarithm or inverse of `value` to the sum, or removes it when `retract`.
+    fn update(&self, sum: &mut CountedSum, value: f64, retract: bool) -> Result<()> {
+        if value < 0.0 {
+            return exec_err!("{} is only defined for non-negative values, got {value}", self.name);
+        }
+        // -0.0 is not negative, and its logarithm and inverse are the ones of 0.0
+        let term = match self.mean {
+            PowerMean::Geometric => value.abs().ln(),
+            PowerMean::Harmonic => value.abs().recip(),
+        };
+        sum.update(term, if retract { -1 } else { 1 });
+        Ok(())
+    }
+
+    /// Calls `update` with each selected non-null value of `values` and its index.
+    fn try_for_each_value(
+        &self,
+        values: &ArrayRef,
+        opt_filter: Option<&BooleanArray>,
+        mut update: impl FnMut(usize, f64) -> Result<()>,
+    ) -> Result<()> {
+        let values = cast(values, &DataType::Float64)?;
+        values
+            .as_primitive::<Float64Type>()
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| is_selected(opt_filter, *index))
+            .try_for_each(|(index, value)| value.map_or(Ok(()), |value| update(index, value)))
+    }
+
+    fn evaluate(&self, sum: &CountedSum) -> Option<f64> {
+        // the infinite sums of the logarithms or inverses of zeros make both means 0
+        let value = sum.value()?;
+        let count = sum.count() as f64;
+        match self.mean {
+            PowerMean::Geometric => Some((value / count).exp()),
+            PowerMean::Harmonic => Some(count / value),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PowerMeanAccumulator {
+    sum: CountedSum,
+    mean_type: MeanType,
+}
+
+impl Accumulator for PowerMeanAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        let Self { sum, mean_type } = self;
+        mean_type.try_for_each_value(&values[0], None, |_, value| mean_type.update(sum, value, false))
+    }
+
+    fn retract_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        let Self { sum, mean_type } = self;
+        mean_type.try_for_each_value(&values[0], None, |_, value| mean_type.update(sum, value, true))
+    }
+
+    fn supports_retract_batch(&self) -> bool {
+        true
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        CountedSum::for_each_state(states, |_, other| self.sum.merge(&other));
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        CountedSum::state(&[self.sum])
+            .iter()
+            .map(|array| ScalarValue::try_from_array(array, 0))
+            .collect()
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        Ok(ScalarValue::Float64(self.mean_type.evaluate(&self.sum)))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+    }
+}
+
+#[derive(Debug)]
+struct PowerMeanGroupsAccumulator {
+    sums: Vec<CountedSum>,
+    mean_type: MeanType,
+}
+
+impl GroupsAccumulator for PowerMeanGroupsAccumulator {
+    fn update_batch(
+        &mut self,
+        values: &[ArrayRef],
+        group_indices: &[usize],
+        opt_filter: Option<&BooleanArray>,
+        total_num_groups: usize,
+    ) -> Result<()> {
+        self.sums.resize(total_num_groups, CountedSum::default());
+        let Self { sums, mean_type } = self;
+        mean_type.try_for_each_value(&values[0], opt_filter, |index, value| {
+            mean_type.update(&mut sums[group_indices[index]], value, false)
+        })
+    }
+
+    fn merge_batch(
+        &mut self,
+        values: &[ArrayRef],
+        group_indices: &[usize],
+        opt_filter: Option<&BooleanArray>,
+        total_num_groups: usize,
+    ) -> Result<()> {
+        self.sums.resize(total_num_groups, CountedSum::default());
+        CountedSum::for_each_state(values, |index, other| {
+            if is_selected(opt_filter, index) {
+                self.sums[group_indices[index]].merge(&other);
+            }
+        });
+        Ok(())
+    }
+
+    fn evaluate(&mut self, emit_to: EmitTo) -> Result<ArrayRef> {
+        let means = emit_to
+            .take_needed(&mut self.sums)
+            .iter()
+            .map(|sum| self.mean_type.evaluate(sum))
+            .collect::<Float64Array>();
+        Ok(Arc::new(means))
+    }
+
+    fn state(&mut self, emit_to: EmitTo) -> Result<Vec<ArrayRef>> {
+        Ok(CountedSum::state(&emit_to.take_needed(&mut self.sums)))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.sums.capacity() * std::mem::size_of::<CountedSum>()
+    }
+}
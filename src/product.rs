@@ -0,0 +1,477 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::any::Any;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use arrow::array::{
+    Array, ArrayRef, ArrowNativeTypeOp, ArrowPrimitiveType, AsArray, BooleanArray, Int32Array, PrimitiveArray,
+};
+use arrow::compute::{cast, cast_with_options, CastOptions};
+use arrow::datatypes::{
+    DataType, Decimal256Type, Field, Float64Type, Int32Type, Int64Type, UInt64Type, DECIMAL128_MAX_PRECISION,
+    DECIMAL256_MAX_PRECISION,
+};
+use datafusion::arrow;
+use datafusion::common::{exec_err, plan_err};
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, EmitTo, GroupsAccumulator, Signature, Volatility};
+use datafusion::scalar::ScalarValue;
+
+use crate::common::groups::is_selected;
+
+make_udaf_expr!(product, x, "Multiplies the values, failing on overflow.", product_udaf);
+create_func!(ProductFunction, product_udaf, ProductFunction::new_product());
+
+make_udaf_expr!(
+    try_product,
+    x,
+    "Multiplies the values, returning null on overflow.",
+    try_product_udaf
+);
+create_func!(ProductFunction, try_product_udaf, ProductFunction::new_try_product());
+
+/// The `ProductFunction` multiplies the values, as DuckDB's `product`, which unlike `exp(sum(ln(x)))` supports
+/// negative values and is exact for integers and decimals.
+///
+/// - Signed integers are multiplied as an `Int64`, unsigned integers as a `UInt64` and floats as a `Float64`.
+///   Decimals return a decimal of the largest precision, `Decimal128(38, s)` or `Decimal256(76, s)`. Their product
+///   is kept exact, at the sum of the scales of the values, and rounded half away from zero to the scale `s` once
+///   evaluated, so it does not depend on the order of the rows. Only the digits which do not fit in a `Decimal256`
+///   are rounded off while multiplying, keeping at least the scale `s`, and at most 76 digits more.
+/// - `product(x)` fails when the product of integers or decimals overflows, and `try_product(x)` returns null
+///   instead. The product is 0 when a value is 0, even if the product of the others overflows. Floats follow IEEE 754
+///   and return infinities instead.
+/// - Null values are ignored, and the result is null when there are no values.
+pub struct ProductFunction {
+    name: String,
+    signature: Signature,
+    try_product: bool,
+}
+
+impl Debug for ProductFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProductFunction")
+            .field("name", &self.name)
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl ProductFunction {
+    /// Creates the `product` function, which fails on overflow.
+    pub fn new_product() -> Self {
+        Self {
+            name: "product".to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
+            try_product: false,
+        }
+    }
+
+    /// Creates the `try_product` function, which returns null on overflow.
+    pub fn new_try_product() -> Self {
+        Self {
+            name: "try_product".to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
+            try_product: true,
+        }
+    }
+
+    /// Returns how the products of values of `data_type`, coerced by `coerce_types`, are computed.
+    fn product_type<T: ArrowPrimitiveType>(&self, scale: i32, data_type: &DataType) -> Result<ProductType<T>> {
+        Ok(ProductType {
+            name: self.name.clone(),
+            scale,
+            state_type: state_type(data_type),
+            return_type: self.return_type(std::slice::from_ref(data_type))?,
+            try_product: self.try_product,
+            phantom: PhantomData,
+        })
+    }
+}
+
+/// Returns the type of the products of values of `data_type` in the state, where decimals are multiplied with the
+/// largest precision. The scale of each product is kept next to it in the state.
+fn state_type(data_type: &DataType) -> DataType {
+    match data_type {
+        DataType::Decimal128(_, scale) | DataType::Decimal256(_, scale) => {
+            DataType::Decimal256(DECIMAL256_MAX_PRECISION, *scale)
+        }
+        data_type => data_type.clone(),
+    }
+}
+
+/// Calls `$FN` with the [`ProductType`] of values of `$DATA_TYPE`.
+macro_rules! with_product_type {
+    ($SELF:expr, $DATA_TYPE:expr, $FN:ident) => {
+        match $DATA_TYPE {
+            DataType::Int64 => $FN($SELF.product_type::<Int64Type>(0, $DATA_TYPE)?),
+            DataType::UInt64 => $FN($SELF.product_type::<UInt64Type>(0, $DATA_TYPE)?),
+            DataType::Float64 => $FN($SELF.product_type::<Float64Type>(0, $DATA_TYPE)?),
+            DataType::Decimal128(_, scale) | DataType::Decimal256(_, scale) => {
+                $FN($SELF.product_type::<Decimal256Type>(*scale as i32, $DATA_TYPE)?)
+            }
+            data_type => plan_err!("{} does not support values of type {data_type}", $SELF.name),
+        }
+    };
+}
+
+impl AggregateUDFImpl for ProductFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let [value_type] = arg_types else {
+            return plan_err!("{} expects exactly 1 argument, got {}", self.name, arg_types.len());
+        };
+        let value_type = match value_type {
+            DataType::Null => DataType::Int64,
+            value_type if value_type.is_signed_integer() => DataType::Int64,
+            value_type if value_type.is_unsigned_integer() => DataType::UInt64,
+            value_type if value_type.is_floating() => DataType::Float64,
+            // the values of decimals with a negative scale are multiplied as integers
+            DataType::Decimal128(_, scale) if *scale < 0 => DataType::Decimal128(DECIMAL128_MAX_PRECISION, 0),
+            DataType::Decimal256(_, scale) if *scale < 0 => DataType::Decimal256(DECIMAL256_MAX_PRECISION, 0),
+            DataType::Decimal128(_, _) | DataType::Decimal256(_, _) => value_type.clone(),
+            _ => return plan_err!("{} does not support values of type {value_type}", self.name),
+        };
+        Ok(vec![value_type])
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+        match &arg_types[0] {
+            DataType::Decimal128(_, scale) => Ok(DataType::Decimal128(DECIMAL128_MAX_PRECISION, *scale)),
+            DataType::Decimal256(_, scale) => Ok(DataType::Decimal256(DECIMAL256_MAX_PRECISION, *scale)),
+            data_type => Ok(data_type.clone()),
+        }
+    }
+
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(vec![
+            Field::new("product", state_type(&args.input_types[0]), true),
+            Field::new("overflow", DataType::Boolean, true),
+            Field::new("scale", DataType::Int32, true),
+        ])
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        fn accumulator<T: ArrowPrimitiveType + Send + Sync + Debug>(
+            product_type: ProductType<T>,
+        ) -> Result<Box<dyn Accumulator>> {
+            Ok(Box::new(ProductAccumulator {
+                product: Product::Empty,
+                product_type,
+            }))
+        }
+        with_product_type!(self, &acc_args.exprs[0].data_type(acc_args.schema)?, accumulator)
+    }
+
+    fn groups_accumulator_supported(&self, _args: AccumulatorArgs) -> bool {
+        true
+    }
+
+    fn create_groups_accumulator(&self, args: AccumulatorArgs) -> Result<Box<dyn GroupsAccumulator>> {
+        fn accumulator<T: ArrowPrimitiveType + Send + Sync + Debug>(
+            product_type: ProductType<T>,
+        ) -> Result<Box<dyn GroupsAccumulator>> {
+            Ok(Box::new(ProductGroupsAccumulator {
+                products: vec![],
+                product_type,
+            }))
+        }
+        with_product_type!(self, &args.exprs[0].data_type(args.schema)?, accumulator)
+    }
+}
+
+/// Number of digits kept beyond the scale of the values in the products of decimals, which is as many as a
+/// `Decimal256` holds.
+const MAX_EXTRA_SCALE: i32 = DECIMAL256_MAX_PRECISION as i32;
+
+/// The product of the values of a group.
+#[derive(Debug, Clone, Copy)]
+enum Product<N> {
+    Empty,
+    /// The unscaled product and its scale, which is 0 for integers and floats
+    Value(N, i32),
+    Overflow,
+}
+
+impl<N: ArrowNativeTypeOp> Product<N> {
+    /// Multiplies two products, keeping at least the scale `scale` of the values, see [`checked_multiply`].
+    fn multiply(self, other: Self, scale: i32) -> Self {
+        match (self, other) {
+            (Product::Empty, product) | (product, Product::Empty) => product,
+            (Product::Overflow, Product::Value(value, scale)) | (Product::Value(value, scale), Product::Overflow)
+                if value.is_zero() =>
+            {
+                Product::Value(value, scale)
+            }
+            (Product::Overflow, _) | (_, Product::Overflow) => Product::Overflow,
+            (Product::Value(left, left_scale), Product::Value(right, right_scale)) => {
+                checked_multiply((left, left_scale), (right, right_scale), scale)
+                    .map_or(Product::Overflow, |(value, scale)| Product::Value(value, scale))
+            }
+        }
+    }
+}
+
+/// Multiplies two unscaled values exactly, at the sum of their scales.
+///
+/// When the product does not fit, the least significant digits of the operand with the largest scale are rounded
+/// off one at a time, as long as the product keeps at least the scale `scale`. The product is also rounded to at
+/// most `scale + MAX_EXTRA_SCALE`, so that the scale of the products of many small values stays bounded.
+fn checked_multiply<N: ArrowNativeTypeOp>(
+    (mut left, mut left_scale): (N, i32),
+    (mut right, mut right_scale): (N, i32),
+    scale: i32,
+) -> Option<(N, i32)> {
+    let product = loop {
+        if let Ok(product) = left.mul_checked(right) {
+            break product;
+        }
+        if left_scale + right_scale <= scale {
+            return None;
+        }
+        if left_scale >= right_scale {
+            left = round_digits(left, 1)?;
+            left_scale -= 1;
+        } else {
+            right = round_digits(right, 1)?;
+            right_scale -= 1;
+        }
+    };
+
+    let excess = left_scale + right_scale - scale - MAX_EXTRA_SCALE;
+    if excess > 0 {
+        return Some((round_digits(product, excess as u32)?, scale + MAX_EXTRA_SCALE));
+    }
+    Some((product, left_scale + right_scale))
+}
+
+/// Rounds off the `digits` least significant digits of an unscaled value, half away from zero.
+fn round_digits<N: ArrowNativeTypeOp>(value: N, digits: u32) -> Option<N> {
+    if digits == 0 {
+        return Some(value);
+    }
+    let ten = N::ONE.add_wrapping(N::ONE).mul_wrapping(N::usize_as(5));
+    let Ok(unit) = ten.pow_checked(digits) else {
+        // the value is smaller than half of the unit
+        return Some(N::ZERO);
+    };
+    let half = unit.div_wrapping(N::ONE.add_wrapping(N::ONE));
+    let rounded = match value.is_lt(N::ZERO) {
+        true => value.sub_checked(half),
+        false => value.add_checked(half),
+    };
+    rounded.ok()?.div_checked(unit).ok()
+}
+
+/// How the products of values of the type `T` are computed, kept in the state and returned.
+#[derive(Debug)]
+struct ProductType<T: ArrowPrimitiveType> {
+    name: String,
+    /// The scale of the values, and of the returned products
+    scale: i32,
+    /// The type of the products in the state, which is wider than the return type for decimals.
+    state_type: DataType,
+    return_type: DataType,
+    try_product: bool,
+    phantom: PhantomData<T>,
+}
+
+impl<T: ArrowPrimitiveType> ProductType<T> {
+    fn update(&self, product: &mut Product<T::Native>, value: T::Native) {
+        *product = product.multiply(Product::Value(value, self.scale), self.scale);
+    }
+
+    fn multiply(&self, product: &mut Product<T::Native>, other: Product<T::Native>) {
+        *product = product.multiply(other, self.scale);
+    }
+
+    /// Returns the unscaled products, whether they overflowed and their scales, as in the state.
+    fn state(&self, products: &[Product<T::Native>]) -> Vec<ArrayRef> {
+        let values = products
+            .iter()
+            .map(|product| match product {
+                Product::Value(value, _) => Some(*value),
+                Product::Empty | Product::Overflow => None,
+            })
+            .collect::<PrimitiveArray<T>>()
+            .with_data_type(self.state_type.clone());
+        let overflows = products
+            .iter()
+            .map(|product| Some(matches!(product, Product::Overflow)))
+            .collect::<BooleanArray>();
+        let scales = products
+            .iter()
+            .map(|product| match product {
+                Product::Value(_, scale) => Some(*scale),
+                Product::Empty | Product::Overflow => None,
+            })
+            .collect::<Int32Array>();
+        vec![Arc::new(values), Arc::new(overflows), Arc::new(scales)]
+    }
+
+    /// Calls `f` with the index and the product of each row of a state.
+    fn for_each_state(&self, states: &[ArrayRef], mut f: impl FnMut(usize, Product<T::Native>)) -> Result<()> {
+        let values = cast(&states[0], &self.state_type)?;
+        let values = values.as_primitive::<T>();
+        let overflows = states[1].as_boolean();
+        let scales = states[2].as_primitive::<Int32Type>();
+        for index in 0..values.len() {
+            let product = if overflows.is_valid(index) && overflows.value(index) {
+                Product::Overflow
+            } else if values.is_valid(index) {
+                Product::Value(values.value(index), scales.value(index))
+            } else {
+                Product::Empty
+            };
+            f(index, product);
+        }
+        Ok(())
+    }
+
+    /// Returns the products rounded to the scale of the values, failing on overflow or returning null for
+    /// `try_product`.
+    fn evaluate(&self, products: &[Product<T::Native>]) -> Result<ArrayRef> {
+        let rounded = products
+            .iter()
+            .map(|product| match product {
+                Product::Value(value, scale) => round_digits(*value, (scale - self.scale) as u32)
+                    .map_or(Product::Overflow, |value| Product::Value(value, self.scale)),
+                product => *product,
+            })
+            .collect::<Vec<_>>();
+        let values = self.state(&rounded).swap_remove(0);
+        // decimals which do not fit in the return type are null
+        let returned = cast_with_options(&values, &self.return_type, &CastOptions::default())?;
+        let overflows = rounded.iter().enumerate().any(|(index, product)| match product {
+            Product::Overflow => true,
+            Product::Value(_, _) => returned.is_null(index),
+            Product::Empty => false,
+        });
+        if overflows && !self.try_product {
+            return exec_err!(
+                "Overflow in {} of type {}, try_product returns null instead",
+                self.name,
+                self.return_type
+            );
+        }
+        Ok(returned)
+    }
+}
+
+#[derive(Debug)]
+struct ProductAccumulator<T: ArrowPrimitiveType> {
+    product: Product<T::Native>,
+    product_type: ProductType<T>,
+}
+
+impl<T: ArrowPrimitiveType + Send + Sync + Debug> Accumulator for ProductAccumulator<T> {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        let values = cast(&values[0], &self.product_type.state_type)?;
+        for value in values.as_primitive::<T>().iter().flatten() {
+            self.product_type.update(&mut self.product, value);
+        }
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        let Self { product, product_type } = self;
+        product_type.for_each_state(states, |_, other| product_type.multiply(product, other))
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        self.product_type
+            .state(&[self.product])
+            .iter()
+            .map(|array| ScalarValue::try_from_array(array, 0))
+            .collect()
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        ScalarValue::try_from_array(&self.product_type.evaluate(&[self.product])?, 0)
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+    }
+}
+
+#[derive(Debug)]
+struct ProductGroupsAccumulator<T: ArrowPrimitiveType> {
+    products: Vec<Product<T::Native>>,
+    product_type: ProductType<T>,
+}
+
+impl<T: ArrowPrimitiveType + Send + Sync + Debug> GroupsAccumulator for ProductGroupsAccumulator<T> {
+    fn update_batch(
+        &mut self,
+        values: &[ArrayRef],
+        group_indices: &[usize],
+        opt_filter: Option<&BooleanArray>,
+        total_num_groups: usize,
+    ) -> Result<()> {
+        self.products.resize(total_num_groups, Product::Empty);
+        let values = cast(&values[0], &self.product_type.state_type)?;
+        for (index, (value, group)) in values.as_primitive::<T>().iter().zip(group_indices).enumerate() {
+            if let Some(value) = value.filter(|_| is_selected(opt_filter, index)) {
+                self.product_type.update(&mut self.products[*group], value);
+            }
+        }
+        Ok(())
+    }
+
+    fn merge_batch(
+        &mut self,
+        values: &[ArrayRef],
+        group_indices: &[usize],
+        opt_filter: Option<&BooleanArray>,
+        total_num_groups: usize,
+    ) -> Result<()> {
+        self.products.resize(total_num_groups, Product::Empty);
+        let Self { products, product_type } = self;
+        product_type.for_each_state(values, |index, product| {
+            if is_selected(opt_filter, index) {
+                product_type.multiply(&mut products[group_indices[index]], product);
+            }
+        })
+    }
+
+    fn evaluate(&mut self, emit_to: EmitTo) -> Result<ArrayRef> {
+        self.product_type.evaluate(&emit_to.take_needed(&mut self.products))
+    }
+
+    fn state(&mut self, emit_to: EmitTo) -> Result<Vec<ArrayRef>> {
+        Ok(self.product_type.state(&emit_to.take_needed(&mut self.products)))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.products.capacity() * std::mem::size_of::<Product<T::Native>>()
+    }
+}
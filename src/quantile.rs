@@ -0,0 +1,345 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::any::Any;
+use std::fmt::Debug;
+
+use arrow::array::{Array, ArrayRef, ArrowPrimitiveType};
+use arrow::datatypes::{DataType, Field, Float64Type, TimeUnit};
+use datafusion::arrow;
+use datafusion::common::{exec_err, plan_err};
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, Signature, Volatility};
+use datafusion::physical_expr::expressions::Literal;
+use datafusion::scalar::ScalarValue;
+
+use crate::common::quantile::{
+    coerce_discrete_quantile_type, coerce_quantile_type, discrete_index, dispatch_exact_quantile_type,
+    dispatch_quantile_type, interpolate_f64, interpolation_bounds, lerp_i128, nanos_per_unit, CollectedValues,
+};
+
+make_udaf_expr!(
+    quantile_cont,
+    x q,
+    "Computes the interpolated quantile, or the list of quantiles when q is a list, of the values.",
+    quantile_cont_udaf
+);
+create_func!(QuantileFunction, quantile_cont_udaf, QuantileFunction::new_cont());
+
+make_udaf_expr!(
+    quantile_disc,
+    x q,
+    "Computes the exact quantile, or the list of quantiles when q is a list, taken among the values.",
+    quantile_disc_udaf
+);
+create_func!(QuantileFunction, quantile_disc_udaf, QuantileFunction::new_disc());
+
+/// The `QuantileFunction` computes exact quantiles of the values with DuckDB semantics, e.g. `quantile_cont(x, 0.5)`
+/// or `quantile_disc(x, [0.25, 0.5, 0.75])`.
+///
+/// - `q` must be a constant between 0 and 1, or a list of such constants. The result is then a list of the
+///   quantiles in the same order.
+/// - `quantile_cont` interpolates between the two values surrounding the quantile. Numbers return a `Float64`,
+///   decimals and temporal values their own type, except dates which return a timestamp.
+/// - `quantile_disc` returns the first value such that a fraction `q` of the values are lower or equal.
+pub struct QuantileFunction {
+    name: String,
+    signature: Signature,
+    discrete: bool,
+}
+
+impl Debug for QuantileFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QuantileFunction")
+            .field("name", &self.name)
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl QuantileFunction {
+    /// Creates the `quantile_cont` function, which interpolates between values.
+    pub fn new_cont() -> Self {
+        Self {
+            name: "quantile_cont".to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
+            discrete: false,
+        }
+    }
+
+    /// Creates the `quantile_disc` function, which returns one of the values.
+    pub fn new_disc() -> Self {
+        Self {
+            name: "quantile_disc".to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
+            discrete: true,
+        }
+    }
+
+    /// Type of the quantiles of values of `input_type`.
+    fn value_type(&self, input_type: &DataType) -> DataType {
+        match input_type {
+            DataType::Date32 | DataType::Date64 if !self.discrete => DataType::Timestamp(TimeUnit::Microsecond, None),
+            _ => input_type.clone(),
+        }
+    }
+}
+
+impl AggregateUDFImpl for QuantileFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let [arg_type, quantile_type] = arg_types else {
+            return plan_err!("{} expects exactly 2 arguments, got {}", self.name, arg_types.len());
+        };
+
+        let value_type = match self.discrete {
+            true => coerce_discrete_quantile_type(arg_type),
+            false => coerce_quantile_type(arg_type),
+        };
+        let Some(value_type) = value_type else {
+            return plan_err!("{} does not support values of type {arg_type}", self.name);
+        };
+
+        let quantile_type = match quantile_type {
+            DataType::List(field) | DataType::LargeList(field) | DataType::FixedSizeList(field, _)
+                if field.data_type().is_numeric() =>
+            {
+                DataType::new_list(DataType::Float64, true)
+            }
+            data_type if data_type.is_numeric() => DataType::Float64,
+            _ => {
+                return plan_err!(
+                    "{} expects a numeric quantile or a list of numeric quantiles, got {quantile_type}",
+                    self.name
+                )
+            }
+        };
+        Ok(vec![value_type, quantile_type])
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+        let value_type = self.value_type(&arg_types[0]);
+        match &arg_types[1] {
+            DataType::List(_) => Ok(DataType::new_list(value_type, true)),
+            _ => Ok(value_type),
+        }
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let input_type = acc_args.exprs[0].data_type(acc_args.schema)?;
+        let quantiles = self.quantiles(&acc_args)?;
+        let value_type = self.value_type(&input_type);
+
+        if self.discrete {
+            return dispatch_quantile_type!(
+                &input_type,
+                discrete_accumulator(&input_type, quantiles),
+                exec_err!("Unsupported data type {input_type} for {}", self.name)
+            );
+        }
+        if input_type == DataType::Float64 {
+            return Ok(Box::new(QuantileAccumulator::<Float64Type> {
+                values: CollectedValues::new(&input_type),
+                quantiles,
+                value_type,
+                quantile: Box::new(|sorted, q| Ok(ScalarValue::Float64(interpolate_f64(sorted, q)))),
+            }));
+        }
+        dispatch_exact_quantile_type!(
+            &input_type,
+            continuous_accumulator(&input_type, &value_type, quantiles),
+            exec_err!("Unsupported data type {input_type} for {}", self.name)
+        )
+    }
+
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(vec![Field::new_list(
+            "values",
+            Field::new("item", args.input_types[0].clone(), true),
+            true,
+        )])
+    }
+}
+
+impl QuantileFunction {
+    /// Returns the quantiles to compute, which must be given as literals.
+    fn quantiles(&self, acc_args: &AccumulatorArgs) -> Result<Quantiles> {
+        let Some(literal) = acc_args.exprs[1].as_any().downcast_ref::<Literal>() else {
+            return plan_err!("The quantiles of {} must be constants", self.name);
+        };
+
+        let quantiles = match literal.value() {
+            ScalarValue::Float64(Some(q)) => Quantiles::Scalar(*q),
+            ScalarValue::List(list) if list.len() == 1 && !list.is_null(0) => {
+                let list = ScalarValue::convert_array_to_scalar_vec(list.as_ref())?;
+                let quantiles = list[0]
+                    .iter()
+                    .map(|q| match q {
+                        ScalarValue::Float64(Some(q)) => Ok(*q),
+                        _ => plan_err!("The quantiles of {} must not be null", self.name),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Quantiles::List(quantiles)
+            }
+            _ => return plan_err!("The quantiles of {} must not be null", self.name),
+        };
+
+        if let Some(q) = quantiles.iter().find(|q| !(0.0..=1.0).contains(*q)) {
+            return plan_err!("The quantiles of {} must be between 0 and 1, got {q}", self.name);
+        }
+        Ok(quantiles)
+    }
+}
+
+/// Quantiles to compute, either a single one returned as a scalar or several ones returned as a list.
+#[derive(Debug, Clone)]
+enum Quantiles {
+    Scalar(f64),
+    List(Vec<f64>),
+}
+
+impl Quantiles {
+    fn iter(&self) -> impl Iterator<Item = &f64> {
+        match self {
+            Quantiles::Scalar(q) => std::slice::from_ref(q).iter(),
+            Quantiles::List(quantiles) => quantiles.iter(),
+        }
+    }
+}
+
+fn discrete_accumulator<T>(input_type: &DataType, quantiles: Quantiles) -> Result<Box<dyn Accumulator>>
+where
+    T: ArrowPrimitiveType + Send + Sync,
+{
+    let data_type = input_type.clone();
+    Ok(Box::new(QuantileAccumulator::<T> {
+        values: CollectedValues::new(input_type),
+        quantiles,
+        value_type: input_type.clone(),
+        quantile: Box::new(move |sorted, q| {
+            let value = discrete_index(sorted.len(), q).map(|index| sorted[index]);
+            ScalarValue::new_primitive::<T>(value, &data_type)
+        }),
+    }))
+}
+
+/// Creates the accumulator interpolating decimal or temporal values exactly, on their unscaled values or on their
+/// own unit.
+fn continuous_accumulator<T>(
+    input_type: &DataType,
+    value_type: &DataType,
+    quantiles: Quantiles,
+) -> Result<Box<dyn Accumulator>>
+where
+    T: ArrowPrimitiveType + Send + Sync,
+    T::Native: Into<i128> + TryFrom<i128>,
+{
+    // Dates are interpolated as timestamps
+    let factor = match (nanos_per_unit(input_type), nanos_per_unit(value_type)) {
+        (Some(input_unit), Some(value_unit)) => input_unit / value_unit,
+        _ => 1,
+    };
+    let (input_type, data_type) = (input_type.clone(), value_type.clone());
+
+    Ok(Box::new(QuantileAccumulator::<T> {
+        values: CollectedValues::new(&input_type),
+        quantiles,
+        value_type: value_type.clone(),
+        quantile: Box::new(move |sorted, q| {
+            let value = interpolation_bounds(sorted.len(), q)
+                .map(|(lo, hi, fraction)| lerp_i128(sorted[lo].into() * factor, sorted[hi].into() * factor, fraction));
+            match &data_type {
+                DataType::Decimal128(precision, scale) => Ok(ScalarValue::Decimal128(value, *precision, *scale)),
+                DataType::Timestamp(TimeUnit::Microsecond, None) if data_type != input_type => {
+                    Ok(ScalarValue::TimestampMicrosecond(value.map(|value| value as i64), None))
+                }
+                _ => ScalarValue::new_primitive::<T>(value.and_then(|value| value.try_into().ok()), &data_type),
+            }
+        }),
+    }))
+}
+
+/// Computes the quantile `q` of sorted values.
+type QuantileFn<T> = Box<dyn Fn(&[<T as ArrowPrimitiveType>::Native], f64) -> Result<ScalarValue> + Send + Sync>;
+
+/// Accumulator collecting the values to compute their exact quantiles.
+struct QuantileAccumulator<T: ArrowPrimitiveType> {
+    values: CollectedValues<T>,
+    quantiles: Quantiles,
+    value_type: DataType,
+    quantile: QuantileFn<T>,
+}
+
+impl<T: ArrowPrimitiveType> Debug for QuantileAccumulator<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QuantileAccumulator")
+            .field("values", &self.values)
+            .field("quantiles", &self.quantiles)
+            .field("value_type", &self.value_type)
+            .finish()
+    }
+}
+
+impl<T: ArrowPrimitiveType + Send + Sync> Accumulator for QuantileAccumulator<T> {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        self.values.update(&values[0])
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        self.values.merge(&states[0])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        let sorted = self.values.sorted();
+        match &self.quantiles {
+            Quantiles::Scalar(q) => (self.quantile)(sorted, *q),
+            Quantiles::List(_) if sorted.is_empty() => {
+                ScalarValue::try_from(&DataType::new_list(self.value_type.clone(), true))
+            }
+            Quantiles::List(quantiles) => {
+                let values = quantiles
+                    .iter()
+                    .map(|q| (self.quantile)(sorted, *q))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(ScalarValue::List(ScalarValue::new_list_nullable(
+                    &values,
+                    &self.value_type,
+                )))
+            }
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.values.size() + self.quantiles.iter().count() * std::mem::size_of::<f64>()
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        Ok(vec![self.values.state()])
+    }
+}
@@ -0,0 +1,438 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::any::Any;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+use arrow::array::{Array, ArrayRef, Float64Array};
+use arrow::datatypes::{DataType, Field};
+use datafusion::arrow;
+use datafusion::common::cast::{as_binary_array, as_float64_array};
+use datafusion::common::{exec_err, plan_err};
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, ColumnarValue, ScalarUDFImpl, Signature, Volatility};
+use datafusion::physical_expr::expressions::Literal;
+use datafusion::scalar::ScalarValue;
+
+use crate::common::kll::{KllSketch, DEFAULT_K, MIN_K};
+use crate::common::sketch::{coerce_sketch_type, sketch_parameter, QuantileSketch, Sketch};
+use crate::common::tdigest::{TDigestSketch, DEFAULT_COMPRESSION, MAX_COMPRESSION};
+
+make_udaf_expr!(
+    approx_quantile_kll,
+    x q,
+    "Estimates the quantile q of the values with a KLL sketch.",
+    approx_quantile_kll_udaf
+);
+create_func!(
+    KllFunction,
+    approx_quantile_kll_udaf,
+    KllFunction::new_approx_quantile()
+);
+
+make_udaf_expr!(
+    kll_sketch,
+    x,
+    "Builds a serialized KLL sketch of the values.",
+    kll_sketch_udaf
+);
+create_func!(KllFunction, kll_sketch_udaf, KllFunction::new_sketch());
+
+make_udaf_expr!(
+    kll_merge,
+    sketch,
+    "Merges serialized KLL sketches into one.",
+    kll_merge_udaf
+);
+create_func!(KllFunction, kll_merge_udaf, KllFunction::new_merge());
+
+make_udf_expr!(
+    kll_estimate,
+    sketch q,
+    "Estimates the quantile q of the values summarized by a serialized KLL sketch.",
+    kll_estimate_udf
+);
+create_udf_func!(KllEstimateFunction, kll_estimate_udf);
+
+make_udaf_expr!(
+    approx_quantile_tdigest,
+    x q,
+    "Estimates the quantile q of the values with a t-digest.",
+    approx_quantile_tdigest_udaf
+);
+create_func!(
+    TDigestFunction,
+    approx_quantile_tdigest_udaf,
+    TDigestFunction::new_approx_quantile()
+);
+
+make_udaf_expr!(
+    tdigest_sketch,
+    x,
+    "Builds a serialized t-digest of the values.",
+    tdigest_sketch_udaf
+);
+create_func!(TDigestFunction, tdigest_sketch_udaf, TDigestFunction::new_sketch());
+
+make_udaf_expr!(
+    tdigest_merge,
+    sketch,
+    "Merges serialized t-digests into one.",
+    tdigest_merge_udaf
+);
+create_func!(TDigestFunction, tdigest_merge_udaf, TDigestFunction::new_merge());
+
+make_udf_expr!(
+    tdigest_estimate,
+    sketch q,
+    "Estimates the quantile q of the values summarized by a serialized t-digest.",
+    tdigest_estimate_udf
+);
+create_udf_func!(TDigestEstimateFunction, tdigest_estimate_udf);
+
+/// The quantile sketch functions of the KLL family: `approx_quantile_kll(x, q [, k])`, `kll_sketch(x [, k])`,
+/// `kll_merge(sketch)` and `kll_estimate(sketch, q)`. See [`KllSketch`].
+pub type KllFunction = QuantileSketchFunction<KllSketch>;
+pub type KllEstimateFunction = QuantileSketchEstimateFunction<KllSketch>;
+
+/// The quantile sketch functions of the t-digest family: `approx_quantile_tdigest(x, q [, compression])`,
+/// `tdigest_sketch(x [, compression])`, `tdigest_merge(sketch)` and `tdigest_estimate(sketch, q)`.
+/// See [`TDigestSketch`].
+pub type TDigestFunction = QuantileSketchFunction<TDigestSketch>;
+pub type TDigestEstimateFunction = QuantileSketchEstimateFunction<TDigestSketch>;
+
+/// A [`QuantileSketch`] exposed as a family of SQL functions, whose accuracy is set by an optional integer argument.
+pub trait QuantileSketchFamily: QuantileSketch + 'static {
+    /// Prefix of the function names, e.g. `kll` for `kll_sketch`.
+    const NAME: &'static str;
+    /// Name of the accuracy argument in error messages.
+    const PARAMETER: &'static str;
+
+    /// Creates an empty sketch, with the default accuracy when `parameter` is `None`.
+    fn with_parameter(function: &str, parameter: Option<i64>) -> Result<Self>;
+}
+
+impl QuantileSketchFamily for KllSketch {
+    const NAME: &'static str = "kll";
+    const PARAMETER: &'static str = "k";
+
+    fn with_parameter(function: &str, parameter: Option<i64>) -> Result<Self> {
+        match parameter {
+            None => Ok(KllSketch::new(DEFAULT_K)),
+            Some(k) if (MIN_K as i64..=u16::MAX as i64).contains(&k) => Ok(KllSketch::new(k as u16)),
+            Some(k) => plan_err!("The k of {function} must be between {MIN_K} and {}, got {k}", u16::MAX),
+        }
+    }
+}
+
+impl QuantileSketchFamily for TDigestSketch {
+    const NAME: &'static str = "tdigest";
+    const PARAMETER: &'static str = "compression";
+
+    fn with_parameter(function: &str, parameter: Option<i64>) -> Result<Self> {
+        match parameter {
+            None => Ok(TDigestSketch::new(DEFAULT_COMPRESSION)),
+            Some(compression) if (1..=MAX_COMPRESSION as i64).contains(&compression) => {
+                Ok(TDigestSketch::new(compression as u32))
+            }
+            Some(compression) => {
+                plan_err!("The compression of {function} must be between 1 and {MAX_COMPRESSION}, got {compression}")
+            }
+        }
+    }
+}
+
+/// What a [`QuantileSketchFunction`] aggregates and returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SketchAggregate {
+    /// `approx_quantile_<family>(x, q [, parameter])` estimates a quantile of the values.
+    ApproxQuantile,
+    /// `<family>_sketch(x [, parameter])` returns the serialized sketch of the values.
+    Sketch,
+    /// `<family>_merge(sketch)` returns the serialized union of serialized sketches.
+    Merge,
+}
+
+/// The `QuantileSketchFunction` estimates quantiles of numbers in bounded memory with a mergeable sketch.
+///
+/// - `q` must be a constant between 0 and 1, and the optional accuracy argument a constant integer.
+/// - The serialized sketches returned by `*_sketch` and `*_merge` can be stored, merged again by `*_merge` and
+///   queried by `*_estimate`.
+/// - `approx_quantile_*` and `*_estimate` return null when there are no values, `*_sketch` and `*_merge` an empty
+///   sketch.
+#[derive(Debug)]
+pub struct QuantileSketchFunction<S> {
+    name: String,
+    signature: Signature,
+    aggregate: SketchAggregate,
+    phantom: PhantomData<fn() -> S>,
+}
+
+impl<S: QuantileSketchFamily> QuantileSketchFunction<S> {
+    fn new(name: String, aggregate: SketchAggregate) -> Self {
+        Self {
+            name,
+            signature: Signature::user_defined(Volatility::Immutable),
+            aggregate,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Creates the `approx_quantile_<family>` function.
+    pub fn new_approx_quantile() -> Self {
+        Self::new(format!("approx_quantile_{}", S::NAME), SketchAggregate::ApproxQuantile)
+    }
+
+    /// Creates the `<family>_sketch` function.
+    pub fn new_sketch() -> Self {
+        Self::new(format!("{}_sketch", S::NAME), SketchAggregate::Sketch)
+    }
+
+    /// Creates the `<family>_merge` function.
+    pub fn new_merge() -> Self {
+        Self::new(format!("{}_merge", S::NAME), SketchAggregate::Merge)
+    }
+
+    /// Returns the index of the optional accuracy argument.
+    fn parameter_index(&self) -> Option<usize> {
+        match self.aggregate {
+            SketchAggregate::ApproxQuantile => Some(2),
+            SketchAggregate::Sketch => Some(1),
+            SketchAggregate::Merge => None,
+        }
+    }
+}
+
+impl<S: QuantileSketchFamily> AggregateUDFImpl for QuantileSketchFunction<S> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let usage = match self.aggregate {
+            SketchAggregate::ApproxQuantile => format!("{}(x, q [, {}])", self.name, S::PARAMETER),
+            SketchAggregate::Sketch => format!("{}(x [, {}])", self.name, S::PARAMETER),
+            SketchAggregate::Merge => format!("{}(sketch)", self.name),
+        };
+        let required = if self.aggregate == SketchAggregate::ApproxQuantile {
+            2
+        } else {
+            1
+        };
+        let allowed = self.parameter_index().map_or(1, |index| index + 1);
+        if !(required..=allowed).contains(&arg_types.len()) {
+            return plan_err!("Expected {usage}, got {} arguments", arg_types.len());
+        }
+
+        let mut coerced = Vec::with_capacity(arg_types.len());
+        for (index, arg_type) in arg_types.iter().enumerate() {
+            let data_type = match (self.aggregate, index) {
+                (SketchAggregate::Merge, _) => coerce_sketch_type(&self.name, arg_type)?,
+                (_, 0) if arg_type.is_numeric() || arg_type.is_null() => DataType::Float64,
+                (_, 0) => return plan_err!("{} does not support values of type {arg_type}", self.name),
+                (SketchAggregate::ApproxQuantile, 1) => coerce_quantile_type(&self.name, arg_type)?,
+                _ if arg_type.is_integer() || arg_type.is_null() => DataType::Int64,
+                _ => {
+                    return plan_err!(
+                        "The {} of {} must be an integer, got {arg_type}",
+                        S::PARAMETER,
+                        self.name
+                    )
+                }
+            };
+            coerced.push(data_type);
+        }
+        Ok(coerced)
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        match self.aggregate {
+            SketchAggregate::ApproxQuantile => Ok(DataType::Float64),
+            _ => Ok(DataType::Binary),
+        }
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let parameter = match self.parameter_index() {
+            Some(index) => sketch_parameter(&acc_args, index, &self.name, S::PARAMETER)?,
+            None => None,
+        };
+        let quantile = match self.aggregate {
+            SketchAggregate::ApproxQuantile => {
+                let q = acc_args.exprs[1]
+                    .as_any()
+                    .downcast_ref::<Literal>()
+                    .map(|literal| literal.value());
+                match q {
+                    Some(ScalarValue::Float64(Some(q))) if (0.0..=1.0).contains(q) => Some(*q),
+                    _ => return plan_err!("The quantile of {} must be a constant between 0 and 1", self.name),
+                }
+            }
+            _ => None,
+        };
+
+        Ok(Box::new(QuantileSketchAccumulator {
+            sketch: S::with_parameter(&self.name, parameter)?,
+            merge_input: self.aggregate == SketchAggregate::Merge,
+            quantile,
+        }))
+    }
+
+    fn state_fields(&self, _args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(vec![Field::new("sketch", DataType::Binary, true)])
+    }
+}
+
+fn coerce_quantile_type(name: &str, arg_type: &DataType) -> Result<DataType> {
+    match arg_type.is_numeric() || arg_type.is_null() {
+        true => Ok(DataType::Float64),
+        false => plan_err!("{name} expects a numeric quantile, got {arg_type}"),
+    }
+}
+
+/// Merges the non-null serialized sketches of a `Binary` array into `sketch`.
+fn merge_sketches<S: Sketch>(sketch: &mut S, sketches: &ArrayRef) -> Result<()> {
+    for bytes in as_binary_array(sketches)?.iter().flatten() {
+        sketch.merge(&S::deserialize(bytes)?);
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+struct QuantileSketchAccumulator<S> {
+    sketch: S,
+    /// Whether the input holds serialized sketches rather than values.
+    merge_input: bool,
+    /// The quantile to estimate, or `None` to return the serialized sketch.
+    quantile: Option<f64>,
+}
+
+impl<S: QuantileSketch> Accumulator for QuantileSketchAccumulator<S> {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        if self.merge_input {
+            return merge_sketches(&mut self.sketch, &values[0]);
+        }
+
+        let array = as_float64_array(&values[0])?;
+        if array.null_count() == 0 {
+            self.sketch.update(array.values());
+        } else {
+            let values: Vec<f64> = array.iter().flatten().collect();
+            self.sketch.update(&values);
+        }
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        merge_sketches(&mut self.sketch, &states[0])
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        Ok(vec![ScalarValue::Binary(Some(self.sketch.to_bytes()))])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        match self.quantile {
+            Some(q) => Ok(ScalarValue::Float64(self.sketch.quantile(q))),
+            None => Ok(ScalarValue::Binary(Some(self.sketch.to_bytes()))),
+        }
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) - std::mem::size_of_val(&self.sketch) + self.sketch.size()
+    }
+}
+
+/// The `QuantileSketchEstimateFunction` estimates the quantile `q` of the values summarized by a serialized sketch,
+/// e.g. `kll_estimate(kll_merge(sketch), 0.99)`.
+///
+/// - `q` may vary by row and must be between 0 and 1.
+/// - Returns null when the sketch or `q` is null, or when the sketch is empty.
+#[derive(Debug)]
+pub struct QuantileSketchEstimateFunction<S> {
+    name: String,
+    signature: Signature,
+    phantom: PhantomData<fn() -> S>,
+}
+
+impl<S: QuantileSketchFamily> Default for QuantileSketchEstimateFunction<S> {
+    fn default() -> Self {
+        Self {
+            name: format!("{}_estimate", S::NAME),
+            signature: Signature::user_defined(Volatility::Immutable),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<S: QuantileSketchFamily> ScalarUDFImpl for QuantileSketchEstimateFunction<S> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let [sketch_type, quantile_type] = arg_types else {
+            return plan_err!("{} expects exactly 2 arguments, got {}", self.name, arg_types.len());
+        };
+        Ok(vec![
+            coerce_sketch_type(&self.name, sketch_type)?,
+            coerce_quantile_type(&self.name, quantile_type)?,
+        ])
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Float64)
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        let arrays = ColumnarValue::values_to_arrays(args)?;
+        let sketches = as_binary_array(&arrays[0])?;
+        let quantiles = as_float64_array(&arrays[1])?;
+
+        let estimates = sketches
+            .iter()
+            .zip(quantiles.iter())
+            .map(|(bytes, q)| match (bytes, q) {
+                (Some(bytes), Some(q)) if (0.0..=1.0).contains(&q) => Ok(S::deserialize(bytes)?.quantile(q)),
+                (Some(_), Some(q)) => exec_err!("The quantile of {} must be between 0 and 1, got {q}", self.name),
+                _ => Ok(None),
+            })
+            .collect::<Result<Float64Array>>()?;
+
+        if args.iter().all(|arg| matches!(arg, ColumnarValue::Scalar(_))) {
+            return Ok(ColumnarValue::Scalar(ScalarValue::try_from_array(&estimates, 0)?));
+        }
+        Ok(ColumnarValue::Array(std::sync::Arc::new(estimates)))
+    }
+}
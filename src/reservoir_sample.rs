@@ -0,0 +1,205 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::any::Any;
+use std::fmt::Debug;
+
+use arrow::array::{Array, ArrayRef};
+use arrow::datatypes::{DataType, Field, UInt64Type};
+use datafusion::arrow;
+use datafusion::common::cast::{as_list_array, as_primitive_array};
+use datafusion::common::plan_err;
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, Signature, Volatility};
+use datafusion::scalar::ScalarValue;
+
+use crate::common::reservoir::{Reservoir, SplitMix64};
+use crate::common::sketch::sketch_parameter;
+
+make_udaf_expr_and_func!(
+    ReservoirSampleFunction,
+    reservoir_sample,
+    x n,
+    "Returns a list of `n` values sampled uniformly at random.",
+    reservoir_sample_udaf
+);
+
+/// The `ReservoirSampleFunction` samples values uniformly at random without replacement, as ClickHouse's
+/// `groupArraySample`, see [`Reservoir`].
+///
+/// - `reservoir_sample(x, n [, seed])` returns a list of `n` values, or of all the values when there are fewer. `n`
+///   must be a constant positive integer.
+/// - `seed` is a constant integer seeding the generator of the priorities of each accumulator, so that the sample is
+///   the same for the same values in the same order and partitioning. Without it, the generator is seeded at random,
+///   and the sample changes each time.
+/// - The samples of partitions are merged by keeping the values of lowest priorities of both.
+/// - Null values are ignored, and the result is null when there are no values.
+#[derive(Debug)]
+pub struct ReservoirSampleFunction {
+    signature: Signature,
+}
+
+impl Default for ReservoirSampleFunction {
+    fn default() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Volatile),
+        }
+    }
+}
+
+impl AggregateUDFImpl for ReservoirSampleFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "reservoir_sample"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let (value_type, parameter_types) = match arg_types {
+            [value_type, parameter_types @ ..] if (1..=2).contains(&parameter_types.len()) => {
+                (value_type, parameter_types)
+            }
+            _ => return plan_err!("{} expects 2 or 3 arguments, got {}", self.name(), arg_types.len()),
+        };
+        for (parameter, parameter_type) in ["n", "seed"].iter().zip(parameter_types) {
+            if !parameter_type.is_integer() && !parameter_type.is_null() {
+                return plan_err!(
+                    "The {parameter} of {} must be an integer, got {parameter_type}",
+                    self.name()
+                );
+            }
+        }
+        let mut coerced = vec![value_type.clone()];
+        coerced.extend(parameter_types.iter().map(|_| DataType::Int64));
+        Ok(coerced)
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::new_list(arg_types[0].clone(), true))
+    }
+
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(vec![
+            Field::new_list("sample", Field::new_list_field(args.input_types[0].clone(), true), true),
+            Field::new_list("priorities", Field::new_list_field(DataType::UInt64, true), true),
+        ])
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let n = match sketch_parameter(&acc_args, 1, self.name(), "n")? {
+            Some(n) if n > 0 => n,
+            Some(n) => return plan_err!("The n of {} must be positive, got {n}", self.name()),
+            None => return plan_err!("The n of {} must be a constant integer", self.name()),
+        };
+        let rng = match sketch_parameter(&acc_args, 2, self.name(), "seed")? {
+            Some(seed) => SplitMix64::new(seed as u64),
+            None => SplitMix64::from_entropy(),
+        };
+        Ok(Box::new(ReservoirSampleAccumulator {
+            reservoir: Reservoir::new(n as usize),
+            rng,
+            value_type: acc_args.exprs[0].data_type(acc_args.schema)?,
+        }))
+    }
+}
+
+#[derive(Debug)]
+struct ReservoirSampleAccumulator {
+    reservoir: Reservoir<ScalarValue>,
+    /// Draws the priority of each value
+    rng: SplitMix64,
+    value_type: DataType,
+}
+
+impl ReservoirSampleAccumulator {
+    /// Returns the sampled values and their priorities, by increasing priority.
+    fn sample(&self) -> (ScalarValue, ScalarValue) {
+        let (priorities, values): (Vec<_>, Vec<_>) = self
+            .reservoir
+            .items()
+            .into_iter()
+            .map(|(priority, value)| (priority, value.clone()))
+            .unzip();
+        let priorities = ScalarValue::new_list_nullable(
+            &priorities.into_iter().map(ScalarValue::from).collect::<Vec<_>>(),
+            &DataType::UInt64,
+        );
+        (
+            ScalarValue::List(ScalarValue::new_list_nullable(&values, &self.value_type)),
+            ScalarValue::List(priorities),
+        )
+    }
+}
+
+impl Accumulator for ReservoirSampleAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        let values = &values[0];
+        for index in 0..values.len() {
+            if values.is_valid(index) {
+                let priority = self.rng.next_u64();
+                self.reservoir
+                    .add_with(priority, || ScalarValue::try_from_array(values, index))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        let samples = as_list_array(&states[0])?;
+        let priorities = as_list_array(&states[1])?;
+        for (sample, priorities) in samples.iter().zip(priorities.iter()) {
+            let (Some(sample), Some(priorities)) = (sample, priorities) else {
+                continue;
+            };
+            let priorities = as_primitive_array::<UInt64Type>(&priorities)?;
+            for (index, priority) in priorities.values().iter().enumerate() {
+                self.reservoir
+                    .add_with(*priority, || ScalarValue::try_from_array(&sample, index))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        let (sample, priorities) = self.sample();
+        Ok(vec![sample, priorities])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        if self.reservoir.is_empty() {
+            return ScalarValue::try_from(&DataType::new_list(self.value_type.clone(), true));
+        }
+        Ok(self.sample().0)
+    }
+
+    fn size(&self) -> usize {
+        // the reservoir does not count what the values allocate
+        let values = self
+            .reservoir
+            .items()
+            .into_iter()
+            .map(|(_, value)| value.size() - std::mem::size_of_val(value))
+            .sum::<usize>();
+        std::mem::size_of_val(self) - std::mem::size_of_val(&self.reservoir) + self.reservoir.size() + values
+    }
+}
@@ -15,18 +15,43 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use arrow::array::{ArrayRef, AsArray};
-use arrow::datatypes::{Float64Type, UInt64Type};
 use datafusion::arrow::datatypes::{DataType, Field};
-use datafusion::common::ScalarValue;
+use datafusion::common::config::ConfigOptions;
+use datafusion::common::tree_node::Transformed;
+use datafusion::common::DFSchema;
+use datafusion::logical_expr::expr::WindowFunction;
+use datafusion::logical_expr::expr_rewriter::FunctionRewrite;
 use datafusion::logical_expr::{function::AccumulatorArgs, function::StateFieldsArgs};
 use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, Signature, Volatility};
+use datafusion::logical_expr::{Expr, WindowFunctionDefinition};
 use std::any::Any;
 use std::fmt::Debug;
 use std::ops::{Div, Mul, Sub};
+use std::sync::Arc;
+
+use crate::common::moments::{coerce_moment_types, moments_accumulator, moments_state_fields, PowerSums};
+use crate::config::{ExtraFunctionsConfig, SkewnessDefinition};
+use crate::skewness_pop::skewness_pop_udaf;
 
 make_udaf_expr_and_func!(SkewnessFunc, skewness, x, "Computes the skewness value.", skewness_udaf);
 
+make_udaf_expr!(
+    skewness_samp,
+    x,
+    "Computes the bias corrected sample skewness value.",
+    skewness_samp_udaf
+);
+create_func!(
+    SkewnessFunc,
+    skewness_samp_udaf,
+    SkewnessFunc::new_samp(),
+    aliases = ["skewSamp", "skewsamp"]
+);
+
+/// The `SkewnessFunc` computes the bias corrected sample skewness, as DuckDB does.
+///
+/// It is registered twice: as `skewness_samp`, which is always the sample skewness, and as `skewness`, which
+/// [`SkewnessRewrite`] replaces by `skewness_pop` when the session selects [`SkewnessDefinition::Population`].
 pub struct SkewnessFunc {
     name: String,
     signature: Signature,
@@ -35,6 +60,7 @@ pub struct SkewnessFunc {
 impl Debug for SkewnessFunc {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("SkewnessFunc")
+            .field("name", &self.name)
             .field("signature", &self.signature)
             .finish()
     }
@@ -50,7 +76,15 @@ impl SkewnessFunc {
     pub fn new() -> Self {
         Self {
             name: "skewness".to_string(),
-            signature: Signature::coercible(vec![DataType::Float64], Volatility::Immutable),
+            signature: Signature::user_defined(Volatility::Immutable),
+        }
+    }
+
+    /// Creates the `skewness_samp` function, which is not affected by [`ExtraFunctionsConfig::skewness`].
+    pub fn new_samp() -> Self {
+        Self {
+            name: "skewness_samp".to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
         }
     }
 }
@@ -67,103 +101,83 @@ impl AggregateUDFImpl for SkewnessFunc {
         &self.signature
     }
 
+    fn coerce_types(&self, arg_types: &[DataType]) -> datafusion::common::Result<Vec<DataType>> {
+        coerce_moment_types(self.name(), arg_types)
+    }
+
     fn return_type(&self, _arg_types: &[DataType]) -> datafusion::common::Result<DataType> {
         Ok(DataType::Float64)
     }
 
-    fn accumulator(&self, _acc_args: AccumulatorArgs) -> datafusion::common::Result<Box<dyn Accumulator>> {
-        Ok(Box::new(SkewnessAccumulator::new()))
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> datafusion::common::Result<Box<dyn Accumulator>> {
+        moments_accumulator(
+            &acc_args.exprs[0].data_type(acc_args.schema)?,
+            3,
+            false,
+            Arc::new(sample_skewness),
+        )
     }
 
-    fn state_fields(&self, _args: StateFieldsArgs) -> datafusion::common::Result<Vec<Field>> {
-        Ok(vec![
-            Field::new("count", DataType::UInt64, true),
-            Field::new("sum", DataType::Float64, true),
-            Field::new("sum_sqr", DataType::Float64, true),
-            Field::new("sum_cub", DataType::Float64, true),
-        ])
+    fn state_fields(&self, args: StateFieldsArgs) -> datafusion::common::Result<Vec<Field>> {
+        Ok(moments_state_fields(&args.input_types[0]))
     }
 }
 
-/// Accumulator for calculating the skewness
-/// This implementation follows the DuckDB implementation:
-/// <https://github.com/duckdb/duckdb/blob/main/src/core_functions/aggregate/distributive/skew.cpp>
-#[derive(Debug)]
-pub struct SkewnessAccumulator {
-    count: u64,
-    sum: f64,
-    sum_sqr: f64,
-    sum_cub: f64,
-}
+/// Rewrites calls to the bare `skewness` function into `skewness_pop` when [`ExtraFunctionsConfig::skewness`] is
+/// [`SkewnessDefinition::Population`], so queries migrated from Spark return the same numbers.
+#[derive(Debug, Default)]
+pub struct SkewnessRewrite {}
 
-impl SkewnessAccumulator {
-    fn new() -> Self {
-        Self {
-            count: 0,
-            sum: 0f64,
-            sum_sqr: 0f64,
-            sum_cub: 0f64,
-        }
+impl FunctionRewrite for SkewnessRewrite {
+    fn name(&self) -> &str {
+        "skewness_rewrite"
     }
-}
 
-impl Accumulator for SkewnessAccumulator {
-    fn update_batch(&mut self, values: &[ArrayRef]) -> datafusion::common::Result<()> {
-        let array = values[0].as_primitive::<Float64Type>();
-        for val in array.iter().flatten() {
-            self.count += 1;
-            self.sum += val;
-            self.sum_sqr += val.powi(2);
-            self.sum_cub += val.powi(3);
+    fn rewrite(
+        &self,
+        expr: Expr,
+        _schema: &DFSchema,
+        config: &ConfigOptions,
+    ) -> datafusion::common::Result<Transformed<Expr>> {
+        let definition = config
+            .extensions
+            .get::<ExtraFunctionsConfig>()
+            .map(|config| config.skewness)
+            .unwrap_or_default();
+        if definition != SkewnessDefinition::Population {
+            return Ok(Transformed::no(expr));
         }
-        Ok(())
-    }
-    fn evaluate(&mut self) -> datafusion::common::Result<ScalarValue> {
-        if self.count <= 2 {
-            return Ok(ScalarValue::Float64(None));
-        }
-        let count = self.count as f64;
-        let t1 = 1f64 / count;
-        let p = (t1 * (self.sum_sqr - self.sum * self.sum * t1)).powi(3).max(0f64);
-        let div = p.sqrt();
-        if div == 0f64 {
-            return Ok(ScalarValue::Float64(None));
+
+        match expr {
+            Expr::AggregateFunction(mut aggregate) if aggregate.func.name() == "skewness" => {
+                aggregate.func = skewness_pop_udaf();
+                Ok(Transformed::yes(Expr::AggregateFunction(aggregate)))
+            }
+            Expr::WindowFunction(mut window) if is_skewness_window(&window) => {
+                window.fun = WindowFunctionDefinition::AggregateUDF(skewness_pop_udaf());
+                Ok(Transformed::yes(Expr::WindowFunction(window)))
+            }
+            _ => Ok(Transformed::no(expr)),
         }
-        let t2 = count.mul(count.sub(1f64)).sqrt().div(count.sub(2f64));
-        let res =
-            t2 * t1 * (self.sum_cub - 3f64 * self.sum_sqr * self.sum * t1 + 2f64 * self.sum.powi(3) * t1 * t1) / div;
-        Ok(ScalarValue::Float64(Some(res)))
     }
+}
 
-    fn size(&self) -> usize {
-        std::mem::size_of_val(self)
-    }
+fn is_skewness_window(window: &WindowFunction) -> bool {
+    matches!(&window.fun, WindowFunctionDefinition::AggregateUDF(udaf) if udaf.name() == "skewness")
+}
 
-    fn state(&mut self) -> datafusion::common::Result<Vec<ScalarValue>> {
-        Ok(vec![
-            ScalarValue::from(self.count),
-            ScalarValue::from(self.sum),
-            ScalarValue::from(self.sum_sqr),
-            ScalarValue::from(self.sum_cub),
-        ])
+/// Bias corrected sample skewness, undefined for fewer than 3 values.
+/// This implementation follows the DuckDB implementation:
+/// <https://github.com/duckdb/duckdb/blob/main/src/core_functions/aggregate/distributive/skew.cpp>
+pub(crate) fn sample_skewness(sums: &PowerSums) -> Option<f64> {
+    if sums.count() <= 2.0 {
+        return None;
     }
-
-    fn merge_batch(&mut self, states: &[ArrayRef]) -> datafusion::common::Result<()> {
-        let counts = states[0].as_primitive::<UInt64Type>();
-        let sums = states[1].as_primitive::<Float64Type>();
-        let sum_sqrs = states[2].as_primitive::<Float64Type>();
-        let sum_cubs = states[3].as_primitive::<Float64Type>();
-
-        for i in 0..counts.len() {
-            let c = counts.value(i);
-            if c == 0 {
-                continue;
-            }
-            self.count += c;
-            self.sum += sums.value(i);
-            self.sum_sqr += sum_sqrs.value(i);
-            self.sum_cub += sum_cubs.value(i);
-        }
-        Ok(())
+    let count = sums.count();
+    let div = sums.central_moment(2)?.powi(3).max(0f64).sqrt();
+    if div == 0f64 {
+        return None;
     }
+    let t2 = count.mul(count.sub(1f64)).sqrt().div(count.sub(2f64));
+    Some(t2 * sums.central_moment(3)? / div)
 }
@@ -0,0 +1,103 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use datafusion::arrow::datatypes::{DataType, Field};
+use datafusion::common::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, Signature, Volatility};
+use std::any::Any;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::common::moments::{coerce_moment_types, moments_accumulator, moments_state_fields, PowerSums};
+
+make_udaf_expr_and_func!(
+    SkewnessPopFunction,
+    skewness_pop,
+    x,
+    "Computes the population skewness value, without bias correction.",
+    skewness_pop_udaf,
+    aliases = ["skewPop", "skewpop"]
+);
+
+pub struct SkewnessPopFunction {
+    signature: Signature,
+}
+
+impl Debug for SkewnessPopFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SkewnessPopFunction")
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl Default for SkewnessPopFunction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SkewnessPopFunction {
+    pub fn new() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Immutable),
+        }
+    }
+}
+
+impl AggregateUDFImpl for SkewnessPopFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "skewness_pop"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        coerce_moment_types(self.name(), arg_types)
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Float64)
+    }
+
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(moments_state_fields(&args.input_types[0]))
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        moments_accumulator(
+            &acc_args.exprs[0].data_type(acc_args.schema)?,
+            3,
+            false,
+            Arc::new(population_skewness),
+        )
+    }
+}
+
+/// Population skewness `m3 / m2^(3/2)`, without bias correction, undefined when the values have no variance.
+/// This is the definition used by Spark's `skewness`:
+/// <https://github.com/apache/spark/blob/master/sql/catalyst/src/main/scala/org/apache/spark/sql/catalyst/expressions/aggregate/CentralMomentAgg.scala>
+pub(crate) fn population_skewness(sums: &PowerSums) -> Option<f64> {
+    sums.standardized_moment(3)
+}
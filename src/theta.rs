@@ -0,0 +1,318 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::any::Any;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use arrow::array::{BinaryArray, Float64Array};
+use arrow::datatypes::{DataType, Field};
+use datafusion::arrow;
+use datafusion::common::cast::as_binary_array;
+use datafusion::common::plan_err;
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, ColumnarValue, ScalarUDFImpl, Signature, Volatility};
+use datafusion::scalar::ScalarValue;
+
+use crate::common::datasketches::{coerce_item_type, DistinctSketchAccumulator};
+use crate::common::sketch::{coerce_sketch_type, sketch_parameter, Sketch};
+use crate::common::theta::{ThetaSketch, DEFAULT_LG_K, MAX_LG_K, MIN_LG_K};
+
+make_udaf_expr!(
+    theta_sketch_agg,
+    x,
+    "Builds a serialized theta sketch of the distinct values.",
+    theta_sketch_agg_udaf
+);
+create_func!(ThetaFunction, theta_sketch_agg_udaf, ThetaFunction::new_sketch_agg());
+
+make_udaf_expr!(
+    theta_union_agg,
+    sketch,
+    "Computes the union of serialized theta sketches.",
+    theta_union_agg_udaf
+);
+create_func!(ThetaFunction, theta_union_agg_udaf, ThetaFunction::new_union_agg());
+
+make_udf_expr!(
+    theta_intersect,
+    a b,
+    "Computes the intersection of two serialized theta sketches.",
+    theta_intersect_udf
+);
+create_udf_func!(
+    ThetaSetOperationFunction,
+    theta_intersect_udf,
+    ThetaSetOperationFunction::new_intersect()
+);
+
+make_udf_expr!(
+    theta_a_not_b,
+    a b,
+    "Computes the difference of two serialized theta sketches, the values of a that are not in b.",
+    theta_a_not_b_udf
+);
+create_udf_func!(
+    ThetaSetOperationFunction,
+    theta_a_not_b_udf,
+    ThetaSetOperationFunction::new_a_not_b()
+);
+
+make_udf_expr!(
+    theta_estimate,
+    sketch,
+    "Estimates the number of distinct values summarized by a serialized theta sketch.",
+    theta_estimate_udf
+);
+create_udf_func!(ThetaEstimateFunction, theta_estimate_udf);
+
+/// The `ThetaFunction` builds theta sketches of the distinct values, binary compatible with the compact sketches of
+/// Apache DataSketches, see [`ThetaSketch`] for the format.
+///
+/// - `theta_sketch_agg(x [, lg_k])` hashes integers, floats, strings and binaries as DataSketches does.
+/// - `theta_union_agg(sketch [, lg_k])` merges sketches.
+/// - `lg_k` must be a constant between 4 and 26 and defaults to 12. Both return an empty sketch when there are no
+///   values.
+pub struct ThetaFunction {
+    name: String,
+    signature: Signature,
+    union: bool,
+}
+
+impl Debug for ThetaFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ThetaFunction")
+            .field("name", &self.name)
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl ThetaFunction {
+    /// Creates the `theta_sketch_agg` function, which builds a sketch of values.
+    pub fn new_sketch_agg() -> Self {
+        Self {
+            name: "theta_sketch_agg".to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
+            union: false,
+        }
+    }
+
+    /// Creates the `theta_union_agg` function, which merges sketches.
+    pub fn new_union_agg() -> Self {
+        Self {
+            name: "theta_union_agg".to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
+            union: true,
+        }
+    }
+}
+
+impl AggregateUDFImpl for ThetaFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let (input_type, lg_k_type) = match arg_types {
+            [input_type] => (input_type, None),
+            [input_type, lg_k_type] => (input_type, Some(lg_k_type)),
+            _ => return plan_err!("{} expects 1 or 2 arguments, got {}", self.name, arg_types.len()),
+        };
+        let input_type = match self.union {
+            true => coerce_sketch_type(&self.name, input_type)?,
+            false => match coerce_item_type(input_type) {
+                Some(input_type) => input_type,
+                None => return plan_err!("{} does not support values of type {input_type}", self.name),
+            },
+        };
+        match lg_k_type {
+            None => Ok(vec![input_type]),
+            Some(lg_k_type) if lg_k_type.is_integer() || lg_k_type.is_null() => Ok(vec![input_type, DataType::Int64]),
+            Some(lg_k_type) => plan_err!("The lg_k of {} must be an integer, got {lg_k_type}", self.name),
+        }
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Binary)
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let lg_k = match sketch_parameter(&acc_args, 1, &self.name, "lg_k")? {
+            None => DEFAULT_LG_K,
+            Some(lg_k) if (MIN_LG_K as i64..=MAX_LG_K as i64).contains(&lg_k) => lg_k as u8,
+            Some(lg_k) => {
+                return plan_err!(
+                    "The lg_k of {} must be between {MIN_LG_K} and {MAX_LG_K}, got {lg_k}",
+                    self.name
+                )
+            }
+        };
+
+        Ok(Box::new(DistinctSketchAccumulator::new(
+            ThetaSketch::new(lg_k),
+            self.union,
+        )))
+    }
+
+    fn state_fields(&self, _args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(vec![Field::new("sketch", DataType::Binary, true)])
+    }
+}
+
+/// The `ThetaSetOperationFunction` computes the intersection, with `theta_intersect(a, b)`, or the difference, with
+/// `theta_a_not_b(a, b)`, of two serialized theta sketches and returns the serialized result. Returns null when a
+/// sketch is null.
+#[derive(Debug)]
+pub struct ThetaSetOperationFunction {
+    name: String,
+    signature: Signature,
+    operation: fn(&ThetaSketch, &ThetaSketch) -> ThetaSketch,
+}
+
+impl ThetaSetOperationFunction {
+    /// Creates the `theta_intersect` function.
+    pub fn new_intersect() -> Self {
+        Self {
+            name: "theta_intersect".to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
+            operation: ThetaSketch::intersect,
+        }
+    }
+
+    /// Creates the `theta_a_not_b` function.
+    pub fn new_a_not_b() -> Self {
+        Self {
+            name: "theta_a_not_b".to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
+            operation: ThetaSketch::a_not_b,
+        }
+    }
+}
+
+impl ScalarUDFImpl for ThetaSetOperationFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let [a_type, b_type] = arg_types else {
+            return plan_err!("{} expects exactly 2 arguments, got {}", self.name, arg_types.len());
+        };
+        Ok(vec![
+            coerce_sketch_type(&self.name, a_type)?,
+            coerce_sketch_type(&self.name, b_type)?,
+        ])
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Binary)
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        let arrays = ColumnarValue::values_to_arrays(args)?;
+        let results = as_binary_array(&arrays[0])?
+            .iter()
+            .zip(as_binary_array(&arrays[1])?.iter())
+            .map(|(a, b)| match (a, b) {
+                (Some(a), Some(b)) => {
+                    let result = (self.operation)(&ThetaSketch::deserialize(a)?, &ThetaSketch::deserialize(b)?);
+                    Ok(Some(result.to_bytes()))
+                }
+                _ => Ok(None),
+            })
+            .collect::<Result<BinaryArray>>()?;
+
+        if args.iter().all(|arg| matches!(arg, ColumnarValue::Scalar(_))) {
+            return Ok(ColumnarValue::Scalar(ScalarValue::try_from_array(&results, 0)?));
+        }
+        Ok(ColumnarValue::Array(Arc::new(results)))
+    }
+}
+
+/// The `ThetaEstimateFunction` estimates the number of distinct values summarized by a serialized theta sketch, e.g.
+/// `theta_estimate(theta_intersect(a, b))`. The estimate of an intersection or a difference may be fractional.
+#[derive(Debug)]
+pub struct ThetaEstimateFunction {
+    signature: Signature,
+}
+
+impl Default for ThetaEstimateFunction {
+    fn default() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Immutable),
+        }
+    }
+}
+
+impl ScalarUDFImpl for ThetaEstimateFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "theta_estimate"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let [sketch_type] = arg_types else {
+            return plan_err!("{} expects exactly 1 argument, got {}", self.name(), arg_types.len());
+        };
+        Ok(vec![coerce_sketch_type(self.name(), sketch_type)?])
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Float64)
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        let estimate = |bytes: &[u8]| Ok(ThetaSketch::deserialize(bytes)?.estimate());
+        match &args[0] {
+            ColumnarValue::Scalar(ScalarValue::Binary(bytes)) => Ok(ColumnarValue::Scalar(ScalarValue::Float64(
+                bytes.as_deref().map(estimate).transpose()?,
+            ))),
+            ColumnarValue::Array(array) => {
+                let estimates = as_binary_array(array)?
+                    .iter()
+                    .map(|bytes| bytes.map(estimate).transpose())
+                    .collect::<Result<Float64Array>>()?;
+                Ok(ColumnarValue::Array(Arc::new(estimates)))
+            }
+            ColumnarValue::Scalar(scalar) => plan_err!("{} expects a binary sketch, got {scalar}", self.name()),
+        }
+    }
+}
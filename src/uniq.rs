@@ -0,0 +1,445 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::any::Any;
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use ahash::RandomState;
+use arrow::array::{Array, ArrayRef, BooleanArray, Int64Array, ListArray, UInt64Array};
+use arrow::buffer::OffsetBuffer;
+use arrow::compute::take;
+use arrow::datatypes::{DataType, Field};
+use datafusion::arrow;
+use datafusion::common::cast::{as_binary_array, as_list_array};
+use datafusion::common::plan_err;
+use datafusion::common::utils::array_into_list_array_nullable;
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, EmitTo, GroupsAccumulator, Signature, Volatility};
+use datafusion::scalar::ScalarValue;
+
+use crate::common::datasketches::{coerce_item_type, for_each_item_hash};
+use crate::common::distinct::{non_null_values, DistinctValues};
+use crate::common::groups::is_selected;
+use crate::common::hll::HllSketch;
+use crate::common::sketch::{sketch_parameter, DistinctSketch, Sketch};
+
+make_udaf_expr!(
+    uniq_exact,
+    x,
+    "Counts the distinct non-null values exactly.",
+    uniq_exact_udaf
+);
+create_func!(UniqFunction, uniq_exact_udaf, UniqFunction::new_uniq_exact());
+
+make_udaf_expr!(
+    uniq_combined,
+    x,
+    "Counts the distinct non-null values exactly up to a threshold, and estimates them with HyperLogLog above it.",
+    uniq_combined_udaf
+);
+create_func!(UniqFunction, uniq_combined_udaf, UniqFunction::new_uniq_combined());
+
+/// Default number of distinct values above which `uniq_combined` estimates the count with HyperLogLog.
+pub const DEFAULT_UNIQ_COMBINED_THRESHOLD: i64 = 16_384;
+
+/// `lg_k` of the HyperLogLog sketch of `uniq_combined`, with a relative standard error of about 0.3%.
+const UNIQ_COMBINED_LG_K: u8 = 17;
+
+/// The `UniqFunction` counts the distinct non-null values of integers, floats, temporals, strings and binaries, as
+/// the `uniqExact` and `uniqCombined` functions of ClickHouse.
+///
+/// - `uniq_exact(x)` keeps the distinct values of each group, with a `GroupsAccumulator` sharing one map of the
+///   values between the groups.
+/// - `uniq_combined(x [, threshold])` keeps the distinct values until there are more than `threshold` of them,
+///   16384 by default, and then switches to a HyperLogLog sketch with `lg_k = 17`, so the count is exact for small
+///   cardinalities and takes bounded memory for huge ones. Like `hll_sketch_agg`, the sketch ignores empty strings
+///   and binaries.
+///
+/// Both return 0 when there are no values. As in DataSketches, `-0.0` and `0.0` are the same value, and so are all
+/// NaNs.
+pub struct UniqFunction {
+    name: String,
+    signature: Signature,
+    combined: bool,
+}
+
+impl Debug for UniqFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UniqFunction")
+            .field("name", &self.name)
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl UniqFunction {
+    /// Creates the `uniq_exact` function.
+    pub fn new_uniq_exact() -> Self {
+        Self {
+            name: "uniq_exact".to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
+            combined: false,
+        }
+    }
+
+    /// Creates the `uniq_combined` function.
+    pub fn new_uniq_combined() -> Self {
+        Self {
+            name: "uniq_combined".to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
+            combined: true,
+        }
+    }
+}
+
+impl AggregateUDFImpl for UniqFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let (value_type, threshold_type) = match arg_types {
+            [value_type] => (value_type, None),
+            [value_type, threshold_type] if self.combined => (value_type, Some(threshold_type)),
+            _ if self.combined => return plan_err!("{} expects 1 or 2 arguments, got {}", self.name, arg_types.len()),
+            _ => return plan_err!("{} expects exactly 1 argument, got {}", self.name, arg_types.len()),
+        };
+        // the distinct values of a dictionary are those of its values
+        let value_type = match value_type {
+            DataType::Dictionary(_, value_type) => value_type.as_ref(),
+            value_type => value_type,
+        };
+        let Some(value_type) = coerce_item_type(value_type) else {
+            return plan_err!("{} does not support values of type {value_type}", self.name);
+        };
+        match threshold_type {
+            None => Ok(vec![value_type]),
+            Some(threshold_type) if threshold_type.is_integer() || threshold_type.is_null() => {
+                Ok(vec![value_type, DataType::Int64])
+            }
+            Some(threshold_type) => plan_err!(
+                "The threshold of {} must be an integer, got {threshold_type}",
+                self.name
+            ),
+        }
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Int64)
+    }
+
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        let values = Field::new_list("values", Field::new_list_field(args.input_types[0].clone(), true), true);
+        match self.combined {
+            true => Ok(vec![values, Field::new("sketch", DataType::Binary, true)]),
+            false => Ok(vec![values]),
+        }
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        let data_type = acc_args.exprs[0].data_type(acc_args.schema)?;
+        if !self.combined {
+            return Ok(Box::new(UniqExactAccumulator {
+                values: DistinctValues::try_new(&data_type)?,
+            }));
+        }
+
+        let threshold = match sketch_parameter(&acc_args, 1, &self.name, "threshold")? {
+            None => DEFAULT_UNIQ_COMBINED_THRESHOLD,
+            Some(threshold) if threshold >= 0 => threshold,
+            Some(threshold) => {
+                return plan_err!("The threshold of {} must not be negative, got {threshold}", self.name)
+            }
+        };
+        Ok(Box::new(UniqCombinedAccumulator {
+            threshold: threshold as usize,
+            uniq: Uniq::Exact(Box::new(DistinctValues::try_new(&data_type)?)),
+            data_type,
+        }))
+    }
+
+    fn groups_accumulator_supported(&self, _args: AccumulatorArgs) -> bool {
+        !self.combined
+    }
+
+    fn create_groups_accumulator(&self, args: AccumulatorArgs) -> Result<Box<dyn GroupsAccumulator>> {
+        let data_type = args.exprs[0].data_type(args.schema)?;
+        Ok(Box::new(UniqExactGroupsAccumulator {
+            values: DistinctValues::try_new(&data_type)?,
+            seen: HashSet::default(),
+            counts: vec![],
+            data_type,
+        }))
+    }
+
+    fn default_value(&self, _data_type: &DataType) -> Result<ScalarValue> {
+        Ok(ScalarValue::Int64(Some(0)))
+    }
+}
+
+#[derive(Debug)]
+struct UniqExactAccumulator {
+    values: DistinctValues,
+}
+
+impl Accumulator for UniqExactAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        self.values.insert(&values[0], |_, _| ())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        for values in as_list_array(&states[0])?.iter().flatten() {
+            self.values.insert(&values, |_, _| ())?;
+        }
+        Ok(())
+    }
+
+    /// Moves the distinct values to the state, which is only called once at the end of a partial aggregation.
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        let values = non_null_values(self.values.take_values())?;
+        Ok(vec![ScalarValue::List(Arc::new(array_into_list_array_nullable(
+            values,
+        )))])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        Ok(ScalarValue::Int64(Some(self.values.len() as i64)))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.values.size()
+    }
+}
+
+/// Accumulator of `uniq_exact` for many groups. The distinct values of all the groups are kept once, in
+/// [`DistinctValues`], and each group has the ids of its values in `seen`.
+#[derive(Debug)]
+struct UniqExactGroupsAccumulator {
+    values: DistinctValues,
+    /// The distinct `(group index, value id)` pairs.
+    seen: HashSet<(usize, usize), RandomState>,
+    /// The number of distinct values of each group.
+    counts: Vec<i64>,
+    data_type: DataType,
+}
+
+impl UniqExactGroupsAccumulator {
+    fn insert(&mut self, values: &ArrayRef, groups: impl Fn(usize) -> Option<usize>) -> Result<()> {
+        let Self { seen, counts, .. } = self;
+        self.values.insert(values, |index, id| {
+            if let Some(group) = groups(index) {
+                if seen.insert((group, id)) {
+                    counts[group] += 1;
+                }
+            }
+        })
+    }
+
+    /// Removes the emitted groups from `seen`, and shifts the index of the others.
+    fn remove_groups(&mut self, emit_to: EmitTo) {
+        match emit_to {
+            EmitTo::All => self.seen.clear(),
+            EmitTo::First(n) => {
+                self.seen = self
+                    .seen
+                    .drain()
+                    .filter(|(group, _)| *group >= n)
+                    .map(|(group, id)| (group - n, id))
+                    .collect();
+            }
+        }
+    }
+}
+
+impl GroupsAccumulator for UniqExactGroupsAccumulator {
+    fn update_batch(
+        &mut self,
+        values: &[ArrayRef],
+        group_indices: &[usize],
+        opt_filter: Option<&BooleanArray>,
+        total_num_groups: usize,
+    ) -> Result<()> {
+        self.counts.resize(total_num_groups, 0);
+        self.insert(&values[0], |index| {
+            is_selected(opt_filter, index).then_some(group_indices[index])
+        })
+    }
+
+    fn merge_batch(
+        &mut self,
+        values: &[ArrayRef],
+        group_indices: &[usize],
+        opt_filter: Option<&BooleanArray>,
+        total_num_groups: usize,
+    ) -> Result<()> {
+        self.counts.resize(total_num_groups, 0);
+        let lists = as_list_array(&values[0])?;
+        let offsets = lists.value_offsets();
+        let (start, end) = (offsets[0] as usize, offsets[lists.len()] as usize);
+
+        // the group of each of the values of the lists
+        let mut groups = vec![None; end - start];
+        for (index, group) in group_indices.iter().enumerate() {
+            if lists.is_valid(index) && is_selected(opt_filter, index) {
+                let range = offsets[index] as usize - start..offsets[index + 1] as usize - start;
+                groups[range].fill(Some(*group));
+            }
+        }
+        self.insert(&lists.values().slice(start, end - start), |index| groups[index])
+    }
+
+    fn evaluate(&mut self, emit_to: EmitTo) -> Result<ArrayRef> {
+        let counts = emit_to.take_needed(&mut self.counts);
+        self.remove_groups(emit_to);
+        if matches!(emit_to, EmitTo::All) {
+            self.values.take_values();
+        }
+        Ok(Arc::new(Int64Array::from(counts)))
+    }
+
+    /// Returns the list of the distinct values of each group.
+    fn state(&mut self, emit_to: EmitTo) -> Result<Vec<ArrayRef>> {
+        let counts = emit_to.take_needed(&mut self.counts);
+        let offsets = OffsetBuffer::<i32>::from_lengths(counts.iter().map(|count| *count as usize));
+
+        // the ids of the values of each group, at the offsets of the group
+        let mut positions = offsets[..counts.len()].to_vec();
+        let mut ids = vec![0; *offsets.last().unwrap() as usize];
+        for (group, id) in &self.seen {
+            if let Some(position) = positions.get_mut(*group) {
+                ids[*position as usize] = *id as u64;
+                *position += 1;
+            }
+        }
+        self.remove_groups(emit_to);
+
+        let values = self.values.take_values();
+        if let EmitTo::First(_) = emit_to {
+            // the remaining groups keep the ids of the values, which are the same when inserted again in order
+            self.values.insert(&values, |_, _| ())?;
+        }
+        let values = take(&values, &UInt64Array::from(ids), None)?;
+        let field = Arc::new(Field::new_list_field(self.data_type.clone(), true));
+        Ok(vec![Arc::new(ListArray::try_new(field, offsets, values, None)?)])
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+            + self.values.size()
+            + self.seen.capacity() * (std::mem::size_of::<(usize, usize)>() + 1)
+            + self.counts.capacity() * std::mem::size_of::<i64>()
+    }
+}
+
+#[derive(Debug)]
+enum Uniq {
+    Exact(Box<DistinctValues>),
+    Estimated(HllSketch),
+}
+
+#[derive(Debug)]
+struct UniqCombinedAccumulator {
+    threshold: usize,
+    uniq: Uniq,
+    data_type: DataType,
+}
+
+impl UniqCombinedAccumulator {
+    fn insert(&mut self, values: &ArrayRef) -> Result<()> {
+        match &mut self.uniq {
+            Uniq::Exact(distinct) => {
+                distinct.insert(values, |_, _| ())?;
+                if distinct.len() > self.threshold {
+                    self.sketch()?;
+                }
+                Ok(())
+            }
+            Uniq::Estimated(sketch) => for_each_item_hash(values, |hash| sketch.update_hash(hash)),
+        }
+    }
+
+    /// Switches to the HyperLogLog sketch of the distinct values, if not done yet, and returns it.
+    fn sketch(&mut self) -> Result<&mut HllSketch> {
+        if let Uniq::Exact(distinct) = &mut self.uniq {
+            let mut sketch = HllSketch::new(UNIQ_COMBINED_LG_K);
+            for_each_item_hash(&distinct.take_values(), |hash| sketch.update_hash(hash))?;
+            self.uniq = Uniq::Estimated(sketch);
+        }
+        match &mut self.uniq {
+            Uniq::Estimated(sketch) => Ok(sketch),
+            Uniq::Exact(_) => unreachable!(),
+        }
+    }
+}
+
+impl Accumulator for UniqCombinedAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        self.insert(&values[0])
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        let lists = as_list_array(&states[0])?;
+        let sketches = as_binary_array(&states[1])?;
+        for (values, sketch) in lists.iter().zip(sketches.iter()) {
+            if let Some(sketch) = sketch {
+                self.sketch()?.merge(&HllSketch::deserialize(sketch)?);
+            }
+            if let Some(values) = values {
+                self.insert(&values)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns either the list of the distinct values or the serialized sketch.
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        let (values, sketch) = match &mut self.uniq {
+            Uniq::Exact(distinct) => (non_null_values(distinct.take_values())?, None),
+            Uniq::Estimated(sketch) => (arrow::array::new_empty_array(&self.data_type), Some(sketch.to_bytes())),
+        };
+        Ok(vec![
+            ScalarValue::List(Arc::new(array_into_list_array_nullable(values))),
+            ScalarValue::Binary(sketch),
+        ])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        let count = match &self.uniq {
+            Uniq::Exact(distinct) => distinct.len() as i64,
+            Uniq::Estimated(sketch) => sketch.estimate().round() as i64,
+        };
+        Ok(ScalarValue::Int64(Some(count)))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+            + match &self.uniq {
+                Uniq::Exact(distinct) => distinct.size(),
+                Uniq::Estimated(sketch) => sketch.size(),
+            }
+    }
+}
@@ -0,0 +1,156 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::any::Any;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use datafusion::arrow::datatypes::{DataType, Field};
+use datafusion::common::plan_err;
+use datafusion::error::Result;
+use datafusion::logical_expr::function::{AccumulatorArgs, StateFieldsArgs};
+use datafusion::logical_expr::{Accumulator, AggregateUDFImpl, Signature, Volatility};
+
+use crate::common::moments::{coerce_moment_type, moments_accumulator, moments_state_fields, PowerSums};
+use crate::kurtosis::sample_kurtosis;
+use crate::kurtosis_pop::population_kurtosis;
+use crate::skewness::sample_skewness;
+
+make_udaf_expr!(
+    weighted_skewness,
+    x w,
+    "Computes the bias corrected sample skewness of the values weighted by frequency weights.",
+    weighted_skewness_udaf
+);
+create_func!(
+    WeightedMomentsFunction,
+    weighted_skewness_udaf,
+    WeightedMomentsFunction::new_skewness()
+);
+
+make_udaf_expr!(
+    weighted_kurtosis,
+    x w,
+    "Computes the excess kurtosis with bias correction of the values weighted by frequency weights.",
+    weighted_kurtosis_udaf
+);
+create_func!(
+    WeightedMomentsFunction,
+    weighted_kurtosis_udaf,
+    WeightedMomentsFunction::new_kurtosis()
+);
+
+make_udaf_expr!(
+    weighted_kurtosis_pop,
+    x w,
+    "Computes the excess kurtosis without bias correction of the values weighted by frequency weights.",
+    weighted_kurtosis_pop_udaf
+);
+create_func!(
+    WeightedMomentsFunction,
+    weighted_kurtosis_pop_udaf,
+    WeightedMomentsFunction::new_kurtosis_pop()
+);
+
+/// The `WeightedMomentsFunction` computes a moment statistic of `x` weighted by `w`, e.g. `weighted_skewness(x, w)`.
+///
+/// - Weights are frequency weights: a row with an integer weight `w` counts as `w` rows, so the bias corrections use
+///   the sum of the weights as the number of values.
+/// - Rows where `x` or `w` is null are ignored, negative weights are an error.
+pub struct WeightedMomentsFunction {
+    name: String,
+    signature: Signature,
+    order: usize,
+    statistic: fn(&PowerSums) -> Option<f64>,
+}
+
+impl Debug for WeightedMomentsFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WeightedMomentsFunction")
+            .field("name", &self.name)
+            .field("signature", &self.signature)
+            .field("order", &self.order)
+            .finish()
+    }
+}
+
+impl WeightedMomentsFunction {
+    fn new(name: &str, order: usize, statistic: fn(&PowerSums) -> Option<f64>) -> Self {
+        Self {
+            name: name.to_string(),
+            signature: Signature::user_defined(Volatility::Immutable),
+            order,
+            statistic,
+        }
+    }
+
+    /// Creates the `weighted_skewness` function, the weighted counterpart of `skewness_samp`.
+    pub fn new_skewness() -> Self {
+        Self::new("weighted_skewness", 3, sample_skewness)
+    }
+
+    /// Creates the `weighted_kurtosis` function, the weighted counterpart of `kurtosis`.
+    pub fn new_kurtosis() -> Self {
+        Self::new("weighted_kurtosis", 4, sample_kurtosis)
+    }
+
+    /// Creates the `weighted_kurtosis_pop` function, the weighted counterpart of `kurtosis_pop`.
+    pub fn new_kurtosis_pop() -> Self {
+        Self::new("weighted_kurtosis_pop", 4, population_kurtosis)
+    }
+}
+
+impl AggregateUDFImpl for WeightedMomentsFunction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let [arg_type, weight_type] = arg_types else {
+            return plan_err!("{} expects exactly 2 arguments, got {}", self.name, arg_types.len());
+        };
+        if !weight_type.is_numeric() && !weight_type.is_null() {
+            return plan_err!("{} expects numeric weights, got {weight_type}", self.name);
+        }
+        Ok(vec![coerce_moment_type(&self.name, arg_type)?, DataType::Float64])
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Float64)
+    }
+
+    fn accumulator(&self, acc_args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        moments_accumulator(
+            &acc_args.exprs[0].data_type(acc_args.schema)?,
+            self.order,
+            true,
+            Arc::new(self.statistic),
+        )
+    }
+
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(moments_state_fields(&args.input_types[0]))
+    }
+}
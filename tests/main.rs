@@ -16,6 +16,12 @@
 // under the License.
 
 use crate::utils::TestExecution;
+use datafusion::functions::core::expr_ext::FieldAccessor;
+use datafusion::logical_expr::AggregateUDF;
+use datafusion::prelude::col;
+use datafusion_functions_extra::first_last_by::{FirstByFunction, LastByFunction};
+use datafusion_functions_extra::max_by_struct::max_by_struct;
+use datafusion_functions_extra::max_min_by::{MaxByFunction, MinByFunction};
 
 mod utils;
 
@@ -177,26 +183,42 @@ async fn test_max_by_and_min_by() {
         .run_and_format("SELECT max_by(x, y) FROM VALUES (1, 10), (2, null), (3, 15), (null, 8) as tab(x, y);")
         .await;
 
-    insta::assert_yaml_snapshot!(actual, @r###"
+    insta::assert_yaml_snapshot!(actual, @r#"
     - +---------------------+
     - "| max_by(tab.x,tab.y) |"
     - +---------------------+
-    - "| 2                   |"
+    - "| 3                   |"
     - +---------------------+
-    "###);
+    "#);
 
     // Test min_by with null values
     let actual = execution
         .run_and_format("SELECT min_by(x, y) FROM VALUES (1, 10), (2, null), (3, 15), (null, 8) as tab(x, y);")
         .await;
 
-    insta::assert_yaml_snapshot!(actual, @r###"
+    insta::assert_yaml_snapshot!(actual, @r#"
     - +---------------------+
     - "| min_by(tab.x,tab.y) |"
     - +---------------------+
-    - "| 2                   |"
+    - "|                     |"
     - +---------------------+
-    "###);
+    "#);
+
+    // Test max_by and min_by over a sliding window frame
+    let actual = execution
+        .run_and_format("SELECT x, max_by(x, y) OVER (ORDER BY x ROWS BETWEEN 1 PRECEDING AND CURRENT ROW) AS max_by, min_by(x, y) OVER (ORDER BY x ROWS BETWEEN 1 PRECEDING AND CURRENT ROW) AS min_by FROM VALUES (1, 10), (2, 5), (3, 15), (4, 8) as tab(x, y) ORDER BY x")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+--------+--------+
+    - "| x | max_by | min_by |"
+    - +---+--------+--------+
+    - "| 1 | 1      | 1      |"
+    - "| 2 | 1      | 2      |"
+    - "| 3 | 3      | 2      |"
+    - "| 4 | 3      | 4      |"
+    - +---+--------+--------+
+    "#);
 
     // Test max_by with a single value
     let actual = execution
@@ -260,26 +282,26 @@ async fn test_kurtosis_pop() {
         .run_and_format("SELECT kurtosis_pop(int64_col) FROM test_table")
         .await;
 
-    insta::assert_yaml_snapshot!(actual, @r###"
-        - +------------------------------------+
-        - "| kurtosis_pop(test_table.int64_col) |"
-        - +------------------------------------+
-        - "| -0.9599999999999755                |"
-        - +------------------------------------+
-    "###);
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +------------------------------------+
+    - "| kurtosis_pop(test_table.int64_col) |"
+    - +------------------------------------+
+    - "| -0.9600000000000044                |"
+    - +------------------------------------+
+    "#);
 
     // Test with float64
     let actual = execution
         .run_and_format("SELECT kurtosis_pop(float64_col) FROM test_table")
         .await;
 
-    insta::assert_yaml_snapshot!(actual, @r###"
+    insta::assert_yaml_snapshot!(actual, @r#"
     - +--------------------------------------+
     - "| kurtosis_pop(test_table.float64_col) |"
     - +--------------------------------------+
-    - "| -0.9599999999999755                  |"
+    - "| -0.9599999999999826                  |"
     - +--------------------------------------+
-"###);
+    "#);
 
     let actual = execution
         .run_and_format("SELECT kurtosis_pop(col) FROM VALUES (1.0) as tab(col)")
@@ -320,26 +342,26 @@ async fn test_skewness() {
         .run_and_format("SELECT skewness(int64_col) FROM test_table")
         .await;
 
-    insta::assert_yaml_snapshot!(actual, @r###"
-        - +--------------------------------+
-        - "| skewness(test_table.int64_col) |"
-        - +--------------------------------+
-        - "| -0.8573214099741201            |"
-        - +--------------------------------+
-    "###);
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------------------------------+
+    - "| skewness(test_table.int64_col) |"
+    - +--------------------------------+
+    - "| -0.8573214099741129            |"
+    - +--------------------------------+
+    "#);
 
     // Test with float64
     let actual = execution
         .run_and_format("SELECT skewness(float64_col) FROM test_table")
         .await;
 
-    insta::assert_yaml_snapshot!(actual, @r###"
+    insta::assert_yaml_snapshot!(actual, @r#"
     - +----------------------------------+
     - "| skewness(test_table.float64_col) |"
     - +----------------------------------+
-    - "| -0.8573214099741201              |"
+    - "| -0.857321409974115               |"
     - +----------------------------------+
-"###);
+    "#);
 
     // Test with single value
     let actual = execution.run_and_format("SELECT skewness(1.0)").await;
@@ -363,6 +385,19 @@ async fn test_skewness() {
         - "|                   |"
         - +-------------------+
     "###);
+
+    // extreme decimals of opposite signs are too far apart for their exact distance
+    let actual = execution
+        .run_and_format("SELECT skewness(x) AS skewness, kurtosis(x) AS kurtosis FROM (SELECT arrow_cast(x, 'Decimal128(38, 0)') AS x FROM (VALUES ('99999999999999999999999999999999999999'), ('-99999999999999999999999999999999999999'), ('-99999999999999999999999999999999999999'), ('-99999999999999999999999999999999999999')) AS t(x))")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------------------+-------------------+
+    - "| skewness           | kurtosis          |"
+    - +--------------------+-------------------+
+    - "| 2.0000000000000044 | 4.000000000000047 |"
+    - +--------------------+-------------------+
+    "#);
 }
 
 #[tokio::test]
@@ -435,3 +470,2317 @@ async fn test_kurtosis() {
           - +----------------+
     "###);
 }
+
+#[tokio::test]
+async fn test_max_by_and_min_by_aliases() {
+    let mut execution = TestExecution::new().await.unwrap();
+
+    let actual = execution
+        .run_and_format("SELECT arg_max(x, y) AS a, argmax(x, y) AS b, \"argMax\"(x, y) AS c FROM VALUES (1, 10), (2, 5), (3, 15), (4, 8) as tab(x, y);")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+---+---+
+    - "| a | b | c |"
+    - +---+---+---+
+    - "| 3 | 3 | 3 |"
+    - +---+---+---+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT arg_min(x, y) AS a, argmin(x, y) AS b, \"argMin\"(x, y) AS c FROM VALUES (1, 10), (2, 5), (3, 15), (4, 8) as tab(x, y);")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+---+---+
+    - "| a | b | c |"
+    - +---+---+---+
+    - "| 2 | 2 | 2 |"
+    - +---+---+---+
+    "#);
+}
+
+#[tokio::test]
+async fn test_extra_functions_aliases() {
+    let mut execution = TestExecution::new().await.unwrap();
+
+    let actual = execution
+        .run_and_format("SELECT kurtSamp(col), kurtPop(col), skewSamp(col), skewPop(col) FROM VALUES (1.0), (10.0), (100.0), (10.0), (1.0) as tab(col);")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------------------+-----------------------+------------------------+-----------------------+
+    - "| kurtosis(tab.col) | kurtosis_pop(tab.col) | skewness_samp(tab.col) | skewness_pop(tab.col) |"
+    - +-------------------+-----------------------+------------------------+-----------------------+
+    - "| 4.777292927667962 | 0.19432323191699075   | 2.1736444073829095     | 1.4581249961461296    |"
+    - +-------------------+-----------------------+------------------------+-----------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT stats_mode(col), geomean(col), kahan_sum(col) FROM VALUES (1.0), (10.0), (100.0), (10.0), (1.0), (10.0) as tab(col);")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---------------+-------------------------+---------------+
+    - "| mode(tab.col) | geometric_mean(tab.col) | fsum(tab.col) |"
+    - +---------------+-------------------------+---------------+
+    - "| 10.0          | 6.812920690579616       | 132.0         |"
+    - +---------------+-------------------------+---------------+
+    "#);
+}
+
+#[tokio::test]
+async fn test_max_by_and_min_by_complex_types() {
+    let mut execution = TestExecution::new().await.unwrap();
+
+    // Test max_by with a struct value
+    let actual = execution
+        .run_and_format("SELECT max_by(named_struct('x', x, 'y', y), y) AS v FROM VALUES (1, 10), (2, 5), (3, 15), (4, 8) as tab(x, y);")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---------------+
+    - "| v             |"
+    - +---------------+
+    - "| {x: 3, y: 15} |"
+    - +---------------+
+    "#);
+
+    // Test min_by with a list value
+    let actual = execution
+        .run_and_format(
+            "SELECT min_by(make_array(x, y), y) AS v FROM VALUES (1, 10), (2, 5), (3, 15), (4, 8) as tab(x, y);",
+        )
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------+
+    - "| v      |"
+    - +--------+
+    - "| [2, 5] |"
+    - +--------+
+    "#);
+
+    // Test max_by ordered by a struct
+    let actual = execution
+        .run_and_format("SELECT max_by(x, named_struct('y', y, 'x', x)) AS v FROM VALUES (1, 10), (2, 15), (3, 15), (4, 8) as tab(x, y);")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+
+    - "| v |"
+    - +---+
+    - "| 3 |"
+    - +---+
+    "#);
+
+    // Test max_by ordered by lists of different lengths, which are compared lexicographically
+    let actual = execution
+        .run_and_format("SELECT max_by(x, y) AS v1, min_by(x, named_struct('l', y)) AS v2 FROM (SELECT * FROM (VALUES (1, [1, 2])) a(x, y) UNION ALL SELECT * FROM (VALUES (2, [3])) b(x, y) UNION ALL SELECT * FROM (VALUES (3, [1])) c(x, y)) t;")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +----+----+
+    - "| v1 | v2 |"
+    - +----+----+
+    - "| 2  | 3  |"
+    - +----+----+
+    "#);
+
+    // Test a sliding window ordered by lists of different lengths
+    let actual = execution
+        .run_and_format("SELECT x, max_by(x, y) OVER (ORDER BY x ROWS BETWEEN 1 PRECEDING AND CURRENT ROW) AS v FROM (SELECT * FROM (VALUES (1, [1, 2])) a(x, y) UNION ALL SELECT * FROM (VALUES (2, [3])) b(x, y) UNION ALL SELECT * FROM (VALUES (3, [1])) c(x, y)) t ORDER BY x;")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+---+
+    - "| x | v |"
+    - +---+---+
+    - "| 1 | 1 |"
+    - "| 2 | 2 |"
+    - "| 3 | 2 |"
+    - +---+---+
+    "#);
+
+    // Test max_by with a dictionary value, which is returned as its value type
+    let actual = execution
+        .run_and_format("SELECT max_by(arrow_cast(x, 'Dictionary(Int32, Utf8)'), y) AS v, arrow_typeof(max_by(arrow_cast(x, 'Dictionary(Int32, Utf8)'), y)) AS t FROM VALUES ('a', 10), ('b', 5), ('c', 15) as tab(x, y);")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+------+
+    - "| v | t    |"
+    - +---+------+
+    - "| c | Utf8 |"
+    - +---+------+
+    "#);
+
+    // Test max_by ordered by a map, which has no natural order
+    let err = execution
+        .run("SELECT max_by(x, map(make_array('k'), make_array(y))) FROM VALUES (1, 10), (2, 5) as tab(x, y);")
+        .await
+        .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .contains("max_by/min_by cannot order by a value of type Map"));
+}
+
+#[tokio::test]
+async fn test_max_by_and_min_by_dictionary_output() {
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_udaf(AggregateUDF::from(MaxByFunction::new().with_dictionary_output(true)))
+        .with_udaf(AggregateUDF::from(MinByFunction::new().with_dictionary_output(true)));
+
+    let actual = execution
+        .run_and_format("SELECT max_by(arrow_cast(x, 'Dictionary(Int32, Utf8)'), y) AS v, arrow_typeof(max_by(arrow_cast(x, 'Dictionary(Int32, Utf8)'), y)) AS t FROM VALUES ('a', 10), ('b', 5), ('c', 15) as tab(x, y);")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+-------------------------+
+    - "| v | t                       |"
+    - +---+-------------------------+
+    - "| c | Dictionary(Int32, Utf8) |"
+    - +---+-------------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT min_by(arrow_cast(x, 'Dictionary(Int32, Utf8)'), y) AS v, arrow_typeof(min_by(arrow_cast(x, 'Dictionary(Int32, Utf8)'), y)) AS t FROM VALUES ('a', 10), ('b', 5), ('c', 15) as tab(x, y);")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+-------------------------+
+    - "| v | t                       |"
+    - +---+-------------------------+
+    - "| b | Dictionary(Int32, Utf8) |"
+    - +---+-------------------------+
+    "#);
+}
+
+#[tokio::test]
+async fn test_first_by_and_last_by() {
+    let mut execution = TestExecution::new().await.unwrap();
+
+    let actual = execution
+        .run_and_format("SELECT first_by(x, y) AS first, last_by(x, y) AS last FROM VALUES (1, 10), (2, 5), (3, 15), (4, 8) as tab(x, y);")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------+------+
+    - "| first | last |"
+    - +-------+------+
+    - "| 2     | 3    |"
+    - +-------+------+
+    "#);
+
+    // Test with null values, rows with a null ordering are ignored
+    let actual = execution
+        .run_and_format("SELECT first_by(x, y) AS first, last_by(x, y) AS last FROM VALUES (1, 10), (2, null), (3, 15), (null, 8) as tab(x, y);")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------+------+
+    - "| first | last |"
+    - +-------+------+
+    - "|       | 3    |"
+    - +-------+------+
+    "#);
+
+    // Test with ties, first_by keeps the first row and last_by the last one
+    let actual = execution
+        .run_and_format("SELECT first_by(x, y) AS first, last_by(x, y) AS last FROM VALUES (1, 5), (2, 5), (3, 15), (4, 15) as tab(x, y);")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------+------+
+    - "| first | last |"
+    - +-------+------+
+    - "| 1     | 4    |"
+    - +-------+------+
+    "#);
+
+    // Test with strings and timestamps per group
+    let actual = execution
+        .run_and_format("SELECT g, first_by(name, ts) AS first, last_by(name, ts) AS last FROM VALUES ('a', 'Alice', TIMESTAMP '2021-01-02 00:00:00'), ('a', 'Bob', TIMESTAMP '2021-01-01 00:00:00'), ('b', 'Charlie', TIMESTAMP '2021-01-03 00:00:00'), ('a', 'Dave', TIMESTAMP '2021-01-03 00:00:00') as tab(g, name, ts) GROUP BY g ORDER BY g;")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+---------+---------+
+    - "| g | first   | last    |"
+    - +---+---------+---------+
+    - "| a | Bob     | Dave    |"
+    - "| b | Charlie | Charlie |"
+    - +---+---------+---------+
+    "#);
+
+    // Test with an empty set
+    let actual = execution
+        .run_and_format("SELECT first_by(x, y) AS first, last_by(x, y) AS last FROM (SELECT * FROM (VALUES (1, 10)) WHERE 1=0) as tab(x, y);")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------+------+
+    - "| first | last |"
+    - +-------+------+
+    - "|       |      |"
+    - +-------+------+
+    "#);
+
+    // Test with a dictionary value, which is returned as its value type as by max_by/min_by
+    let actual = execution
+        .run_and_format("SELECT arrow_typeof(first_by(arrow_cast(x, 'Dictionary(Int32, Utf8)'), y)) AS first, arrow_typeof(last_by(arrow_cast(x, 'Dictionary(Int32, Utf8)'), y)) AS last FROM VALUES ('a', 10), ('b', 5) as tab(x, y);")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------+------+
+    - "| first | last |"
+    - +-------+------+
+    - "| Utf8  | Utf8 |"
+    - +-------+------+
+    "#);
+
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_udaf(AggregateUDF::from(FirstByFunction::new().with_dictionary_output(true)))
+        .with_udaf(AggregateUDF::from(LastByFunction::new().with_dictionary_output(true)));
+
+    let actual = execution
+        .run_and_format("SELECT first_by(arrow_cast(x, 'Dictionary(Int32, Utf8)'), y) AS first, arrow_typeof(last_by(arrow_cast(x, 'Dictionary(Int32, Utf8)'), y)) AS last FROM VALUES ('a', 10), ('b', 5) as tab(x, y);")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------+-------------------------+
+    - "| first | last                    |"
+    - +-------+-------------------------+
+    - "| b     | Dictionary(Int32, Utf8) |"
+    - +-------+-------------------------+
+    "#);
+}
+
+#[tokio::test]
+async fn test_first_by_and_last_by_window() {
+    let mut execution = TestExecution::new().await.unwrap();
+
+    let actual = execution
+        .run_and_format("SELECT i, first_by(x, y) OVER (ORDER BY i ROWS BETWEEN 1 PRECEDING AND 1 FOLLOWING) AS first, last_by(x, y) OVER (ORDER BY i ROWS BETWEEN 1 PRECEDING AND 1 FOLLOWING) AS last FROM VALUES (1, 'a', 10), (2, 'b', 5), (3, 'c', 15), (4, 'd', 8), (5, 'e', null), (6, 'f', 7) as tab(i, x, y) ORDER BY i;")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+-------+------+
+    - "| i | first | last |"
+    - +---+-------+------+
+    - "| 1 | b     | a    |"
+    - "| 2 | b     | c    |"
+    - "| 3 | b     | c    |"
+    - "| 4 | d     | c    |"
+    - "| 5 | f     | d    |"
+    - "| 6 | f     | f    |"
+    - +---+-------+------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT i, first_by(x, y) OVER (ORDER BY i) AS first, last_by(x, y) OVER (ORDER BY i) AS last FROM VALUES (1, 'a', 10), (2, 'b', 5), (3, 'c', 15), (4, 'd', 8) as tab(i, x, y) ORDER BY i;")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+-------+------+
+    - "| i | first | last |"
+    - +---+-------+------+
+    - "| 1 | a     | a    |"
+    - "| 2 | b     | a    |"
+    - "| 3 | b     | c    |"
+    - "| 4 | b     | c    |"
+    - +---+-------+------+
+    "#);
+}
+
+#[tokio::test]
+async fn test_max_by_struct() {
+    let mut execution = TestExecution::new().await.unwrap();
+
+    let actual = execution
+        .run_and_format("SELECT max_by_struct(y, x, name) AS v FROM VALUES (1, 10, 'a'), (2, 5, 'b'), (3, 15, 'c'), (4, 8, 'd') as tab(x, y, name);")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +----------------+
+    - "| v              |"
+    - +----------------+
+    - "| {c0: 3, c1: c} |"
+    - +----------------+
+    "#);
+
+    // Test the arg_max_row alias, rows with a null ordering are ignored
+    let actual = execution
+        .run_and_format("SELECT g, arg_max_row(y, x, name) AS v FROM VALUES ('g1', 1, 10, 'a'), ('g1', 2, null, 'b'), ('g2', 3, 15, null), ('g1', 4, 8, 'd') as tab(g, x, y, name) GROUP BY g ORDER BY g;")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +----+----------------+
+    - "| g  | v              |"
+    - +----+----------------+
+    - "| g1 | {c0: 1, c1: a} |"
+    - "| g2 | {c0: 3, c1: }  |"
+    - +----+----------------+
+    "#);
+
+    // Test accessing a field of the result
+    let actual = execution
+        .run_and_format("SELECT max_by_struct(y, x, name)['c1'] AS name FROM VALUES (1, 10, 'a'), (2, 5, 'b'), (3, 15, 'c'), (4, 8, 'd') as tab(x, y, name);")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +------+
+    - "| name |"
+    - +------+
+    - "| c    |"
+    - +------+
+    "#);
+
+    // Test with an empty set
+    let actual = execution
+        .run_and_format("SELECT max_by_struct(y, x) AS v FROM (SELECT * FROM (VALUES (1, 10)) WHERE 1=0) as tab(x, y);")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+
+    - "| v |"
+    - +---+
+    - "|   |"
+    - +---+
+    "#);
+
+    // Test with a sliding window frame
+    let actual = execution
+        .run_and_format("SELECT i, max_by_struct(y, x, i) OVER (ORDER BY i ROWS BETWEEN 1 PRECEDING AND CURRENT ROW) AS v FROM VALUES (1, 'a', 10), (2, 'b', 5), (3, 'c', 15), (4, 'd', 8) as tab(i, x, y) ORDER BY i;")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+----------------+
+    - "| i | v              |"
+    - +---+----------------+
+    - "| 1 | {c0: a, c1: 1} |"
+    - "| 2 | {c0: a, c1: 1} |"
+    - "| 3 | {c0: c, c1: 3} |"
+    - "| 4 | {c0: c, c1: 3} |"
+    - +---+----------------+
+    "#);
+
+    // Test the fields named after the arguments of the expression function
+    let actual = execution
+        .run_dataframe_and_format(
+            "SELECT * FROM VALUES (1, 10, 'a'), (2, 5, 'b'), (3, 15, 'c'), (4, 8, 'd') as tab(x, y, name);",
+            |df| {
+                df.aggregate(
+                    vec![],
+                    vec![max_by_struct(vec![col("y"), col("x"), col("name")]).alias("v")],
+                )?
+                .select(vec![col("v"), col("v").field("name").alias("name")])
+            },
+        )
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-----------------+------+
+    - "| v               | name |"
+    - +-----------------+------+
+    - "| {x: 3, name: c} | c    |"
+    - +-----------------+------+
+    "#);
+
+    // Test the columns of the same name from different relations, whose fields are suffixed
+    let actual = execution
+        .run_dataframe_and_format(
+            "SELECT * FROM (VALUES (1, 10), (2, 5)) AS t1(ts, price) JOIN (VALUES (1, 20), (2, 30)) AS t2(ts, price) ON t1.ts = t2.ts",
+            |df| {
+                df.aggregate(vec![], vec![max_by_struct(vec![col("t1.ts"), col("t1.price"), col("t2.price")]).alias("v")])?
+                    .select(vec![col("v"), col("v").field("price_1").alias("price_1")])
+            },
+        )
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------------------------+---------+
+    - "| v                       | price_1 |"
+    - +-------------------------+---------+
+    - "| {price: 5, price_1: 30} | 30      |"
+    - +-------------------------+---------+
+    "#);
+}
+
+#[tokio::test]
+async fn test_skewness_pop() {
+    let mut execution = TestExecution::new().await.unwrap().with_setup(TEST_TABLE).await;
+
+    let actual = execution
+        .run_and_format("SELECT skewness_pop(int64_col) FROM test_table")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +------------------------------------+
+    - "| skewness_pop(test_table.int64_col) |"
+    - +------------------------------------+
+    - "| -0.6260990336999415                |"
+    - +------------------------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT skewness_pop(col) FROM VALUES (1.0), (10.0), (100.0), (10.0), (1.0) as tab(col)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-----------------------+
+    - "| skewness_pop(tab.col) |"
+    - +-----------------------+
+    - "| 1.4581249961461296    |"
+    - +-----------------------+
+    "#);
+
+    // Test with a single value, which has no variance
+    let actual = execution.run_and_format("SELECT skewness_pop(1.0)").await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------------------------+
+    - "| skewness_pop(Float64(1)) |"
+    - +--------------------------+
+    - "|                          |"
+    - +--------------------------+
+    "#);
+
+    let actual = execution.run_and_format("SELECT skewness_pop(null)").await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------------------+
+    - "| skewness_pop(NULL) |"
+    - +--------------------+
+    - "|                    |"
+    - +--------------------+
+    "#);
+}
+
+#[tokio::test]
+async fn test_skewness_definition_config() {
+    let mut execution = TestExecution::new().await.unwrap().with_setup(TEST_TABLE).await;
+
+    let actual = execution
+        .run_and_format("SELECT skewness(int64_col), skewness_samp(int64_col) FROM test_table")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------------------------------+-------------------------------------+
+    - "| skewness(test_table.int64_col) | skewness_samp(test_table.int64_col) |"
+    - +--------------------------------+-------------------------------------+
+    - "| -0.8573214099741129            | -0.8573214099741129                 |"
+    - +--------------------------------+-------------------------------------+
+    "#);
+
+    execution
+        .run("SET functions_extra.skewness = 'population'")
+        .await
+        .unwrap();
+
+    // The bare skewness follows the session option, skewness_samp does not
+    let actual = execution
+        .run_and_format("SELECT skewness(int64_col), skewness_samp(int64_col), skewness_pop(int64_col) FROM test_table")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------------------------------+-------------------------------------+------------------------------------+
+    - "| skewness(test_table.int64_col) | skewness_samp(test_table.int64_col) | skewness_pop(test_table.int64_col) |"
+    - +--------------------------------+-------------------------------------+------------------------------------+
+    - "| -0.6260990336999415            | -0.8573214099741129                 | -0.6260990336999415                |"
+    - +--------------------------------+-------------------------------------+------------------------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT skewness(int64_col) OVER () AS skewness FROM test_table LIMIT 1")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---------------------+
+    - "| skewness            |"
+    - +---------------------+
+    - "| -0.6260990336999415 |"
+    - +---------------------+
+    "#);
+
+    let err = execution
+        .run("SET functions_extra.skewness = 'unbiased'")
+        .await
+        .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .contains("Unknown skewness definition 'unbiased', expected 'sample' or 'population'"));
+}
+
+#[tokio::test]
+async fn test_moments_exact_inputs() {
+    let mut execution = TestExecution::new().await.unwrap();
+
+    // Integers far from zero compared to their spread, the moments match the ones of 1, 2, 2, 3, 3, 3
+    let actual = execution
+        .run_and_format("SELECT kurtosis_pop(col) AS kurtosis_pop, skewness_pop(col) AS skewness_pop, kurtosis(col) AS kurtosis, skewness(col) AS skewness FROM VALUES (1000000000000001), (1000000000000002), (1000000000000002), (1000000000000003), (1000000000000003), (1000000000000003) as tab(col)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---------------------+---------------------+---------------------+---------------------+
+    - "| kurtosis_pop        | skewness_pop        | kurtosis            | skewness            |"
+    - +---------------------+---------------------+---------------------+---------------------+
+    - "| -0.9600000000000044 | -0.6260990336999415 | -0.3000000000000136 | -0.8573214099741129 |"
+    - +---------------------+---------------------+---------------------+---------------------+
+    "#);
+
+    // Decimals are accumulated without casting them to floats
+    let actual = execution
+        .run_and_format("SELECT kurtosis_pop(col) AS kurtosis_pop, skewness_pop(col) AS skewness_pop, arrow_typeof(col) AS type FROM (SELECT arrow_cast(col, 'Decimal128(38, 10)') AS col FROM VALUES ('123456789012.0000000001'), ('123456789012.0000000002'), ('123456789012.0000000002'), ('123456789012.0000000003'), ('123456789012.0000000003'), ('123456789012.0000000003') as tab(col)) GROUP BY type")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---------------------+---------------------+--------------------+
+    - "| kurtosis_pop        | skewness_pop        | type               |"
+    - +---------------------+---------------------+--------------------+
+    - "| -0.9600000000000044 | -0.6260990336999416 | Decimal128(38, 10) |"
+    - +---------------------+---------------------+--------------------+
+    "#);
+
+    // Unsigned integers above i64::MAX
+    let actual = execution
+        .run_and_format("SELECT kurtosis_pop(col) AS kurtosis_pop FROM (SELECT arrow_cast(col, 'UInt64') AS col FROM VALUES ('18446744073709551611'), ('18446744073709551612'), ('18446744073709551612'), ('18446744073709551613'), ('18446744073709551613'), ('18446744073709551613') as tab(col))")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---------------------+
+    - "| kurtosis_pop        |"
+    - +---------------------+
+    - "| -0.9600000000000044 |"
+    - +---------------------+
+    "#);
+}
+
+#[tokio::test]
+async fn test_moment_and_standardized_moment() {
+    let mut execution = TestExecution::new().await.unwrap().with_setup(TEST_TABLE).await;
+
+    // The second central moment is the population variance, the third standardized moment the population skewness
+    let actual = execution
+        .run_and_format("SELECT moment(col, 2) AS m2, var_pop(col) AS var_pop, moment(col, 3) AS m3, standardized_moment(col, 3) AS s3, skewness_pop(col) AS skewness_pop, standardized_moment(col, 4) AS s4 FROM VALUES (1.0), (2.0), (2.0), (3.0), (3.0), (3.0) as tab(col)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------------------+--------------------+---------------------+--------------------+--------------------+--------------------+
+    - "| m2                 | var_pop            | m3                  | s3                 | skewness_pop       | s4                 |"
+    - +--------------------+--------------------+---------------------+--------------------+--------------------+--------------------+
+    - "| 0.5555555555555548 | 0.5555555555555555 | -0.2592592592592595 | -0.626099033699943 | -0.626099033699943 | 2.0400000000000174 |"
+    - +--------------------+--------------------+---------------------+--------------------+--------------------+--------------------+
+    "#);
+
+    // Exact inputs are accumulated like in the other moment aggregates
+    let actual = execution
+        .run_and_format("SELECT moment(int64_col, 1) AS m1, moment(int64_col, 2) AS m2, standardized_moment(int64_col, 2) AS s2, standardized_moment(arrow_cast(int64_col, 'Decimal128(10, 2)'), 3) AS s3 FROM test_table")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-----+--------------------+-----+---------------------+
+    - "| m1  | m2                 | s2  | s3                  |"
+    - +-----+--------------------+-----+---------------------+
+    - "| 0.0 | 0.5555555555555557 | 1.0 | -0.6260990336999394 |"
+    - +-----+--------------------+-----+---------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT moment(col, 2) AS m2, standardized_moment(col, 3) AS s3 FROM VALUES (1.0), (1.0), (null) as tab(col)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-----+----+
+    - "| m2  | s3 |"
+    - +-----+----+
+    - "| 0.0 |    |"
+    - +-----+----+
+    "#);
+
+    let err = execution
+        .run("SELECT moment(int64_col, 0) FROM test_table")
+        .await
+        .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .contains("The order of moment must be a constant positive integer"));
+
+    let err = execution
+        .run("SELECT standardized_moment(int64_col, int64_col) FROM test_table")
+        .await
+        .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .contains("The order of standardized_moment must be a constant positive integer"));
+
+    let err = execution
+        .run("SELECT moment(col, 200000000) FROM VALUES (1.0) as tab(col)")
+        .await
+        .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .contains("The order of moment must be at most 16, got 200000000"));
+
+    // only numbers, and strings parsed as numbers, are accepted, the moments of other types are rejected when planning
+    for (sql, message) in [
+        (
+            "SELECT moment(date64_col, 2) FROM test_table",
+            "moment does not support values of type Date32",
+        ),
+        (
+            "SELECT skewness(date64_col) FROM test_table",
+            "skewness does not support values of type Date32",
+        ),
+        (
+            "SELECT kurtosis(x) FROM VALUES (true), (false) as tab(x)",
+            "kurtosis does not support values of type Boolean",
+        ),
+        (
+            "SELECT weighted_skewness(x, 1.0) FROM VALUES (true) as tab(x)",
+            "weighted_skewness does not support values of type Boolean",
+        ),
+    ] {
+        let err = execution.run(sql).await.unwrap_err();
+        assert!(err.to_string().contains(message), "{err}");
+    }
+
+    let actual = execution
+        .run_and_format("SELECT skewness_pop(arrow_cast(x, 'Decimal256(40, 2)')) AS decimal256, skewness_pop(x) AS float FROM VALUES (1.5), (2.5), (10.5) as tab(x)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------------------+--------------------+
+    - "| decimal256         | float              |"
+    - +--------------------+--------------------+
+    - "| 0.6745554845457647 | 0.6745554845457647 |"
+    - +--------------------+--------------------+
+    "#);
+}
+
+#[tokio::test]
+async fn test_describe_stats() {
+    let mut execution = TestExecution::new().await.unwrap().with_setup(TEST_TABLE).await;
+
+    let actual = execution
+        .run_and_format(
+            "SELECT describe_stats(col) AS stats FROM VALUES (1.0), (10.0), (100.0), (10.0), (1.0), (null) as tab(col)",
+        )
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +------------------------------------------------------------------------------------------------------------------------------------------------------+
+    - "| stats                                                                                                                                                |"
+    - +------------------------------------------------------------------------------------------------------------------------------------------------------+
+    - "| {count: 5, mean: 24.4, stddev: 42.50058823122334, variance: 1806.3, min: 1.0, max: 100.0, skewness: 2.1736444073829095, kurtosis: 4.777292927667962} |"
+    - +------------------------------------------------------------------------------------------------------------------------------------------------------+
+    "#);
+
+    // Each statistic matches the one of the dedicated aggregate
+    let actual = execution
+        .run_and_format("SELECT stats['count'] = count AS count, round(stats['mean'], 12) = round(mean, 12) AS mean, round(stats['stddev'], 12) = round(stddev, 12) AS stddev, round(stats['variance'], 12) = round(variance, 12) AS variance, stats['min'] = min AS min, stats['max'] = max AS max, stats['skewness'] = skewness AS skewness, stats['kurtosis'] = kurtosis AS kurtosis FROM (SELECT describe_stats(int64_col) AS stats, count(int64_col) AS count, avg(int64_col) AS mean, stddev(int64_col) AS stddev, var(int64_col) AS variance, min(int64_col) AS min, max(int64_col) AS max, skewness(int64_col) AS skewness, kurtosis(int64_col) AS kurtosis FROM test_table)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------+------+--------+----------+------+------+----------+----------+
+    - "| count | mean | stddev | variance | min  | max  | skewness | kurtosis |"
+    - +-------+------+--------+----------+------+------+----------+----------+
+    - "| true  | true | true   | true     | true | true | true     | true     |"
+    - +-------+------+--------+----------+------+------+----------+----------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT utf8_col, describe_stats(arrow_cast(int64_col, 'Decimal128(10, 2)')) AS stats FROM test_table GROUP BY utf8_col ORDER BY utf8_col")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +----------+----------------------------------------------------------------------------------------------------------------+
+    - "| utf8_col | stats                                                                                                          |"
+    - +----------+----------------------------------------------------------------------------------------------------------------+
+    - "| apple    | {count: 3, mean: 2.0, stddev: 1.0, variance: 1.0, min: 1.00, max: 3.00, skewness: 0.0, kurtosis: }             |"
+    - "| banana   | {count: 2, mean: 2.5, stddev: 0.7071067811865476, variance: 0.5, min: 2.00, max: 3.00, skewness: , kurtosis: } |"
+    - "| orange   | {count: 1, mean: 3.0, stddev: , variance: , min: 3.00, max: 3.00, skewness: , kurtosis: }                      |"
+    - "|          | {count: 0, mean: , stddev: , variance: , min: , max: , skewness: , kurtosis: }                                 |"
+    - +----------+----------------------------------------------------------------------------------------------------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT describe_stats(col) AS stats FROM VALUES (null::double) as tab(col)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------------------------------------------------------------------------------+
+    - "| stats                                                                          |"
+    - +--------------------------------------------------------------------------------+
+    - "| {count: 0, mean: , stddev: , variance: , min: , max: , skewness: , kurtosis: } |"
+    - +--------------------------------------------------------------------------------+
+    "#);
+}
+
+#[tokio::test]
+async fn test_weighted_moments() {
+    let mut execution = TestExecution::new().await.unwrap();
+
+    let actual = execution
+        .run_and_format("SELECT weighted_skewness(x, w) AS skewness, weighted_kurtosis(x, w) AS kurtosis, weighted_kurtosis_pop(x, w) AS kurtosis_pop FROM VALUES (1.0, 2), (10.0, 1), (100.0, 1), (10.0, 3), (1.0, 0), (5.0, null), (null, 4) as tab(x, w)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------------------+-------------------+--------------------+
+    - "| skewness          | kurtosis          | kurtosis_pop       |"
+    - +-------------------+-------------------+--------------------+
+    - "| 2.564637702894835 | 6.686400000000006 | 2.0360000000000023 |"
+    - +-------------------+-------------------+--------------------+
+    "#);
+
+    // Integer weights give the same results as repeating each row as many times as its weight
+    for x in ["x", "arrow_cast(x, 'Int64')", "arrow_cast(x, 'Decimal128(10, 2)')"] {
+        let actual = execution
+            .run_and_format(&format!("WITH tab(x, w) AS (VALUES (1.0, 2), (10.0, 1), (100.0, 1), (10.0, 3), (1.0, 0), (7.0, 2)), weighted AS (SELECT weighted_skewness({x}, w) AS skewness, weighted_kurtosis({x}, w) AS kurtosis, weighted_kurtosis_pop({x}, w) AS kurtosis_pop FROM tab), expanded AS (SELECT skewness({x}) AS skewness, kurtosis({x}) AS kurtosis, kurtosis_pop({x}) AS kurtosis_pop FROM (SELECT x, unnest(range(w)) FROM tab)) SELECT round(weighted.skewness, 12) = round(expanded.skewness, 12) AS skewness, round(weighted.kurtosis, 12) = round(expanded.kurtosis, 12) AS kurtosis, round(weighted.kurtosis_pop, 12) = round(expanded.kurtosis_pop, 12) AS kurtosis_pop FROM weighted, expanded"))
+            .await;
+
+        insta::allow_duplicates! {
+            insta::assert_yaml_snapshot!(actual, @r#"
+            - +----------+----------+--------------+
+            - "| skewness | kurtosis | kurtosis_pop |"
+            - +----------+----------+--------------+
+            - "| true     | true     | true         |"
+            - +----------+----------+--------------+
+            "#);
+        }
+    }
+
+    let err = execution
+        .run("SELECT weighted_skewness(x, w) FROM VALUES (1.0, -1.0) as tab(x, w)")
+        .await
+        .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .contains("Weights must be finite and non-negative, got -1"));
+}
+
+#[tokio::test]
+async fn test_mad() {
+    let mut execution = TestExecution::new().await.unwrap().with_setup(TEST_TABLE).await;
+
+    let actual = execution
+        .run_and_format("SELECT mad(int64_col) AS int64, mad(float64_col) AS float64, mad(arrow_cast(int64_col, 'Decimal128(10, 2)')) AS decimal, mad(date64_col) AS date, mad(time64_col) AS time FROM test_table")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------+---------+---------+----------+---------+
+    - "| int64 | float64 | decimal | date     | time    |"
+    - +-------+---------+---------+----------+---------+
+    - "| 0.5   | 0.5     | 0.50    | 12 hours | 30 mins |"
+    - +-------+---------+---------+----------+---------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT mad(col) AS mad FROM VALUES (1.0), (2.0), (3.0), (4.0), (100.0), (null) as tab(col)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-----+
+    - "| mad |"
+    - +-----+
+    - "| 1.0 |"
+    - +-----+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT mad(col) AS mad, arrow_typeof(mad(col)) AS type FROM (SELECT arrow_cast(col, 'Timestamp(Microsecond, None)') AS col FROM VALUES ('2024-01-01T00:00:00'), ('2024-01-03T00:00:00'), ('2024-01-04T06:00:00'), ('2024-01-10T00:00:00') as tab(col))")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-----------------+------------------------+
+    - "| mad             | type                   |"
+    - +-----------------+------------------------+
+    - "| 1 days 15 hours | Interval(MonthDayNano) |"
+    - +-----------------+------------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT utf8_col, mad(int64_col) AS mad FROM test_table GROUP BY utf8_col ORDER BY utf8_col")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +----------+-----+
+    - "| utf8_col | mad |"
+    - +----------+-----+
+    - "| apple    | 1.0 |"
+    - "| banana   | 0.5 |"
+    - "| orange   | 0.0 |"
+    - "|          |     |"
+    - +----------+-----+
+    "#);
+
+    let err = execution.run("SELECT mad(utf8_col) FROM test_table").await.unwrap_err();
+
+    assert!(err.to_string().contains("mad does not support values of type Utf8"));
+
+    // the distances between extreme decimals of opposite signs do not fit in an i128
+    let actual = execution
+        .run_and_format("SELECT mad(x) AS mad, quantile_cont(x, 0.5) AS median, quantile_cont(x, 0.75) AS q3, mad(CASE WHEN i = 1 THEN -x ELSE x END) AS skewed FROM (SELECT i, arrow_cast(x, 'Decimal128(38, 0)') AS x FROM (VALUES (1, '99999999999999999999999999999999999999'), (2, '-99999999999999999999999999999999999999'), (3, '99999999999999999999999999999999999999'), (4, '-99999999999999999999999999999999999999')) AS t(i, x))")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +----------------------------------------+--------+----------------------------------------+--------+
+    - "| mad                                    | median | q3                                     | skewed |"
+    - +----------------------------------------+--------+----------------------------------------+--------+
+    - "| 99999999999999999999999999999999999999 | 0      | 99999999999999999999999999999999999999 | 0      |"
+    - +----------------------------------------+--------+----------------------------------------+--------+
+    "#);
+}
+
+#[tokio::test]
+async fn test_mad_partitioned() {
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_setup("SET datafusion.execution.target_partitions = 4; SET datafusion.execution.batch_size = 2;")
+        .await;
+
+    // Partial states of several partitions are merged
+    let actual = execution
+        .run_and_format("SELECT x % 3 AS key, mad(x) AS mad, mad(arrow_cast(x, 'Decimal128(10, 1)')) AS decimal FROM (SELECT unnest(range(30)) AS x) GROUP BY key ORDER BY key")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-----+-----+---------+
+    - "| key | mad | decimal |"
+    - +-----+-----+---------+
+    - "| 0   | 7.5 | 7.5     |"
+    - "| 1   | 7.5 | 7.5     |"
+    - "| 2   | 7.5 | 7.5     |"
+    - +-----+-----+---------+
+    "#);
+}
+
+#[tokio::test]
+async fn test_quantile_cont_and_quantile_disc() {
+    let mut execution = TestExecution::new().await.unwrap().with_setup(TEST_TABLE).await;
+
+    let actual = execution
+        .run_and_format("SELECT quantile_cont(col, 0.5) AS cont, quantile_disc(col, 0.5) AS disc, quantile_cont(col, [0.0, 0.25, 0.5, 1.0]) AS cont_list, quantile_disc(col, [0.0, 0.25, 0.5, 1.0]) AS disc_list FROM VALUES (4), (1), (2), (10), (null) as tab(col)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +------+------+------------------------+---------------+
+    - "| cont | disc | cont_list              | disc_list     |"
+    - +------+------+------------------------+---------------+
+    - "| 3.0  | 2    | [1.0, 1.75, 3.0, 10.0] | [1, 1, 2, 10] |"
+    - +------+------+------------------------+---------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT quantile_cont(arrow_cast(int64_col, 'Decimal128(10, 2)'), 0.3) AS decimal, quantile_cont(date64_col, 0.5) AS date, quantile_disc(date64_col, 0.5) AS date_disc, quantile_cont(time64_col, [0.1, 0.9]) AS time FROM test_table")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---------+---------------------+------------+----------------------+
+    - "| decimal | date                | date_disc  | time                 |"
+    - +---------+---------------------+------------+----------------------+
+    - "| 2.00    | 2021-01-02T12:00:00 | 2021-01-02 | [01:30:00, 03:00:00] |"
+    - +---------+---------------------+------------+----------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT quantile_cont(col, [0.5, 0.75]) AS cont, quantile_disc(col, 0.75) AS disc, arrow_typeof(quantile_disc(col, 0.75)) AS type FROM (SELECT arrow_cast(col, 'Timestamp(Nanosecond, None)') AS col FROM VALUES ('2024-01-01T00:00:00'), ('2024-01-02T00:00:01'), ('2024-01-10T00:00:00') as tab(col))")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +------------------------------------------------+---------------------+-----------------------------+
+    - "| cont                                           | disc                | type                        |"
+    - +------------------------------------------------+---------------------+-----------------------------+
+    - "| [2024-01-02T00:00:01, 2024-01-06T00:00:00.500] | 2024-01-10T00:00:00 | Timestamp(Nanosecond, None) |"
+    - +------------------------------------------------+---------------------+-----------------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT utf8_col, quantile_cont(int64_col, [0.5]) AS cont, quantile_disc(int64_col, 0.5) AS disc FROM test_table GROUP BY utf8_col ORDER BY utf8_col")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +----------+-------+------+
+    - "| utf8_col | cont  | disc |"
+    - +----------+-------+------+
+    - "| apple    | [2.0] | 2    |"
+    - "| banana   | [2.5] | 2    |"
+    - "| orange   | [3.0] | 3    |"
+    - "|          |       |      |"
+    - +----------+-------+------+
+    "#);
+
+    let err = execution
+        .run("SELECT quantile_cont(int64_col, 1.5) FROM test_table")
+        .await
+        .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .contains("The quantiles of quantile_cont must be between 0 and 1, got 1.5"));
+
+    let err = execution
+        .run("SELECT quantile_disc(int64_col, float64_col) FROM test_table")
+        .await
+        .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .contains("The quantiles of quantile_disc must be constants"));
+}
+
+#[tokio::test]
+async fn test_kll_quantile_sketches() {
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_setup("SET datafusion.execution.target_partitions = 4; SET datafusion.execution.batch_size = 100;")
+        .await
+        .with_setup(TEST_TABLE)
+        .await;
+
+    let actual = execution
+        .run_and_format("SELECT approx_quantile_kll(col, 0.5) AS median, approx_quantile_kll(col, 0.0, 8) AS min, kll_estimate(kll_sketch(col), 0.75) AS q75, quantile_disc(col, 0.75) AS exact FROM VALUES (4), (1), (2), (10), (null) as tab(col)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------+-----+-----+-------+
+    - "| median | min | q75 | exact |"
+    - +--------+-----+-----+-------+
+    - "| 2.0    | 1.0 | 4.0 | 4     |"
+    - +--------+-----+-----+-------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT approx_quantile_kll(x, 0.9) BETWEEN 88000 AND 92000 AS q90, kll_estimate(kll_sketch(x, 400), 0.1) BETWEEN 9000 AND 11000 AS q10, approx_quantile_kll(x, 1.0) AS max FROM (SELECT unnest(range(100000)) AS x)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +------+------+---------+
+    - "| q90  | q10  | max     |"
+    - +------+------+---------+
+    - "| true | true | 99999.0 |"
+    - +------+------+---------+
+    "#);
+
+    execution
+        .run("CREATE TABLE daily AS SELECT x % 7 AS day, kll_sketch(x) AS sketch FROM (SELECT unnest(range(10000)) AS x) GROUP BY x % 7")
+        .await
+        .unwrap();
+
+    let actual = execution
+        .run_and_format("SELECT arrow_typeof(kll_merge(sketch)) AS type, kll_estimate(kll_merge(sketch), 0.5) BETWEEN 4800 AND 5200 AS median, kll_estimate(kll_merge(sketch), 0.0) AS min, kll_estimate(kll_merge(sketch), NULL) AS null_q FROM daily")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------+--------+-----+--------+
+    - "| type   | median | min | null_q |"
+    - +--------+--------+-----+--------+
+    - "| Binary | true   | 0.0 |        |"
+    - +--------+--------+-----+--------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT utf8_col, approx_quantile_kll(int64_col, 0.5) AS median, kll_estimate(kll_sketch(int64_col), 0.5) AS estimate FROM test_table GROUP BY utf8_col ORDER BY utf8_col")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +----------+--------+----------+
+    - "| utf8_col | median | estimate |"
+    - +----------+--------+----------+
+    - "| apple    | 2.0    | 2.0      |"
+    - "| banana   | 2.0    | 2.0      |"
+    - "| orange   | 3.0    | 3.0      |"
+    - "|          |        |          |"
+    - +----------+--------+----------+
+    "#);
+
+    let err = execution
+        .run("SELECT approx_quantile_kll(int64_col, 0.5, 4) FROM test_table")
+        .await
+        .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .contains("The k of approx_quantile_kll must be between 8 and 65535, got 4"));
+
+    let err = execution
+        .run("SELECT kll_estimate(arrow_cast('not a sketch', 'Binary'), 0.5)")
+        .await
+        .unwrap_err();
+
+    assert!(err.to_string().contains("Invalid KLL sketch"));
+
+    let err = execution
+        .run("SELECT kll_merge(sketch) FROM (SELECT tdigest_sketch(int64_col) AS sketch FROM test_table)")
+        .await
+        .unwrap_err();
+
+    assert!(err.to_string().contains("Invalid KLL sketch: unexpected sketch family"));
+}
+
+#[tokio::test]
+async fn test_tdigest_quantile_sketches() {
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_setup("SET datafusion.execution.target_partitions = 4; SET datafusion.execution.batch_size = 100;")
+        .await
+        .with_setup(TEST_TABLE)
+        .await;
+
+    let actual = execution
+        .run_and_format("SELECT approx_quantile_tdigest(col, 0.5) AS median, approx_quantile_tdigest(col, 1.0, 10) AS max, tdigest_estimate(tdigest_sketch(col), 0.0) AS min FROM VALUES (4), (1), (2), (10), (null) as tab(col)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------+------+-----+
+    - "| median | max  | min |"
+    - +--------+------+-----+
+    - "| 2.0    | 10.0 | 1.0 |"
+    - +--------+------+-----+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT approx_quantile_tdigest(x, 0.99) BETWEEN 98500 AND 99500 AS q99, tdigest_estimate(tdigest_sketch(x, 500), 0.5) BETWEEN 49000 AND 51000 AS median FROM (SELECT unnest(range(100000)) AS x)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +------+--------+
+    - "| q99  | median |"
+    - +------+--------+
+    - "| true | true   |"
+    - +------+--------+
+    "#);
+
+    execution
+        .run("CREATE TABLE daily AS SELECT x % 7 AS day, tdigest_sketch(x) AS sketch FROM (SELECT unnest(range(10000)) AS x) GROUP BY x % 7")
+        .await
+        .unwrap();
+
+    let actual = execution
+        .run_and_format("SELECT tdigest_estimate(tdigest_merge(sketch), 0.5) BETWEEN 4800 AND 5200 AS median, tdigest_estimate(tdigest_merge(sketch), 1.0) AS max, tdigest_estimate(tdigest_merge(sketch), q) AS estimate FROM daily, (VALUES (0.0), (1.0)) AS quantiles(q) GROUP BY q ORDER BY q")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------+--------+----------+
+    - "| median | max    | estimate |"
+    - +--------+--------+----------+
+    - "| true   | 9999.0 | 0.0      |"
+    - "| true   | 9999.0 | 9999.0   |"
+    - +--------+--------+----------+
+    "#);
+
+    let err = execution
+        .run("SELECT approx_quantile_tdigest(int64_col, 0.5, 0) FROM test_table")
+        .await
+        .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .contains("The compression of approx_quantile_tdigest must be between 1 and 100000, got 0"));
+
+    let err = execution
+        .run("SELECT tdigest_estimate(tdigest_sketch(int64_col), 2.0) FROM test_table")
+        .await
+        .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .contains("The quantile of tdigest_estimate must be between 0 and 1, got 2"));
+}
+
+#[tokio::test]
+async fn test_hll_sketches() {
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_setup("SET datafusion.execution.target_partitions = 4; SET datafusion.execution.batch_size = 100;")
+        .await
+        .with_setup(TEST_TABLE)
+        .await;
+
+    let actual = execution
+        .run_and_format("SELECT hll_sketch_estimate(hll_sketch_agg(utf8_col)) AS strings, hll_sketch_estimate(hll_sketch_agg(int64_col, 4)) AS ints, hll_sketch_estimate(hll_sketch_agg(float64_col)) AS floats, (SELECT hll_sketch_agg(int64_col) FROM test_table WHERE int64_col > 100) AS empty FROM test_table")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---------+------+--------+------------------+
+    - "| strings | ints | floats | empty            |"
+    - +---------+------+--------+------------------+
+    - "| 3       | 3    | 3      | 0201070c030c0008 |"
+    - +---------+------+--------+------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT hll_sketch_estimate(hll_sketch_agg(x, 14)) BETWEEN 97000 AND 103000 AS estimate, hll_sketch_estimate(hll_sketch_agg(x)) BETWEEN 95000 AND 105000 AS default_lg_k FROM (SELECT unnest(range(100000)) AS x)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +----------+--------------+
+    - "| estimate | default_lg_k |"
+    - +----------+--------------+
+    - "| true     | true         |"
+    - +----------+--------------+
+    "#);
+
+    execution
+        .run("CREATE TABLE daily AS SELECT x % 7 AS day, hll_sketch_agg(CAST(x % 5000 AS VARCHAR)) AS sketch FROM (SELECT unnest(range(20000)) AS x) GROUP BY x % 7")
+        .await
+        .unwrap();
+
+    let actual = execution
+        .run_and_format("SELECT hll_sketch_estimate(hll_union_agg(sketch)) BETWEEN 4850 AND 5150 AS weekly, sum(hll_sketch_estimate(sketch)) BETWEEN 19000 AND 21000 AS daily_total, hll_sketch_estimate(NULL) AS null_sketch FROM daily")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------+-------------+-------------+
+    - "| weekly | daily_total | null_sketch |"
+    - +--------+-------------+-------------+
+    - "| true   | true        |             |"
+    - +--------+-------------+-------------+
+    "#);
+
+    let err = execution
+        .run("SELECT hll_sketch_agg(int64_col, 3) FROM test_table")
+        .await
+        .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .contains("The lg_k of hll_sketch_agg must be between 4 and 21, got 3"));
+
+    let err = execution
+        .run("SELECT hll_sketch_estimate(arrow_cast('not a sketch', 'Binary'))")
+        .await
+        .unwrap_err();
+
+    assert!(err.to_string().contains("Invalid HLL sketch"));
+}
+
+#[tokio::test]
+async fn test_theta_sketches() {
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_setup("SET datafusion.execution.target_partitions = 4; SET datafusion.execution.batch_size = 100;")
+        .await
+        .with_setup(TEST_TABLE)
+        .await;
+
+    execution
+        .run("CREATE TABLE audiences AS SELECT 'a' AS audience, theta_sketch_agg(x) AS sketch FROM (SELECT unnest(range(0, 60000)) AS x) UNION ALL SELECT 'b' AS audience, theta_sketch_agg(x) AS sketch FROM (SELECT unnest(range(40000, 100000)) AS x) UNION ALL SELECT 'small' AS audience, theta_sketch_agg(utf8_col) AS sketch FROM test_table")
+        .await
+        .unwrap();
+
+    let actual = execution
+        .run_and_format("SELECT audience, theta_estimate(sketch) AS estimate FROM audiences WHERE audience = 'small'")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +----------+----------+
+    - "| audience | estimate |"
+    - +----------+----------+
+    - "| small    | 3.0      |"
+    - +----------+----------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT theta_estimate(theta_intersect(a.sketch, b.sketch)) BETWEEN 19000 AND 21000 AS intersection, theta_estimate(theta_a_not_b(a.sketch, b.sketch)) BETWEEN 38000 AND 42000 AS difference, theta_estimate(theta_intersect(a.sketch, small.sketch)) AS disjoint FROM audiences a, audiences b, audiences small WHERE a.audience = 'a' AND b.audience = 'b' AND small.audience = 'small'")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------------+------------+----------+
+    - "| intersection | difference | disjoint |"
+    - +--------------+------------+----------+
+    - "| true         | true       | 0.0      |"
+    - +--------------+------------+----------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT theta_estimate(theta_union_agg(sketch)) BETWEEN 97000 AND 103000 AS union_estimate, theta_estimate(theta_union_agg(sketch, 14)) BETWEEN 98000 AND 102000 AS union_lg_k, theta_estimate(theta_intersect(NULL, NULL)) AS null_sketch FROM audiences")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +----------------+------------+-------------+
+    - "| union_estimate | union_lg_k | null_sketch |"
+    - +----------------+------------+-------------+
+    - "| true           | true       |             |"
+    - +----------------+------------+-------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT theta_sketch_agg(int64_col) AS empty, theta_sketch_agg(int64_col, 20) AS empty_lg_k FROM test_table WHERE int64_col > 100")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +------------------+------------------+
+    - "| empty            | empty_lg_k       |"
+    - +------------------+------------------+
+    - "| 01030300001ecc93 | 01030300001ecc93 |"
+    - +------------------+------------------+
+    "#);
+
+    let err = execution
+        .run("SELECT theta_sketch_agg(int64_col, 27) FROM test_table")
+        .await
+        .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .contains("The lg_k of theta_sketch_agg must be between 4 and 26, got 27"));
+
+    let err = execution
+        .run("SELECT theta_estimate(hll_sketch_agg(int64_col)) FROM test_table")
+        .await
+        .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .contains("Invalid theta sketch: unexpected sketch family 7"));
+}
+
+#[tokio::test]
+async fn test_count_min_sketch() {
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_setup("SET datafusion.execution.target_partitions = 4; SET datafusion.execution.batch_size = 100;")
+        .await
+        .with_setup(TEST_TABLE)
+        .await;
+
+    execution
+        .run("CREATE TABLE sketches AS SELECT count_min_sketch(x % 1000, 0.001, 0.99, 42) AS numbers, count_min_sketch(CAST(x % 10 AS VARCHAR), 0.01, 0.95, 1) AS strings FROM (SELECT unnest(range(0, 100000)) AS x) WHERE x % 1000 != 0 OR x < 5000")
+        .await
+        .unwrap();
+
+    let actual = execution
+        .run_and_format("SELECT cms_estimate(numbers, 7) BETWEEN 100 AND 300 AS frequent, cms_estimate(numbers, 0) BETWEEN 5 AND 205 AS rare, cms_estimate(numbers, CAST(7 AS INT)) = cms_estimate(numbers, 7) AS coerced, cms_estimate(strings, '3') BETWEEN 9995 AND 11000 AS strings, cms_estimate(numbers, NULL) AS null_value FROM sketches")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +----------+------+---------+---------+------------+
+    - "| frequent | rare | coerced | strings | null_value |"
+    - +----------+------+---------+---------+------------+
+    - "| true     | true | true    | true    |            |"
+    - +----------+------+---------+---------+------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT utf8_col, cms_estimate((SELECT count_min_sketch(utf8_col, 0.1, 0.9, 0) FROM test_table), utf8_col) AS estimate FROM test_table GROUP BY utf8_col ORDER BY utf8_col")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +----------+----------+
+    - "| utf8_col | estimate |"
+    - +----------+----------+
+    - "| apple    | 3        |"
+    - "| banana   | 2        |"
+    - "| orange   | 1        |"
+    - "|          |          |"
+    - +----------+----------+
+    "#);
+
+    let actual = execution
+        .run_and_format(
+            "SELECT count_min_sketch(int64_col, 0.5, 0.5, 0) AS empty FROM test_table WHERE int64_col > 100",
+        )
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +----------------------------------------------------------------------------------------------------------------------+
+    - "| empty                                                                                                                |"
+    - +----------------------------------------------------------------------------------------------------------------------+
+    - "| 43010100000004000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000 |"
+    - +----------------------------------------------------------------------------------------------------------------------+
+    "#);
+
+    let err = execution
+        .run("SELECT count_min_sketch(int64_col, 0.0, 0.9, 0) FROM test_table")
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("The eps of a count-min sketch must be between 0 and 1 (exclusive), got 0"));
+
+    let err = execution
+        .run("SELECT count_min_sketch(int64_col, 0.1, 0.9, int64_col) FROM test_table")
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("The seed of count_min_sketch must be a constant integer"));
+
+    let err = execution
+        .run("SELECT count_min_sketch(float64_col, 0.1, 0.9, 0) FROM test_table")
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("count_min_sketch does not support values of type Float64"));
+
+    let err = execution.run("SELECT cms_estimate(X'4301', 1)").await.unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Invalid count-min sketch: unexpected end of input"));
+}
+
+#[tokio::test]
+async fn test_bloom_filter() {
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_setup("SET datafusion.execution.target_partitions = 4; SET datafusion.execution.batch_size = 100;")
+        .await
+        .with_setup(TEST_TABLE)
+        .await;
+
+    execution
+        .run("CREATE TABLE filters AS SELECT bloom_filter_agg(x * 2, 10000) AS evens, bloom_filter_agg(CAST(x AS VARCHAR), 10000, 100000) AS strings FROM (SELECT unnest(range(0, 10000)) AS x)")
+        .await
+        .unwrap();
+
+    let actual = execution
+        .run_and_format("SELECT bool_and(might_contain(evens, x * 2)) AS all_evens, sum(CASE WHEN might_contain(evens, x * 2 + 1) THEN 1 ELSE 0 END) < 300 AS few_odds, bool_and(might_contain(strings, CAST(x AS VARCHAR))) AS all_strings, sum(CASE WHEN might_contain(strings, CAST(x + 10000 AS VARCHAR)) THEN 1 ELSE 0 END) < 300 AS few_other_strings FROM filters, (SELECT unnest(range(0, 10000)) AS x)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-----------+----------+-------------+-------------------+
+    - "| all_evens | few_odds | all_strings | few_other_strings |"
+    - +-----------+----------+-------------+-------------------+
+    - "| true      | true     | true        | true              |"
+    - +-----------+----------+-------------+-------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT bloom_filter_agg(int64_col, 3, 64) AS filter, might_contain(bloom_filter_agg(int64_col, 3, 64), 2) AS two, might_contain(bloom_filter_agg(int64_col, 3, 64), 4) AS four FROM test_table")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +------------------------------------------+------+-------+
+    - "| filter                                   | two  | four  |"
+    - +------------------------------------------+------+-------+
+    - "| 000000010000000f0000000153ce1432af09b20e | true | false |"
+    - +------------------------------------------+------+-------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT utf8_col, might_contain((SELECT bloom_filter_agg(utf8_col) FROM test_table WHERE int64_col < 3), utf8_col) AS maybe FROM test_table GROUP BY utf8_col ORDER BY utf8_col")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +----------+-------+
+    - "| utf8_col | maybe |"
+    - +----------+-------+
+    - "| apple    | true  |"
+    - "| banana   | true  |"
+    - "| orange   | false |"
+    - "|          |       |"
+    - +----------+-------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT bloom_filter_agg(int64_col) AS empty, might_contain(NULL, 1) AS null_filter FROM test_table WHERE int64_col > 100")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------+-------------+
+    - "| empty | null_filter |"
+    - +-------+-------------+
+    - "|       |             |"
+    - +-------+-------------+
+    "#);
+
+    let err = execution
+        .run("SELECT bloom_filter_agg(int64_col, 0) FROM test_table")
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("The expected_items of bloom_filter_agg must be positive, got 0"));
+
+    let err = execution
+        .run("SELECT bloom_filter_agg(float64_col) FROM test_table")
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("bloom_filter_agg does not support values of type Float64"));
+
+    let err = execution.run("SELECT might_contain(X'00000002', 1)").await.unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Invalid Bloom filter sketch: unexpected end of input"));
+}
+
+#[tokio::test]
+async fn test_mode_multiple_partitions() {
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_setup("SET datafusion.execution.target_partitions = 4; SET datafusion.execution.batch_size = 100;")
+        .await;
+
+    let actual = execution
+        .run_and_format("SELECT x % 2 AS parity, mode(x % 7 + x % 2) AS int_mode, mode(CAST(x % 5 AS VARCHAR)) AS utf8_mode, mode(CAST(x % 3 AS DOUBLE)) AS float_mode FROM (SELECT unnest(range(0, 10001)) AS x) GROUP BY x % 2 ORDER BY parity")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------+----------+-----------+------------+
+    - "| parity | int_mode | utf8_mode | float_mode |"
+    - +--------+----------+-----------+------------+
+    - "| 0      | 0        | 0         | 0.0        |"
+    - "| 1      | 2        | 1         | 0.0        |"
+    - +--------+----------+-----------+------------+
+    "#);
+}
+
+#[tokio::test]
+async fn test_histogram() {
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_setup("SET datafusion.execution.target_partitions = 4; SET datafusion.execution.batch_size = 100;")
+        .await
+        .with_setup(TEST_TABLE)
+        .await;
+
+    let actual = execution
+        .run_and_format("SELECT histogram(utf8_col) AS utf8, histogram(int64_col) AS int64, histogram(float64_col) AS float64, histogram(date64_col) AS date64, histogram(time64_col) AS time64 FROM test_table")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +----------------------------------+--------------------+--------------------------+-----------------------------------------------+-----------------------------------------+
+    - "| utf8                             | int64              | float64                  | date64                                        | time64                                  |"
+    - +----------------------------------+--------------------+--------------------------+-----------------------------------------------+-----------------------------------------+
+    - "| {apple: 3, banana: 2, orange: 1} | {1: 1, 2: 2, 3: 3} | {1.0: 1, 2.0: 2, 3.0: 3} | {2021-01-01: 1, 2021-01-02: 2, 2021-01-03: 3} | {01:00:00: 1, 02:00:00: 2, 03:00:00: 3} |"
+    - +----------------------------------+--------------------+--------------------------+-----------------------------------------------+-----------------------------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT x % 2 AS parity, histogram(x % 5) AS numbers, histogram(arrow_cast(CAST(x % 3 AS VARCHAR), 'Utf8View')) AS views, histogram(arrow_cast(CAST(x % 3 AS VARCHAR), 'LargeUtf8')) AS large FROM (SELECT unnest(range(0, 1000)) AS x) GROUP BY x % 2 ORDER BY parity")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------+------------------------------------------+--------------------------+--------------------------+
+    - "| parity | numbers                                  | views                    | large                    |"
+    - +--------+------------------------------------------+--------------------------+--------------------------+
+    - "| 0      | {0: 100, 1: 100, 2: 100, 3: 100, 4: 100} | {0: 167, 1: 166, 2: 167} | {0: 167, 1: 166, 2: 167} |"
+    - "| 1      | {0: 100, 1: 100, 2: 100, 3: 100, 4: 100} | {0: 167, 1: 167, 2: 166} | {0: 167, 1: 167, 2: 166} |"
+    - +--------+------------------------------------------+--------------------------+--------------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT histogram(int64_col) AS empty FROM test_table WHERE int64_col > 100")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------+
+    - "| empty |"
+    - +-------+
+    - "|       |"
+    - +-------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT histogram(utf8_col)['apple'] AS apples FROM test_table")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------+
+    - "| apples |"
+    - +--------+
+    - "| 3      |"
+    - +--------+
+    "#);
+
+    // the counts of a growing window frame accumulate
+    let actual = execution
+        .run_and_format("SELECT i, histogram(s) OVER (ORDER BY i) AS utf8, histogram(arrow_cast(s, 'Utf8View')) OVER (ORDER BY i) AS views FROM (VALUES (1, 'a'), (2, 'b'), (3, 'a'), (4, 'c')) AS t(i, s) ORDER BY i")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+--------------------+--------------------+
+    - "| i | utf8               | views              |"
+    - +---+--------------------+--------------------+
+    - "| 1 | {a: 1}             | {a: 1}             |"
+    - "| 2 | {a: 1, b: 1}       | {a: 1, b: 1}       |"
+    - "| 3 | {a: 2, b: 1}       | {a: 2, b: 1}       |"
+    - "| 4 | {a: 2, b: 1, c: 1} | {a: 2, b: 1, c: 1} |"
+    - +---+--------------------+--------------------+
+    "#);
+}
+
+#[tokio::test]
+async fn test_binned_histograms() {
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_setup("SET datafusion.execution.target_partitions = 4; SET datafusion.execution.batch_size = 100;")
+        .await
+        .with_setup(TEST_TABLE)
+        .await;
+
+    let actual = execution
+        .run_and_format("SELECT histogram(x, [1000, 0, 10, 100]) AS ints, histogram(x / 10.0, [50.5]) AS floats, histogram(x, [10]) AS above FROM (SELECT unnest(range(0, 2000)) AS x)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-----------------------------------------------------------------------------------+-------------------------------------+----------------------------------+
+    - "| ints                                                                              | floats                              | above                            |"
+    - +-----------------------------------------------------------------------------------+-------------------------------------+----------------------------------+
+    - "| [{x: 0, y: 1}, {x: 10, y: 10}, {x: 100, y: 90}, {x: 1000, y: 900}, {x: , y: 999}] | [{x: 50.5, y: 506}, {x: , y: 1494}] | [{x: 10, y: 11}, {x: , y: 1989}] |"
+    - +-----------------------------------------------------------------------------------+-------------------------------------+----------------------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT histogram(CAST(date64_col AS TIMESTAMP), [TIMESTAMP '2021-01-01', TIMESTAMP '2021-01-02 12:00:00']) AS timestamps, histogram(int64_col, [2]) AS nullable FROM test_table")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------------------------------------------------------------------------------+-----------------------------+
+    - "| timestamps                                                                    | nullable                    |"
+    - +-------------------------------------------------------------------------------+-----------------------------+
+    - "| [{x: 2021-01-01T00:00:00, y: 1}, {x: 2021-01-02T12:00:00, y: 2}, {x: , y: 3}] | [{x: 2, y: 3}, {x: , y: 3}] |"
+    - +-------------------------------------------------------------------------------+-----------------------------+
+    "#);
+
+    // integers binned by float boundaries are not truncated to the integer boundaries
+    let actual = execution
+        .run_and_format("SELECT histogram(x, [-0.5, 10]) AS negative, histogram(x, [0.5, 1.5, 10]) AS fractional FROM VALUES (0), (-1), (1) AS tab(x)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +------------------------------------+---------------------------------------------------+
+    - "| negative                           | fractional                                        |"
+    - +------------------------------------+---------------------------------------------------+
+    - "| [{x: -0.5, y: 1}, {x: 10.0, y: 2}] | [{x: 0.5, y: 2}, {x: 1.5, y: 1}, {x: 10.0, y: 0}] |"
+    - +------------------------------------+---------------------------------------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT histogram_numeric(x % 4 * 1000 + x % 3, 4) AS ints, histogram_numeric(x % 2 * 100.0 + 0.5, 2) AS floats FROM (SELECT unnest(range(0, 1200)) AS x)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-----------------------------------------------------------------------------------+--------------------------------------------+
+    - "| ints                                                                              | floats                                     |"
+    - +-----------------------------------------------------------------------------------+--------------------------------------------+
+    - "| [{x: 1, y: 300.0}, {x: 1001, y: 300.0}, {x: 2001, y: 300.0}, {x: 3001, y: 300.0}] | [{x: 0.5, y: 600.0}, {x: 100.5, y: 600.0}] |"
+    - +-----------------------------------------------------------------------------------+--------------------------------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT histogram_numeric(int64_col, 10) AS exact, histogram_numeric(CAST(date64_col AS TIMESTAMP), 2) AS timestamps FROM test_table")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------------------------------------------------+----------------------------------------------------------------------+
+    - "| exact                                            | timestamps                                                           |"
+    - +--------------------------------------------------+----------------------------------------------------------------------+
+    - "| [{x: 1, y: 1.0}, {x: 2, y: 2.0}, {x: 3, y: 3.0}] | [{x: 2021-01-01T16:00:00, y: 3.0}, {x: 2021-01-03T00:00:00, y: 3.0}] |"
+    - +--------------------------------------------------+----------------------------------------------------------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT histogram(int64_col, [1]) AS binned, histogram_numeric(int64_col, 3) AS adaptive FROM test_table WHERE int64_col > 100")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------+----------+
+    - "| binned | adaptive |"
+    - +--------+----------+
+    - "|        |          |"
+    - +--------+----------+
+    "#);
+
+    let err = execution
+        .run("SELECT histogram_numeric(int64_col, 1) FROM test_table")
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("The nb of histogram_numeric must be at least 2, got 1"));
+
+    let err = execution
+        .run("SELECT histogram(int64_col, make_array(int64_col)) FROM test_table")
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("The bins of histogram must be a constant list"));
+
+    let err = execution
+        .run("SELECT histogram(utf8_col, ['a']) FROM test_table")
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("histogram with bins does not support values of type Utf8"));
+}
+
+#[tokio::test]
+async fn test_entropy() {
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_setup("SET datafusion.execution.target_partitions = 4; SET datafusion.execution.batch_size = 100;")
+        .await
+        .with_setup(TEST_TABLE)
+        .await;
+
+    let actual = execution
+        .run_and_format("SELECT entropy(utf8_col) AS utf8, entropy(arrow_cast(utf8_col, 'Dictionary(Int32, Utf8)')) AS dictionary, entropy(int64_col) AS int64, entropy(float64_col) AS float64, normalized_entropy(utf8_col) AS normalized FROM test_table")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------------------+--------------------+--------------------+--------------------+-------------------+
+    - "| utf8               | dictionary         | int64              | float64            | normalized        |"
+    - +--------------------+--------------------+--------------------+--------------------+-------------------+
+    - "| 1.4591479170272446 | 1.4591479170272446 | 1.4591479170272446 | 1.4591479170272446 | 0.920619835714305 |"
+    - +--------------------+--------------------+--------------------+--------------------+-------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT entropy(x % 8) AS uniform, normalized_entropy(x % 8) AS normalized_uniform, entropy(CAST(x % 2 AS VARCHAR)) AS coin, entropy(arrow_cast(CAST(x % 4 AS VARCHAR), 'Dictionary(Int8, LargeUtf8)')) AS large_dictionary, entropy(x % 1) AS constant, normalized_entropy(x % 1) AS normalized_constant FROM (SELECT unnest(range(0, 4000)) AS x)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---------+--------------------+------+------------------+----------+---------------------+
+    - "| uniform | normalized_uniform | coin | large_dictionary | constant | normalized_constant |"
+    - +---------+--------------------+------+------------------+----------+---------------------+
+    - "| 3.0     | 1.0                | 1.0  | 2.0              | 0.0      | 0.0                 |"
+    - +---------+--------------------+------+------------------+----------+---------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT entropy(utf8_col) AS empty, normalized_entropy(int64_col) AS normalized_empty FROM test_table WHERE int64_col > 100")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------+------------------+
+    - "| empty | normalized_empty |"
+    - +-------+------------------+
+    - "|       |                  |"
+    - +-------+------------------+
+    "#);
+
+    // the frequencies of a growing window frame accumulate, for strings as for numbers
+    let actual = execution
+        .run_and_format("SELECT i, entropy(s) OVER (ORDER BY i) AS utf8, normalized_entropy(arrow_cast(s, 'Utf8View')) OVER (ORDER BY i) AS views, entropy(i % 2) OVER (ORDER BY i) AS numbers FROM (VALUES (1, 'a'), (2, 'b'), (3, 'a'), (4, 'c')) AS t(i, s) ORDER BY i")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+--------------------+--------------------+--------------------+
+    - "| i | utf8               | views              | numbers            |"
+    - +---+--------------------+--------------------+--------------------+
+    - "| 1 | 0.0                | 0.0                | 0.0                |"
+    - "| 2 | 1.0                | 1.0                | 1.0                |"
+    - "| 3 | 0.9182958340544893 | 0.9182958340544893 | 0.9182958340544893 |"
+    - "| 4 | 1.5                | 0.9463946303571862 | 1.0                |"
+    - +---+--------------------+--------------------+--------------------+
+    "#);
+}
+
+#[tokio::test]
+async fn test_uniq() {
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_setup("SET datafusion.execution.target_partitions = 4; SET datafusion.execution.batch_size = 100;")
+        .await
+        .with_setup(TEST_TABLE)
+        .await;
+
+    let actual = execution
+        .run_and_format("SELECT uniq_exact(utf8_col) AS utf8, uniq_exact(arrow_cast(utf8_col, 'LargeUtf8')) AS large_utf8, uniq_exact(arrow_cast(utf8_col, 'Utf8View')) AS utf8_view, uniq_exact(arrow_cast(utf8_col, 'Dictionary(Int32, Utf8)')) AS dictionary, uniq_exact(arrow_cast(utf8_col, 'Binary')) AS binary, uniq_exact(int64_col) AS int64, uniq_exact(float64_col) AS float64, uniq_exact(date64_col) AS date, uniq_combined(utf8_col) AS combined_utf8, uniq_combined(float64_col) AS combined_float64 FROM test_table")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +------+------------+-----------+------------+--------+-------+---------+------+---------------+------------------+
+    - "| utf8 | large_utf8 | utf8_view | dictionary | binary | int64 | float64 | date | combined_utf8 | combined_float64 |"
+    - +------+------------+-----------+------------+--------+-------+---------+------+---------------+------------------+
+    - "| 3    | 3          | 3         | 3          | 3      | 3     | 3       | 3    | 3             | 3                |"
+    - +------+------------+-----------+------------+--------+-------+---------+------+---------------+------------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT uniq_exact(utf8_col) AS empty, uniq_combined(int64_col) AS combined_empty FROM test_table WHERE int64_col > 100")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------+----------------+
+    - "| empty | combined_empty |"
+    - +-------+----------------+
+    - "| 0     | 0              |"
+    - +-------+----------------+
+    "#);
+
+    // many groups, compared with count(DISTINCT ...)
+    let actual = execution
+        .run_and_format("SELECT count(*) AS groups, bool_and(strings = expected) AS strings, bool_and(ints = expected) AS ints, bool_and(filtered = expected_filtered) AS filtered, sum(strings) AS total FROM (SELECT g, uniq_exact(arrow_cast(v, 'Utf8')) AS strings, uniq_exact(v) AS ints, count(DISTINCT v) AS expected, uniq_exact(CASE WHEN selected THEN v END) AS filtered, count(DISTINCT CASE WHEN selected THEN v END) AS expected_filtered FROM (SELECT x % 50 AS g, x % 37 AS v, x % 3 = 0 AS selected FROM (SELECT unnest(range(0, 5000)) AS x)) GROUP BY g)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------+---------+------+----------+-------+
+    - "| groups | strings | ints | filtered | total |"
+    - +--------+---------+------+----------+-------+
+    - "| 50     | true    | true | true     | 1850  |"
+    - +--------+---------+------+----------+-------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT uniq_combined(x % 1000) AS exact, uniq_combined(x, 100) BETWEEN 3950 AND 4050 AS estimated, uniq_combined(CAST(x AS VARCHAR), 0) BETWEEN 3950 AND 4050 AS estimated_strings, uniq_exact(x) AS uniq_exact FROM (SELECT unnest(range(0, 4000)) AS x)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------+-----------+-------------------+------------+
+    - "| exact | estimated | estimated_strings | uniq_exact |"
+    - +-------+-----------+-------------------+------------+
+    - "| 1000  | true      | true              | 4000       |"
+    - +-------+-----------+-------------------+------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT x % 3 AS g, uniq_combined(x, 100) BETWEEN 1300 AND 1370 AS estimated, uniq_combined(x % 10) AS exact FROM (SELECT unnest(range(0, 4000)) AS x) GROUP BY x % 3 ORDER BY g")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+-----------+-------+
+    - "| g | estimated | exact |"
+    - +---+-----------+-------+
+    - "| 0 | true      | 10    |"
+    - "| 1 | true      | 10    |"
+    - "| 2 | true      | 10    |"
+    - +---+-----------+-------+
+    "#);
+
+    assert!(execution
+        .run("SELECT uniq_combined(int64_col, -1) FROM test_table")
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("The threshold of uniq_combined must not be negative, got -1"));
+    assert!(execution
+        .run("SELECT uniq_exact(int64_col, 10) FROM test_table")
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("uniq_exact expects exactly 1 argument, got 2"));
+    assert!(execution
+        .run("SELECT uniq_exact(int64_col > 1) FROM test_table")
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("uniq_exact does not support values of type Boolean"));
+}
+
+#[tokio::test]
+async fn test_reservoir_sample() {
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_setup("SET datafusion.execution.target_partitions = 1;")
+        .await;
+
+    // reproducible with a seed on a single partition
+    let actual = execution
+        .run_and_format("SELECT reservoir_sample(x, 5, 42) AS sample, reservoir_sample(x, 5, 42) = reservoir_sample(x + 0, 5, 42) AS same, cardinality(reservoir_sample(x, 5)) AS unseeded FROM (SELECT unnest(range(0, 1000)) AS x)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---------------------------+------+----------+
+    - "| sample                    | same | unseeded |"
+    - +---------------------------+------+----------+
+    - "| [171, 215, 920, 808, 718] | true | 5        |"
+    - +---------------------------+------+----------+
+    "#);
+
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_setup("SET datafusion.execution.target_partitions = 4; SET datafusion.execution.batch_size = 100;")
+        .await
+        .with_setup(TEST_TABLE)
+        .await;
+
+    let actual = execution
+        .run_and_format("SELECT array_sort(reservoir_sample(int64_col, 10, 7)) AS all_ints, array_sort(reservoir_sample(utf8_col, 10)) AS all_strings, cardinality(reservoir_sample(utf8_col, 2, 7)) AS two, (SELECT reservoir_sample(int64_col, 3) FROM test_table WHERE int64_col > 100) AS empty FROM test_table")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------------------+-----------------------------------------------+-----+-------+
+    - "| all_ints           | all_strings                                   | two | empty |"
+    - +--------------------+-----------------------------------------------+-----+-------+
+    - "| [1, 2, 2, 3, 3, 3] | [apple, apple, apple, banana, banana, orange] | 2   |       |"
+    - +--------------------+-----------------------------------------------+-----+-------+
+    "#);
+
+    // each row is sampled uniformly, even among duplicate values: the single 2 of 100 values is in a sample of 3
+    // values about 3 times in 100 seeds
+    let mut with_two = 0;
+    for seed in 1..=100 {
+        let actual = execution
+            .run_and_format(&format!("SELECT array_has(reservoir_sample(x, 3, {seed}), 2) AS with_two FROM (SELECT CASE WHEN x = 50 THEN 2 ELSE 1 END AS x FROM (SELECT unnest(range(0, 100)) AS x))"))
+            .await;
+        if actual[3].contains("true") {
+            with_two += 1;
+        }
+    }
+    assert!((1..=8).contains(&with_two), "the 2 is in {with_two} samples");
+
+    // the inputs of 1000 and 9000 values are sampled in proportion to their number of values
+    let actual = execution
+        .run_and_format("SELECT count(*) AS size, avg(CASE WHEN i < 1000 THEN 0.0 ELSE 1.0 END) BETWEEN 0.85 AND 0.95 AS weighted, count(DISTINCT i) AS distinct_values FROM (SELECT unnest(reservoir_sample(i, 1000, 5)) AS i FROM (SELECT x AS i FROM (SELECT unnest(range(0, 1000)) AS x) UNION ALL SELECT x AS i FROM (SELECT unnest(range(1000, 10000)) AS x)))")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +------+----------+-----------------+
+    - "| size | weighted | distinct_values |"
+    - +------+----------+-----------------+
+    - "| 1000 | true     | 1000            |"
+    - +------+----------+-----------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT x % 3 AS g, cardinality(reservoir_sample(x, 4, 1)) AS size, array_sort(reservoir_sample(x % 3, 2, 1)) AS values FROM (SELECT unnest(range(0, 3000)) AS x) GROUP BY x % 3 ORDER BY g")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+------+--------+
+    - "| g | size | values |"
+    - +---+------+--------+
+    - "| 0 | 4    | [0, 0] |"
+    - "| 1 | 4    | [1, 1] |"
+    - "| 2 | 4    | [2, 2] |"
+    - +---+------+--------+
+    "#);
+
+    assert!(execution
+        .run("SELECT reservoir_sample(int64_col, 0) FROM test_table")
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("The n of reservoir_sample must be positive, got 0"));
+    assert!(execution
+        .run("SELECT reservoir_sample(int64_col, int64_col) FROM test_table")
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("The n of reservoir_sample must be a constant integer"));
+    assert!(execution
+        .run("SELECT reservoir_sample(int64_col) FROM test_table")
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("reservoir_sample expects 2 or 3 arguments, got 1"));
+}
+
+#[tokio::test]
+async fn test_group_uniq_array() {
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_setup("SET datafusion.execution.target_partitions = 1;")
+        .await
+        .with_setup(TEST_TABLE)
+        .await;
+
+    // in the order the values are first seen on a single partition
+    let actual = execution
+        .run_and_format("SELECT group_uniq_array(utf8_col) AS strings, group_uniq_array(utf8_col, 2) AS first_strings, group_uniq_array(arrow_cast(utf8_col, 'Dictionary(Int32, Utf8)')) AS dictionary, group_uniq_array(arrow_cast(utf8_col, 'Utf8View')) AS utf8_view, group_uniq_array(int64_col) AS ints, group_uniq_array(float64_col, 1) AS floats, group_uniq_array(date64_col) AS dates FROM test_table")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------------------------+-----------------+-------------------------+-------------------------+-----------+--------+--------------------------------------+
+    - "| strings                 | first_strings   | dictionary              | utf8_view               | ints      | floats | dates                                |"
+    - +-------------------------+-----------------+-------------------------+-------------------------+-----------+--------+--------------------------------------+
+    - "| [apple, banana, orange] | [apple, banana] | [apple, banana, orange] | [apple, banana, orange] | [1, 2, 3] | [1.0]  | [2021-01-01, 2021-01-02, 2021-01-03] |"
+    - +-------------------------+-----------------+-------------------------+-------------------------+-----------+--------+--------------------------------------+
+    "#);
+
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_setup("SET datafusion.execution.target_partitions = 4; SET datafusion.execution.batch_size = 100;")
+        .await
+        .with_setup(TEST_TABLE)
+        .await;
+
+    let actual = execution
+        .run_and_format("SELECT array_sort(group_uniq_array(x % 7)) AS all_values, cardinality(group_uniq_array(x, 5)) AS first_values, array_sort(group_uniq_array(arrow_cast(CAST(x % 3 AS VARCHAR), 'LargeUtf8'))) AS large_strings, cardinality(group_uniq_array(x % 13, 100)) AS under_max_size FROM (SELECT unnest(range(0, 4000)) AS x)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-----------------------+--------------+---------------+----------------+
+    - "| all_values            | first_values | large_strings | under_max_size |"
+    - +-----------------------+--------------+---------------+----------------+
+    - "| [0, 1, 2, 3, 4, 5, 6] | 5            | [0, 1, 2]     | 13             |"
+    - +-----------------------+--------------+---------------+----------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT x % 3 AS g, array_sort(group_uniq_array(x % 4)) AS values FROM (SELECT unnest(range(0, 3000)) AS x) GROUP BY x % 3 ORDER BY g")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+--------------+
+    - "| g | values       |"
+    - +---+--------------+
+    - "| 0 | [0, 1, 2, 3] |"
+    - "| 1 | [0, 1, 2, 3] |"
+    - "| 2 | [0, 1, 2, 3] |"
+    - +---+--------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT group_uniq_array(utf8_col) AS empty FROM test_table WHERE int64_col > 100")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------+
+    - "| empty |"
+    - +-------+
+    - "|       |"
+    - +-------+
+    "#);
+
+    assert!(execution
+        .run("SELECT group_uniq_array(int64_col, 0) FROM test_table")
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("The max_size of group_uniq_array must be positive, got 0"));
+    assert!(execution
+        .run("SELECT group_uniq_array(int64_col > 1) FROM test_table")
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("group_uniq_array does not support values of type Boolean"));
+}
+
+#[tokio::test]
+async fn test_product() {
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_setup("SET datafusion.execution.target_partitions = 4; SET datafusion.execution.batch_size = 100;")
+        .await
+        .with_setup(TEST_TABLE)
+        .await;
+
+    let actual = execution
+        .run_and_format("SELECT product(int64_col) AS int64, product(float64_col) AS float64, try_product(int64_col) AS try_int64, product(arrow_cast(int64_col, 'UInt8')) AS uint8, product(arrow_cast(float64_col, 'Decimal128(10, 2)')) AS decimal, (SELECT product(int64_col) AS p FROM test_table WHERE int64_col > 100) AS empty FROM test_table")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------+---------+-----------+-------+---------+-------+
+    - "| int64 | float64 | try_int64 | uint8 | decimal | empty |"
+    - +-------+---------+-----------+-------+---------+-------+
+    - "| 108   | 108.0   | 108       | 108   | 108.00  |       |"
+    - +-------+---------+-----------+-------+---------+-------+
+    "#);
+
+    // compounding returns, with negative ones, and decimals rounded at their scale
+    let actual = execution
+        .run_and_format("SELECT product(1 + r) AS compounded, product(arrow_cast(1 + r, 'Decimal128(10, 2)')) AS decimal, product(arrow_cast(r * 10, 'Decimal128(10, 2)')) AS negative FROM (VALUES (0.10), (0.20), (-0.05)) AS t(r)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +------------+---------+----------+
+    - "| compounded | decimal | negative |"
+    - +------------+---------+----------+
+    - "| 1.254      | 1.25    | -1.00    |"
+    - +------------+---------+----------+
+    "#);
+
+    // decimals are multiplied exactly and rounded once, 0.5³ = 0.125 and not 0.5 * 0.5 = 0.3, 0.3 * 0.5 = 0.2, and
+    // digits beyond a Decimal256 are only rounded off while multiplying
+    let actual = execution
+        .run_and_format("SELECT product(arrow_cast(x, 'Decimal128(3, 1)')) AS halves, product(arrow_cast('1.5', 'Decimal128(38, 30)')) AS wide, x FROM (SELECT 0.5 AS x FROM (SELECT unnest(range(0, 3)))) GROUP BY x")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------+----------------------------------+-----+
+    - "| halves | wide                             | x   |"
+    - +--------+----------------------------------+-----+
+    - "| 0.1    | 3.375000000000000000000000000000 | 0.5 |"
+    - +--------+----------------------------------+-----+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT product(arrow_cast('1.5', 'Decimal128(38, 30)')) AS wide, product(arrow_cast('1.1', 'Decimal128(38, 30)')) AS rounded FROM (SELECT unnest(range(0, 10)))")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-----------------------------------+----------------------------------+
+    - "| wide                              | rounded                          |"
+    - +-----------------------------------+----------------------------------+
+    - "| 57.665039062500000000000000000000 | 2.593742460100000000000000000000 |"
+    - +-----------------------------------+----------------------------------+
+    "#);
+
+    // 25! overflows a 64-bit integer, unless a value is 0
+    let actual = execution
+        .run_and_format("SELECT try_product(x) AS overflow, product(CASE WHEN x = 25 THEN 0 ELSE x END) AS zero, try_product(arrow_cast(x, 'Decimal128(20, 0)')) AS decimal, try_product(arrow_cast(x, 'Decimal128(20, 0)') * 100000) AS decimal_overflow, product(arrow_cast(x, 'Float64')) AS float, product(arrow_cast(x, 'Float64') * 1e300) AS infinity FROM (SELECT unnest(range(1, 26)) AS x)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +----------+------+----------------------------+------------------+-----------------------+----------+
+    - "| overflow | zero | decimal                    | decimal_overflow | float                 | infinity |"
+    - +----------+------+----------------------------+------------------+-----------------------+----------+
+    - "|          | 0    | 15511210043330985984000000 |                  | 1.5511210043330986e25 | inf      |"
+    - +----------+------+----------------------------+------------------+-----------------------+----------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT x % 4 AS g, try_product(CASE WHEN x % 4 = 0 THEN x ELSE 1 END) IS NULL AS overflow, product(CASE WHEN x % 4 = 1 AND x < 10 THEN -1 ELSE 1 END) AS sign FROM (SELECT unnest(range(1, 2001)) AS x) GROUP BY x % 4 ORDER BY g")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+----------+------+
+    - "| g | overflow | sign |"
+    - +---+----------+------+
+    - "| 0 | true     | 1    |"
+    - "| 1 | false    | -1   |"
+    - "| 2 | false    | 1    |"
+    - "| 3 | false    | 1    |"
+    - +---+----------+------+
+    "#);
+
+    assert!(execution
+        .run("SELECT product(x) FROM (SELECT unnest(range(1, 26)) AS x)")
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("Overflow in product of type Int64, try_product returns null instead"));
+    assert!(execution
+        .run("SELECT product(CAST(x AS DECIMAL(20, 2)) * 100000) FROM (SELECT unnest(range(1, 26)) AS x)")
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("Overflow in product of type Decimal128(38, 2)"));
+    assert!(execution
+        .run("SELECT product(utf8_col) FROM test_table")
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("product does not support values of type Utf8"));
+}
+
+#[tokio::test]
+async fn test_fsum() {
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_setup("SET datafusion.execution.target_partitions = 4; SET datafusion.execution.batch_size = 100;")
+        .await
+        .with_setup(TEST_TABLE)
+        .await;
+
+    let actual = execution
+        .run_and_format("SELECT fsum(int64_col) AS int64, kahan_sum(float64_col) AS float64, favg(float64_col) AS avg, (SELECT fsum(int64_col) AS s FROM test_table WHERE int64_col > 100) AS empty FROM test_table")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-------+---------+--------------------+-------+
+    - "| int64 | float64 | avg                | empty |"
+    - +-------+---------+--------------------+-------+
+    - "| 14.0  | 14.0    | 2.3333333333333335 |       |"
+    - +-------+---------+--------------------+-------+
+    "#);
+
+    // the compensation keeps the digits lost by the naive sums, across partitions and groups
+    let actual = execution
+        .run_and_format("SELECT fsum(0.1) AS fsum, favg(0.1) AS favg, (SELECT fsum(x) AS s FROM (VALUES (1e100), (1.0), (-1e100)) AS t(x)) AS cancelled FROM (SELECT unnest(range(0, 10000)) AS x)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------+------+-----------+
+    - "| fsum   | favg | cancelled |"
+    - +--------+------+-----------+
+    - "| 1000.0 | 0.1  | 1.0       |"
+    - +--------+------+-----------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT x % 4 AS g, fsum(0.1) AS fsum, favg(arrow_cast(x, 'Float64')) AS favg FROM (SELECT unnest(range(0, 10000)) AS x) GROUP BY x % 4 ORDER BY g")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+-------+--------+
+    - "| g | fsum  | favg   |"
+    - +---+-------+--------+
+    - "| 0 | 250.0 | 4998.0 |"
+    - "| 1 | 250.0 | 4999.0 |"
+    - "| 2 | 250.0 | 5000.0 |"
+    - "| 3 | 250.0 | 5001.0 |"
+    - +---+-------+--------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT x, fsum(x) OVER (ORDER BY i ROWS BETWEEN 1 PRECEDING AND CURRENT ROW) AS fsum, favg(x) OVER (ORDER BY i ROWS BETWEEN 1 PRECEDING AND CURRENT ROW) AS favg FROM (VALUES (1, 1.0), (2, 1e100), (3, -1e100), (4, 2.0)) AS t(i, x) ORDER BY i")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +--------+--------+-------+
+    - "| x      | fsum   | favg  |"
+    - +--------+--------+-------+
+    - "| 1.0    | 1.0    | 1.0   |"
+    - "| 1e100  | 1e100  | 5e99  |"
+    - "| -1e100 | 0.0    | 0.0   |"
+    - "| 2.0    | -1e100 | -5e99 |"
+    - +--------+--------+-------+
+    "#);
+
+    // infinite and NaN values leave the window frame once retracted
+    let actual = execution
+        .run_and_format("SELECT x, fsum(x) OVER (ORDER BY i ROWS BETWEEN 1 PRECEDING AND CURRENT ROW) AS fsum, favg(x) OVER (ORDER BY i ROWS BETWEEN 1 PRECEDING AND CURRENT ROW) AS favg FROM (VALUES (1, arrow_cast('inf', 'Float64')), (2, 1.0), (3, 2.0), (4, 3.0), (5, arrow_cast('NaN', 'Float64')), (6, arrow_cast('-inf', 'Float64')), (7, 4.0)) AS t(i, x) ORDER BY i")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +------+------+------+
+    - "| x    | fsum | favg |"
+    - +------+------+------+
+    - "| inf  | inf  | inf  |"
+    - "| 1.0  | inf  | inf  |"
+    - "| 2.0  | 3.0  | 1.5  |"
+    - "| 3.0  | 5.0  | 2.5  |"
+    - "| NaN  | NaN  | NaN  |"
+    - "| -inf | NaN  | NaN  |"
+    - "| 4.0  | -inf | -inf |"
+    - +------+------+------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT fsum(x) AS fsum, fsum(CASE WHEN x > 0 THEN x END) AS positive, fsum(CASE WHEN x < 3 THEN x END) AS negative FROM (VALUES (arrow_cast('inf', 'Float64')), (1.0), (arrow_cast('-inf', 'Float64'))) AS t(x)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +------+----------+----------+
+    - "| fsum | positive | negative |"
+    - +------+----------+----------+
+    - "| NaN  | inf      | -inf     |"
+    - +------+----------+----------+
+    "#);
+
+    let err = execution
+        .run("SELECT fsum(utf8_col) FROM test_table")
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("fsum does not support values of type Utf8"));
+}
+
+#[tokio::test]
+async fn test_power_means() {
+    let mut execution = TestExecution::new()
+        .await
+        .unwrap()
+        .with_setup("SET datafusion.execution.target_partitions = 4; SET datafusion.execution.batch_size = 100;")
+        .await
+        .with_setup(TEST_TABLE)
+        .await;
+
+    let actual = execution
+        .run_and_format("SELECT geometric_mean(x) AS geometric, geomean(arrow_cast(x, 'Decimal128(10, 2)')) AS decimal, harmonic_mean(x) AS harmonic, (SELECT harmonic_mean(int64_col) AS h FROM test_table WHERE int64_col > 100) AS empty FROM (VALUES (1), (2), (4), (8), (NULL)) AS t(x)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +------------------+------------------+--------------------+-------+
+    - "| geometric        | decimal          | harmonic           | empty |"
+    - +------------------+------------------+--------------------+-------+
+    - "| 2.82842712474619 | 2.82842712474619 | 2.1333333333333333 |       |"
+    - +------------------+------------------+--------------------+-------+
+    "#);
+
+    // growth rates compounded over 10000 periods, in groups, and zeros
+    let actual = execution
+        .run_and_format("SELECT x % 4 AS g, geometric_mean(1.01) AS growth, harmonic_mean(CASE WHEN x % 2 = 0 THEN 1.0 ELSE 3.0 END) AS harmonic, geometric_mean(CASE WHEN x = 7 THEN 0 ELSE 2 END) AS zero, harmonic_mean(CASE WHEN x = 7 THEN 0 ELSE 2 END) AS harmonic_zero FROM (SELECT unnest(range(0, 10000)) AS x) GROUP BY x % 4 ORDER BY g")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +---+--------+--------------------+------+---------------+
+    - "| g | growth | harmonic           | zero | harmonic_zero |"
+    - +---+--------+--------------------+------+---------------+
+    - "| 0 | 1.01   | 1.0                | 2.0  | 2.0           |"
+    - "| 1 | 1.01   | 3.0000000000000004 | 2.0  | 2.0           |"
+    - "| 2 | 1.01   | 1.0                | 2.0  | 2.0           |"
+    - "| 3 | 1.01   | 3.0000000000000004 | 0.0  | 0.0           |"
+    - +---+--------+--------------------+------+---------------+
+    "#);
+
+    let actual = execution
+        .run_and_format("SELECT harmonic_mean(CASE WHEN x % 2 = 0 THEN 1.0 ELSE 3.0 END) AS harmonic, geometric_mean(CASE WHEN x % 2 = 0 THEN 1.0 ELSE 4.0 END) AS geometric FROM (SELECT unnest(range(0, 10000)) AS x)")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +----------+-----------+
+    - "| harmonic | geometric |"
+    - +----------+-----------+
+    - "| 1.5      | 2.0       |"
+    - +----------+-----------+
+    "#);
+
+    // values leaving the frame are retracted, including zeros
+    let actual = execution
+        .run_and_format("SELECT x, geometric_mean(x) OVER (ORDER BY i ROWS BETWEEN 1 PRECEDING AND CURRENT ROW) AS geometric, harmonic_mean(x) OVER (ORDER BY i ROWS BETWEEN 1 PRECEDING AND CURRENT ROW) AS harmonic FROM (VALUES (1, 2.0), (2, 8.0), (3, 0.0), (4, 4.0), (5, 1.0)) AS t(i, x) ORDER BY i")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-----+-----------+----------+
+    - "| x   | geometric | harmonic |"
+    - +-----+-----------+----------+
+    - "| 2.0 | 2.0       | 2.0      |"
+    - "| 8.0 | 4.0       | 3.2      |"
+    - "| 0.0 | 0.0       | 0.0      |"
+    - "| 4.0 | 0.0       | 0.0      |"
+    - "| 1.0 | 2.0       | 1.6      |"
+    - +-----+-----------+----------+
+    "#);
+
+    // infinite and NaN values leave the frame once retracted too
+    let actual = execution
+        .run_and_format("SELECT x, geometric_mean(x) OVER (ORDER BY i ROWS BETWEEN 1 PRECEDING AND CURRENT ROW) AS geometric, harmonic_mean(x) OVER (ORDER BY i ROWS BETWEEN 1 PRECEDING AND CURRENT ROW) AS harmonic FROM (VALUES (1, arrow_cast('inf', 'Float64')), (2, 2.0), (3, 8.0), (4, arrow_cast('NaN', 'Float64')), (5, 0.0), (6, 4.0), (7, 1.0)) AS t(i, x) ORDER BY i")
+        .await;
+
+    insta::assert_yaml_snapshot!(actual, @r#"
+    - +-----+-----------+----------+
+    - "| x   | geometric | harmonic |"
+    - +-----+-----------+----------+
+    - "| inf | inf       | inf      |"
+    - "| 2.0 | inf       | 4.0      |"
+    - "| 8.0 | 4.0       | 3.2      |"
+    - "| NaN | NaN       | NaN      |"
+    - "| 0.0 | NaN       | NaN      |"
+    - "| 4.0 | 0.0       | 0.0      |"
+    - "| 1.0 | 2.0       | 1.6      |"
+    - +-----+-----------+----------+
+    "#);
+
+    let err = execution
+        .run("SELECT geometric_mean(x) FROM (VALUES (1.0), (-2.0)) AS t(x)")
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("geometric_mean is only defined for non-negative values, got -2"));
+
+    let err = execution
+        .run("SELECT harmonic_mean(utf8_col) FROM test_table")
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("harmonic_mean does not support values of type Utf8"));
+}
@@ -19,8 +19,11 @@ use arrow::record_batch::RecordBatch;
 use arrow::util::pretty::pretty_format_batches;
 use datafusion::error::Result;
 use datafusion::execution::context::SessionContext;
+use datafusion::logical_expr::AggregateUDF;
+use datafusion::prelude::DataFrame;
 use datafusion::prelude::SessionConfig;
 use datafusion::sql::parser::DFParser;
+use datafusion_functions_extra::config::ExtraFunctionsConfig;
 use datafusion_functions_extra::register_all_extra_functions;
 use log::debug;
 
@@ -30,12 +33,17 @@ pub struct TestExecution {
 
 impl TestExecution {
     pub async fn new() -> Result<Self> {
-        let config = SessionConfig::new();
+        let config = SessionConfig::new().with_option_extension(ExtraFunctionsConfig::default());
         let mut ctx = SessionContext::new_with_config(config);
         register_all_extra_functions(&mut ctx)?;
         Ok(Self { ctx })
     }
 
+    pub fn with_udaf(self, udaf: AggregateUDF) -> Self {
+        self.ctx.register_udaf(udaf);
+        self
+    }
+
     pub async fn with_setup(self, sql: &str) -> Self {
         debug!("Running setup query: {sql}");
         let statements = DFParser::parse_sql(sql).expect("Error parsing setup query");
@@ -62,6 +70,22 @@ impl TestExecution {
         let results = self.run(sql).await.expect("Error running query");
         format_results(&results)
     }
+
+    /// Runs the DataFrame built by `build` on top of the result of `sql`.
+    pub async fn run_dataframe_and_format(
+        &mut self,
+        sql: &str,
+        build: impl FnOnce(DataFrame) -> Result<DataFrame>,
+    ) -> Vec<String> {
+        debug!("Running DataFrame on query: {sql}");
+        let dataframe = self.ctx.sql(sql).await.expect("Error planning query");
+        let results = build(dataframe)
+            .expect("Error building DataFrame")
+            .collect()
+            .await
+            .expect("Error running DataFrame");
+        format_results(&results)
+    }
 }
 
 fn format_results(results: &[RecordBatch]) -> Vec<String> {